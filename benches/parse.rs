@@ -0,0 +1,57 @@
+//! Compares querying the schema per table with querying it for all tables at once
+//! Run with `cargo bench`
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rusqlite::Connection;
+use sqlite_parser::backend::{parse_backend, RusqliteBackend};
+use sqlite_parser::ParseOptions;
+
+const TABLES_QUERY: &str = "SELECT name FROM sqlite_master WHERE type = 'table';";
+
+/// Tables with a foreign key to the previous table, a unique constraint and an index
+fn schema(tables: usize) -> Connection {
+    let connection = Connection::open_in_memory().unwrap();
+    let mut sql = "CREATE TABLE table_0 (id INTEGER NOT NULL PRIMARY KEY);".to_string();
+
+    for i in 1..tables {
+        sql.push_str(&format!(
+            "CREATE TABLE table_{i} (
+                id INTEGER NOT NULL PRIMARY KEY,
+                previous_id INTEGER NOT NULL REFERENCES table_{}(id),
+                code TEXT NOT NULL UNIQUE,
+                name TEXT,
+                created REAL
+            );
+            CREATE INDEX table_{i}_name ON table_{i}(name, created);",
+            i - 1
+        ));
+    }
+
+    connection.execute_batch(&sql).unwrap();
+
+    connection
+}
+
+fn bench_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse");
+
+    for tables in [10, 100, 500] {
+        let connection = schema(tables);
+
+        group.bench_with_input(BenchmarkId::new("per table", tables), &tables, |b, _| {
+            b.iter(|| {
+                let backend = RusqliteBackend::with_tables_query(&connection, TABLES_QUERY, &[]);
+
+                parse_backend(&backend, ParseOptions::default())
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("all at once", tables), &tables, |b, _| {
+            b.iter(|| parse_backend(&RusqliteBackend::new(&connection), ParseOptions::default()))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse);
+criterion_main!(benches);
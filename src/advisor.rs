@@ -0,0 +1,338 @@
+//! Suggests indexes for the queries of a workload that scan a whole table and finds the indexes
+//! that the workload doesn't use
+//!
+//! Every statement is explained with `explain::explain`. For every table the plan scans, the
+//! columns of the table that are compared in the WHERE and ON clauses are read from the SQL:
+//! columns compared with `=`, `IN` or `IS` come first in the suggested index, followed by the
+//! first column compared with `<`, `>`, `<=`, `>=` or `BETWEEN`. The SQL is only tokenized, so
+//! the columns are a best guess for complex statements.
+
+use std::collections::HashSet;
+
+use rusqlite::Connection;
+
+use crate::ddl::{create_index_sql, explicit_indexes};
+use crate::explain::{explain, is_clause_keyword, is_identifier};
+use crate::storage::query_storage_stats;
+use crate::tokenizer::{tokenize_without_trivia, unquote, Token};
+use crate::{quote_identifier, Index, Metadata, Table};
+
+/// A statement of the workload that scans a whole table
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct FullScan {
+    /// The statement as it is in the workload
+    pub sql: String,
+    pub table: String,
+    /// The compared columns of the table, in the order of the suggested index
+    pub columns: Vec<String>,
+    /// The CREATE INDEX statement on the columns, `None` if no columns are compared or an index
+    /// with these columns already exists
+    pub suggestion: Option<String>,
+}
+
+/// An index that is a candidate to drop
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct UnusedIndex {
+    pub table: String,
+    pub index: String,
+    pub reason: UnusedReason,
+    /// The bytes the index uses according to `dbstat`, `None` if `dbstat` is not available
+    pub bytes: Option<u64>,
+    /// The DROP INDEX statement
+    pub drop_sql: String,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Eq)]
+pub enum UnusedReason {
+    /// No plan of the workload uses the index
+    NotInPlans,
+    /// Without a workload: according to `sqlite_stat1`, a value of the first column matches at
+    /// least half of the rows, so the planner is unlikely to use the index
+    LowSelectivity,
+}
+
+/// The indexes that are candidates to drop, sorted by table and index name
+/// With a workload, the indexes that no plan of the workload uses are returned. Without a
+/// workload, the indexes with `UnusedReason::LowSelectivity` are returned, indexes without
+/// statistics are kept. Unique indexes and indexes of which the first columns are the columns of
+/// a foreign key are never returned: they enforce the uniqueness or are used to check the foreign
+/// key when a referred row is deleted or updated, which a workload of queries doesn't show.
+/// Fails if a statement can't be explained.
+pub fn unused_indexes(
+    connection: &Connection,
+    metadata: &Metadata,
+    workload: &[&str],
+) -> rusqlite::Result<Vec<UnusedIndex>> {
+    let mut used = HashSet::new();
+
+    for sql in workload {
+        for index in explain(connection, sql, metadata)?.indexes() {
+            used.insert(index.name.to_lowercase());
+        }
+    }
+
+    let storage = query_storage_stats(connection, None);
+    let mut unused = vec![];
+
+    for table in metadata.sorted_tables() {
+        let mut indexes = explicit_indexes(table)
+            .filter(|i| !i.unique && !backs_foreign_key(table, i))
+            .collect::<Vec<_>>();
+
+        indexes.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for index in indexes {
+            let reason = if !workload.is_empty() {
+                if used.contains(&index.name.to_lowercase()) {
+                    continue;
+                }
+
+                UnusedReason::NotInPlans
+            } else if index
+                .statistics
+                .as_ref()
+                .and_then(|s| s.selectivity(1))
+                .is_some_and(|s| s >= 0.5)
+            {
+                UnusedReason::LowSelectivity
+            } else {
+                continue;
+            };
+
+            unused.push(UnusedIndex {
+                table: table.table_name.clone(),
+                index: index.name.clone(),
+                reason,
+                bytes: storage
+                    .as_ref()
+                    .and_then(|s| s.get(&index.name))
+                    .map(|s| s.bytes),
+                drop_sql: format!("DROP INDEX {};", quote_identifier(&index.name)),
+            });
+        }
+    }
+
+    Ok(unused)
+}
+
+/// Checks if the leading columns of the index are the columns of a foreign key of the table
+fn backs_foreign_key(table: &Table, index: &Index) -> bool {
+    table.foreign_keys.iter().any(|f| {
+        let columns = f
+            .from_column
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>();
+
+        index.covers_with(&columns, table.lookup)
+    })
+}
+
+/// The full scans of the statements, in the order of the workload
+/// Fails if a statement can't be explained, e.g. because it refers to a missing table.
+pub fn suggest_indexes(
+    connection: &Connection,
+    metadata: &Metadata,
+    workload: &[&str],
+) -> rusqlite::Result<Vec<FullScan>> {
+    let mut full_scans = vec![];
+
+    for sql in workload {
+        let tokens = tokenize_without_trivia(sql);
+
+        for step in explain(connection, sql, metadata)?.full_scans() {
+            let table = step.table.unwrap();
+            let columns = compared_columns(&tokens, table, step.alias.as_deref().unwrap());
+            let names = columns.iter().map(String::as_str).collect::<Vec<_>>();
+            let suggestion = if columns.is_empty() || table.covering_index_for(&names).is_some() {
+                None
+            } else {
+                let index = Index {
+                    name: format!("{}_{}", table.table_name, columns.join("_")),
+                    columns: names
+                        .iter()
+                        .map(|c| table.column(c).unwrap().clone())
+                        .collect(),
+                    unique: false,
+                    storage: None,
+                    statistics: None,
+                };
+
+                Some(create_index_sql(table, &index))
+            };
+
+            full_scans.push(FullScan {
+                sql: sql.to_string(),
+                table: table.table_name.clone(),
+                columns,
+                suggestion,
+            });
+        }
+    }
+
+    Ok(full_scans)
+}
+
+/// The columns of the table that are compared in a WHERE or ON clause, equality comparisons first
+fn compared_columns(tokens: &[Token], table: &Table, alias: &str) -> Vec<String> {
+    let mut equal = vec![];
+    let mut range = vec![];
+    let mut in_condition = false;
+
+    for (i, token) in tokens.iter().enumerate() {
+        if token.is_keyword("WHERE") || token.is_keyword("ON") {
+            in_condition = true;
+        } else if is_clause_keyword(token) {
+            in_condition = false;
+        }
+
+        if !in_condition || !is_identifier(token) {
+            continue;
+        }
+
+        // A qualified column must be qualified with the alias of the scanned table
+        let qualified = i >= 2 && tokens[i - 1].text == ".";
+
+        if qualified && !unquote(tokens[i - 2].text).eq_ignore_ascii_case(alias) {
+            continue;
+        }
+
+        if tokens.get(i + 1).is_some_and(|t| t.text == ".") {
+            continue;
+        }
+
+        let Some(column) = table.column(&unquote(token.text)) else {
+            continue;
+        };
+        let before = if qualified {
+            i.checked_sub(3)
+        } else {
+            i.checked_sub(1)
+        };
+        let operators = [before.map(|b| &tokens[b]), tokens.get(i + 1)];
+        let columns = if operators.iter().flatten().any(|t| is_equality(t)) {
+            &mut equal
+        } else if operators.iter().flatten().any(|t| is_range(t)) {
+            &mut range
+        } else {
+            continue;
+        };
+
+        if !columns.contains(&column.name) {
+            columns.push(column.name.clone());
+        }
+    }
+
+    equal.extend(range.into_iter().find(|c| !equal.contains(c)));
+
+    equal
+}
+
+fn is_equality(token: &Token) -> bool {
+    ["=", "=="].contains(&token.text) || token.is_keyword("IN") || token.is_keyword("IS")
+}
+
+fn is_range(token: &Token) -> bool {
+    ["<", ">", "<=", ">="].contains(&token.text) || token.is_keyword("BETWEEN")
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::advisor::{suggest_indexes, unused_indexes, UnusedReason};
+    use crate::metadata_from_connection;
+
+    #[test]
+    fn test_suggest_indexes() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE user (user_id INTEGER PRIMARY KEY, email TEXT, age INTEGER);
+                CREATE TABLE post (post_id INTEGER PRIMARY KEY, user_id INTEGER, title TEXT);
+                CREATE INDEX post_title ON post(title);",
+            )
+            .unwrap();
+
+        let metadata = metadata_from_connection(&connection, Default::default());
+        let full_scans = suggest_indexes(
+            &connection,
+            &metadata,
+            &[
+                "SELECT * FROM post WHERE title = ?;",
+                "SELECT * FROM user WHERE age > 18 AND email = ?;",
+                "SELECT p.title FROM user AS u JOIN post p ON p.user_id = u.user_id WHERE u.user_id = 1;",
+                "UPDATE user SET email = ? WHERE age BETWEEN 1 AND 2;",
+                "SELECT * FROM user;",
+            ],
+        )
+        .unwrap();
+        let suggestions = full_scans
+            .iter()
+            .map(|f| (f.table.as_str(), f.suggestion.as_deref()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                (
+                    "user",
+                    Some("CREATE INDEX \"user_email_age\" ON \"user\" (\"email\", \"age\");")
+                ),
+                (
+                    "post",
+                    Some("CREATE INDEX \"post_user_id\" ON \"post\" (\"user_id\");")
+                ),
+                (
+                    "user",
+                    Some("CREATE INDEX \"user_age\" ON \"user\" (\"age\");")
+                ),
+                ("user", None),
+            ],
+            suggestions
+        );
+    }
+
+    #[test]
+    fn test_unused_indexes() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE user (user_id INTEGER PRIMARY KEY, email TEXT, active INTEGER);
+                CREATE TABLE post (post_id INTEGER PRIMARY KEY, user_id INTEGER REFERENCES user);
+                CREATE INDEX user_email ON user(email);
+                CREATE INDEX user_active ON user(active);
+                CREATE UNIQUE INDEX user_email_unique ON user(email, user_id);
+                CREATE INDEX post_user_id ON post(user_id);
+                WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 100)
+                INSERT INTO user SELECT i, 'user' || i, i % 2 FROM n;
+                ANALYZE;",
+            )
+            .unwrap();
+
+        let metadata = metadata_from_connection(&connection, Default::default());
+        let unused = unused_indexes(
+            &connection,
+            &metadata,
+            &["SELECT * FROM user WHERE email = ?;"],
+        )
+        .unwrap();
+
+        assert_eq!(1, unused.len());
+        assert_eq!("user_active", unused[0].index);
+        assert_eq!(UnusedReason::NotInPlans, unused[0].reason);
+        assert_eq!("DROP INDEX \"user_active\";", unused[0].drop_sql);
+        assert!(unused[0].bytes.unwrap() > 0);
+
+        let unused = unused_indexes(&connection, &metadata, &[]).unwrap();
+
+        assert_eq!(
+            vec![("user_active", UnusedReason::LowSelectivity)],
+            unused
+                .iter()
+                .map(|u| (u.index.as_str(), u.reason))
+                .collect::<Vec<_>>()
+        );
+    }
+}
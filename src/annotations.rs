@@ -0,0 +1,335 @@
+//! A file next to the database with descriptions, logical types, enum names and ignore flags per
+//! table and column, merged into the metadata so exporters and code generators see them
+//!
+//! ```toml
+//! [user]
+//! description = "The accounts"
+//!
+//! [user.columns.created]
+//! description = "When the account was created"
+//! logical_type = "DateTime"
+//!
+//! [user.columns.status]
+//! enum_name = "UserStatus"
+//!
+//! [user.columns.password_hash]
+//! ignore = true
+//! ```
+//!
+//! The files are read with `Annotations::from_toml` and `Annotations::from_yaml` when the
+//! `annotations` feature is enabled, and merged with `ParseOptions::annotations` or
+//! `Metadata::annotate`.
+
+use std::collections::BTreeMap;
+
+use crate::report::ParseWarning;
+use crate::{Column, LogicalType, LookupMode, Metadata};
+
+/// The annotations per table name
+#[derive(Debug, PartialEq, Clone, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Annotations {
+    pub tables: BTreeMap<String, TableAnnotation>,
+}
+
+#[derive(Debug, PartialEq, Clone, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct TableAnnotation {
+    /// See `Metadata::table_description`
+    pub description: Option<String>,
+    /// Removes the table and the foreign keys that refer to it from the metadata
+    pub ignore: bool,
+    /// The annotations per column name
+    pub columns: BTreeMap<String, ColumnAnnotation>,
+}
+
+#[derive(Debug, PartialEq, Clone, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default, deny_unknown_fields))]
+pub struct ColumnAnnotation {
+    /// Replaces `Column::comment`
+    pub description: Option<String>,
+    /// Replaces `Column::logical_type`, e.g. for a TEXT column that holds dates
+    pub logical_type: Option<LogicalType>,
+    /// The name of the enum that a code generator should use, see `Metadata::enum_name`
+    pub enum_name: Option<String>,
+    /// Removes the column and the indexes and foreign keys that contain it from the metadata
+    pub ignore: bool,
+}
+
+#[cfg(feature = "annotations")]
+impl Annotations {
+    pub fn from_toml(toml: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(toml)
+    }
+
+    pub fn from_yaml(yaml: &str) -> Result<Self, serde_yaml::Error> {
+        serde_yaml::from_str(yaml)
+    }
+}
+
+impl Annotations {
+    /// If the table is ignored, the names are compared with `lookup`
+    pub fn ignores_table(&self, lookup: LookupMode, table_name: &str) -> bool {
+        self.tables
+            .iter()
+            .any(|(name, annotation)| annotation.ignore && lookup.matches(name, table_name))
+    }
+}
+
+impl ColumnAnnotation {
+    fn apply(&self, column: &mut Column) {
+        if let Some(description) = &self.description {
+            column.comment = Some(description.clone());
+        }
+
+        if let Some(logical_type) = self.logical_type {
+            column.logical_type = Some(logical_type);
+        }
+    }
+}
+
+impl Metadata {
+    /// Merges the annotations into the metadata, the names are compared with `lookup`
+    /// Returns a `ParseWarning::UnknownAnnotation` for every table and column that doesn't exist.
+    pub fn annotate(&mut self, annotations: &Annotations) -> Vec<ParseWarning> {
+        let mut warnings = vec![];
+        let lookup = self.lookup;
+        let annotation = |table_name: &str| {
+            annotations
+                .tables
+                .iter()
+                .find(|(name, _)| lookup.matches(name, table_name))
+                .map(|(_, annotation)| annotation)
+        };
+        let column_annotation = |table_name: &str, column_name: &str| {
+            annotation(table_name)?
+                .columns
+                .iter()
+                .find(|(name, _)| lookup.matches(name, column_name))
+                .map(|(_, annotation)| annotation)
+        };
+
+        for (table_name, table_annotation) in &annotations.tables {
+            let Some(table) = self.table(table_name) else {
+                warnings.push(ParseWarning::UnknownAnnotation {
+                    table: table_name.clone(),
+                    column: None,
+                });
+
+                continue;
+            };
+
+            for column_name in table_annotation.columns.keys() {
+                if table.column(column_name).is_none() {
+                    warnings.push(ParseWarning::UnknownAnnotation {
+                        table: table_name.clone(),
+                        column: Some(column_name.clone()),
+                    });
+                }
+            }
+        }
+
+        self.tables
+            .retain(|table_name, _| !annotation(table_name).is_some_and(|a| a.ignore));
+
+        for table in self.tables.values_mut() {
+            let is_ignored = |table_name: &str, column: &Column| {
+                column_annotation(table_name, &column.name).is_some_and(|a| a.ignore)
+            };
+            let table_name = table.table_name.clone();
+
+            table.columns.retain(|c| !is_ignored(&table_name, c));
+            table
+                .indexes
+                .retain(|i| !i.columns.iter().any(|c| is_ignored(&table_name, c)));
+            table
+                .automatic_indexes
+                .retain(|i| !i.columns.iter().any(|c| is_ignored(&table_name, c)));
+            table.foreign_keys.retain(|f| {
+                annotation(&f.table).is_none_or(|a| !a.ignore)
+                    && !f.from_column.iter().any(|c| is_ignored(&table_name, c))
+                    && !f.to_column.iter().any(|c| is_ignored(&f.table, c))
+            });
+
+            for column in &mut table.columns {
+                if let Some(annotation) = column_annotation(&table_name, &column.name) {
+                    annotation.apply(column);
+                }
+            }
+
+            for foreign_key in &mut table.foreign_keys {
+                for column in &mut foreign_key.from_column {
+                    if let Some(annotation) = column_annotation(&table_name, &column.name) {
+                        annotation.apply(column);
+                    }
+                }
+
+                for column in &mut foreign_key.to_column {
+                    if let Some(annotation) = column_annotation(&foreign_key.table, &column.name) {
+                        annotation.apply(column);
+                    }
+                }
+            }
+        }
+
+        self.annotations = annotations.clone();
+
+        warnings
+    }
+
+    /// The description of the table from the annotations
+    pub fn table_description(&self, table_name: &str) -> Option<&str> {
+        self.table_annotation(table_name)?.description.as_deref()
+    }
+
+    /// The enum name of the column from the annotations
+    pub fn enum_name(&self, table_name: &str, column_name: &str) -> Option<&str> {
+        self.table_annotation(table_name)?
+            .columns
+            .iter()
+            .find(|(name, _)| self.lookup.matches(name, column_name))?
+            .1
+            .enum_name
+            .as_deref()
+    }
+
+    fn table_annotation(&self, table_name: &str) -> Option<&TableAnnotation> {
+        self.annotations
+            .tables
+            .iter()
+            .find(|(name, _)| self.lookup.matches(name, table_name))
+            .map(|(_, annotation)| annotation)
+    }
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::annotations::{Annotations, ColumnAnnotation, TableAnnotation};
+    use crate::report::ParseWarning;
+    use crate::tests::metadata_from_sql;
+    use crate::LogicalType;
+
+    #[test]
+    fn test_annotate() {
+        let mut metadata = metadata_from_sql(
+            "CREATE TABLE user (
+                user_id INTEGER NOT NULL PRIMARY KEY,
+                -- The status
+                status TEXT NOT NULL,
+                created TEXT NOT NULL,
+                password_hash TEXT
+            );
+            CREATE INDEX user_password_hash ON user (password_hash);
+            CREATE TABLE secret (secret_id INTEGER NOT NULL PRIMARY KEY);
+            CREATE TABLE post (
+                post_id INTEGER NOT NULL PRIMARY KEY,
+                user_id INTEGER REFERENCES user,
+                secret_id INTEGER REFERENCES secret
+            );",
+        );
+        let annotations = Annotations {
+            tables: [
+                (
+                    "USER".to_string(),
+                    TableAnnotation {
+                        description: Some("The accounts".to_string()),
+                        columns: [
+                            (
+                                "status".to_string(),
+                                ColumnAnnotation {
+                                    enum_name: Some("UserStatus".to_string()),
+                                    ..Default::default()
+                                },
+                            ),
+                            (
+                                "created".to_string(),
+                                ColumnAnnotation {
+                                    description: Some("When the account was created".to_string()),
+                                    logical_type: Some(LogicalType::DateTime),
+                                    ..Default::default()
+                                },
+                            ),
+                            (
+                                "password_hash".to_string(),
+                                ColumnAnnotation {
+                                    ignore: true,
+                                    ..Default::default()
+                                },
+                            ),
+                            ("unknown".to_string(), ColumnAnnotation::default()),
+                        ]
+                        .into(),
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "secret".to_string(),
+                    TableAnnotation {
+                        ignore: true,
+                        ..Default::default()
+                    },
+                ),
+            ]
+            .into(),
+        };
+
+        assert_eq!(
+            vec![ParseWarning::UnknownAnnotation {
+                table: "USER".to_string(),
+                column: Some("unknown".to_string()),
+            }],
+            metadata.annotate(&annotations)
+        );
+
+        let user = metadata.table("user").unwrap();
+        let created = user.column("created").unwrap();
+
+        assert_eq!(3, user.columns.len());
+        assert!(user.indexes.is_empty());
+        assert_eq!(
+            Some("The status"),
+            user.column("status").unwrap().comment.as_deref()
+        );
+        assert_eq!(
+            Some("When the account was created"),
+            created.comment.as_deref()
+        );
+        assert_eq!(Some(LogicalType::DateTime), created.logical_type);
+        assert_eq!(None, metadata.table("secret"));
+        assert_eq!(1, metadata.table("post").unwrap().foreign_keys.len());
+        assert_eq!(Some("The accounts"), metadata.table_description("user"));
+        assert_eq!(Some("UserStatus"), metadata.enum_name("user", "STATUS"));
+        assert_eq!(None, metadata.enum_name("user", "created"));
+    }
+
+    #[cfg(feature = "annotations")]
+    #[test]
+    fn test_annotation_files() {
+        let toml = Annotations::from_toml(
+            "[user]
+            description = \"The accounts\"
+
+            [user.columns.created]
+            logical_type = \"DateTime\"",
+        )
+        .unwrap();
+        let yaml = Annotations::from_yaml(
+            "user:
+              description: The accounts
+              columns:
+                created:
+                  logical_type: DateTime",
+        )
+        .unwrap();
+
+        assert_eq!(toml, yaml);
+        assert_eq!(
+            Some(LogicalType::DateTime),
+            toml.tables["user"].columns["created"].logical_type
+        );
+        assert!(Annotations::from_toml("[user]\nunknown = true").is_err());
+    }
+}
@@ -0,0 +1,198 @@
+//! Renames the tables, columns and indexes, so a schema can be shared (e.g. in a bug report)
+//! without the naming of the application
+
+use std::collections::HashMap;
+
+use crate::ddl::{automatic_indexes, create_table_sql, rename_create_index};
+use crate::{Column, Metadata};
+
+impl Metadata {
+    /// A copy with the same structure and generated names
+    /// Tables are named `t1`, `t2`, ... in order of name, the columns of a table `c1`, `c2`, ...
+    /// in column order and the indexes of CREATE INDEX statements `t1_i1`, `t1_i2`, ... per table
+    /// in order of name. Foreign keys to tables that are not in the metadata refer to the next
+    /// table names.
+    ///
+    /// The types, constraints, default values, row counts and storage are kept, `create_sql` is
+    /// regenerated from the structure so CHECK constraints and comments are dropped. The CREATE
+    /// INDEX statements are kept with the names replaced, expressions and literals included. Data that
+    /// could contain values is removed: the samples, profiles, integrity check and the samples of
+    /// the index statistics. The views and triggers are removed, since their SQL contains the names.
+    pub fn anonymized(&self) -> Metadata {
+        let tables = self.sorted_tables();
+        let mut table_names: HashMap<String, String> = tables
+            .iter()
+            .enumerate()
+            .map(|(i, t)| (t.table_name.to_lowercase(), format!("t{}", i + 1)))
+            .collect();
+
+        for table in &tables {
+            for foreign_key in &table.foreign_keys {
+                let next = format!("t{}", table_names.len() + 1);
+
+                table_names
+                    .entry(foreign_key.table.to_lowercase())
+                    .or_insert(next);
+            }
+        }
+
+        let rename_columns = |table_name: &str, columns: &[Column]| {
+            columns
+                .iter()
+                .map(|c| {
+                    // Columns of missing tables are named after their position in the foreign key
+                    let id = self
+                        .table(table_name)
+                        .and_then(|t| t.column(&c.name))
+                        .map_or(c.id, |c| c.id);
+
+                    // The declared type can be named after the application as well
+                    let declared_type = match c.logical_type {
+                        Some(logical_type) => logical_type.to_string(),
+                        None => c.the_type.to_string(),
+                    };
+
+                    Column {
+                        name: format!("c{}", id + 1),
+                        declared_type,
+                        // Comments describe the application
+                        comment: None,
+                        enum_values: None,
+                        ..c.clone()
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut metadata = Metadata {
+            tables: HashMap::new(),
+            samples: HashMap::new(),
+            profiles: HashMap::new(),
+            integrity: None,
+            database: self.database.clone(),
+            lookup: self.lookup,
+            triggers: vec![],
+            views: vec![],
+            annotations: Default::default(),
+        };
+
+        for table in tables {
+            let mut anonymized = table.clone();
+
+            anonymized.table_name = table_names[&table.table_name.to_lowercase()].clone();
+            anonymized.columns = rename_columns(&table.table_name, &table.columns);
+
+            for foreign_key in &mut anonymized.foreign_keys {
+                foreign_key.from_column =
+                    rename_columns(&table.table_name, &foreign_key.from_column);
+                foreign_key.to_column = rename_columns(&foreign_key.table, &foreign_key.to_column);
+                foreign_key.table = table_names[&foreign_key.table.to_lowercase()].clone();
+            }
+
+            let column_names = table
+                .columns
+                .iter()
+                .zip(&anonymized.columns)
+                .map(|(c, a)| (c.name.to_lowercase(), a.name.clone()))
+                .collect::<HashMap<_, _>>();
+            let index_names = table
+                .index_sql
+                .keys()
+                .enumerate()
+                .map(|(i, name)| (name, format!("{}_i{}", anonymized.table_name, i + 1)))
+                .collect::<HashMap<_, _>>();
+
+            for index in &mut anonymized.indexes {
+                index.columns = rename_columns(&table.table_name, &index.columns);
+                // The indexes of UNIQUE constraints are named after their column
+                index.name = match index_names.get(&index.name) {
+                    Some(name) => name.clone(),
+                    None => index.columns[0].name.clone(),
+                };
+
+                if let Some(statistics) = &mut index.statistics {
+                    statistics.samples.clear();
+                }
+            }
+
+            // Like parsing does, the indexes of CREATE INDEX statements come first in order of name
+            anonymized.indexes.sort_by_key(|i| {
+                if index_names.values().any(|n| *n == i.name) {
+                    (false, i.name.clone())
+                } else {
+                    (true, String::new())
+                }
+            });
+
+            for automatic_index in &mut anonymized.automatic_indexes {
+                automatic_index.columns =
+                    rename_columns(&table.table_name, &automatic_index.columns);
+            }
+
+            anonymized.create_sql = create_table_sql(&anonymized);
+            anonymized.automatic_indexes = automatic_indexes(
+                &anonymized.table_name,
+                &anonymized.create_sql,
+                &anonymized.columns,
+            );
+            anonymized.index_sql = table
+                .index_sql
+                .iter()
+                .map(|(name, sql)| {
+                    let index_name = &index_names[name];
+                    let sql =
+                        rename_create_index(sql, index_name, &anonymized.table_name, &column_names);
+
+                    (index_name.clone(), sql)
+                })
+                .collect();
+            metadata
+                .tables
+                .insert(anonymized.table_name.clone(), anonymized);
+        }
+
+        metadata
+    }
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_anonymized() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (
+                user_id INTEGER NOT NULL PRIMARY KEY,
+                email TEXT NOT NULL UNIQUE,
+                secret BLOB CHECK (length(secret) > 0)
+            );
+            CREATE TABLE post (
+                post_id INTEGER NOT NULL PRIMARY KEY,
+                author_id INTEGER NOT NULL REFERENCES user(user_id) ON DELETE CASCADE,
+                title TEXT DEFAULT 'untitled',
+                slug TEXT,
+                UNIQUE (title, slug)
+            );
+            CREATE INDEX post_title ON post(title, author_id);
+            CREATE UNIQUE INDEX post_slug ON post(slug) WHERE title <> 'draft';
+            CREATE INDEX post_lower ON post(lower(slug), \"Title\");",
+        );
+        let anonymized = metadata.anonymized();
+        let sql = anonymized.schema_sql();
+
+        assert_eq!(
+            "CREATE TABLE \"t1\" (\"c1\" INTEGER NOT NULL, \"c2\" INTEGER NOT NULL, \"c3\" TEXT DEFAULT 'untitled', \"c4\" TEXT, PRIMARY KEY (\"c1\"), UNIQUE (\"c3\", \"c4\"), FOREIGN KEY (\"c2\") REFERENCES \"t2\" (\"c1\") ON UPDATE NO ACTION ON DELETE CASCADE);
+CREATE INDEX \"t1_i1\" ON \"t1\"(lower(\"c4\"), \"c3\");
+CREATE UNIQUE INDEX \"t1_i2\" ON \"t1\"(\"c4\") WHERE \"c3\" <> 'draft';
+CREATE INDEX \"t1_i3\" ON \"t1\"(\"c3\", \"c2\");
+CREATE TABLE \"t2\" (\"c1\" INTEGER NOT NULL, \"c2\" TEXT NOT NULL, \"c3\" BLOB, PRIMARY KEY (\"c1\"), UNIQUE (\"c2\"));
+",
+            sql
+        );
+        assert!(!sql.contains("user") && !sql.contains("secret") && !sql.contains("slug"));
+
+        // The anonymized schema parses to the same metadata
+        assert_eq!(anonymized.tables, metadata_from_sql(&sql).tables);
+        assert_eq!(anonymized, anonymized.anonymized());
+    }
+}
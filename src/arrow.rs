@@ -0,0 +1,138 @@
+//! Converts the tables to `arrow` schemas, to export the rows of a table to the Arrow ecosystem
+//!
+//! ```
+//! use arrow::datatypes::{DataType, Schema};
+//! use sqlite_parser::parse_sql;
+//!
+//! let metadata = parse_sql("CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT);");
+//! let schema = Schema::from(metadata.table("user").unwrap());
+//!
+//! assert_eq!(&DataType::Utf8, schema.field_with_name("name").unwrap().data_type());
+//! ```
+//!
+//! The fields are in the order of the columns. Dates are `Date32` and date times and timestamps
+//! are `Timestamp` in microseconds, SQLite stores them as text so they need to be parsed when
+//! the rows are converted. The declared type of a column is in the metadata of its field under
+//! `sqlite.declared_type`, the table name in the metadata of the schema under `sqlite.table_name`.
+
+use std::collections::HashMap;
+
+use ::arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+
+use crate::{Affinity, Column, LogicalType, Metadata, Table, Type};
+
+impl From<&Table> for Schema {
+    fn from(table: &Table) -> Self {
+        Schema::new_with_metadata(
+            table.columns.iter().map(Field::from).collect::<Vec<_>>(),
+            HashMap::from([("sqlite.table_name".to_string(), table.table_name.clone())]),
+        )
+    }
+}
+
+impl From<&Column> for Field {
+    fn from(column: &Column) -> Self {
+        Field::new(&column.name, data_type(column), column.nullable).with_metadata(HashMap::from([
+            (
+                "sqlite.declared_type".to_string(),
+                column.declared_type.clone(),
+            ),
+        ]))
+    }
+}
+
+/// The schemas of the tables, keyed by table name
+pub fn arrow_schemas(metadata: &Metadata) -> HashMap<String, Schema> {
+    metadata
+        .tables
+        .iter()
+        .map(|(name, table)| (name.clone(), Schema::from(table)))
+        .collect()
+}
+
+/// The Arrow type of a column, the logical type decides the type if the column has one
+/// Unknown types are mapped by their affinity, NUMERIC and DECIMAL with a precision up to 38
+/// become `Decimal128`.
+pub fn data_type(column: &Column) -> DataType {
+    match column.logical_type {
+        Some(LogicalType::Boolean) => return DataType::Boolean,
+        Some(LogicalType::Date) => return DataType::Date32,
+        Some(LogicalType::DateTime | LogicalType::Timestamp) => {
+            return DataType::Timestamp(TimeUnit::Microsecond, None)
+        }
+        Some(LogicalType::Json | LogicalType::Uuid) => return DataType::Utf8,
+        None if column.boolean_hint => return DataType::Boolean,
+        None => {}
+    }
+
+    match column.the_type {
+        Type::Integer => DataType::Int64,
+        Type::Real => DataType::Float64,
+        Type::Numeric {
+            precision: Some(precision),
+            scale,
+        } if precision <= 38 => DataType::Decimal128(precision as u8, scale.unwrap_or(0) as i8),
+        Type::Numeric { .. } => DataType::Float64,
+        Type::Text | Type::String => DataType::Utf8,
+        Type::Blob => DataType::Binary,
+        Type::Unknown => match column.affinity() {
+            Affinity::Integer => DataType::Int64,
+            Affinity::Text => DataType::Utf8,
+            Affinity::Real | Affinity::Numeric => DataType::Float64,
+            Affinity::Blob => DataType::Binary,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::arrow::datatypes::{DataType, Schema, TimeUnit};
+
+    use crate::arrow::arrow_schemas;
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_arrow_schema() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (
+                user_id INTEGER NOT NULL PRIMARY KEY,
+                name VARCHAR(100) NOT NULL,
+                balance DECIMAL(10, 2),
+                score REAL,
+                active BOOLEAN,
+                created DATETIME,
+                birthday DATE,
+                photo BLOB
+            );",
+        );
+        let schema = Schema::from(metadata.table("user").unwrap());
+
+        assert_eq!(
+            vec![
+                ("user_id", DataType::Int64, false),
+                ("name", DataType::Utf8, false),
+                ("balance", DataType::Decimal128(10, 2), true),
+                ("score", DataType::Float64, true),
+                ("active", DataType::Boolean, true),
+                (
+                    "created",
+                    DataType::Timestamp(TimeUnit::Microsecond, None),
+                    true
+                ),
+                ("birthday", DataType::Date32, true),
+                ("photo", DataType::Binary, true),
+            ],
+            schema
+                .fields()
+                .iter()
+                .map(|f| (f.name().as_str(), f.data_type().clone(), f.is_nullable()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("user", schema.metadata["sqlite.table_name"]);
+        assert_eq!(
+            "VARCHAR(100)",
+            schema.field(1).metadata()["sqlite.declared_type"]
+        );
+        assert_eq!(schema, arrow_schemas(&metadata)["user"]);
+    }
+}
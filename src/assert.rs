@@ -0,0 +1,190 @@
+//! The implementation of the `assert_schema!` macro
+
+use crate::{Metadata, Type};
+
+/// Asserts that the metadata contains exactly the tables and columns of a compact description
+/// Columns are listed in order as `name: Type` followed by the flags `not_null` and `pk`, columns
+/// without `not_null` are expected to be nullable. Indexes and foreign keys are not checked.
+///
+/// The failure message names the table, column and attribute that differ.
+///
+/// ```
+/// use sqlite_parser::assert_schema;
+/// # use sqlite_parser::builder::{ColumnBuilder, MetadataBuilder, TableBuilder};
+/// # use sqlite_parser::Type;
+/// # let metadata = MetadataBuilder::new()
+/// #     .table(
+/// #         TableBuilder::new("user")
+/// #             .column(ColumnBuilder::new("user_id", Type::Integer).primary_key())
+/// #             .column(ColumnBuilder::new("name", Type::Text)),
+/// #     )
+/// #     .build();
+///
+/// assert_schema!(metadata, {
+///     user {
+///         user_id: Integer, not_null, pk;
+///         name: Text;
+///     }
+/// });
+/// ```
+#[macro_export]
+macro_rules! assert_schema {
+    ($metadata:expr, { $( $table:ident { $( $column:ident : $the_type:ident $(, $flag:ident)* ; )* } )* }) => {
+        $crate::assert::assert_schema(
+            &$metadata,
+            &[$(
+                (
+                    stringify!($table),
+                    &[$(
+                        (
+                            stringify!($column),
+                            $crate::Type::$the_type,
+                            &[$(stringify!($flag)),*][..],
+                        )
+                    ),*][..],
+                )
+            ),*],
+        )
+    };
+}
+
+/// A table name with its columns: name, type and flags
+#[doc(hidden)]
+pub type ExpectedTable<'a> = (&'a str, &'a [(&'a str, Type, &'a [&'a str])]);
+
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_schema(metadata: &Metadata, expected: &[ExpectedTable]) {
+    let mut problems = vec![];
+
+    for table in metadata.sorted_tables() {
+        if !expected.iter().any(|(name, _)| *name == table.table_name) {
+            problems.push(format!("unexpected table {}", table.table_name));
+        }
+    }
+
+    for (table_name, columns) in expected {
+        let table = match metadata.table(table_name) {
+            Some(table) => table,
+            None => {
+                problems.push(format!("missing table {table_name}"));
+
+                continue;
+            }
+        };
+
+        for column in &table.columns {
+            if !columns.iter().any(|(name, _, _)| *name == column.name) {
+                problems.push(format!("{table_name}: unexpected column {}", column.name));
+            }
+        }
+
+        for (position, (column_name, the_type, flags)) in columns.iter().enumerate() {
+            let column = match table.columns.iter().position(|c| c.name == *column_name) {
+                Some(i) if i != position => {
+                    problems.push(format!(
+                        "{table_name}.{column_name}: expected at position {position}, found at {i}"
+                    ));
+
+                    &table.columns[i]
+                }
+                Some(i) => &table.columns[i],
+                None => {
+                    problems.push(format!("{table_name}: missing column {column_name}"));
+
+                    continue;
+                }
+            };
+
+            for flag in *flags {
+                assert!(
+                    matches!(*flag, "not_null" | "pk"),
+                    "Unknown flag {flag} for {table_name}.{column_name}, expected not_null or pk"
+                );
+            }
+
+            let not_null = flags.contains(&"not_null");
+            let pk = flags.contains(&"pk");
+
+            if column.the_type != *the_type {
+                problems.push(format!(
+                    "{table_name}.{column_name}: expected type {the_type}, found {}",
+                    column.the_type
+                ));
+            }
+
+            if column.nullable == not_null {
+                problems.push(format!(
+                    "{table_name}.{column_name}: expected {}, found {}",
+                    nullability(!not_null),
+                    nullability(column.nullable)
+                ));
+            }
+
+            if column.part_of_pk != pk {
+                problems.push(format!(
+                    "{table_name}.{column_name}: expected {}part of the primary key",
+                    if pk { "" } else { "not " }
+                ));
+            }
+        }
+    }
+
+    assert!(
+        problems.is_empty(),
+        "Schema doesn't match:\n{}",
+        problems.join("\n")
+    );
+}
+
+fn nullability(nullable: bool) -> &'static str {
+    if nullable {
+        "nullable"
+    } else {
+        "NOT NULL"
+    }
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_assert_schema() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT);
+        CREATE TABLE post (post_id INTEGER NOT NULL PRIMARY KEY, body TEXT NOT NULL);",
+        );
+
+        assert_schema!(metadata, {
+            user {
+                user_id: Integer, not_null, pk;
+                name: Text;
+            }
+            post {
+                post_id: Integer, not_null, pk;
+                body: Text, not_null;
+            }
+        });
+
+        let message = *std::panic::catch_unwind(|| {
+            assert_schema!(metadata, {
+                user {
+                    user_id: Integer, not_null, pk;
+                    name: Blob, not_null;
+                }
+            });
+        })
+        .unwrap_err()
+        .downcast::<String>()
+        .unwrap();
+
+        assert_eq!(
+            "Schema doesn't match:
+unexpected table post
+user.name: expected type BLOB, found TEXT
+user.name: expected NOT NULL, found nullable",
+            message
+        );
+    }
+}
@@ -0,0 +1,96 @@
+//! The CREATE TABLE statements parsed with `sqlparser`, for the details of the constraints that the
+//! structured model doesn't cover, like the expressions of CHECK constraints and the conflict
+//! clauses
+//!
+//! ```
+//! use sqlite_parser::parse_sql;
+//! use sqlparser::ast::TableConstraint;
+//!
+//! let metadata = parse_sql(
+//!     "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, age INTEGER, CHECK (age >= 0));",
+//! );
+//! let create_table = metadata.table("user").unwrap().create_table_ast().unwrap();
+//!
+//! assert!(matches!(create_table.constraints[0], TableConstraint::Check { .. }));
+//! ```
+//!
+//! The statement is parsed on every call, the AST isn't stored in `Table` so the metadata stays
+//! serializable and comparable without `sqlparser`.
+
+use ::sqlparser::ast::{CreateTable, Statement};
+use ::sqlparser::dialect::SQLiteDialect;
+use ::sqlparser::parser::{Parser, ParserError};
+
+use crate::Table;
+
+impl Table {
+    /// Parses `create_sql` with the SQLite dialect of `sqlparser`
+    /// Fails for the statements `sqlparser` doesn't support, e.g. a COLLATE after another column
+    /// constraint.
+    pub fn ast(&self) -> Result<Statement, ParserError> {
+        let mut statements = Parser::parse_sql(&SQLiteDialect {}, &self.create_sql)?;
+
+        if statements.len() != 1 {
+            return Err(ParserError::ParserError(format!(
+                "expected one statement, found {}",
+                statements.len()
+            )));
+        }
+
+        Ok(statements.remove(0))
+    }
+
+    /// The parsed CREATE TABLE statement, `None` if it can't be parsed or is a CREATE VIRTUAL TABLE
+    pub fn create_table_ast(&self) -> Option<CreateTable> {
+        match self.ast().ok()? {
+            Statement::CreateTable(create_table) => Some(create_table),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::sqlparser::ast::{ColumnOption, Statement, TableConstraint};
+
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_ast() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (
+                user_id INTEGER NOT NULL PRIMARY KEY,
+                email TEXT COLLATE NOCASE NOT NULL,
+                CONSTRAINT email_check CHECK (email LIKE '%@%')
+            ) STRICT;
+            CREATE VIRTUAL TABLE user_fts USING fts5(email);",
+        );
+        let create_table = metadata.table("user").unwrap().create_table_ast().unwrap();
+
+        assert!(create_table.strict);
+        assert_eq!(
+            "NOCASE",
+            create_table.columns[1]
+                .collation
+                .as_ref()
+                .unwrap()
+                .to_string()
+        );
+        assert!(create_table.columns[1]
+            .options
+            .iter()
+            .any(|o| o.option == ColumnOption::NotNull));
+        assert!(matches!(
+            &create_table.constraints[0],
+            TableConstraint::Check { name: Some(name), .. } if name.value == "email_check"
+        ));
+
+        let fts = metadata.table("user_fts").unwrap();
+
+        assert!(matches!(
+            fts.ast(),
+            Ok(Statement::CreateVirtualTable { .. })
+        ));
+        assert_eq!(None, fts.create_table_ast());
+    }
+}
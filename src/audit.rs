@@ -0,0 +1,162 @@
+//! Compares the declared types of the columns with the types that are actually stored
+//! SQLite is dynamically typed, so a column declared as INTEGER can hold TEXT. Such values block
+//! converting the table to a STRICT table.
+
+use rusqlite::Connection;
+
+use crate::{quote_identifier, Metadata, Table, Type};
+
+/// The storage class of a value, as returned by `typeof()`
+#[derive(Debug, PartialEq, Copy, Clone, Eq)]
+pub enum StoredType {
+    Integer,
+    Real,
+    Text,
+    Blob,
+}
+
+impl StoredType {
+    pub(crate) fn from_str(s: &str) -> Option<Self> {
+        match s {
+            "integer" => Some(Self::Integer),
+            "real" => Some(Self::Real),
+            "text" => Some(Self::Text),
+            "blob" => Some(Self::Blob),
+            _ => None,
+        }
+    }
+
+    /// The storage class a value of a column with the declared type should have
+    /// Unknown types are expected to be BLOB. Numeric
+    /// types are expected to be REAL, INTEGER is accepted too since SQLite stores whole numbers
+    /// as INTEGER.
+    pub fn expected(the_type: Type) -> Self {
+        match the_type {
+            Type::Text | Type::String => Self::Text,
+            Type::Integer => Self::Integer,
+            Type::Real | Type::Numeric { .. } => Self::Real,
+            Type::Blob | Type::Unknown => Self::Blob,
+        }
+    }
+}
+
+/// Values of a column that are stored with a different type than the declared type
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct TypeMismatch {
+    pub table: String,
+    pub column: String,
+    pub declared: Type,
+    pub stored: StoredType,
+    /// The amount of sampled values that are stored with the `stored` type
+    pub count: u64,
+}
+
+/// Audits the first `sample_size` rows of every table, NULL values are ignored
+/// The mismatches are sorted by table name and column id
+pub fn type_audit(
+    connection: &Connection,
+    metadata: &Metadata,
+    sample_size: usize,
+) -> Vec<TypeMismatch> {
+    let tables = metadata.sorted_tables();
+
+    tables
+        .into_iter()
+        .flat_map(|t| audit_table(connection, t, sample_size))
+        .collect()
+}
+
+/// Audits the first `sample_size` rows of a single table
+pub fn audit_table(
+    connection: &Connection,
+    table: &Table,
+    sample_size: usize,
+) -> Vec<TypeMismatch> {
+    let mut mismatches = vec![];
+
+    // The declared type of unknown types is not known, and columns without a declared type
+    // accept every storage class, so their values can't mismatch
+    for column in table
+        .columns
+        .iter()
+        .filter(|c| c.the_type != Type::Unknown && !c.declared_type.is_empty())
+    {
+        let c = quote_identifier(&column.name);
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT typeof({c}), COUNT(*) FROM (SELECT {c} FROM {} LIMIT {sample_size}) GROUP BY 1 ORDER BY 1;",
+                quote_identifier(&table.table_name)
+            ))
+            .unwrap();
+        let mut rows = stmt.query([]).unwrap();
+
+        while let Some(row) = rows.next().unwrap() {
+            let stored = match StoredType::from_str(&row.get::<_, String>(0).unwrap()) {
+                Some(stored) => stored,
+                // NULL
+                None => continue,
+            };
+
+            let whole_number =
+                matches!(column.the_type, Type::Numeric { .. }) && stored == StoredType::Integer;
+
+            if whole_number || stored == StoredType::expected(column.the_type) {
+                continue;
+            }
+
+            mismatches.push(TypeMismatch {
+                table: table.table_name.clone(),
+                column: column.name.clone(),
+                declared: column.the_type,
+                stored,
+                count: row.get(1).unwrap(),
+            });
+        }
+    }
+
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::audit::{type_audit, StoredType, TypeMismatch};
+    use crate::tests::metadata_from_sql;
+    use crate::Type;
+
+    #[test]
+    fn test_type_audit() {
+        let sql = "CREATE TABLE user (
+            user_id INTEGER NOT NULL PRIMARY KEY,
+            age INTEGER,
+            score REAL
+        );
+        CREATE TABLE kv (k, v);
+        INSERT INTO user VALUES (1, 20, 1), (2, 'unknown', 2.5), (3, NULL, 3), (4, x'00', 'high');
+        INSERT INTO kv VALUES ('a', 1), (x'00', 2.5);";
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection.execute_batch(sql).unwrap();
+
+        let metadata = metadata_from_sql(sql);
+        let mismatches = type_audit(&connection, &metadata, 100);
+        let mismatch = |column: &str, declared, stored| TypeMismatch {
+            table: "user".to_string(),
+            column: column.to_string(),
+            declared,
+            stored,
+            count: 1,
+        };
+
+        assert_eq!(
+            vec![
+                mismatch("age", Type::Integer, StoredType::Blob),
+                mismatch("age", Type::Integer, StoredType::Text),
+                mismatch("score", Type::Real, StoredType::Text),
+            ],
+            mismatches
+        );
+        assert!(type_audit(&connection, &metadata, 1).is_empty());
+    }
+}
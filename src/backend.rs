@@ -0,0 +1,835 @@
+//! The querying layer behind the parser, implement `IntrospectionBackend` to read the schema from
+//! somewhere else than a SQLite connection (e.g. a remote database or a mock in tests)
+
+#[cfg(feature = "rusqlite")]
+use std::cell::OnceCell;
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(feature = "rusqlite")]
+use rusqlite::{Connection, ToSql};
+
+use crate::check::{IntegrityCheck, IntegrityReport};
+use crate::database::DatabaseInfo;
+use crate::ddl::{
+    automatic_indexes, canonical_sql, check_in_values, column_comments, generated_index_sql,
+};
+#[cfg(feature = "rusqlite")]
+use crate::introspection::Schema;
+use crate::profile::TableProfile;
+use crate::report::{ParseReport, ParseWarning, StrictError};
+use crate::sample::SampleRow;
+use crate::statistics::IndexStatistics;
+use crate::storage::StorageStats;
+use crate::trigger::Trigger;
+use crate::view::View;
+#[cfg(feature = "rusqlite")]
+use crate::Parser;
+use crate::{
+    AutomaticIndex, Column, ForeignKey, Index, Metadata, ParseMode, ParseOptions, RowCount, Table,
+};
+
+/// Provides the schema information that `parse_backend` assembles into `Metadata`
+/// The methods that read data have a default implementation for backends that can't read data,
+/// the options that need them are ignored
+/// Anomalies in the schema that can be recovered from are added to the warnings
+pub trait IntrospectionBackend {
+    /// The names of the tables to parse
+    fn table_names(&self) -> Vec<String>;
+
+    /// The CREATE TABLE statement as stored in `sqlite_master`
+    fn create_sql(&self, table_name: &str) -> String;
+
+    /// The columns, ordered by id
+    fn columns(&self, table_name: &str, warnings: &mut Vec<ParseWarning>) -> Vec<Column>;
+
+    /// The foreign keys, ordered by id
+    fn foreign_keys(&self, table_name: &str, warnings: &mut Vec<ParseWarning>) -> Vec<ForeignKey>;
+
+    /// The indexes, without the automatic indexes of primary and foreign keys
+    fn indexes(
+        &self,
+        table_name: &str,
+        columns: &[Column],
+        foreign_keys: &[ForeignKey],
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Vec<Index>;
+
+    /// The indexes of the PRIMARY KEY and UNIQUE constraints, derived from `create_sql` by default
+    fn automatic_indexes(&self, table_name: &str, columns: &[Column]) -> Vec<AutomaticIndex> {
+        automatic_indexes(table_name, &self.create_sql(table_name), columns)
+    }
+
+    /// The CREATE INDEX statements as stored in `sqlite_master` keyed by index name, `None` if the
+    /// backend can't read them, then they are generated from the indexes
+    fn index_sql(&self, _table_name: &str) -> Option<BTreeMap<String, String>> {
+        None
+    }
+
+    fn database_info(&self) -> DatabaseInfo;
+
+    /// The triggers, sorted by name, empty if the backend can't read triggers
+    fn triggers(&self) -> Vec<Trigger> {
+        vec![]
+    }
+
+    /// The views, sorted by name, empty if the backend can't read views
+    fn views(&self) -> Vec<View> {
+        vec![]
+    }
+
+    /// The amount of rows, `None` if the backend can't count rows
+    fn row_count(&self, _table_name: &str, _row_count: RowCount) -> Option<u64> {
+        None
+    }
+
+    /// The value of the table in `sqlite_sequence`, `None` if the backend can't read data
+    fn sequence(&self, _table_name: &str) -> Option<i64> {
+        None
+    }
+
+    /// The statistics of the indexes, keyed by index name
+    fn index_statistics(&self) -> HashMap<String, IndexStatistics> {
+        HashMap::new()
+    }
+
+    /// The storage statistics of the tables and indexes, keyed by name
+    fn storage_stats(&self) -> Option<HashMap<String, StorageStats>> {
+        None
+    }
+
+    fn sample_rows(&self, _table: &Table, _n: usize) -> Option<Vec<SampleRow>> {
+        None
+    }
+
+    fn profile_table(&self, _table: &Table) -> Option<TableProfile> {
+        None
+    }
+
+    /// The INTEGER columns that only store 0, 1 and NULL in the first `n` rows
+    fn boolean_columns(&self, _table: &Table, _n: usize) -> Option<Vec<String>> {
+        None
+    }
+
+    fn integrity_check(&self, _check: IntegrityCheck) -> Option<IntegrityReport> {
+        None
+    }
+
+    /// Called before a table is parsed
+    fn progress(&self, _tables_done: usize, _tables_total: usize, _current_table: &str) {}
+
+    /// Calls the parse, e.g. in a read transaction so the metadata is of one point in time when
+    /// the database is modified concurrently
+    fn snapshot(&self, parse: &mut dyn FnMut()) {
+        parse()
+    }
+}
+
+/// Parses the schema provided by the backend
+/// Panics on an anomaly in `ParseMode::Strict`
+pub fn parse_backend<B: IntrospectionBackend + ?Sized>(
+    backend: &B,
+    options: ParseOptions,
+) -> Metadata {
+    parse_backend_report(backend, options)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .metadata
+}
+
+/// Same as `parse_backend`, with the anomalies that were recovered from
+/// Returns an error on an anomaly in `ParseMode::Strict`
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+pub fn parse_backend_report<B: IntrospectionBackend + ?Sized>(
+    backend: &B,
+    options: ParseOptions,
+) -> Result<ParseReport, StrictError> {
+    let mut report = None;
+
+    backend.snapshot(&mut || report = Some(parse_snapshot(backend, &options)));
+
+    report.unwrap()
+}
+
+fn parse_snapshot<B: IntrospectionBackend + ?Sized>(
+    backend: &B,
+    options: &ParseOptions,
+) -> Result<ParseReport, StrictError> {
+    let integrity = options
+        .integrity_check
+        .and_then(|check| backend.integrity_check(check));
+    let database = backend.database_info();
+    let storage_stats = if options.storage_stats {
+        backend.storage_stats()
+    } else {
+        None
+    };
+    let index_statistics = backend.index_statistics();
+    let triggers = backend.triggers();
+    let views = backend.views();
+    let table_names = backend.table_names();
+    let mut tables = vec![];
+    let mut warnings = vec![];
+
+    for (i, table_name) in table_names.iter().enumerate() {
+        backend.progress(i, table_names.len(), table_name);
+
+        tables.push(parse_backend_table(
+            backend,
+            table_name,
+            options,
+            &index_statistics,
+            storage_stats.as_ref(),
+            &mut warnings,
+        ));
+    }
+
+    // The referenced columns get the comments and enum values of the referenced table, if it
+    // exists
+    let comments = tables
+        .iter()
+        .map(|t| {
+            (
+                t.table_name.to_lowercase(),
+                (
+                    column_comments(&t.create_sql),
+                    check_in_values(&t.create_sql),
+                ),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+
+    for table in &mut tables {
+        for foreign_key in &mut table.foreign_keys {
+            if let Some((comments, enum_values)) = comments.get(&foreign_key.table.to_lowercase()) {
+                set_comments(comments, &mut foreign_key.to_column);
+                set_enum_values(enum_values, &mut foreign_key.to_column);
+            }
+        }
+    }
+
+    if let Some(n) = options.detect_booleans {
+        for table in &mut tables {
+            let Some(boolean_columns) = backend.boolean_columns(table, n) else {
+                continue;
+            };
+
+            for column in &mut table.columns {
+                column.boolean_hint = boolean_columns.contains(&column.name);
+            }
+        }
+    }
+
+    let samples = match options.samples {
+        Some(n) => tables
+            .iter()
+            .filter_map(|t| Some((t.table_name.clone(), backend.sample_rows(t, n)?)))
+            .collect(),
+        None => HashMap::new(),
+    };
+    let profiles = if options.profile {
+        tables
+            .iter()
+            .filter_map(|t| Some((t.table_name.clone(), backend.profile_table(t)?)))
+            .collect()
+    } else {
+        HashMap::new()
+    };
+
+    let mut metadata = Metadata {
+        tables: tables
+            .into_iter()
+            .map(|t| (t.table_name.clone(), t))
+            .collect(),
+        samples,
+        profiles,
+        integrity,
+        database,
+        lookup: options.lookup,
+        triggers,
+        views,
+        annotations: Default::default(),
+    };
+
+    metadata.analyze_dependencies();
+
+    if let Some(annotations) = &options.annotations {
+        warnings.extend(metadata.annotate(annotations));
+    }
+
+    if options.mode == ParseMode::Strict && !warnings.is_empty() {
+        return Err(StrictError { warnings });
+    }
+
+    Ok(ParseReport { metadata, warnings })
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        level = "debug",
+        skip(backend, options, index_statistics, storage_stats, warnings)
+    )
+)]
+fn parse_backend_table<B: IntrospectionBackend + ?Sized>(
+    backend: &B,
+    table_name: &str,
+    options: &ParseOptions,
+    index_statistics: &HashMap<String, IndexStatistics>,
+    storage_stats: Option<&HashMap<String, StorageStats>>,
+    warnings: &mut Vec<ParseWarning>,
+) -> Table {
+    let mut columns = backend.columns(table_name, warnings);
+    let mut foreign_keys = backend.foreign_keys(table_name, warnings);
+    let create_sql = backend.create_sql(table_name);
+    let comments = column_comments(&create_sql);
+    let enum_values = check_in_values(&create_sql);
+
+    set_comments(&comments, &mut columns);
+    set_enum_values(&enum_values, &mut columns);
+
+    for foreign_key in &mut foreign_keys {
+        set_comments(&comments, &mut foreign_key.from_column);
+        set_enum_values(&enum_values, &mut foreign_key.from_column);
+    }
+
+    if let Some(type_mapper) = &options.type_mapper {
+        type_mapper.map_columns(table_name, &mut columns, warnings);
+
+        // The columns of a foreign key are copies, the warnings are reported by their own table
+        for foreign_key in &mut foreign_keys {
+            type_mapper.map_columns(table_name, &mut foreign_key.from_column, &mut vec![]);
+            type_mapper.map_columns(&foreign_key.table, &mut foreign_key.to_column, &mut vec![]);
+        }
+    }
+
+    let mut indexes = backend.indexes(table_name, &columns, &foreign_keys, warnings);
+    let mut automatic_indexes = backend.automatic_indexes(table_name, &columns);
+    let mut storage = None;
+
+    for index in &mut indexes {
+        index.statistics = index_statistics.get(&index.name).cloned();
+    }
+
+    for index in &mut automatic_indexes {
+        index.statistics = index_statistics.get(&index.name).cloned();
+    }
+
+    if let Some(storage_stats) = storage_stats {
+        storage = storage_stats.get(table_name).cloned();
+
+        for index in &mut indexes {
+            index.storage = storage_stats.get(&index.name).cloned();
+        }
+
+        for index in &mut automatic_indexes {
+            index.storage = storage_stats.get(&index.name).cloned();
+        }
+    }
+
+    let mut table = Table {
+        table_name: table_name.to_string(),
+        columns,
+        foreign_keys,
+        indexes,
+        automatic_indexes,
+        row_count: options
+            .row_counts
+            .and_then(|r| backend.row_count(table_name, r)),
+        sequence: options
+            .sequences
+            .then(|| backend.sequence(table_name))
+            .flatten(),
+        storage,
+        create_sql: if options.canonical_sql {
+            canonical_sql(&create_sql)
+        } else {
+            create_sql
+        },
+        index_sql: BTreeMap::new(),
+        lookup: options.lookup,
+    };
+
+    table.index_sql = backend
+        .index_sql(table_name)
+        .unwrap_or_else(|| generated_index_sql(&table));
+
+    table
+}
+
+fn set_comments(comments: &HashMap<String, String>, columns: &mut [Column]) {
+    for column in columns {
+        column.comment = comments.get(&column.name.to_lowercase()).cloned();
+    }
+}
+
+fn set_enum_values(enum_values: &HashMap<String, Vec<String>>, columns: &mut [Column]) {
+    for column in columns {
+        column.enum_values = enum_values.get(&column.name.to_lowercase()).cloned();
+    }
+}
+
+/// The default backend, queries a SQLite connection
+#[cfg(feature = "rusqlite")]
+pub struct RusqliteBackend<'a> {
+    connection: &'a Connection,
+    tables_query: &'a str,
+    tables_params: &'a [&'a dyn ToSql],
+    /// The hooks of the parser, used instead of the default queries
+    parser: Option<&'a dyn Parser>,
+    /// The columns, foreign keys and indexes of all tables, `None` if they are queried per table
+    schema: Option<OnceCell<Schema>>,
+    /// Limits the triggers, statistics and storage to the table, see `with_table`
+    table: Option<&'a str>,
+}
+
+#[cfg(feature = "rusqlite")]
+impl<'a> RusqliteBackend<'a> {
+    /// Parses all tables, like the default `Parser::query_all_tables`
+    /// The columns, foreign keys and indexes of all tables are read with a query per pragma,
+    /// instead of queries per table
+    pub fn new(connection: &'a Connection) -> Self {
+        Self {
+            schema: Some(OnceCell::new()),
+            ..Self::with_tables_query(connection, crate::DEFAULT_TABLES_QUERY, &[])
+        }
+    }
+
+    /// Only parses the tables selected by the query, the first column must be the table name
+    /// The schema is queried per table, which is faster when only a few tables are selected
+    pub fn with_tables_query(
+        connection: &'a Connection,
+        tables_query: &'a str,
+        tables_params: &'a [&'a dyn ToSql],
+    ) -> Self {
+        Self {
+            connection,
+            tables_query,
+            tables_params,
+            parser: None,
+            schema: None,
+            table: None,
+        }
+    }
+
+    pub(crate) fn with_parser(mut self, parser: &'a dyn Parser) -> Self {
+        self.parser = Some(parser);
+
+        self
+    }
+
+    /// Only reads the triggers, index statistics and storage of the table, and skips the views
+    /// since they don't belong to a table
+    pub(crate) fn with_table(mut self, table_name: &'a str) -> Self {
+        self.table = Some(table_name);
+
+        self
+    }
+
+    fn schema(&self) -> Option<&Schema> {
+        self.schema
+            .as_ref()
+            .map(|s| s.get_or_init(|| Schema::query(self.connection)))
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl IntrospectionBackend for RusqliteBackend<'_> {
+    fn table_names(&self) -> Vec<String> {
+        let mut stmt = self.connection.prepare(self.tables_query).unwrap();
+        let mut rows = stmt.query(self.tables_params).unwrap();
+        let mut names = vec![];
+
+        while let Some(row) = rows.next().unwrap() {
+            names.push(row.get(0).unwrap());
+        }
+
+        names
+    }
+
+    fn create_sql(&self, table_name: &str) -> String {
+        self.connection
+            .query_row(
+                "SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?;",
+                [table_name],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    fn columns(&self, table_name: &str, warnings: &mut Vec<ParseWarning>) -> Vec<Column> {
+        if let Some(parser) = self.parser {
+            return parser.query_columns(self.connection, table_name, warnings);
+        }
+
+        match self.schema() {
+            Some(schema) => schema.columns(table_name, warnings),
+            None => crate::query_columns(self.connection, table_name, warnings),
+        }
+    }
+
+    fn foreign_keys(&self, table_name: &str, warnings: &mut Vec<ParseWarning>) -> Vec<ForeignKey> {
+        if let Some(parser) = self.parser {
+            return parser.query_foreign_keys(self.connection, table_name, warnings);
+        }
+
+        match self.schema() {
+            Some(schema) => schema.foreign_keys(table_name, warnings),
+            None => crate::query_fk(self.connection, table_name, warnings),
+        }
+    }
+
+    fn indexes(
+        &self,
+        table_name: &str,
+        columns: &[Column],
+        foreign_keys: &[ForeignKey],
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Vec<Index> {
+        if let Some(parser) = self.parser {
+            return parser.query_indexes(
+                self.connection,
+                table_name,
+                columns,
+                foreign_keys,
+                warnings,
+            );
+        }
+
+        match self.schema() {
+            Some(schema) => schema.indexes(table_name, columns, foreign_keys, warnings),
+            None => {
+                crate::query_indexes(self.connection, table_name, columns, foreign_keys, warnings)
+            }
+        }
+    }
+
+    fn automatic_indexes(&self, table_name: &str, columns: &[Column]) -> Vec<AutomaticIndex> {
+        match self.schema() {
+            Some(schema) if self.parser.is_none() => schema.automatic_indexes(table_name, columns),
+            _ => crate::query_automatic_indexes(self.connection, table_name, columns),
+        }
+    }
+
+    fn index_sql(&self, table_name: &str) -> Option<BTreeMap<String, String>> {
+        Some(crate::query_index_sql(self.connection, table_name))
+    }
+
+    fn database_info(&self) -> DatabaseInfo {
+        crate::database::query_database_info(self.connection)
+    }
+
+    fn triggers(&self) -> Vec<Trigger> {
+        crate::trigger::query_triggers(self.connection, self.table)
+    }
+
+    fn views(&self) -> Vec<View> {
+        match self.table {
+            Some(_) => vec![],
+            None => crate::view::query_views(self.connection),
+        }
+    }
+
+    fn row_count(&self, table_name: &str, row_count: RowCount) -> Option<u64> {
+        Some(crate::query_row_count(
+            self.connection,
+            table_name,
+            row_count,
+        ))
+    }
+
+    fn sequence(&self, table_name: &str) -> Option<i64> {
+        crate::query_sequence(self.connection, table_name)
+    }
+
+    fn index_statistics(&self) -> HashMap<String, IndexStatistics> {
+        crate::statistics::query_index_statistics(self.connection, self.table)
+    }
+
+    fn storage_stats(&self) -> Option<HashMap<String, StorageStats>> {
+        crate::storage::query_storage_stats(self.connection, self.table)
+    }
+
+    fn sample_rows(&self, table: &Table, n: usize) -> Option<Vec<SampleRow>> {
+        Some(crate::sample::sample_rows(self.connection, table, n))
+    }
+
+    fn profile_table(&self, table: &Table) -> Option<TableProfile> {
+        Some(crate::profile::profile_table(self.connection, table))
+    }
+
+    fn boolean_columns(&self, table: &Table, n: usize) -> Option<Vec<String>> {
+        Some(crate::inference::boolean_columns(self.connection, table, n))
+    }
+
+    fn integrity_check(&self, check: IntegrityCheck) -> Option<IntegrityReport> {
+        Some(crate::check::integrity_check(self.connection, check))
+    }
+
+    fn progress(&self, tables_done: usize, tables_total: usize, current_table: &str) {
+        if let Some(parser) = self.parser {
+            parser.progress(tables_done, tables_total, current_table);
+        }
+    }
+
+    /// Parses in a read transaction, unless the connection is already in a transaction
+    /// In WAL mode, writes of other connections during the parse are not seen.
+    fn snapshot(&self, parse: &mut dyn FnMut()) {
+        if !self.connection.is_autocommit() {
+            return parse();
+        }
+
+        self.connection.execute_batch("BEGIN DEFERRED;").unwrap();
+
+        let _transaction = ReadTransaction(self.connection);
+
+        // A deferred transaction starts at the first read
+        self.connection
+            .query_row("SELECT COUNT(*) FROM sqlite_master;", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .unwrap();
+
+        parse()
+    }
+}
+
+/// Ends the read transaction of `RusqliteBackend::snapshot`, also when the parse panics
+#[cfg(feature = "rusqlite")]
+struct ReadTransaction<'a>(&'a Connection);
+
+#[cfg(feature = "rusqlite")]
+impl Drop for ReadTransaction<'_> {
+    fn drop(&mut self) {
+        let _ = self.0.execute_batch("COMMIT;");
+    }
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::backend::{
+        parse_backend, parse_backend_report, IntrospectionBackend, RusqliteBackend,
+    };
+    use crate::builder::{ColumnBuilder, TableBuilder};
+    use crate::database::DatabaseInfo;
+    use crate::report::ParseWarning;
+    use crate::{Column, ForeignKey, Index, ParseOptions, RowCount, Table, Type};
+
+    /// A backend that serves a fixed table
+    struct Mock(Table);
+
+    impl IntrospectionBackend for Mock {
+        fn table_names(&self) -> Vec<String> {
+            vec![self.0.table_name.clone()]
+        }
+
+        fn create_sql(&self, _table_name: &str) -> String {
+            self.0.create_sql.clone()
+        }
+
+        fn columns(&self, _: &str, _: &mut Vec<ParseWarning>) -> Vec<Column> {
+            self.0.columns.clone()
+        }
+
+        fn foreign_keys(&self, _: &str, _: &mut Vec<ParseWarning>) -> Vec<ForeignKey> {
+            self.0.foreign_keys.clone()
+        }
+
+        fn indexes(
+            &self,
+            _: &str,
+            _: &[Column],
+            _: &[ForeignKey],
+            _: &mut Vec<ParseWarning>,
+        ) -> Vec<Index> {
+            self.0.indexes.clone()
+        }
+
+        fn database_info(&self) -> DatabaseInfo {
+            DatabaseInfo::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_backend() {
+        let table = TableBuilder::new("user")
+            .column(ColumnBuilder::new("user_id", Type::Integer).primary_key())
+            .column(ColumnBuilder::new("parent_id", Type::Integer))
+            .foreign_key(&["parent_id"], "user", &["user_id"])
+            .index("user_parent_id", &["parent_id"], false)
+            .build();
+        let metadata = parse_backend(
+            &Mock(table.clone()),
+            ParseOptions {
+                row_counts: Some(RowCount::Exact),
+                samples: Some(10),
+                profile: true,
+                ..Default::default()
+            },
+        );
+
+        // The options that read data are ignored
+        assert_eq!(Some(&table), metadata.table("user"));
+        assert!(metadata.samples.is_empty());
+        assert!(metadata.profiles.is_empty());
+    }
+
+    #[test]
+    fn test_schema_queried_per_table_and_at_once() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "PRAGMA foreign_keys = OFF;
+            CREATE TABLE user (
+                user_id INTEGER NOT NULL,
+                tenant_id INTEGER NOT NULL,
+                email TEXT NOT NULL UNIQUE,
+                name DECIMAL(1.5),
+                PRIMARY KEY (tenant_id, user_id)
+            );
+            CREATE TABLE post (
+                post_id INTEGER NOT NULL PRIMARY KEY,
+                user_id INTEGER NOT NULL,
+                tenant_id INTEGER NOT NULL,
+                title TEXT UNIQUE,
+                parent_id INTEGER REFERENCES missing,
+                FOREIGN KEY (tenant_id, user_id) REFERENCES USER
+            );
+            CREATE INDEX post_lower_title ON post(lower(title), user_id);
+            CREATE INDEX post_user_id ON post(user_id);
+            CREATE UNIQUE INDEX post_user_id_2 ON post(user_id);",
+            )
+            .unwrap();
+
+        let per_table =
+            RusqliteBackend::with_tables_query(&connection, crate::DEFAULT_TABLES_QUERY, &[]);
+        let report =
+            parse_backend_report(&RusqliteBackend::new(&connection), Default::default()).unwrap();
+
+        assert_eq!(
+            parse_backend_report(&per_table, Default::default()).unwrap(),
+            report
+        );
+        // The unknown type, the missing table, the expression and the two duplicates
+        assert_eq!(5, report.warnings.len());
+
+        let foreign_key = &report.metadata.table("post").unwrap().foreign_keys[0];
+
+        // The primary key of the referenced table, in primary key order
+        assert_eq!(
+            vec!["tenant_id", "user_id"],
+            foreign_key
+                .to_column
+                .iter()
+                .map(|c| &c.name)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_tracing_spans() {
+        use std::sync::{Arc, Mutex};
+
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        /// Records the names of the created spans
+        struct Spans(Arc<Mutex<Vec<&'static str>>>);
+
+        impl Subscriber for Spans {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                let mut spans = self.0.lock().unwrap();
+
+                spans.push(span.metadata().name());
+
+                Id::from_u64(spans.len() as u64)
+            }
+
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+            fn event(&self, _: &Event<'_>) {}
+
+            fn enter(&self, _: &Id) {}
+
+            fn exit(&self, _: &Id) {}
+        }
+
+        let spans = Arc::new(Mutex::new(vec![]));
+
+        tracing::subscriber::with_default(Spans(spans.clone()), || {
+            crate::parse_sql("CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY);")
+        });
+
+        let spans = spans.lock().unwrap();
+
+        for name in [
+            "parse_backend_report",
+            "parse_backend_table",
+            "query_schema",
+        ] {
+            assert!(spans.contains(&name), "{name} not in {spans:?}");
+        }
+    }
+
+    #[test]
+    fn test_snapshot() {
+        use crate::{Metadata, Parser};
+
+        /// Inserts a row with another connection before the first table is parsed
+        struct Writer(Connection);
+
+        impl Parser for Writer {
+            fn progress(&self, tables_done: usize, _: usize, _: &str) {
+                if tables_done == 0 {
+                    self.0.execute_batch("INSERT INTO b VALUES (1);").unwrap();
+                }
+            }
+
+            fn process_tables(&mut self, _: Metadata) {}
+        }
+
+        let path = std::env::temp_dir().join("sqlite_parser_snapshot.sqlite3");
+        let _ = std::fs::remove_file(&path);
+        let connection = Connection::open(&path).unwrap();
+
+        connection
+            .execute_batch(
+                "PRAGMA journal_mode = WAL;
+                CREATE TABLE a (id INTEGER PRIMARY KEY);
+                CREATE TABLE b (id INTEGER PRIMARY KEY);",
+            )
+            .unwrap();
+
+        let writer = Writer(Connection::open(&path).unwrap());
+        let options = ParseOptions {
+            row_counts: Some(RowCount::Exact),
+            ..Default::default()
+        };
+        let row_count = |backend: &RusqliteBackend| {
+            parse_backend(backend, options.clone())
+                .table("b")
+                .unwrap()
+                .row_count
+        };
+
+        // The insert is after the start of the parse
+        assert_eq!(
+            Some(0),
+            row_count(&RusqliteBackend::new(&connection).with_parser(&writer))
+        );
+        assert!(connection.is_autocommit());
+        assert_eq!(Some(1), row_count(&RusqliteBackend::new(&connection)));
+
+        drop(connection);
+        drop(writer);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,363 @@
+//! Builders to construct metadata by hand, e.g. as the expected value in tests
+//!
+//! ```
+//! use sqlite_parser::builder::{ColumnBuilder, MetadataBuilder, TableBuilder};
+//! use sqlite_parser::Type;
+//!
+//! let metadata = MetadataBuilder::new()
+//!     .table(
+//!         TableBuilder::new("user")
+//!             .column(ColumnBuilder::new("user_id", Type::Integer).primary_key())
+//!             .column(ColumnBuilder::new("parent_id", Type::Integer))
+//!             .foreign_key(&["parent_id"], "user", &["user_id"]),
+//!     )
+//!     .build();
+//!
+//! assert_eq!("user", metadata.table("user").unwrap().foreign_keys[0].table);
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::database::DatabaseInfo;
+use crate::ddl::{automatic_indexes, generated_index_sql};
+use crate::{Column, ForeignKey, Index, LookupMode, Metadata, OnUpdateAndDelete, Table, Type};
+
+/// Builds a `Column`, by default the column is nullable, not part of the primary key and has no
+/// default value
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct ColumnBuilder {
+    column: Column,
+}
+
+impl ColumnBuilder {
+    pub fn new(name: &str, the_type: Type) -> Self {
+        Self {
+            column: Column {
+                id: 0,
+                name: name.to_string(),
+                the_type,
+                declared_type: the_type.to_string(),
+                nullable: true,
+                part_of_pk: false,
+                default_value: None,
+                boolean_hint: false,
+                logical_type: None,
+                extension: None,
+                comment: None,
+                enum_values: None,
+            },
+        }
+    }
+
+    /// Only needed when building a column without a `TableBuilder`, which sets the id to the
+    /// position of the column
+    pub fn id(mut self, id: i32) -> Self {
+        self.column.id = id;
+        self
+    }
+
+    pub fn not_null(mut self) -> Self {
+        self.column.nullable = false;
+        self
+    }
+
+    /// Makes the column part of the primary key and NOT NULL
+    pub fn primary_key(mut self) -> Self {
+        self.column.part_of_pk = true;
+        self.column.nullable = false;
+        self
+    }
+
+    /// The default value as SQL, e.g. `'text'` or `0`
+    pub fn default_value(mut self, default_value: &str) -> Self {
+        self.column.default_value = Some(default_value.to_string());
+        self
+    }
+
+    pub fn build(self) -> Column {
+        self.column
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Eq)]
+struct PendingForeignKey {
+    from_column: Vec<String>,
+    table: String,
+    to_column: Vec<String>,
+    on_update: OnUpdateAndDelete,
+    on_delete: OnUpdateAndDelete,
+}
+
+/// Builds a `Table`, columns and indexes refer to the columns by name
+/// Foreign keys get their id in the order they are added. Note: SQLite numbers the foreign keys
+/// in reverse order of declaration.
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct TableBuilder {
+    table_name: String,
+    columns: Vec<Column>,
+    foreign_keys: Vec<PendingForeignKey>,
+    indexes: Vec<(String, Vec<String>, bool)>,
+    row_count: Option<u64>,
+    sequence: Option<i64>,
+    create_sql: String,
+}
+
+impl TableBuilder {
+    pub fn new(table_name: &str) -> Self {
+        Self {
+            table_name: table_name.to_string(),
+            columns: vec![],
+            foreign_keys: vec![],
+            indexes: vec![],
+            row_count: None,
+            sequence: None,
+            create_sql: String::new(),
+        }
+    }
+
+    /// Adds a column, the id is the position of the column
+    pub fn column(mut self, column: ColumnBuilder) -> Self {
+        let id = self.columns.len() as i32;
+
+        self.columns.push(column.id(id).build());
+        self
+    }
+
+    /// Adds a foreign key with `NO ACTION` as actions
+    pub fn foreign_key(self, from_column: &[&str], table: &str, to_column: &[&str]) -> Self {
+        self.foreign_key_with_actions(
+            from_column,
+            table,
+            to_column,
+            OnUpdateAndDelete::NoAction,
+            OnUpdateAndDelete::NoAction,
+        )
+    }
+
+    pub fn foreign_key_with_actions(
+        mut self,
+        from_column: &[&str],
+        table: &str,
+        to_column: &[&str],
+        on_update: OnUpdateAndDelete,
+        on_delete: OnUpdateAndDelete,
+    ) -> Self {
+        self.foreign_keys.push(PendingForeignKey {
+            from_column: from_column.iter().map(|c| c.to_string()).collect(),
+            table: table.to_string(),
+            to_column: to_column.iter().map(|c| c.to_string()).collect(),
+            on_update,
+            on_delete,
+        });
+        self
+    }
+
+    pub fn index(mut self, name: &str, columns: &[&str], unique: bool) -> Self {
+        self.indexes.push((
+            name.to_string(),
+            columns.iter().map(|c| c.to_string()).collect(),
+            unique,
+        ));
+        self
+    }
+
+    pub fn row_count(mut self, row_count: u64) -> Self {
+        self.row_count = Some(row_count);
+        self
+    }
+
+    pub fn sequence(mut self, sequence: i64) -> Self {
+        self.sequence = Some(sequence);
+        self
+    }
+
+    /// The automatic indexes are derived from the statement
+    pub fn create_sql(mut self, create_sql: &str) -> Self {
+        self.create_sql = create_sql.to_string();
+        self
+    }
+
+    /// Builds the table, foreign keys can only refer to the table itself
+    /// Use `MetadataBuilder` for foreign keys to other tables
+    pub fn build(self) -> Table {
+        self.build_with(&[])
+    }
+
+    /// Builds the table, resolving the referenced columns of foreign keys in the other tables
+    fn build_with(self, others: &[&TableBuilder]) -> Table {
+        let foreign_keys = self
+            .foreign_keys
+            .iter()
+            .enumerate()
+            .map(|(id, f)| {
+                let referenced = if f.table == self.table_name {
+                    &self
+                } else {
+                    others
+                        .iter()
+                        .find(|t| t.table_name == f.table)
+                        .unwrap_or_else(|| {
+                            panic!(
+                                "Foreign key of {} refers to unknown table {}",
+                                self.table_name, f.table
+                            )
+                        })
+                };
+
+                ForeignKey {
+                    id: id as i32,
+                    table: f.table.clone(),
+                    from_column: self.find_columns(&f.from_column),
+                    to_column: referenced.find_columns(&f.to_column),
+                    on_update: f.on_update,
+                    on_delete: f.on_delete,
+                }
+            })
+            .collect();
+        let indexes = self
+            .indexes
+            .iter()
+            .map(|(name, columns, unique)| Index {
+                name: name.clone(),
+                columns: self.find_columns(columns),
+                unique: *unique,
+                storage: None,
+                statistics: None,
+            })
+            .collect();
+
+        let mut table = Table {
+            automatic_indexes: automatic_indexes(&self.table_name, &self.create_sql, &self.columns),
+            table_name: self.table_name,
+            columns: self.columns,
+            foreign_keys,
+            indexes,
+            row_count: self.row_count,
+            sequence: self.sequence,
+            storage: None,
+            create_sql: self.create_sql,
+            index_sql: BTreeMap::new(),
+            lookup: LookupMode::default(),
+        };
+
+        table.index_sql = generated_index_sql(&table);
+
+        table
+    }
+
+    fn find_columns(&self, names: &[String]) -> Vec<Column> {
+        names
+            .iter()
+            .map(|name| {
+                self.columns
+                    .iter()
+                    .find(|c| &c.name == name)
+                    .unwrap_or_else(|| panic!("Unknown column {name} in {}", self.table_name))
+                    .clone()
+            })
+            .collect()
+    }
+}
+
+/// Builds `Metadata` without samples, profiles and integrity report
+#[derive(Debug, PartialEq, Clone, Eq, Default)]
+pub struct MetadataBuilder {
+    tables: Vec<TableBuilder>,
+    database: DatabaseInfo,
+    lookup: LookupMode,
+}
+
+impl MetadataBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn table(mut self, table: TableBuilder) -> Self {
+        self.tables.push(table);
+        self
+    }
+
+    pub fn database(mut self, database: DatabaseInfo) -> Self {
+        self.database = database;
+        self
+    }
+
+    /// Sets the lookup mode of the metadata and all the tables
+    pub fn lookup(mut self, lookup: LookupMode) -> Self {
+        self.lookup = lookup;
+        self
+    }
+
+    /// Builds the metadata, panics if a foreign key refers to an unknown table or column
+    pub fn build(self) -> Metadata {
+        let tables = self
+            .tables
+            .iter()
+            .map(|t| {
+                let others = self.tables.iter().collect::<Vec<_>>();
+                let mut table = t.clone().build_with(&others);
+
+                table.lookup = self.lookup;
+
+                (table.table_name.clone(), table)
+            })
+            .collect::<HashMap<_, _>>();
+
+        Metadata {
+            tables,
+            samples: HashMap::new(),
+            profiles: HashMap::new(),
+            integrity: None,
+            database: self.database,
+            lookup: self.lookup,
+            triggers: vec![],
+            views: vec![],
+            annotations: Default::default(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::builder::{ColumnBuilder, MetadataBuilder, TableBuilder};
+    use crate::tests::metadata_from_sql;
+    use crate::{OnUpdateAndDelete, Type};
+
+    #[test]
+    fn test_builders() {
+        const USER_SQL: &str =
+            "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT DEFAULT 'x')";
+        const POST_SQL: &str = "CREATE TABLE post (
+            post_id INTEGER NOT NULL PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            FOREIGN KEY(user_id) REFERENCES user(user_id) ON DELETE CASCADE
+        )";
+        let parsed = metadata_from_sql(&format!(
+            "{USER_SQL}; {POST_SQL}; CREATE INDEX \"user_name\" ON \"user\" (\"name\");"
+        ));
+        let built = MetadataBuilder::new()
+            .table(
+                TableBuilder::new("user")
+                    .column(ColumnBuilder::new("user_id", Type::Integer).primary_key())
+                    .column(ColumnBuilder::new("name", Type::Text).default_value("'x'"))
+                    .index("user_name", &["name"], false)
+                    .create_sql(USER_SQL),
+            )
+            .table(
+                TableBuilder::new("post")
+                    .column(ColumnBuilder::new("post_id", Type::Integer).primary_key())
+                    .column(ColumnBuilder::new("user_id", Type::Integer).not_null())
+                    .foreign_key_with_actions(
+                        &["user_id"],
+                        "user",
+                        &["user_id"],
+                        OnUpdateAndDelete::NoAction,
+                        OnUpdateAndDelete::Cascade,
+                    )
+                    .create_sql(POST_SQL),
+            )
+            .build();
+
+        assert_eq!(parsed.tables, built.tables);
+    }
+}
@@ -0,0 +1,246 @@
+//! Keeps parsed metadata up to date for services that poll a database
+
+use std::collections::{HashMap, HashSet};
+
+use rusqlite::Connection;
+
+use crate::database::{pragma, query_database_info};
+use crate::trigger::query_triggers;
+use crate::view::query_views;
+use crate::{metadata_from_connection, parse_table_from_connection, Metadata, ParseOptions};
+
+/// The last parsed metadata, refreshed when `PRAGMA schema_version` changes
+/// Only the tables whose SQL (of the table or one of its indexes) changed are parsed again, with
+/// the tables that refer to them. When the options read data (samples, profiles or the integrity
+/// check) the whole database is parsed again, because those are not per table.
+///
+/// Data that can change without a schema change, like row counts and index statistics, is only
+/// refreshed for the tables that are parsed again.
+#[derive(Debug, Clone)]
+pub struct SchemaCache {
+    metadata: Metadata,
+    options: ParseOptions,
+    /// The SQL of every table and its indexes, keyed by table name
+    sql: HashMap<String, Vec<String>>,
+}
+
+impl SchemaCache {
+    /// Parses the database
+    pub fn new(connection: &Connection, options: ParseOptions) -> Self {
+        Self {
+            metadata: metadata_from_connection(connection, options.clone()),
+            sql: query_schema_sql(connection, &options),
+            options,
+        }
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    pub fn into_metadata(self) -> Metadata {
+        self.metadata
+    }
+
+    /// Parses the changes since the last parse, returns true if the schema changed
+    pub fn refresh(&mut self, connection: &Connection) -> bool {
+        let schema_version: i32 = pragma(connection, "schema_version");
+
+        if schema_version == self.metadata.database.schema_version {
+            return false;
+        }
+
+        let sql = query_schema_sql(connection, &self.options);
+
+        if self.options.samples.is_some()
+            || self.options.profile
+            || self.options.detect_booleans.is_some()
+            || self.options.integrity_check.is_some()
+        {
+            self.metadata = metadata_from_connection(connection, self.options.clone());
+            self.sql = sql;
+
+            return true;
+        }
+
+        let mut changed = sql
+            .iter()
+            .filter(|(name, table_sql)| self.sql.get(*name) != Some(table_sql))
+            .map(|(name, _)| name.clone())
+            .chain(self.sql.keys().filter(|n| !sql.contains_key(*n)).cloned())
+            .collect::<HashSet<_>>();
+
+        // Foreign keys contain the columns of the referenced table
+        let referring = self
+            .metadata
+            .tables
+            .values()
+            .filter(|t| {
+                t.foreign_keys
+                    .iter()
+                    .any(|f| changed.iter().any(|c| c.eq_ignore_ascii_case(&f.table)))
+            })
+            .map(|t| t.table_name.clone())
+            .collect::<Vec<_>>();
+
+        changed.extend(referring);
+
+        for table_name in changed {
+            self.metadata.tables.remove(&table_name);
+
+            if !sql.contains_key(&table_name) {
+                continue;
+            }
+
+            // The table is removed if it is dropped since the schema SQL was queried
+            match parse_table_from_connection(connection, &table_name, self.options.clone()) {
+                Ok(table) => {
+                    self.metadata.tables.insert(table_name, table);
+                }
+                Err(rusqlite::Error::QueryReturnedNoRows) => {}
+                Err(e) => panic!("{e}"),
+            }
+        }
+
+        self.metadata.database = query_database_info(connection);
+        self.metadata.triggers = query_triggers(connection, None);
+        self.metadata.views = query_views(connection);
+        self.metadata.analyze_dependencies();
+        self.sql = sql;
+
+        true
+    }
+}
+
+/// The SQL of the tables that are parsed by default, with the SQL of their indexes
+/// Tables that the annotations ignore are left out, they are never parsed.
+fn query_schema_sql(
+    connection: &Connection,
+    options: &ParseOptions,
+) -> HashMap<String, Vec<String>> {
+    let mut stmt = connection
+        .prepare(
+            "SELECT t.name, s.sql
+FROM sqlite_master AS t
+JOIN sqlite_master AS s ON s.tbl_name = t.name AND s.type IN ('table', 'index')
+WHERE t.type = 'table' AND s.sql IS NOT NULL
+ORDER BY s.type DESC, s.name;",
+        )
+        .unwrap();
+    let mut rows = stmt.query([]).unwrap();
+    let mut sql: HashMap<String, Vec<String>> = HashMap::new();
+
+    while let Some(row) = rows.next().unwrap() {
+        let table_name: String = row.get(0).unwrap();
+
+        if options
+            .annotations
+            .as_ref()
+            .is_some_and(|a| a.ignores_table(options.lookup, &table_name))
+        {
+            continue;
+        }
+
+        sql.entry(table_name).or_default().push(row.get(1).unwrap());
+    }
+
+    sql
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::annotations::{Annotations, TableAnnotation};
+    use crate::cache::SchemaCache;
+    use crate::{ParseOptions, RowCount};
+
+    #[test]
+    fn test_refresh() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY);
+            CREATE TABLE post (
+                post_id INTEGER NOT NULL PRIMARY KEY,
+                user_id INTEGER NOT NULL REFERENCES user(user_id)
+            );
+            CREATE TABLE tag (tag_id INTEGER NOT NULL PRIMARY KEY);",
+            )
+            .unwrap();
+
+        let mut cache = SchemaCache::new(
+            &connection,
+            ParseOptions {
+                row_counts: Some(RowCount::Exact),
+                ..Default::default()
+            },
+        );
+
+        assert!(!cache.refresh(&connection));
+
+        // The row counts show which tables are parsed again
+        connection
+            .execute_batch(
+                "INSERT INTO user VALUES (1);
+            INSERT INTO post VALUES (1, 1);
+            INSERT INTO tag VALUES (1);
+            CREATE INDEX user_id ON user(user_id DESC);",
+            )
+            .unwrap();
+
+        assert!(cache.refresh(&connection));
+
+        let metadata = cache.metadata();
+
+        assert_eq!("user_id", metadata.table("user").unwrap().indexes[0].name);
+        assert_eq!(Some(1), metadata.table("user").unwrap().row_count);
+        assert_eq!(Some(1), metadata.table("post").unwrap().row_count);
+        assert_eq!(Some(0), metadata.table("tag").unwrap().row_count);
+
+        connection.execute_batch("DROP TABLE tag;").unwrap();
+
+        assert!(cache.refresh(&connection));
+        assert!(cache.metadata().table("tag").is_none());
+        assert!(!cache.refresh(&connection));
+    }
+
+    #[test]
+    fn test_refresh_ignored_table() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE a (a_id INTEGER NOT NULL PRIMARY KEY);
+            CREATE TABLE b (b_id INTEGER NOT NULL PRIMARY KEY);",
+            )
+            .unwrap();
+
+        let annotations = Annotations {
+            tables: [(
+                "B".to_string(),
+                TableAnnotation {
+                    ignore: true,
+                    ..Default::default()
+                },
+            )]
+            .into(),
+        };
+        let mut cache = SchemaCache::new(
+            &connection,
+            ParseOptions {
+                annotations: Some(annotations),
+                ..Default::default()
+            },
+        );
+
+        connection
+            .execute_batch("ALTER TABLE b ADD COLUMN x TEXT;")
+            .unwrap();
+
+        assert!(cache.refresh(&connection));
+        assert!(cache.metadata().table("b").is_none());
+        assert!(cache.metadata().table("a").is_some());
+    }
+}
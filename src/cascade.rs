@@ -0,0 +1,187 @@
+//! Computes which tables are affected by deleting or updating rows, following the ON DELETE and
+//! ON UPDATE actions of the foreign keys
+
+use std::collections::{HashSet, VecDeque};
+
+use crate::{Column, ForeignKey, Metadata, OnUpdateAndDelete, Table};
+
+/// The statement that is executed on the rows of the table
+#[derive(Debug, PartialEq, Copy, Clone, Eq)]
+pub enum CascadeEvent {
+    Delete,
+    Update,
+}
+
+/// A change to a table caused by a foreign key action
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct CascadeEffect<'a> {
+    /// The table that is changed
+    pub table: &'a Table,
+    /// The foreign key that causes the change
+    pub foreign_key: &'a ForeignKey,
+    /// What happens to the referencing rows
+    pub event: CascadeEvent,
+    /// The columns that are changed, empty when the rows are deleted
+    pub columns: Vec<&'a Column>,
+    /// The amount of foreign keys between the changed table and the starting table
+    pub depth: usize,
+}
+
+/// The result of a cascade analysis
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct CascadeImpact<'a> {
+    /// The changes in breadth first order
+    pub effects: Vec<CascadeEffect<'a>>,
+    /// The foreign keys with RESTRICT or NO ACTION that make the statement fail if there are
+    /// referencing rows
+    pub blocked_by: Vec<(&'a Table, &'a ForeignKey)>,
+}
+
+impl<'a> CascadeImpact<'a> {
+    /// The names of the tables that are changed, sorted and without duplicates
+    pub fn affected_tables(&self) -> Vec<&'a str> {
+        let mut tables = self
+            .effects
+            .iter()
+            .map(|e| e.table.table_name.as_str())
+            .collect::<Vec<_>>();
+
+        tables.sort();
+        tables.dedup();
+
+        tables
+    }
+}
+
+impl Metadata {
+    /// Computes the transitive changes of deleting or updating rows of the table
+    /// When updating, every column of the table is considered changed
+    pub fn cascade_impact(&self, table_name: &str, event: CascadeEvent) -> CascadeImpact<'_> {
+        let mut impact = CascadeImpact {
+            effects: vec![],
+            blocked_by: vec![],
+        };
+        let table = match self.table(table_name) {
+            Some(table) => table,
+            None => return impact,
+        };
+        let mut queue = VecDeque::new();
+        // Foreign keys can form cycles, every foreign key is followed once per event
+        let mut visited = HashSet::new();
+
+        queue.push_back((table, event, table.columns.iter().collect::<Vec<_>>(), 0));
+
+        while let Some((table, event, changed, depth)) = queue.pop_front() {
+            for (referencing, foreign_key) in self.referencing(&table.table_name) {
+                if event == CascadeEvent::Update
+                    && !foreign_key
+                        .to_column
+                        .iter()
+                        .any(|c| changed.iter().any(|changed| changed.name == c.name))
+                {
+                    continue;
+                }
+
+                let key = (
+                    referencing.table_name.as_str(),
+                    foreign_key.id,
+                    event == CascadeEvent::Delete,
+                );
+
+                if !visited.insert(key) {
+                    continue;
+                }
+
+                let action = match event {
+                    CascadeEvent::Delete => foreign_key.on_delete,
+                    CascadeEvent::Update => foreign_key.on_update,
+                };
+                let (next_event, columns) = match action {
+                    OnUpdateAndDelete::NoAction | OnUpdateAndDelete::Restrict => {
+                        impact.blocked_by.push((referencing, foreign_key));
+
+                        continue;
+                    }
+                    OnUpdateAndDelete::Cascade if event == CascadeEvent::Delete => {
+                        (CascadeEvent::Delete, vec![])
+                    }
+                    _ => (
+                        CascadeEvent::Update,
+                        foreign_key.from_column.iter().collect::<Vec<_>>(),
+                    ),
+                };
+
+                impact.effects.push(CascadeEffect {
+                    table: referencing,
+                    foreign_key,
+                    event: next_event,
+                    columns: columns.clone(),
+                    depth: depth + 1,
+                });
+
+                let changed = match next_event {
+                    CascadeEvent::Delete => referencing.columns.iter().collect(),
+                    CascadeEvent::Update => columns,
+                };
+
+                queue.push_back((referencing, next_event, changed, depth + 1));
+            }
+        }
+
+        impact
+    }
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::cascade::CascadeEvent;
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_cascade_impact() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY);
+        CREATE TABLE post (
+            post_id INTEGER NOT NULL PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            FOREIGN KEY(user_id) REFERENCES user(user_id) ON DELETE CASCADE
+        );
+        CREATE TABLE comment (
+            comment_id INTEGER NOT NULL PRIMARY KEY,
+            post_id INTEGER NOT NULL,
+            parent_id INTEGER,
+            FOREIGN KEY(post_id) REFERENCES post(post_id) ON DELETE CASCADE,
+            FOREIGN KEY(parent_id) REFERENCES comment(comment_id) ON DELETE SET NULL
+        );
+        CREATE TABLE invoice (
+            invoice_id INTEGER NOT NULL PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            FOREIGN KEY(user_id) REFERENCES user(user_id) ON DELETE RESTRICT
+        );",
+        );
+        let impact = metadata.cascade_impact("user", CascadeEvent::Delete);
+
+        assert_eq!(vec!["comment", "post"], impact.affected_tables());
+        assert_eq!(
+            vec![
+                ("post", CascadeEvent::Delete, 1),
+                ("comment", CascadeEvent::Delete, 2),
+                ("comment", CascadeEvent::Update, 3)
+            ],
+            impact
+                .effects
+                .iter()
+                .map(|e| (e.table.table_name.as_str(), e.event, e.depth))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("parent_id", impact.effects[2].columns[0].name);
+        assert_eq!(1, impact.blocked_by.len());
+        assert_eq!("invoice", impact.blocked_by[0].0.table_name);
+
+        // Updates use the ON UPDATE action, which is NO ACTION for all the foreign keys
+        let impact = metadata.cascade_impact("user", CascadeEvent::Update);
+
+        assert!(impact.effects.is_empty());
+        assert_eq!(2, impact.blocked_by.len());
+    }
+}
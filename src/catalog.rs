@@ -0,0 +1,266 @@
+//! Flattens the schema into CSV files, to load it into a spreadsheet or a data catalog
+//!
+//! The files have a header and a row per table, column, foreign key column and index column.
+//! Booleans are written as `true` and `false`, missing values as empty fields.
+
+use std::path::Path;
+
+use crate::ddl::explicit_indexes;
+use crate::{IndexOrigin, Metadata};
+
+/// The contents of `tables.csv`, `columns.csv`, `foreign_keys.csv` and `indexes.csv`
+/// The indexes of PRIMARY KEY and UNIQUE constraints are in `indexes.csv` with the constraint,
+/// they aren't counted in the `indexes` of `tables.csv`.
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct Catalog {
+    pub tables: String,
+    pub columns: String,
+    pub foreign_keys: String,
+    pub indexes: String,
+}
+
+impl Catalog {
+    /// The file names with their contents
+    pub fn files(&self) -> [(&'static str, &str); 4] {
+        [
+            ("tables.csv", &self.tables),
+            ("columns.csv", &self.columns),
+            ("foreign_keys.csv", &self.foreign_keys),
+            ("indexes.csv", &self.indexes),
+        ]
+    }
+
+    /// Writes the files to the directory, existing files are overwritten
+    pub fn write_to(&self, directory: impl AsRef<Path>) -> std::io::Result<()> {
+        for (name, contents) in self.files() {
+            std::fs::write(directory.as_ref().join(name), contents)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Metadata {
+    /// The schema as CSV files, the tables are sorted by name
+    pub fn catalog(&self) -> Catalog {
+        let tables = self.sorted_tables();
+        let mut table_rows = vec![];
+        let mut column_rows = vec![];
+        let mut foreign_key_rows = vec![];
+        let mut index_rows = vec![];
+
+        for table in tables {
+            let name = &table.table_name;
+
+            table_rows.push(vec![
+                name.clone(),
+                table.columns.len().to_string(),
+                table
+                    .primary_key()
+                    .iter()
+                    .map(|c| c.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(","),
+                table.foreign_keys.len().to_string(),
+                explicit_indexes(table).count().to_string(),
+                optional(table.row_count),
+                table.create_sql.clone(),
+            ]);
+
+            for column in &table.columns {
+                column_rows.push(vec![
+                    name.clone(),
+                    column.id.to_string(),
+                    column.name.clone(),
+                    column.declared_type.clone(),
+                    column.the_type.to_string(),
+                    optional(column.logical_type),
+                    column.nullable.to_string(),
+                    column.part_of_pk.to_string(),
+                    column.default_value.clone().unwrap_or_default(),
+                    column.comment.clone().unwrap_or_default(),
+                ]);
+            }
+
+            for foreign_key in &table.foreign_keys {
+                for (position, (from, to)) in foreign_key
+                    .from_column
+                    .iter()
+                    .zip(&foreign_key.to_column)
+                    .enumerate()
+                {
+                    foreign_key_rows.push(vec![
+                        name.clone(),
+                        foreign_key.id.to_string(),
+                        position.to_string(),
+                        from.name.clone(),
+                        foreign_key.table.clone(),
+                        to.name.clone(),
+                        foreign_key.on_update.to_string(),
+                        foreign_key.on_delete.to_string(),
+                    ]);
+                }
+            }
+
+            // The indexes of constraints are listed once, as automatic index
+            let indexes = explicit_indexes(table)
+                .map(|i| (&i.name, i.unique, "", &i.columns))
+                .chain(table.automatic_indexes.iter().map(|i| {
+                    let origin = match i.origin {
+                        IndexOrigin::PrimaryKey => "primary key",
+                        IndexOrigin::Unique => "unique",
+                    };
+
+                    (&i.name, true, origin, &i.columns)
+                }));
+
+            for (index, unique, origin, columns) in indexes {
+                for (position, column) in columns.iter().enumerate() {
+                    index_rows.push(vec![
+                        name.clone(),
+                        index.clone(),
+                        unique.to_string(),
+                        origin.to_string(),
+                        position.to_string(),
+                        column.name.clone(),
+                    ]);
+                }
+            }
+        }
+
+        Catalog {
+            tables: csv(
+                &[
+                    "table_name",
+                    "columns",
+                    "primary_key",
+                    "foreign_keys",
+                    "indexes",
+                    "row_count",
+                    "create_sql",
+                ],
+                table_rows,
+            ),
+            columns: csv(
+                &[
+                    "table_name",
+                    "column_id",
+                    "column_name",
+                    "declared_type",
+                    "type",
+                    "logical_type",
+                    "nullable",
+                    "primary_key",
+                    "default_value",
+                    "comment",
+                ],
+                column_rows,
+            ),
+            foreign_keys: csv(
+                &[
+                    "table_name",
+                    "foreign_key_id",
+                    "position",
+                    "from_column",
+                    "referenced_table",
+                    "to_column",
+                    "on_update",
+                    "on_delete",
+                ],
+                foreign_key_rows,
+            ),
+            indexes: csv(
+                &[
+                    "table_name",
+                    "index_name",
+                    "unique",
+                    "constraint",
+                    "position",
+                    "column_name",
+                ],
+                index_rows,
+            ),
+        }
+    }
+}
+
+fn optional(value: Option<impl ToString>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Writes the rows with the header, fields are quoted when needed like RFC 4180 describes
+fn csv(header: &[&str], rows: Vec<Vec<String>>) -> String {
+    let header = header.iter().map(|h| h.to_string()).collect();
+
+    std::iter::once(header)
+        .chain(rows)
+        .map(|row| {
+            row.iter()
+                .map(|field| {
+                    if field.contains([',', '"', '\n', '\r']) {
+                        format!("\"{}\"", field.replace('"', "\"\""))
+                    } else {
+                        field.clone()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(",")
+                + "\n"
+        })
+        .collect()
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_catalog() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (
+                user_id INTEGER NOT NULL PRIMARY KEY,
+                -- The \"login\", unique
+                email TEXT NOT NULL UNIQUE
+            );
+            CREATE TABLE post (
+                post_id INTEGER PRIMARY KEY,
+                user_id INTEGER NOT NULL REFERENCES user ON DELETE CASCADE,
+                created DATETIME DEFAULT CURRENT_TIMESTAMP
+            );
+            CREATE INDEX post_user_created ON post (user_id, created);",
+        );
+        let catalog = metadata.catalog();
+
+        assert_eq!(
+            "table_name,columns,primary_key,foreign_keys,indexes,row_count,create_sql",
+            catalog.tables.lines().next().unwrap()
+        );
+        assert!(catalog
+            .tables
+            .contains("\npost,3,post_id,1,1,,\"CREATE TABLE post (\n"));
+        assert_eq!(
+            vec![
+                "table_name,column_id,column_name,declared_type,type,logical_type,nullable,primary_key,default_value,comment",
+                "post,0,post_id,INTEGER,INTEGER,,true,true,,",
+                "post,1,user_id,INTEGER,INTEGER,,false,false,,",
+                "post,2,created,DATETIME,TEXT,DATETIME,true,false,CURRENT_TIMESTAMP,",
+                "user,0,user_id,INTEGER,INTEGER,,false,true,,",
+                "user,1,email,TEXT,TEXT,,false,false,,\"The \"\"login\"\", unique\"",
+            ],
+            catalog.columns.lines().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "post,0,0,user_id,user,user_id,NO ACTION,CASCADE",
+            catalog.foreign_keys.lines().nth(1).unwrap()
+        );
+        assert_eq!(
+            vec![
+                "table_name,index_name,unique,constraint,position,column_name",
+                "post,post_user_created,false,,0,user_id",
+                "post,post_user_created,false,,1,created",
+                "user,sqlite_autoindex_user_1,true,unique,0,email",
+            ],
+            catalog.indexes.lines().collect::<Vec<_>>()
+        );
+    }
+}
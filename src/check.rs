@@ -0,0 +1,219 @@
+//! Checks the database file for corruption and the data for foreign key violations
+
+#[cfg(feature = "rusqlite")]
+use rusqlite::Connection;
+
+#[cfg(feature = "rusqlite")]
+use crate::Metadata;
+use crate::{ForeignKey, Table};
+
+/// The kind of integrity check to run
+#[derive(Debug, PartialEq, Copy, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IntegrityCheck {
+    /// `PRAGMA integrity_check`, verifies everything including index contents
+    Full,
+    /// `PRAGMA quick_check`, skips verifying that indexes match the table contents
+    Quick,
+}
+
+/// The result of an integrity check
+#[derive(Debug, PartialEq, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IntegrityReport {
+    pub check: IntegrityCheck,
+    /// The problems found, empty if the database is ok
+    pub problems: Vec<String>,
+}
+
+impl IntegrityReport {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Runs the integrity check on the connection
+#[cfg(feature = "rusqlite")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip(connection))
+)]
+pub fn integrity_check(connection: &Connection, check: IntegrityCheck) -> IntegrityReport {
+    let pragma = match check {
+        IntegrityCheck::Full => "integrity_check",
+        IntegrityCheck::Quick => "quick_check",
+    };
+    let mut stmt = connection.prepare(&format!("PRAGMA {pragma};")).unwrap();
+    let mut rows = stmt.query([]).unwrap();
+    let mut problems = vec![];
+
+    while let Some(row) = rows.next().unwrap() {
+        let problem: String = row.get(0).unwrap();
+
+        // A single row with 'ok' is returned when there are no problems
+        if problem != "ok" {
+            problems.push(problem);
+        }
+    }
+
+    IntegrityReport { check, problems }
+}
+
+/// A row that refers to a row that doesn't exist
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct ForeignKeyViolation<'a> {
+    /// The table of the referring row
+    pub table: &'a Table,
+    /// The rowid of the referring row, `None` for WITHOUT ROWID tables
+    pub rowid: Option<i64>,
+    /// The table that should contain the referred row
+    pub referenced_table: String,
+    /// The foreign key that is violated
+    pub foreign_key: &'a ForeignKey,
+}
+
+/// Runs `PRAGMA foreign_key_check` and ties every violation back to the foreign keys in the
+/// metadata, violations of tables and foreign keys not in the metadata are skipped
+#[cfg(feature = "rusqlite")]
+pub fn foreign_key_violations<'a>(
+    connection: &Connection,
+    metadata: &'a Metadata,
+) -> Vec<ForeignKeyViolation<'a>> {
+    let mut stmt = connection.prepare("PRAGMA foreign_key_check;").unwrap();
+    let mut rows = stmt.query([]).unwrap();
+    let mut violations = vec![];
+
+    while let Some(row) = rows.next().unwrap() {
+        let table_name: String = row.get(0).unwrap();
+        let id: i32 = row.get(3).unwrap();
+        let Some(table) = metadata.table(&table_name) else {
+            continue;
+        };
+        let Some(foreign_key) = table.foreign_keys.iter().find(|f| f.id == id) else {
+            continue;
+        };
+
+        violations.push(ForeignKeyViolation {
+            table,
+            rowid: row.get(1).unwrap(),
+            referenced_table: row.get(2).unwrap(),
+            foreign_key,
+        });
+    }
+
+    violations
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::check::{foreign_key_violations, integrity_check, IntegrityCheck};
+    use crate::tests::metadata_from_sql;
+    use crate::{parse_connection, Metadata, ParseOptions, Parser};
+
+    #[test]
+    fn test_integrity_check() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT NOT NULL);
+            CREATE INDEX user_name ON user(name);
+            INSERT INTO user (name) VALUES ('a'), ('b');",
+            )
+            .unwrap();
+
+        assert!(integrity_check(&connection, IntegrityCheck::Quick).is_ok());
+
+        struct Parse(Option<Metadata>);
+
+        impl Parser for Parse {
+            fn parse_options(&self) -> ParseOptions {
+                ParseOptions {
+                    integrity_check: Some(IntegrityCheck::Full),
+                    ..Default::default()
+                }
+            }
+
+            fn process_tables(&mut self, tables: Metadata) {
+                self.0 = Some(tables)
+            }
+        }
+
+        let mut parse = Parse(None);
+
+        parse_connection(&connection, &mut parse);
+
+        let metadata = parse.0.unwrap();
+
+        assert_eq!(Some(true), metadata.integrity_ok());
+
+        // Make the index definition disagree with its contents, which SQLite reports as corruption
+        let path = std::env::temp_dir().join("sqlite_parser_integrity_check.sqlite3");
+        let _ = std::fs::remove_file(&path);
+        let connection = Connection::open(&path).unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT NOT NULL);
+            CREATE INDEX user_name ON user(name);
+            INSERT INTO user (name) VALUES ('a'), ('b');
+            PRAGMA writable_schema = ON;
+            UPDATE sqlite_master SET sql = 'CREATE INDEX user_name ON user(user_id)' WHERE name = 'user_name';
+            PRAGMA writable_schema = OFF;",
+            )
+            .unwrap();
+        drop(connection);
+
+        let connection = Connection::open(&path).unwrap();
+        let report = integrity_check(&connection, IntegrityCheck::Full);
+
+        assert!(!report.is_ok());
+
+        drop(connection);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_foreign_key_violations() {
+        let sql = "PRAGMA foreign_keys = OFF;
+        CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY);
+        CREATE TABLE contacts (
+            contact_id INTEGER NOT NULL PRIMARY KEY,
+            user_id INTEGER,
+            friend_id INTEGER,
+            FOREIGN KEY(user_id) REFERENCES user(user_id),
+            FOREIGN KEY(friend_id) REFERENCES user(user_id)
+        );
+        INSERT INTO user VALUES (1);
+        INSERT INTO contacts VALUES (1, 1, 1), (2, 1, 5), (3, NULL, NULL);";
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection.execute_batch(sql).unwrap();
+
+        let metadata = metadata_from_sql(sql);
+        let violations = foreign_key_violations(&connection, &metadata);
+
+        assert_eq!(1, violations.len());
+        assert_eq!("contacts", violations[0].table.table_name);
+        assert_eq!(Some(2), violations[0].rowid);
+        assert_eq!("user", violations[0].referenced_table);
+        assert_eq!(
+            "friend_id",
+            violations[0].foreign_key.from_column[0].name.as_str()
+        );
+
+        // Stale metadata without the foreign key
+        let mut stale = metadata.clone();
+
+        stale
+            .tables
+            .get_mut("contacts")
+            .unwrap()
+            .foreign_keys
+            .clear();
+
+        assert!(foreign_key_violations(&connection, &stale).is_empty());
+    }
+}
@@ -0,0 +1,349 @@
+//! The SELECT, INSERT, UPDATE, DELETE and upsert statements of a table, with a `?` parameter per
+//! value
+//!
+//! The parameters are in the order of `Table::columns`, the parameters of the WHERE clause come
+//! last and are in the order of `Table::primary_key`.
+
+use crate::ddl::parse_create_index;
+use crate::{quote_identifier, Column, IndexOrigin, Table};
+
+/// How `Table::upsert_sql` handles a conflict
+#[derive(Debug, PartialEq, Clone, Eq, Default)]
+pub struct UpsertOptions {
+    /// The columns of the primary key or a unique index, `None` uses the first of
+    /// `Table::conflict_targets`
+    pub conflict_target: Option<Vec<String>>,
+    pub update: UpsertUpdate,
+}
+
+/// The columns that are updated when the row already exists
+#[derive(Debug, PartialEq, Clone, Eq, Default)]
+pub enum UpsertUpdate {
+    /// Every column that is not part of the conflict target
+    #[default]
+    AllOther,
+    Columns(Vec<String>),
+    /// Keeps the existing row, `DO NOTHING`
+    Nothing,
+}
+
+impl Table {
+    /// Selects every column of every row
+    pub fn select_sql(&self) -> String {
+        format!(
+            "SELECT {} FROM {};",
+            names(self.columns.iter()),
+            quote_identifier(&self.table_name)
+        )
+    }
+
+    /// Inserts a value for every column
+    pub fn insert_sql(&self) -> String {
+        format!(
+            "INSERT INTO {} ({}) VALUES ({});",
+            quote_identifier(&self.table_name),
+            names(self.columns.iter()),
+            vec!["?"; self.columns.len()].join(", ")
+        )
+    }
+
+    /// Updates the columns that are not part of the primary key, `None` if the table has no
+    /// primary key or only primary key columns
+    pub fn update_by_pk_sql(&self) -> Option<String> {
+        let non_pk_columns = self.non_pk_columns();
+
+        if non_pk_columns.is_empty() {
+            return None;
+        }
+
+        Some(format!(
+            "UPDATE {} SET {} WHERE {};",
+            quote_identifier(&self.table_name),
+            assignments(non_pk_columns, ", "),
+            self.pk_condition()?
+        ))
+    }
+
+    /// Deletes a row, `None` if the table has no primary key
+    pub fn delete_by_pk_sql(&self) -> Option<String> {
+        Some(format!(
+            "DELETE FROM {} WHERE {};",
+            quote_identifier(&self.table_name),
+            self.pk_condition()?
+        ))
+    }
+
+    /// The sets of columns that can be the target of `ON CONFLICT`: the primary key followed by
+    /// the UNIQUE constraints and the unique indexes without expressions or a WHERE clause
+    pub fn conflict_targets(&self) -> Vec<Vec<&Column>> {
+        let constraints = self
+            .automatic_indexes
+            .iter()
+            .filter(|i| i.origin == IndexOrigin::Unique)
+            .map(|i| i.columns.iter().collect());
+        let unique_indexes = self
+            .indexes
+            .iter()
+            .filter(|i| {
+                i.unique
+                    && self
+                        .index_sql
+                        .get(&i.name)
+                        .and_then(|sql| parse_create_index(sql))
+                        .is_some_and(|d| !d.expressions && !d.partial)
+            })
+            .map(|i| i.columns.iter().collect());
+        let mut targets = vec![];
+
+        for target in std::iter::once(self.primary_key())
+            .chain(constraints)
+            .chain(unique_indexes)
+        {
+            if !target.is_empty() && !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+
+        targets
+    }
+
+    /// Inserts a value for every column and updates the existing row on a conflict
+    /// `None` if the table has no conflict targets or a column of the options doesn't exist. The
+    /// conflict target of the options can be in any order, but must be one of `conflict_targets`.
+    pub fn upsert_sql(&self, options: &UpsertOptions) -> Option<String> {
+        let targets = self.conflict_targets();
+        let target = match &options.conflict_target {
+            Some(names) => targets.into_iter().find(|target| {
+                target.len() == names.len()
+                    && names
+                        .iter()
+                        .all(|n| target.iter().any(|c| self.lookup.matches(&c.name, n)))
+            })?,
+            None => targets.into_iter().next()?,
+        };
+        let update = match &options.update {
+            UpsertUpdate::AllOther => self
+                .columns
+                .iter()
+                .filter(|c| !target.contains(c))
+                .collect(),
+            UpsertUpdate::Columns(names) => names
+                .iter()
+                .map(|n| self.column(n))
+                .collect::<Option<Vec<_>>>()?,
+            UpsertUpdate::Nothing => vec![],
+        };
+        let action = if update.is_empty() {
+            "NOTHING".to_string()
+        } else {
+            format!(
+                "UPDATE SET {}",
+                update
+                    .iter()
+                    .map(|c| {
+                        let name = quote_identifier(&c.name);
+
+                        format!("{name} = excluded.{name}")
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+
+        Some(format!(
+            "{} ON CONFLICT ({}) DO {action};",
+            self.insert_sql().strip_suffix(';').unwrap(),
+            names(target.into_iter())
+        ))
+    }
+
+    fn pk_condition(&self) -> Option<String> {
+        let primary_key = self.primary_key();
+
+        if primary_key.is_empty() {
+            None
+        } else {
+            Some(assignments(primary_key, " AND "))
+        }
+    }
+}
+
+fn names<'a>(columns: impl Iterator<Item = &'a Column>) -> String {
+    columns
+        .map(|c| quote_identifier(&c.name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `"a" = ?` for every column
+fn assignments(columns: Vec<&Column>, separator: &str) -> String {
+    columns
+        .iter()
+        .map(|c| format!("{} = ?", quote_identifier(&c.name)))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::crud::{UpsertOptions, UpsertUpdate};
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_crud_sql() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE \"user tag\" (
+                user_id INTEGER NOT NULL,
+                tag TEXT NOT NULL,
+                \"order\" INTEGER,
+                PRIMARY KEY (user_id, tag)
+            );
+            CREATE TABLE log (message TEXT);",
+        );
+        let user_tag = metadata.table("user tag").unwrap();
+
+        assert_eq!(
+            "SELECT \"user_id\", \"tag\", \"order\" FROM \"user tag\";",
+            user_tag.select_sql()
+        );
+        assert_eq!(
+            "INSERT INTO \"user tag\" (\"user_id\", \"tag\", \"order\") VALUES (?, ?, ?);",
+            user_tag.insert_sql()
+        );
+        assert_eq!(
+            Some("UPDATE \"user tag\" SET \"order\" = ? WHERE \"user_id\" = ? AND \"tag\" = ?;"),
+            user_tag.update_by_pk_sql().as_deref()
+        );
+        assert_eq!(
+            Some("DELETE FROM \"user tag\" WHERE \"user_id\" = ? AND \"tag\" = ?;"),
+            user_tag.delete_by_pk_sql().as_deref()
+        );
+
+        let log = metadata.table("log").unwrap();
+
+        assert_eq!(None, log.update_by_pk_sql());
+        assert_eq!(None, log.delete_by_pk_sql());
+
+        // The statements are valid
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+
+        connection.execute_batch(&metadata.schema_sql()).unwrap();
+
+        for sql in [
+            user_tag.select_sql(),
+            user_tag.insert_sql(),
+            user_tag.update_by_pk_sql().unwrap(),
+            user_tag.delete_by_pk_sql().unwrap(),
+        ] {
+            connection.prepare(&sql).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_upsert_sql() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (
+                user_id INTEGER NOT NULL PRIMARY KEY,
+                email TEXT NOT NULL UNIQUE,
+                name TEXT
+            );
+            CREATE TABLE log (message TEXT);",
+        );
+        let user = metadata.table("user").unwrap();
+        let upsert = |conflict_target: Option<&[&str]>, update| {
+            user.upsert_sql(&UpsertOptions {
+                conflict_target: conflict_target.map(|t| t.iter().map(|c| c.to_string()).collect()),
+                update,
+            })
+        };
+
+        assert_eq!(2, user.conflict_targets().len());
+        assert_eq!(
+            Some(
+                "INSERT INTO \"user\" (\"user_id\", \"email\", \"name\") VALUES (?, ?, ?) \
+                ON CONFLICT (\"user_id\") DO UPDATE SET \"email\" = excluded.\"email\", \
+                \"name\" = excluded.\"name\";"
+                    .to_string()
+            ),
+            upsert(None, UpsertUpdate::AllOther)
+        );
+        assert_eq!(
+            Some(
+                "INSERT INTO \"user\" (\"user_id\", \"email\", \"name\") VALUES (?, ?, ?) \
+                ON CONFLICT (\"email\") DO UPDATE SET \"name\" = excluded.\"name\";"
+                    .to_string()
+            ),
+            upsert(
+                Some(&["email"]),
+                UpsertUpdate::Columns(vec!["name".to_string()])
+            )
+        );
+        assert!(upsert(Some(&["email"]), UpsertUpdate::Nothing)
+            .unwrap()
+            .ends_with("ON CONFLICT (\"email\") DO NOTHING;"));
+        assert_eq!(None, upsert(Some(&["name"]), UpsertUpdate::AllOther));
+        assert_eq!(
+            None,
+            metadata
+                .table("log")
+                .unwrap()
+                .upsert_sql(&UpsertOptions::default())
+        );
+
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+
+        connection.execute_batch(&metadata.schema_sql()).unwrap();
+
+        let sql = upsert(Some(&["email"]), UpsertUpdate::AllOther).unwrap();
+
+        connection.execute(&sql, (1, "a", "first")).unwrap();
+        connection.execute(&sql, (2, "a", "second")).unwrap();
+
+        let row: (i64, String) = connection
+            .query_row("SELECT user_id, name FROM user;", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+
+        assert_eq!((2, "second".to_string()), row);
+    }
+
+    #[test]
+    fn test_upsert_composite_unique() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE t (id INTEGER PRIMARY KEY, a TEXT, b TEXT, c TEXT, UNIQUE (a, b));
+            CREATE UNIQUE INDEX t_live ON t(c) WHERE a IS NULL;
+            CREATE UNIQUE INDEX t_lower ON t(lower(c), b);
+            CREATE UNIQUE INDEX t_c ON t(c, id);",
+        );
+        let t = metadata.table("t").unwrap();
+        let targets = t
+            .conflict_targets()
+            .iter()
+            .map(|t| t.iter().map(|c| c.name.as_str()).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+
+        assert_eq!(vec![vec!["id"], vec!["a", "b"], vec!["c", "id"]], targets);
+
+        let upsert = |conflict_target: &[&str]| {
+            t.upsert_sql(&UpsertOptions {
+                conflict_target: Some(conflict_target.iter().map(|c| c.to_string()).collect()),
+                update: UpsertUpdate::AllOther,
+            })
+        };
+
+        assert_eq!(None, upsert(&["a"]));
+        assert_eq!(None, upsert(&["c"]));
+
+        let sql = upsert(&["b", "a"]).unwrap();
+
+        assert!(sql.ends_with(
+            "ON CONFLICT (\"a\", \"b\") DO UPDATE SET \"id\" = excluded.\"id\", \"c\" = excluded.\"c\";"
+        ));
+
+        let connection = rusqlite::Connection::open_in_memory().unwrap();
+
+        connection.execute_batch(&metadata.schema_sql()).unwrap();
+        connection.prepare(&sql).unwrap();
+    }
+}
@@ -0,0 +1,169 @@
+//! Database level settings read from pragmas
+
+#[cfg(feature = "rusqlite")]
+use rusqlite::Connection;
+
+/// The settings of the database file
+#[derive(Debug, PartialEq, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DatabaseInfo {
+    /// The size of a page in bytes
+    pub page_size: u32,
+    /// The text encoding, e.g. `UTF-8`
+    pub encoding: String,
+    /// The journal mode, e.g. `wal` or `delete`
+    pub journal_mode: String,
+    pub auto_vacuum: AutoVacuum,
+    /// Free to use by applications, often used as schema version by migration tools
+    pub user_version: i32,
+    /// Identifies the application that owns the file
+    pub application_id: i32,
+    /// Incremented by SQLite on every schema change
+    pub schema_version: i32,
+    /// The version of the SQLite library that did the parsing, e.g. `3.44.0`
+    pub sqlite_version: String,
+    /// The compile options of the SQLite library that did the parsing, without `SQLITE_` prefix
+    pub compile_options: Vec<String>,
+}
+
+impl DatabaseInfo {
+    /// The parsed `sqlite_version` as (major, minor, patch)
+    pub fn version(&self) -> (u32, u32, u32) {
+        let mut parts = self
+            .sqlite_version
+            .split('.')
+            .map(|p| p.parse().unwrap_or(0));
+
+        (
+            parts.next().unwrap_or(0),
+            parts.next().unwrap_or(0),
+            parts.next().unwrap_or(0),
+        )
+    }
+
+    /// Checks if SQLite was compiled with the option, e.g. `ENABLE_DBSTAT_VTAB`
+    /// Options with a value like `THREADSAFE=1` match on their name
+    pub fn has_compile_option(&self, option: &str) -> bool {
+        self.compile_options
+            .iter()
+            .any(|o| o == option || o.split('=').next() == Some(option))
+    }
+
+    /// Generated columns are supported since SQLite 3.31.0
+    pub fn supports_generated_columns(&self) -> bool {
+        self.version() >= (3, 31, 0)
+    }
+
+    /// STRICT tables are supported since SQLite 3.37.0
+    pub fn supports_strict_tables(&self) -> bool {
+        self.version() >= (3, 37, 0)
+    }
+
+    /// `ALTER TABLE ... RENAME COLUMN` is supported since SQLite 3.25.0
+    pub fn supports_rename_column(&self) -> bool {
+        self.version() >= (3, 25, 0)
+    }
+}
+
+/// The settings of a new database, the SQLite version and compile options are unknown
+impl Default for DatabaseInfo {
+    fn default() -> Self {
+        Self {
+            page_size: 4096,
+            encoding: "UTF-8".to_string(),
+            journal_mode: "delete".to_string(),
+            auto_vacuum: AutoVacuum::None,
+            user_version: 0,
+            application_id: 0,
+            schema_version: 0,
+            sqlite_version: String::new(),
+            compile_options: vec![],
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AutoVacuum {
+    None,
+    Full,
+    Incremental,
+}
+
+#[cfg(feature = "rusqlite")]
+impl AutoVacuum {
+    fn from_i32(i: i32) -> Self {
+        match i {
+            0 => Self::None,
+            1 => Self::Full,
+            2 => Self::Incremental,
+            _ => panic!("Unknown auto_vacuum: {i}"),
+        }
+    }
+}
+
+/// Queries the settings of the main database of the connection
+#[cfg(feature = "rusqlite")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
+pub(crate) fn query_database_info(connection: &Connection) -> DatabaseInfo {
+    DatabaseInfo {
+        page_size: pragma(connection, "page_size"),
+        encoding: pragma(connection, "encoding"),
+        journal_mode: pragma(connection, "journal_mode"),
+        auto_vacuum: AutoVacuum::from_i32(pragma(connection, "auto_vacuum")),
+        user_version: pragma(connection, "user_version"),
+        application_id: pragma(connection, "application_id"),
+        schema_version: pragma(connection, "schema_version"),
+        sqlite_version: connection
+            .query_row("SELECT sqlite_version();", [], |row| row.get(0))
+            .unwrap(),
+        compile_options: query_compile_options(connection),
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+fn query_compile_options(connection: &Connection) -> Vec<String> {
+    let mut stmt = connection.prepare("PRAGMA compile_options;").unwrap();
+    let mut rows = stmt.query([]).unwrap();
+    let mut options = vec![];
+
+    while let Some(row) = rows.next().unwrap() {
+        options.push(row.get(0).unwrap());
+    }
+
+    options
+}
+
+/// Reads the value of a pragma that returns a single value
+#[cfg(feature = "rusqlite")]
+pub(crate) fn pragma<T: rusqlite::types::FromSql>(connection: &Connection, pragma: &str) -> T {
+    connection
+        .query_row(&format!("PRAGMA {pragma};"), [], |row| row.get(0))
+        .unwrap()
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::database::AutoVacuum;
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_database_info() {
+        let metadata = metadata_from_sql(
+            "PRAGMA user_version = 3;
+        PRAGMA application_id = 42;
+        CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY);",
+        );
+
+        assert_eq!(3, metadata.database.user_version);
+        assert_eq!(42, metadata.database.application_id);
+        assert_eq!(1, metadata.database.schema_version);
+        assert_eq!("UTF-8", metadata.database.encoding);
+        assert_eq!("memory", metadata.database.journal_mode);
+        assert_eq!(AutoVacuum::None, metadata.database.auto_vacuum);
+        assert!(metadata.database.page_size > 0);
+        assert_eq!(rusqlite::version(), metadata.database.sqlite_version);
+        assert!(metadata.database.supports_strict_tables());
+        assert!(metadata.database.has_compile_option("THREADSAFE"));
+    }
+}
@@ -0,0 +1,156 @@
+//! Generates the `sources:` YAML of dbt, to bootstrap the source definitions of a dbt project that
+//! reads a SQLite database
+//!
+//! Every table and column is listed, the comments in the CREATE TABLE statement become the
+//! descriptions. The primary key gets the `unique` and `not_null` tests, NOT NULL columns the
+//! `not_null` test and single column foreign keys the `relationships` test.
+
+use crate::Metadata;
+
+impl Metadata {
+    /// The YAML of a source named `source_name` with the tables sorted by name
+    pub fn dbt_sources(&self, source_name: &str) -> String {
+        let mut yaml = format!(
+            "version: 2\n\nsources:\n  - name: {}\n    tables:\n",
+            string(source_name)
+        );
+
+        for table in self.sorted_tables() {
+            let primary_key = table.primary_key();
+
+            yaml.push_str(&format!(
+                "      - name: {}\n        columns:\n",
+                string(&table.table_name)
+            ));
+
+            for column in &table.columns {
+                let mut tests = vec![];
+
+                if primary_key.len() == 1 && primary_key[0].name == column.name {
+                    tests.push("unique".to_string());
+                }
+
+                if !column.nullable {
+                    tests.push("not_null".to_string());
+                }
+
+                for foreign_key in &table.foreign_keys {
+                    if let ([from], [to]) =
+                        (&foreign_key.from_column[..], &foreign_key.to_column[..])
+                    {
+                        if from.name == column.name {
+                            tests.push(format!(
+                                "relationships:\n                  to: source({}, {})\n                  field: {}",
+                                jinja_string(source_name),
+                                jinja_string(&foreign_key.table),
+                                string(&to.name)
+                            ));
+                        }
+                    }
+                }
+
+                yaml.push_str(&format!("          - name: {}\n", string(&column.name)));
+
+                if let Some(comment) = &column.comment {
+                    yaml.push_str(&format!("            description: {}\n", string(comment)));
+                }
+
+                if !column.declared_type.is_empty() {
+                    yaml.push_str(&format!(
+                        "            data_type: {}\n",
+                        string(&column.declared_type)
+                    ));
+                }
+
+                if !tests.is_empty() {
+                    yaml.push_str("            tests:\n");
+
+                    for test in tests {
+                        yaml.push_str(&format!("              - {test}\n"));
+                    }
+                }
+            }
+        }
+
+        yaml
+    }
+}
+
+/// A single quoted Jinja string, for the `source()` of a `relationships` test
+fn jinja_string(s: &str) -> String {
+    format!("'{}'", s.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// A double quoted YAML string
+fn string(s: &str) -> String {
+    let mut quoted = String::from('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+
+    quoted
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_dbt_sources() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (
+                -- The \"id\"
+                user_id INTEGER NOT NULL PRIMARY KEY,
+                name TEXT
+            );
+            CREATE TABLE post (
+                post_id INTEGER NOT NULL PRIMARY KEY,
+                user_id INTEGER NOT NULL REFERENCES user (user_id)
+            );",
+        );
+
+        assert_eq!(
+            r#"version: 2
+
+sources:
+  - name: "app"
+    tables:
+      - name: "post"
+        columns:
+          - name: "post_id"
+            data_type: "INTEGER"
+            tests:
+              - unique
+              - not_null
+          - name: "user_id"
+            data_type: "INTEGER"
+            tests:
+              - not_null
+              - relationships:
+                  to: source('app', 'user')
+                  field: "user_id"
+      - name: "user"
+        columns:
+          - name: "user_id"
+            description: "The \"id\""
+            data_type: "INTEGER"
+            tests:
+              - unique
+              - not_null
+          - name: "name"
+            data_type: "TEXT"
+"#,
+            metadata.dbt_sources("app")
+        );
+    }
+}
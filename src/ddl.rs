@@ -0,0 +1,1497 @@
+//! Extracts the definitions of tables and indexes from the SQL that SQLite stores in
+//! `sqlite_master`, for when the schema can't be queried from SQLite itself
+//!
+//! The SQL is expected to be valid since SQLite accepted it, so the parsing is lenient: anything
+//! that isn't needed for the metadata is skipped.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::tokenizer::{tokenize, tokenize_without_trivia, unquote, Token, TokenKind};
+use crate::{
+    find_column, quote_identifier, AutomaticIndex, Column, Index, IndexOrigin, OnUpdateAndDelete,
+    Table,
+};
+
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub(crate) struct TableDefinition {
+    pub columns: Vec<ColumnDefinition>,
+    /// The primary key columns, in primary key order
+    pub primary_key: Vec<String>,
+    /// In order of declaration
+    pub foreign_keys: Vec<ForeignKeyDefinition>,
+    /// The columns of every UNIQUE constraint, in order of declaration
+    pub unique: Vec<Vec<String>>,
+    /// The PRIMARY KEY and UNIQUE constraints in order of declaration, `true` for the primary key
+    pub key_constraints: Vec<(bool, Vec<String>)>,
+    /// If the PRIMARY KEY column constraint is DESC, which keeps an INTEGER column from being the
+    /// rowid
+    pub primary_key_desc: bool,
+    pub without_rowid: bool,
+    pub strict: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub(crate) struct ColumnDefinition {
+    pub name: String,
+    /// The type as written, empty if there is no type
+    pub declared_type: String,
+    pub not_null: bool,
+    /// The default expression as written, without surrounding parentheses
+    pub default_value: Option<String>,
+    /// Generated columns are hidden in `pragma_table_info`
+    pub generated: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub(crate) struct ForeignKeyDefinition {
+    pub from_column: Vec<String>,
+    pub table: String,
+    /// Empty if the foreign key refers to the primary key
+    pub to_column: Vec<String>,
+    pub on_update: OnUpdateAndDelete,
+    pub on_delete: OnUpdateAndDelete,
+}
+
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub(crate) struct IndexDefinition {
+    pub unique: bool,
+    /// The indexed columns, expressions are skipped
+    pub columns: Vec<String>,
+    /// If any of the indexed items is an expression
+    pub expressions: bool,
+    /// If the index has a WHERE clause
+    pub partial: bool,
+}
+
+/// Parses a CREATE TABLE statement, `None` if it has no column definitions
+pub(crate) fn parse_create_table(sql: &str) -> Option<TableDefinition> {
+    let tokens = tokenize_without_trivia(sql);
+    let open = tokens.iter().position(|t| is_punctuation(t, "("))?;
+    let close = matching_paren(&tokens, open);
+    let mut definition = TableDefinition {
+        columns: vec![],
+        primary_key: vec![],
+        foreign_keys: vec![],
+        unique: vec![],
+        key_constraints: vec![],
+        primary_key_desc: false,
+        without_rowid: tokens[close..]
+            .windows(2)
+            .any(|t| t[0].is_keyword("WITHOUT") && t[1].is_keyword("ROWID")),
+        strict: tokens[close..].iter().any(|t| t.is_keyword("STRICT")),
+    };
+
+    for item in split_top_level(&tokens[open + 1..close]) {
+        let start = if item[0].is_keyword("CONSTRAINT") {
+            2
+        } else {
+            0
+        };
+
+        match item.get(start) {
+            Some(t)
+                if t.is_keyword("PRIMARY")
+                    || t.is_keyword("UNIQUE")
+                    || t.is_keyword("CHECK")
+                    || t.is_keyword("FOREIGN") =>
+            {
+                table_constraint(&item[start..], &mut definition)
+            }
+            Some(_) if start == 0 => column_definition(sql, item, &mut definition),
+            _ => {}
+        }
+    }
+
+    Some(definition)
+}
+
+/// Parses a CREATE VIRTUAL TABLE statement, `None` if it is not a virtual table
+/// Only the columns are known, they follow from the arguments for the modules fts3, fts4, fts5,
+/// rtree and rtree_i32 in the same way as the modules declare them. Other modules have no columns.
+pub(crate) fn parse_create_virtual_table(sql: &str) -> Option<TableDefinition> {
+    let tokens = tokenize_without_trivia(sql);
+
+    if !tokens.get(1)?.is_keyword("VIRTUAL") {
+        return None;
+    }
+
+    let using = tokens.iter().position(|t| t.is_keyword("USING"))?;
+    let module = unquote(tokens.get(using + 1)?.text).to_lowercase();
+    let arguments = match tokens.get(using + 2) {
+        Some(t) if is_punctuation(t, "(") => {
+            let close = matching_paren(&tokens, using + 2);
+
+            split_top_level(&tokens[using + 3..close])
+        }
+        _ => vec![],
+    };
+    let columns = match module.as_str() {
+        // Arguments with an = are options
+        "fts3" | "fts4" | "fts5" => arguments
+            .iter()
+            .filter(|a| !a.iter().any(|t| is_punctuation(t, "=")))
+            .map(|a| (unquote(a[0].text), String::new()))
+            .collect(),
+        // The id, the coordinates and auxiliary columns that start with +
+        "rtree" | "rtree_i32" => arguments
+            .iter()
+            .enumerate()
+            .filter_map(|(i, a)| {
+                let (name, declared_type) = if is_punctuation(&a[0], "+") {
+                    (a.get(1)?, "")
+                } else if i == 0 || module == "rtree_i32" {
+                    (&a[0], "INT")
+                } else {
+                    (&a[0], "REAL")
+                };
+
+                Some((unquote(name.text), declared_type.to_string()))
+            })
+            .collect(),
+        _ => vec![],
+    };
+
+    Some(TableDefinition {
+        columns: columns
+            .into_iter()
+            .map(|(name, declared_type)| ColumnDefinition {
+                name,
+                declared_type,
+                not_null: false,
+                default_value: None,
+                generated: false,
+            })
+            .collect(),
+        primary_key: vec![],
+        foreign_keys: vec![],
+        unique: vec![],
+        key_constraints: vec![],
+        primary_key_desc: false,
+        without_rowid: false,
+        strict: false,
+    })
+}
+
+/// The expressions of the column and table CHECK constraints of a CREATE TABLE statement, as
+/// written
+pub(crate) fn check_constraints(sql: &str) -> Vec<&str> {
+    let tokens = tokenize_without_trivia(sql);
+
+    tokens
+        .iter()
+        .enumerate()
+        .filter(|(i, t)| {
+            t.is_keyword("CHECK") && tokens.get(i + 1).is_some_and(|t| is_punctuation(t, "("))
+        })
+        .filter_map(|(i, _)| {
+            let close = matching_paren(&tokens, i + 1);
+
+            (close > i + 2).then(|| span(sql, &tokens[i + 2..close]))
+        })
+        .collect()
+}
+
+/// The values of the CHECK constraints of the form `column IN ('a', 'b')`, keyed by the lowercase
+/// column name
+/// Only constraints that consist of the IN are used, the first one of a column wins.
+pub(crate) fn check_in_values(sql: &str) -> HashMap<String, Vec<String>> {
+    let mut values = HashMap::new();
+
+    for check in check_constraints(sql) {
+        let tokens = tokenize_without_trivia(check);
+        let mut tokens = &tokens[..];
+
+        while tokens.len() > 2
+            && is_punctuation(&tokens[0], "(")
+            && matching_paren(tokens, 0) == tokens.len() - 1
+        {
+            tokens = &tokens[1..tokens.len() - 1];
+        }
+
+        let [column, in_, open, literals @ .., close] = tokens else {
+            continue;
+        };
+
+        if !matches!(column.kind, TokenKind::Word | TokenKind::QuotedIdentifier)
+            || !in_.is_keyword("IN")
+            || !is_punctuation(open, "(")
+            || !is_punctuation(close, ")")
+        {
+            continue;
+        }
+
+        let column_values = literals
+            .split(|t| is_punctuation(t, ","))
+            .map(|literal| match literal {
+                [literal] if matches!(literal.kind, TokenKind::String | TokenKind::Number) => {
+                    Some(unquote(literal.text))
+                }
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>();
+
+        if let Some(column_values) = column_values {
+            values
+                .entry(unquote(column.text).to_lowercase())
+                .or_insert(column_values);
+        }
+    }
+
+    values
+}
+
+/// The automatic indexes of the PRIMARY KEY and UNIQUE constraints of a CREATE TABLE statement
+/// Constraints on unknown columns are skipped.
+pub(crate) fn automatic_indexes(
+    table_name: &str,
+    sql: &str,
+    columns: &[Column],
+) -> Vec<AutomaticIndex> {
+    let Some(definition) = parse_create_table(sql) else {
+        return vec![];
+    };
+
+    automatic_index_columns(&definition)
+        .into_iter()
+        .zip(1..)
+        .filter_map(|((origin, names), position)| {
+            Some(AutomaticIndex {
+                name: format!("sqlite_autoindex_{table_name}_{position}"),
+                origin,
+                columns: names
+                    .iter()
+                    .map(|n| find_column(columns, n).cloned())
+                    .collect::<Option<_>>()?,
+                position,
+                storage: None,
+                statistics: None,
+            })
+        })
+        .collect()
+}
+
+/// The origin and columns of the automatic indexes of the table, in order of their position
+/// Follows SQLite: an INTEGER PRIMARY KEY is the rowid, except for WITHOUT ROWID tables where its
+/// index is created after the other constraints. A constraint with the same columns as an earlier
+/// constraint shares its index, which becomes the primary key index for a PRIMARY KEY.
+fn automatic_index_columns(definition: &TableDefinition) -> Vec<(IndexOrigin, Vec<String>)> {
+    let is_integer = |name: &String| {
+        definition.columns.iter().any(|c| {
+            c.name.eq_ignore_ascii_case(name) && c.declared_type.eq_ignore_ascii_case("INTEGER")
+        })
+    };
+    let integer_primary_key = match definition.primary_key.as_slice() {
+        [name] => is_integer(name) && !definition.primary_key_desc,
+        _ => false,
+    };
+    let mut indexes: Vec<(IndexOrigin, Vec<String>)> = vec![];
+    let mut add = |origin: IndexOrigin, columns: &Vec<String>| {
+        let existing = indexes.iter_mut().find(|(_, c)| {
+            c.len() == columns.len()
+                && c.iter()
+                    .zip(columns)
+                    .all(|(a, b)| a.eq_ignore_ascii_case(b))
+        });
+
+        match existing {
+            Some(existing) if origin == IndexOrigin::PrimaryKey => existing.0 = origin,
+            Some(_) => {}
+            None => indexes.push((origin, columns.clone())),
+        }
+    };
+
+    for (primary_key, columns) in &definition.key_constraints {
+        if !primary_key {
+            add(IndexOrigin::Unique, columns);
+        } else if !integer_primary_key {
+            add(IndexOrigin::PrimaryKey, columns);
+        }
+    }
+
+    if integer_primary_key && definition.without_rowid {
+        add(IndexOrigin::PrimaryKey, &definition.primary_key);
+    }
+
+    indexes
+}
+
+/// Parses a CREATE INDEX statement, `None` if it has no column list
+pub(crate) fn parse_create_index(sql: &str) -> Option<IndexDefinition> {
+    let tokens = tokenize_without_trivia(sql);
+    let open = tokens.iter().position(|t| is_punctuation(t, "("))?;
+    let close = matching_paren(&tokens, open);
+    let columns = column_list(&tokens[open + 1..close]);
+
+    Some(IndexDefinition {
+        unique: tokens.get(1).map(|t| t.is_keyword("UNIQUE")) == Some(true),
+        expressions: split_top_level(&tokens[open + 1..close]).len() != columns.len(),
+        partial: tokens[close..].iter().any(|t| t.is_keyword("WHERE")),
+        columns,
+    })
+}
+
+/// The definition of the column as written in a CREATE TABLE statement, e.g. `name TEXT NOT NULL`
+pub(crate) fn column_sql<'a>(sql: &'a str, column_name: &str) -> Option<&'a str> {
+    let tokens = tokenize_without_trivia(sql);
+    let open = tokens.iter().position(|t| is_punctuation(t, "("))?;
+    let close = matching_paren(&tokens, open);
+
+    split_top_level(&tokens[open + 1..close])
+        .into_iter()
+        .find(|item| {
+            !is_table_constraint(item) && unquote(item[0].text).eq_ignore_ascii_case(column_name)
+        })
+        .map(|item| span(sql, item))
+}
+
+/// The comments of the columns of a CREATE TABLE statement, keyed by the lowercase column name
+/// A column gets the comments on the lines right above it and the comments after it on the same
+/// line, e.g. after its comma. Multiple comments are joined with a newline.
+pub(crate) fn column_comments(sql: &str) -> HashMap<String, String> {
+    let tokens = tokenize(sql);
+    let Some(open) = tokens.iter().position(|t| is_punctuation(t, "(")) else {
+        return HashMap::new();
+    };
+    let close = matching_paren(&tokens, open);
+    let mut comments: Vec<(Option<String>, Vec<&str>)> = vec![];
+
+    for item in split_top_level(&tokens[open + 1..close]) {
+        let first = item
+            .iter()
+            .position(|t| !t.is_trivia())
+            .unwrap_or(item.len());
+        // The comments before the first newline belong to the previous item
+        let newline = item[..first]
+            .iter()
+            .position(|t| t.kind == TokenKind::Whitespace && t.text.contains('\n'))
+            .unwrap_or(first);
+
+        if let Some((_, previous)) = comments.last_mut() {
+            previous.extend(comment_texts(&item[..newline]));
+        }
+
+        let name = item
+            .get(first)
+            .filter(|_| !is_table_constraint(&item[first..]))
+            .map(|t| unquote(t.text).to_lowercase());
+        let mut texts = comment_texts(&item[newline..first]);
+        let last = item.iter().rposition(|t| !t.is_trivia()).unwrap_or(first);
+
+        texts.extend(comment_texts(&item[last.max(first)..]));
+        comments.push((name, texts));
+    }
+
+    comments
+        .into_iter()
+        .filter_map(|(name, texts)| Some((name?, texts)))
+        .filter(|(_, texts)| !texts.is_empty())
+        .map(|(name, texts)| (name, texts.join("\n")))
+        .collect()
+}
+
+/// The text of the comments without the comment markers
+fn comment_texts<'a>(tokens: &[Token<'a>]) -> Vec<&'a str> {
+    tokens
+        .iter()
+        .filter_map(|t| match t.kind {
+            TokenKind::LineComment => Some(&t.text[2..]),
+            TokenKind::BlockComment => Some(t.text[2..].strip_suffix("*/").unwrap_or(&t.text[2..])),
+            _ => None,
+        })
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+/// Appends column definitions to a CREATE TABLE statement, like ALTER TABLE ADD COLUMN does
+pub(crate) fn with_added_columns(sql: &str, definitions: &[&str]) -> String {
+    let tokens = tokenize_without_trivia(sql);
+    let Some(open) = tokens.iter().position(|t| is_punctuation(t, "(")) else {
+        return sql.to_string();
+    };
+    let close = tokens[matching_paren(&tokens, open)].offset;
+
+    definitions
+        .iter()
+        .fold(sql[..close].to_string(), |sql, d| format!("{sql}, {d}"))
+        + &sql[close..]
+}
+
+/// Replaces the table name of a CREATE TABLE statement, the schema name is kept
+pub(crate) fn rename_create_table(sql: &str, table_name: &str) -> String {
+    let tokens = tokenize_without_trivia(sql);
+    let mut i = tokens.iter().position(|t| t.is_keyword("TABLE")).unwrap() + 1;
+
+    if tokens[i].is_keyword("IF") {
+        // IF NOT EXISTS
+        i += 3;
+    }
+
+    if tokens.get(i + 1).map(|t| is_punctuation(t, ".")) == Some(true) {
+        i += 2;
+    }
+
+    let name = &tokens[i];
+
+    format!(
+        "{}{}{}",
+        &sql[..name.offset],
+        quote_identifier(table_name),
+        &sql[name.offset + name.text.len()..]
+    )
+}
+
+/// Renames a column in the CREATE TABLE statement of its table: in the definition, the
+/// constraints and the foreign keys of the table that refer to itself
+pub(crate) fn rename_column_in_create_table(
+    sql: &str,
+    table_name: &str,
+    column_name: &str,
+    new_name: &str,
+) -> String {
+    let tokens = tokenize_without_trivia(sql);
+    let mut renamed = vec![];
+    let mut i = tokens
+        .iter()
+        .position(|t| is_punctuation(t, "("))
+        .unwrap_or(tokens.len());
+
+    while i < tokens.len() {
+        if tokens[i].is_keyword("REFERENCES") {
+            let self_reference = tokens
+                .get(i + 1)
+                .is_some_and(|t| unquote(t.text).eq_ignore_ascii_case(table_name));
+
+            i += 2;
+
+            // The columns of another table are left as is
+            if tokens.get(i).is_some_and(|t| is_punctuation(t, "(")) && !self_reference {
+                i = matching_paren(&tokens, i) + 1;
+            }
+
+            continue;
+        }
+
+        if is_name(&tokens[i], column_name) {
+            renamed.push(i);
+        }
+
+        i += 1;
+    }
+
+    replace_names(sql, &tokens, &renamed, new_name)
+}
+
+/// Renames a column of the table in a CREATE INDEX statement, in the indexed columns and
+/// expressions and the WHERE clause
+pub(crate) fn rename_column_in_create_index(
+    sql: &str,
+    column_name: &str,
+    new_name: &str,
+) -> String {
+    let tokens = tokenize_without_trivia(sql);
+    let open = tokens
+        .iter()
+        .position(|t| is_punctuation(t, "("))
+        .unwrap_or(tokens.len());
+    // Function names are followed by their arguments
+    let renamed = (open..tokens.len())
+        .filter(|i| is_name(&tokens[*i], column_name))
+        .filter(|i| !tokens.get(i + 1).is_some_and(|t| is_punctuation(t, "(")))
+        .collect::<Vec<_>>();
+
+    replace_names(sql, &tokens, &renamed, new_name)
+}
+
+/// Renames the index, its table and the columns of the table in a CREATE INDEX statement
+/// `columns` maps the lowercase column names to their new names, other names are kept.
+pub(crate) fn rename_create_index(
+    sql: &str,
+    index_name: &str,
+    table_name: &str,
+    columns: &HashMap<String, String>,
+) -> String {
+    let tokens = tokenize_without_trivia(sql);
+    let on = tokens.iter().position(|t| t.is_keyword("ON")).unwrap();
+    let mut renamed = vec![(on - 1, index_name), (on + 1, table_name)];
+
+    for i in on + 2..tokens.len() {
+        let token = &tokens[i];
+
+        // Function names are followed by their arguments
+        if !matches!(token.kind, TokenKind::Word | TokenKind::QuotedIdentifier)
+            || tokens.get(i + 1).is_some_and(|t| is_punctuation(t, "("))
+        {
+            continue;
+        }
+
+        if let Some(name) = columns.get(&unquote(token.text).to_lowercase()) {
+            renamed.push((i, name));
+        }
+    }
+
+    replace_tokens(sql, &tokens, &renamed)
+}
+
+/// Renames the column in the column lists of the REFERENCES clauses that refer to its table
+pub(crate) fn rename_referenced_column(
+    sql: &str,
+    table_name: &str,
+    column_name: &str,
+    new_name: &str,
+) -> String {
+    let tokens = tokenize_without_trivia(sql);
+    let mut renamed = vec![];
+
+    for (i, token) in tokens.iter().enumerate() {
+        let refers_to_table = token.is_keyword("REFERENCES")
+            && tokens
+                .get(i + 1)
+                .is_some_and(|t| unquote(t.text).eq_ignore_ascii_case(table_name))
+            && tokens.get(i + 2).is_some_and(|t| is_punctuation(t, "("));
+
+        if refers_to_table {
+            let close = matching_paren(&tokens, i + 2);
+
+            renamed.extend((i + 3..close).filter(|c| is_name(&tokens[*c], column_name)));
+        }
+    }
+
+    replace_names(sql, &tokens, &renamed, new_name)
+}
+
+/// Renames a column of the table in the SQL of a view or trigger
+/// Qualified names are renamed when the qualifier is the table, an alias of the table or, when
+/// `row_table` is set, `new` or `old`. Unqualified names are renamed when `unqualified` is set,
+/// which is only correct when no other table of the statement has a column with the name.
+pub(crate) fn rename_column_reference(
+    sql: &str,
+    table_name: &str,
+    column_name: &str,
+    new_name: &str,
+    unqualified: bool,
+    row_table: bool,
+) -> String {
+    let tokens = tokenize_without_trivia(sql);
+    let mut qualifiers = vec![table_name.to_lowercase()];
+
+    if row_table {
+        qualifiers.extend(["new".to_string(), "old".to_string()]);
+    }
+
+    for (i, token) in tokens.iter().enumerate().skip(1) {
+        let is_table_reference = is_name(token, table_name)
+            && ["FROM", "JOIN", "UPDATE", "INTO", ","]
+                .iter()
+                .any(|k| tokens[i - 1].is_keyword(k) || tokens[i - 1].text == *k);
+        let alias = match (tokens.get(i + 1), tokens.get(i + 2)) {
+            (Some(as_), Some(alias)) if as_.is_keyword("AS") => Some(alias),
+            (Some(alias), _) if alias.kind == TokenKind::Word && !alias.is_any_keyword() => {
+                Some(alias)
+            }
+            _ => None,
+        };
+
+        if let (true, Some(alias)) = (is_table_reference, alias) {
+            qualifiers.push(unquote(alias.text).to_lowercase());
+        }
+    }
+
+    let renamed = (0..tokens.len())
+        .filter(|i| is_name(&tokens[*i], column_name))
+        .filter(|i| match i.checked_sub(2) {
+            Some(q) if is_punctuation(&tokens[i - 1], ".") => {
+                qualifiers.contains(&unquote(tokens[q].text).to_lowercase())
+            }
+            _ => unqualified,
+        })
+        .collect::<Vec<_>>();
+
+    replace_names(sql, &tokens, &renamed, new_name)
+}
+
+/// Checks if the token is the identifier, case insensitive
+fn is_name(token: &Token, name: &str) -> bool {
+    matches!(token.kind, TokenKind::Word | TokenKind::QuotedIdentifier)
+        && unquote(token.text).eq_ignore_ascii_case(name)
+}
+
+/// Replaces the tokens at the indexes with the quoted name
+fn replace_names(sql: &str, tokens: &[Token], indexes: &[usize], name: &str) -> String {
+    let renamed = indexes.iter().map(|i| (*i, name)).collect::<Vec<_>>();
+
+    replace_tokens(sql, tokens, &renamed)
+}
+
+/// Replaces the tokens at the indexes, in ascending order, with their quoted names
+fn replace_tokens(sql: &str, tokens: &[Token], renamed: &[(usize, &str)]) -> String {
+    let mut replaced = String::new();
+    let mut last = 0;
+
+    for (i, name) in renamed {
+        replaced.push_str(&sql[last..tokens[*i].offset]);
+        replaced.push_str(&quote_identifier(name));
+        last = tokens[*i].offset + tokens[*i].text.len();
+    }
+
+    replaced.push_str(&sql[last..]);
+
+    replaced
+}
+
+/// Makes a CREATE TABLE statement STRICT, `types` are the new declared types of columns by name
+/// The other columns and the table constraints are kept as written.
+#[cfg(feature = "rusqlite")]
+pub(crate) fn strict_create_table(sql: &str, types: &[(&str, &str)]) -> String {
+    let tokens = tokenize_without_trivia(sql);
+    let Some(open) = tokens.iter().position(|t| is_punctuation(t, "(")) else {
+        return sql.to_string();
+    };
+    let close = matching_paren(&tokens, open);
+    let mut strict = String::new();
+    let mut last = 0;
+
+    for item in split_top_level(&tokens[open + 1..close]) {
+        let Some((_, the_type)) = types
+            .iter()
+            .find(|(c, _)| c.eq_ignore_ascii_case(&unquote(item[0].text)))
+        else {
+            continue;
+        };
+
+        if ["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"]
+            .iter()
+            .any(|k| item[0].is_keyword(k))
+        {
+            continue;
+        }
+
+        let mut end = 1;
+
+        while end < item.len() && !is_column_constraint(&item[end]) {
+            if is_punctuation(&item[end], "(") {
+                end = matching_paren(item, end);
+            }
+
+            end += 1;
+        }
+
+        let name_end = item[0].offset + item[0].text.len();
+
+        if end > 1 {
+            strict.push_str(&sql[last..item[1].offset]);
+            strict.push_str(the_type);
+            last = item[end - 1].offset + item[end - 1].text.len();
+        } else {
+            strict.push_str(&sql[last..name_end]);
+            strict.push(' ');
+            strict.push_str(the_type);
+            last = name_end;
+        }
+    }
+
+    strict.push_str(&sql[last..]);
+
+    let strict = strict.trim_end().trim_end_matches(';').trim_end();
+
+    // Table options are separated by commas, like `WITHOUT ROWID, STRICT`
+    if tokens[close + 1..].iter().any(|t| t.text != ";") {
+        format!("{strict}, STRICT")
+    } else {
+        format!("{strict} STRICT")
+    }
+}
+
+fn column_definition(sql: &str, item: &[Token], definition: &mut TableDefinition) {
+    let name = unquote(item[0].text);
+    let mut i = 1;
+
+    while i < item.len() && !is_column_constraint(&item[i]) {
+        if is_punctuation(&item[i], "(") {
+            i = matching_paren(item, i);
+        }
+
+        i += 1;
+    }
+
+    let mut column = ColumnDefinition {
+        name: name.clone(),
+        declared_type: if i > 1 {
+            span(sql, &item[1..i]).to_string()
+        } else {
+            String::new()
+        },
+        not_null: false,
+        default_value: None,
+        generated: false,
+    };
+
+    while i < item.len() {
+        let token = &item[i];
+
+        if token.is_keyword("CONSTRAINT") || token.is_keyword("COLLATE") {
+            i += 2;
+        } else if token.is_keyword("PRIMARY") {
+            definition.primary_key = vec![name.clone()];
+            definition.key_constraints.push((true, vec![name.clone()]));
+            definition.primary_key_desc = next_is_keyword(item, i + 1, "DESC");
+            i += 2;
+        } else if token.is_keyword("NOT") && next_is_keyword(item, i, "NULL") {
+            column.not_null = true;
+            i += 2;
+        } else if token.is_keyword("UNIQUE") {
+            definition.unique.push(vec![name.clone()]);
+            definition.key_constraints.push((false, vec![name.clone()]));
+            i += 1;
+        } else if token.is_keyword("DEFAULT") {
+            i = default_value(sql, item, i + 1, &mut column);
+        } else if token.is_keyword("REFERENCES") {
+            let (mut foreign_key, next) = references(item, i + 1);
+
+            foreign_key.from_column = vec![name.clone()];
+            definition.foreign_keys.push(foreign_key);
+            i = next;
+        } else if token.is_keyword("GENERATED") || token.is_keyword("AS") {
+            column.generated = true;
+            i += 1;
+        } else if is_punctuation(token, "(") {
+            // The expression of CHECK or AS
+            i = matching_paren(item, i) + 1;
+        } else {
+            i += 1;
+        }
+    }
+
+    definition.columns.push(column);
+}
+
+/// Reads the default value starting at `i`, returns the index after the value
+fn default_value(sql: &str, item: &[Token], i: usize, column: &mut ColumnDefinition) -> usize {
+    let token = match item.get(i) {
+        Some(token) => token,
+        None => return i,
+    };
+    let (value, next) = if is_punctuation(token, "(") {
+        let close = matching_paren(item, i);
+
+        (span(sql, &item[i + 1..close]), close + 1)
+    } else if (is_punctuation(token, "-") || is_punctuation(token, "+")) && i + 1 < item.len() {
+        (span(sql, &item[i..i + 2]), i + 2)
+    } else {
+        (token.text, i + 1)
+    };
+
+    column.default_value = Some(value.to_string());
+
+    next
+}
+
+fn table_constraint(item: &[Token], definition: &mut TableDefinition) {
+    let columns = || {
+        item.iter()
+            .position(|t| is_punctuation(t, "("))
+            .map(|open| (open, matching_paren(item, open)))
+    };
+
+    if item[0].is_keyword("PRIMARY") {
+        if let Some((open, close)) = columns() {
+            definition.primary_key = column_list(&item[open + 1..close]);
+            definition
+                .key_constraints
+                .push((true, definition.primary_key.clone()));
+        }
+    } else if item[0].is_keyword("UNIQUE") {
+        if let Some((open, close)) = columns() {
+            definition.unique.push(column_list(&item[open + 1..close]));
+            definition
+                .key_constraints
+                .push((false, column_list(&item[open + 1..close])));
+        }
+    } else if item[0].is_keyword("FOREIGN") {
+        if let Some((open, close)) = columns() {
+            let from_column = column_list(&item[open + 1..close]);
+
+            if next_is_keyword(item, close, "REFERENCES") {
+                let (mut foreign_key, _) = references(item, close + 2);
+
+                foreign_key.from_column = from_column;
+                definition.foreign_keys.push(foreign_key);
+            }
+        }
+    }
+}
+
+/// Parses the foreign key clause after REFERENCES, returns the index after the clause
+/// The `from_column` of the result is empty
+fn references(item: &[Token], mut i: usize) -> (ForeignKeyDefinition, usize) {
+    let mut foreign_key = ForeignKeyDefinition {
+        from_column: vec![],
+        table: item.get(i).map(|t| unquote(t.text)).unwrap_or_default(),
+        to_column: vec![],
+        on_update: OnUpdateAndDelete::NoAction,
+        on_delete: OnUpdateAndDelete::NoAction,
+    };
+
+    i += 1;
+
+    if item.get(i).map(|t| is_punctuation(t, "(")) == Some(true) {
+        let close = matching_paren(item, i);
+
+        foreign_key.to_column = column_list(&item[i + 1..close]);
+        i = close + 1;
+    }
+
+    while let Some(token) = item.get(i) {
+        if token.is_keyword("ON") {
+            let is_delete = next_is_keyword(item, i, "DELETE");
+            let words = if next_is_keyword(item, i + 1, "SET") || next_is_keyword(item, i + 1, "NO")
+            {
+                2
+            } else {
+                1
+            };
+            let action = item
+                .get(i + 2..i + 2 + words)
+                .unwrap_or_default()
+                .iter()
+                .map(|t| t.text.to_uppercase())
+                .collect::<Vec<_>>()
+                .join(" ");
+            let action = OnUpdateAndDelete::from_str(&action);
+
+            if is_delete {
+                foreign_key.on_delete = action;
+            } else {
+                foreign_key.on_update = action;
+            }
+
+            i += 2 + words;
+        } else if token.is_keyword("MATCH") || token.is_keyword("INITIALLY") {
+            i += 2;
+        } else if token.is_keyword("DEFERRABLE")
+            || (token.is_keyword("NOT") && next_is_keyword(item, i, "DEFERRABLE"))
+        {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+
+    (foreign_key, i)
+}
+
+/// The names of the columns in a list of indexed columns, expressions are skipped
+fn column_list(tokens: &[Token]) -> Vec<String> {
+    split_top_level(tokens)
+        .into_iter()
+        .filter(|item| {
+            item.len() == 1
+                || item[1].is_keyword("COLLATE")
+                || item[1].is_keyword("ASC")
+                || item[1].is_keyword("DESC")
+        })
+        .map(|item| unquote(item[0].text))
+        .collect()
+}
+
+/// The CREATE TABLE statement of the columns, foreign keys and the UNIQUE constraints of the
+/// automatic indexes, other indexes are separate statements
+pub(crate) fn create_table_sql(table: &Table) -> String {
+    let names = |columns: &[Column]| {
+        columns
+            .iter()
+            .map(|c| quote_identifier(&c.name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut definitions = table
+        .columns
+        .iter()
+        .map(|c| {
+            let mut definition = quote_identifier(&c.name);
+
+            if !c.declared_type.is_empty() {
+                definition.push_str(&format!(" {}", c.declared_type));
+            }
+
+            if !c.nullable {
+                definition.push_str(" NOT NULL");
+            }
+
+            if let Some(default_value) = &c.default_value {
+                definition.push_str(&format!(" DEFAULT {default_value}"));
+            }
+
+            definition
+        })
+        .collect::<Vec<_>>();
+    let pk = table
+        .columns
+        .iter()
+        .filter(|c| c.part_of_pk)
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if !pk.is_empty() {
+        definitions.push(format!("PRIMARY KEY ({})", names(&pk)));
+    }
+
+    for index in &table.automatic_indexes {
+        if index.origin == IndexOrigin::Unique {
+            definitions.push(format!("UNIQUE ({})", names(&index.columns)));
+        }
+    }
+
+    // SQLite numbers the foreign keys in reverse order of declaration
+    for foreign_key in table.foreign_keys.iter().rev() {
+        definitions.push(format!(
+            "FOREIGN KEY ({}) REFERENCES {} ({}) ON UPDATE {} ON DELETE {}",
+            names(&foreign_key.from_column),
+            quote_identifier(&foreign_key.table),
+            names(&foreign_key.to_column),
+            foreign_key.on_update,
+            foreign_key.on_delete
+        ));
+    }
+
+    format!(
+        "CREATE TABLE {} ({})",
+        quote_identifier(&table.table_name),
+        definitions.join(", ")
+    )
+}
+
+/// Formats a CREATE statement canonically, so statements that only differ in formatting are equal
+/// Comments and the final `;` are removed, keywords and declared types are uppercased and
+/// identifiers are only quoted when needed, always with "". Like SQLite does when it stores the
+/// SQL, `TEMP`, `IF NOT EXISTS` and the `main` schema are removed.
+pub fn canonical_sql(sql: &str) -> String {
+    let mut tokens = tokenize_without_trivia(sql);
+
+    while tokens.last().is_some_and(|t| is_punctuation(t, ";")) {
+        tokens.pop();
+    }
+
+    let mut skipped = vec![];
+    let mut names = vec![];
+    let mut types = vec![];
+
+    if tokens.first().is_some_and(|t| t.is_keyword("CREATE")) {
+        if tokens
+            .get(1)
+            .is_some_and(|t| t.is_keyword("TEMP") || t.is_keyword("TEMPORARY"))
+        {
+            skipped.push(1);
+        }
+
+        let object = (1..tokens.len().min(4)).find(|&i| {
+            ["TABLE", "INDEX", "VIEW", "TRIGGER"]
+                .iter()
+                .any(|k| tokens[i].is_keyword(k))
+        });
+
+        if let Some(mut i) = object.map(|o| o + 1) {
+            if tokens.get(i).is_some_and(|t| t.is_keyword("IF")) {
+                skipped.extend(i..i + 3);
+                i += 3;
+            }
+
+            if tokens
+                .get(i)
+                .is_some_and(|t| unquote(t.text).eq_ignore_ascii_case("main"))
+                && tokens.get(i + 1).is_some_and(|t| is_punctuation(t, "."))
+            {
+                skipped.extend(i..i + 2);
+                i += 2;
+            }
+
+            names.push(i);
+
+            if tokens[object.unwrap()].is_keyword("TABLE")
+                && tokens.get(i + 1).is_some_and(|t| is_punctuation(t, "("))
+            {
+                let close = matching_paren(&tokens, i + 1);
+                let mut start = i + 2;
+
+                for item in split_top_level(&tokens[i + 2..close]) {
+                    start += tokens[start..].iter().position(|t| t == &item[0]).unwrap();
+
+                    if is_table_constraint(item) {
+                        continue;
+                    }
+
+                    names.push(start);
+                    types.extend(
+                        (1..item.len())
+                            .take_while(|&t| !is_column_constraint(&item[t]))
+                            .map(|t| start + t),
+                    );
+                }
+            }
+        }
+    }
+
+    let parts = tokens
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !skipped.contains(i))
+        .map(|(i, token)| {
+            let after_default = i > 0 && tokens[i - 1].is_keyword("DEFAULT");
+
+            match token.kind {
+                // A double quoted DEFAULT is a string literal for SQLite
+                TokenKind::QuotedIdentifier if !after_default => {
+                    (canonical_identifier(&unquote(token.text)), Part::Name)
+                }
+                TokenKind::Word if names.contains(&i) => {
+                    (canonical_identifier(token.text), Part::Name)
+                }
+                TokenKind::Word if types.contains(&i) => (token.text.to_uppercase(), Part::Name),
+                TokenKind::Word
+                    if token.is_any_keyword()
+                        || CONTEXT_KEYWORDS.iter().any(|k| token.is_keyword(k)) =>
+                {
+                    (token.text.to_uppercase(), Part::Keyword)
+                }
+                TokenKind::Word => (token.text.to_string(), Part::Name),
+                TokenKind::Punctuation if token.text == ")" => (")".to_string(), Part::Value),
+                TokenKind::Punctuation => (token.text.to_string(), Part::Punctuation),
+                _ => (token.text.to_string(), Part::Value),
+            }
+        })
+        .collect::<Vec<_>>();
+    let mut canonical = String::new();
+
+    for (i, (text, _)) in parts.iter().enumerate() {
+        if i > 0 {
+            let previous = &parts[i - 1].0;
+            // A sign after an operator, keyword or the start of a list
+            let is_sign = ["-", "+"].contains(&previous.as_str())
+                && (i == 1 || matches!(parts[i - 2].1, Part::Keyword | Part::Punctuation));
+            let no_space = ["(", "."].contains(&previous.as_str())
+                || [")", ",", "."].contains(&text.as_str())
+                || (text == "(" && parts[i - 1].1 == Part::Name)
+                || is_sign;
+
+            if !no_space {
+                canonical.push(' ');
+            }
+        }
+
+        canonical.push_str(text);
+    }
+
+    canonical
+}
+
+/// Words that are only keywords in a CREATE TABLE statement, like in `WITHOUT ROWID`
+const CONTEXT_KEYWORDS: [&str; 3] = ["ROWID", "STORED", "STRICT"];
+
+/// What a token of `canonical_sql` is, to decide on the spacing
+#[derive(Debug, PartialEq, Copy, Clone, Eq)]
+enum Part {
+    /// An identifier, declared type or function name, followed by `(` without a space
+    Name,
+    Keyword,
+    /// A literal, parameter or `)`
+    Value,
+    Punctuation,
+}
+
+/// The identifier without quotes if that is possible, otherwise quoted with ""
+fn canonical_identifier(identifier: &str) -> String {
+    let plain = identifier
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && identifier
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_');
+    let is_keyword = tokenize(identifier)
+        .first()
+        .is_some_and(|t| t.is_any_keyword());
+
+    if plain && !is_keyword {
+        identifier.to_string()
+    } else {
+        quote_identifier(identifier)
+    }
+}
+
+/// The indexes that are not created by the CREATE TABLE statement
+pub(crate) fn explicit_indexes(table: &Table) -> impl Iterator<Item = &Index> {
+    table.indexes.iter().filter(|i| !is_constraint(table, i))
+}
+
+/// Indexes of UNIQUE constraints are created with the table
+/// A UNIQUE constraint of multiple columns is listed as an index per column, so every unique index
+/// of which the columns are part of a UNIQUE constraint and that has no CREATE INDEX statement
+/// belongs to the constraint.
+pub(crate) fn is_constraint(table: &Table, index: &Index) -> bool {
+    if index.name.starts_with("sqlite_autoindex_") {
+        return true;
+    }
+
+    if !index.unique || table.index_sql.contains_key(&index.name) {
+        return false;
+    }
+
+    let Some(definition) = parse_create_table(&table.create_sql) else {
+        return false;
+    };
+
+    definition.unique.iter().any(|unique| {
+        index
+            .columns
+            .iter()
+            .all(|c| unique.iter().any(|u| u.eq_ignore_ascii_case(&c.name)))
+    })
+}
+
+/// The CREATE INDEX statements of the explicit indexes, for tables of which the statements as
+/// stored by SQLite are unknown
+pub(crate) fn generated_index_sql(table: &Table) -> BTreeMap<String, String> {
+    explicit_indexes(table)
+        .map(|i| {
+            let sql = create_index_sql(table, i);
+
+            (i.name.clone(), sql.trim_end_matches(';').to_string())
+        })
+        .collect()
+}
+
+pub(crate) fn create_index_sql(table: &Table, index: &Index) -> String {
+    format!(
+        "CREATE {}INDEX {} ON {} ({});",
+        if index.unique { "UNIQUE " } else { "" },
+        quote_identifier(&index.name),
+        quote_identifier(&table.table_name),
+        index
+            .columns
+            .iter()
+            .map(|c| quote_identifier(&c.name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// Table constraints start with a keyword, column definitions with the column name
+fn is_table_constraint(item: &[Token]) -> bool {
+    ["CONSTRAINT", "PRIMARY", "UNIQUE", "CHECK", "FOREIGN"]
+        .iter()
+        .any(|k| item[0].is_keyword(k))
+}
+
+fn is_column_constraint(token: &Token) -> bool {
+    [
+        "CONSTRAINT",
+        "PRIMARY",
+        "NOT",
+        "NULL",
+        "UNIQUE",
+        "CHECK",
+        "DEFAULT",
+        "COLLATE",
+        "REFERENCES",
+        "GENERATED",
+        "AS",
+    ]
+    .iter()
+    .any(|k| token.is_keyword(k))
+}
+
+fn is_punctuation(token: &Token, punctuation: &str) -> bool {
+    token.kind == TokenKind::Punctuation && token.text == punctuation
+}
+
+fn next_is_keyword(tokens: &[Token], i: usize, keyword: &str) -> bool {
+    tokens.get(i + 1).map(|t| t.is_keyword(keyword)) == Some(true)
+}
+
+/// The index of the closing parenthesis, or the last index if it isn't closed
+fn matching_paren(tokens: &[Token], open: usize) -> usize {
+    let mut depth = 0;
+
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        if is_punctuation(token, "(") {
+            depth += 1;
+        } else if is_punctuation(token, ")") {
+            depth -= 1;
+
+            if depth == 0 {
+                return i;
+            }
+        }
+    }
+
+    tokens.len().saturating_sub(1)
+}
+
+/// Splits on the commas that are not inside parentheses, empty items are removed
+fn split_top_level<'a, 'b>(tokens: &'b [Token<'a>]) -> Vec<&'b [Token<'a>]> {
+    let mut items = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, token) in tokens.iter().enumerate() {
+        if is_punctuation(token, "(") {
+            depth += 1;
+        } else if is_punctuation(token, ")") {
+            depth -= 1;
+        } else if depth == 0 && is_punctuation(token, ",") {
+            items.push(&tokens[start..i]);
+            start = i + 1;
+        }
+    }
+
+    items.push(&tokens[start..]);
+    items.retain(|item| !item.is_empty());
+
+    items
+}
+
+/// The SQL from the start of the first token until the end of the last token
+fn span<'a>(sql: &'a str, tokens: &[Token]) -> &'a str {
+    let last = tokens.last().unwrap();
+
+    &sql[tokens[0].offset..last.offset + last.text.len()]
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::ddl::{
+        automatic_indexes, canonical_sql, check_in_values, column_comments, parse_create_index,
+        parse_create_table, ForeignKeyDefinition,
+    };
+    use crate::tests::metadata_from_sql;
+    use crate::{parse_sql_with_options, IndexOrigin, OnUpdateAndDelete, ParseOptions};
+
+    #[test]
+    fn test_parse_create_table() {
+        let definition = parse_create_table(
+            "CREATE TABLE IF NOT EXISTS \"a \"\"b\"\"\" (
+            id INTEGER CONSTRAINT pk PRIMARY KEY AUTOINCREMENT,
+            [name] VARCHAR ( 10 ) NOT NULL DEFAULT 'x, y' COLLATE NOCASE UNIQUE,
+            amount DEFAULT -1 CHECK (amount > 0),
+            total INT GENERATED ALWAYS AS (amount * 2) STORED,
+            parent INTEGER REFERENCES a(id) ON DELETE SET NULL DEFERRABLE INITIALLY DEFERRED,
+            CONSTRAINT fk FOREIGN KEY (amount, total) REFERENCES other ON UPDATE CASCADE,
+            UNIQUE (amount, parent)
+        ) WITHOUT ROWID",
+        )
+        .unwrap();
+
+        assert_eq!(
+            vec![
+                ("id", "INTEGER", false, None, false),
+                ("name", "VARCHAR ( 10 )", true, Some("'x, y'"), false),
+                ("amount", "", false, Some("-1"), false),
+                ("total", "INT", false, None, true),
+                ("parent", "INTEGER", false, None, false),
+            ],
+            definition
+                .columns
+                .iter()
+                .map(|c| (
+                    c.name.as_str(),
+                    c.declared_type.as_str(),
+                    c.not_null,
+                    c.default_value.as_deref(),
+                    c.generated
+                ))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(vec!["id"], definition.primary_key);
+        assert_eq!(
+            vec![
+                vec!["name".to_string()],
+                vec!["amount".to_string(), "parent".to_string()]
+            ],
+            definition.unique
+        );
+        assert_eq!(
+            vec![
+                ForeignKeyDefinition {
+                    from_column: vec!["parent".to_string()],
+                    table: "a".to_string(),
+                    to_column: vec!["id".to_string()],
+                    on_update: OnUpdateAndDelete::NoAction,
+                    on_delete: OnUpdateAndDelete::SetNull,
+                },
+                ForeignKeyDefinition {
+                    from_column: vec!["amount".to_string(), "total".to_string()],
+                    table: "other".to_string(),
+                    to_column: vec![],
+                    on_update: OnUpdateAndDelete::Cascade,
+                    on_delete: OnUpdateAndDelete::NoAction,
+                },
+            ],
+            definition.foreign_keys
+        );
+
+        let index = parse_create_index(
+            "CREATE UNIQUE INDEX i ON t (a COLLATE NOCASE DESC, lower(b), \"c\") WHERE a > 0",
+        )
+        .unwrap();
+
+        assert!(index.unique);
+        assert!(index.expressions);
+        assert_eq!(vec!["a", "c"], index.columns);
+    }
+
+    #[test]
+    fn test_column_comments() {
+        let comments = column_comments(
+            "CREATE TABLE user ( -- the users
+                -- The id
+                user_id INTEGER PRIMARY KEY, -- starts with 1
+                /* The email,
+                unique */
+                email TEXT,
+                name TEXT /* no comment */ NOT NULL,
+                age INTEGER -- in years
+            )",
+        );
+
+        assert_eq!(3, comments.len());
+        assert_eq!("The id\nstarts with 1", comments["user_id"]);
+        assert_eq!("The email,\n                unique", comments["email"]);
+        assert_eq!("in years", comments["age"]);
+
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER PRIMARY KEY /* The id */);
+            CREATE TABLE post (user_id INTEGER REFERENCES user -- The author
+            );",
+        );
+        let foreign_key = &metadata.table("post").unwrap().foreign_keys[0];
+
+        assert_eq!(
+            Some("The author"),
+            foreign_key.from_column[0].comment.as_deref()
+        );
+        assert_eq!(Some("The id"), foreign_key.to_column[0].comment.as_deref());
+    }
+
+    #[test]
+    fn test_check_in_values() {
+        let values = check_in_values(
+            "CREATE TABLE task (
+                status TEXT NOT NULL CHECK (status IN ('new', 'active', 'it''s done')),
+                priority INTEGER CHECK ((\"Priority\" IN (1, 2, 3))),
+                kind TEXT CHECK (kind IN ('a', 'b') OR kind IS NULL),
+                size TEXT CHECK (size IN ('s', upper('m'))),
+                CHECK (status IN ('other'))
+            )",
+        );
+
+        assert_eq!(2, values.len());
+        assert_eq!(vec!["new", "active", "it's done"], values["status"]);
+        assert_eq!(vec!["1", "2", "3"], values["priority"]);
+
+        let metadata = metadata_from_sql(
+            "CREATE TABLE status (name TEXT NOT NULL PRIMARY KEY CHECK (name IN ('new', 'done')));
+            CREATE TABLE task (status TEXT REFERENCES status, title TEXT);",
+        );
+        let task = metadata.table("task").unwrap();
+
+        assert_eq!(
+            Some(vec!["new".to_string(), "done".to_string()]),
+            task.foreign_keys[0].to_column[0].enum_values
+        );
+        assert_eq!(None, task.column("title").unwrap().enum_values);
+    }
+
+    #[test]
+    fn test_automatic_indexes() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE a (x UNIQUE, y TEXT PRIMARY KEY, z, UNIQUE (x), UNIQUE (z, x));
+            CREATE TABLE b (id INTEGER PRIMARY KEY, x UNIQUE) WITHOUT ROWID;
+            CREATE TABLE c (x UNIQUE, y, PRIMARY KEY (\"X\"));
+            CREATE TABLE d (x INTEGER PRIMARY KEY DESC, y INTEGER UNIQUE);
+            CREATE TABLE e (x INTEGER, PRIMARY KEY (x DESC));",
+        );
+        let origins = |table: &str| {
+            metadata
+                .table(table)
+                .unwrap()
+                .automatic_indexes
+                .iter()
+                .map(|i| (i.name.as_str(), i.origin, i.columns.len()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            vec![
+                ("sqlite_autoindex_a_1", IndexOrigin::Unique, 1),
+                ("sqlite_autoindex_a_2", IndexOrigin::PrimaryKey, 1),
+                ("sqlite_autoindex_a_3", IndexOrigin::Unique, 2),
+            ],
+            origins("a")
+        );
+        // The INTEGER PRIMARY KEY of a WITHOUT ROWID table is indexed last
+        assert_eq!(
+            vec![
+                ("sqlite_autoindex_b_1", IndexOrigin::Unique, 1),
+                ("sqlite_autoindex_b_2", IndexOrigin::PrimaryKey, 1),
+            ],
+            origins("b")
+        );
+        // The primary key takes over the index of the UNIQUE constraint
+        assert_eq!(
+            vec![("sqlite_autoindex_c_1", IndexOrigin::PrimaryKey, 1)],
+            origins("c")
+        );
+        assert_eq!(2, origins("d").len());
+        assert!(origins("e").is_empty());
+
+        // The statements give the same indexes as SQLite
+        for table in metadata.tables.values() {
+            assert_eq!(
+                table.automatic_indexes,
+                automatic_indexes(&table.table_name, &table.create_sql, &table.columns)
+            );
+        }
+    }
+
+    #[test]
+    fn test_canonical_sql() {
+        let canonical = "CREATE TABLE user(id INTEGER NOT NULL PRIMARY KEY, \"first name\" \
+            VARCHAR(10) DEFAULT -1 CHECK (\"first name\" != ''), \"select\" TEXT, \
+            FOREIGN KEY (id) REFERENCES other(id) ON DELETE CASCADE) STRICT";
+
+        assert_eq!(
+            canonical,
+            canonical_sql(
+                "CREATE TABLE IF NOT EXISTS main.[user] ( -- the id
+                    id integer NOT   NULL primary key,
+                    \"first name\" varchar ( 10 ) default -1 check(\"first name\" != ''),
+                    `select` TEXT,
+                    FOREIGN KEY (id) REFERENCES \"other\" ( id ) on delete cascade
+                ) strict;"
+            )
+        );
+        assert_eq!(canonical, canonical_sql(canonical));
+
+        let metadata = parse_sql_with_options(
+            "CREATE TABLE user (
+                id INTEGER PRIMARY KEY -- the id
+            );",
+            ParseOptions {
+                canonical_sql: true,
+                ..Default::default()
+            },
+        );
+        let user = metadata.table("user").unwrap();
+
+        assert_eq!("CREATE TABLE user(id INTEGER PRIMARY KEY)", user.create_sql);
+        assert_eq!(Some("the id"), user.columns[0].comment.as_deref());
+        assert_eq!(
+            "CREATE TABLE t(a, b AS (a * 2) STORED, c DEFAULT \"x\")",
+            canonical_sql("create temp table t(a,b as (a*2) stored,c default \"x\")")
+        );
+        assert_eq!(
+            "CREATE UNIQUE INDEX i ON t(a DESC, b) WHERE a > -1",
+            canonical_sql("CREATE UNIQUE INDEX i ON t (a desc, b) WHERE a>-1")
+        );
+    }
+}
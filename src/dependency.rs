@@ -0,0 +1,465 @@
+//! The tables and columns that the SQL of views and triggers uses, found by walking the tokens
+//! The analysis doesn't fully parse the SQL: unqualified columns are resolved to the first table of
+//! the statement that has a column with the name, and names that don't resolve are skipped.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use crate::tokenizer::{unquote, Token, TokenKind};
+use crate::trigger::TriggerEvent;
+use crate::Metadata;
+
+/// How a statement uses a table
+#[derive(Debug, PartialEq, Copy, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AccessKind {
+    Read,
+    Insert,
+    Update,
+    Delete,
+}
+
+/// The columns of a table that are read or written
+#[derive(Debug, PartialEq, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TableAccess {
+    pub table_name: String,
+    pub kind: AccessKind,
+    /// Sorted, empty when no columns are named, e.g. for a DELETE or `SELECT COUNT(*)`
+    pub columns: Vec<String>,
+}
+
+impl TableAccess {
+    /// The event of the triggers that this access fires, `None` for a read
+    pub fn event(&self) -> Option<TriggerEvent> {
+        match self.kind {
+            AccessKind::Read => None,
+            AccessKind::Insert => Some(TriggerEvent::Insert),
+            AccessKind::Update => Some(TriggerEvent::Update {
+                columns: self.columns.clone(),
+            }),
+            AccessKind::Delete => Some(TriggerEvent::Delete),
+        }
+    }
+}
+
+/// Collects the accesses of statements, merged per table and kind
+pub(crate) struct Accesses<'a> {
+    metadata: &'a Metadata,
+    accesses: BTreeMap<(String, AccessKind), BTreeSet<String>>,
+}
+
+impl<'a> Accesses<'a> {
+    pub fn new(metadata: &'a Metadata) -> Self {
+        Self {
+            metadata,
+            accesses: BTreeMap::new(),
+        }
+    }
+
+    pub fn into_vec(self) -> Vec<TableAccess> {
+        self.accesses
+            .into_iter()
+            .map(|((table_name, kind), columns)| TableAccess {
+                table_name,
+                kind,
+                columns: columns.into_iter().collect(),
+            })
+            .collect()
+    }
+
+    /// Adds the access with the names of the metadata, columns that don't exist (like `rowid`) are
+    /// skipped
+    fn add(&mut self, table_name: &str, kind: AccessKind, columns: &[String]) {
+        let (table_name, columns) = if let Some(table) = self.metadata.table(table_name) {
+            (
+                table.table_name.clone(),
+                columns
+                    .iter()
+                    .filter_map(|c| table.column(c).map(|c| c.name.clone()))
+                    .collect::<Vec<_>>(),
+            )
+        } else if let Some(view) = self
+            .metadata
+            .view(table_name)
+            .filter(|v| !v.columns.is_empty())
+        {
+            (
+                view.name.clone(),
+                columns
+                    .iter()
+                    .filter_map(|c| view.columns.iter().find(|v| v.eq_ignore_ascii_case(c)))
+                    .cloned()
+                    .collect(),
+            )
+        } else {
+            (table_name.to_string(), columns.to_vec())
+        };
+
+        self.accesses
+            .entry((table_name, kind))
+            .or_default()
+            .extend(columns);
+    }
+
+    /// The columns of the table or view, empty if it doesn't exist
+    fn all_columns(&self, table_name: &str) -> Vec<String> {
+        match self.metadata.table(table_name) {
+            Some(table) => table.columns.iter().map(|c| c.name.clone()).collect(),
+            None => self
+                .metadata
+                .view(table_name)
+                .map(|v| v.columns.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn has_column(&self, table_name: &str, column_name: &str) -> bool {
+        match self.metadata.table(table_name) {
+            Some(table) => table.column(column_name).is_some(),
+            None => self.metadata.view(table_name).is_some_and(|v| {
+                v.columns
+                    .iter()
+                    .any(|c| c.eq_ignore_ascii_case(column_name))
+            }),
+        }
+    }
+
+    /// Adds the accesses of a statement or expression
+    /// `row_table` is the table of `new` and `old` in the statements of a trigger
+    pub fn statement(&mut self, tokens: &[Token], row_table: Option<&str>) {
+        // The aliases and names of the tables in the statement, lowercase, with the table name
+        let mut scope: Vec<(String, String)> = vec![];
+        // The tokens that name tables, aliases or written columns
+        let mut skip = HashSet::new();
+        let ctes = cte_names(tokens);
+        let main = main_keyword(tokens);
+
+        if let Some(main) = main {
+            let keyword = tokens[main].text.to_uppercase();
+
+            match keyword.as_str() {
+                "INSERT" | "REPLACE" => {
+                    if let Some(into) = tokens[main..].iter().position(|t| t.is_keyword("INTO")) {
+                        let (table_name, mut end) =
+                            table_reference(tokens, main + into + 1, &mut skip);
+
+                        if let Some(table_name) = table_name {
+                            end = table_alias(tokens, end, &mut skip).1;
+
+                            let mut columns = vec![];
+
+                            if tokens.get(end).map(|t| t.text) == Some("(") {
+                                columns = column_list(tokens, end, &mut skip);
+                            } else if let Some(table) = self.metadata.table(&table_name) {
+                                columns = table.columns.iter().map(|c| c.name.clone()).collect();
+                            }
+
+                            self.add(&table_name, AccessKind::Insert, &columns);
+                        }
+                    }
+                }
+                "UPDATE" => {
+                    let mut start = main + 1;
+
+                    if tokens.get(start).is_some_and(|t| t.is_keyword("OR")) {
+                        start += 2;
+                    }
+
+                    let (table_name, end) = table_reference(tokens, start, &mut skip);
+
+                    if let Some(table_name) = table_name {
+                        let (alias, _) = table_alias(tokens, end, &mut skip);
+                        let columns = assigned_columns(tokens, end, &mut skip);
+
+                        scope.push((table_name.to_lowercase(), table_name.clone()));
+                        scope.extend(alias.map(|a| (a.to_lowercase(), table_name.clone())));
+                        self.add(&table_name, AccessKind::Update, &columns);
+                    }
+                }
+                "DELETE" => {
+                    let (table_name, end) = table_reference(tokens, main + 2, &mut skip);
+
+                    skip.insert(main + 1);
+
+                    if let Some(table_name) = table_name {
+                        let (alias, _) = table_alias(tokens, end, &mut skip);
+
+                        scope.push((table_name.to_lowercase(), table_name.clone()));
+                        scope.extend(alias.map(|a| (a.to_lowercase(), table_name.clone())));
+                        self.add(&table_name, AccessKind::Delete, &[]);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for i in 0..tokens.len() {
+            // `IS DISTINCT FROM` compares values
+            let is_from = tokens[i].is_keyword("FROM")
+                && !skip.contains(&i)
+                && !(i > 0 && tokens[i - 1].is_keyword("DISTINCT"));
+
+            if !is_from && !tokens[i].is_keyword("JOIN") {
+                continue;
+            }
+
+            let mut start = i + 1;
+
+            while let (Some(table_name), end) = table_reference(tokens, start, &mut skip) {
+                // A table-valued function like `json_each(...)`
+                if tokens.get(end).map(|t| t.text) == Some("(") {
+                    break;
+                }
+
+                let (alias, end) = table_alias(tokens, end, &mut skip);
+
+                if !ctes.contains(&table_name.to_lowercase()) {
+                    scope.push((table_name.to_lowercase(), table_name.clone()));
+                    scope.extend(alias.map(|a| (a.to_lowercase(), table_name.clone())));
+                    self.add(&table_name, AccessKind::Read, &[]);
+                }
+
+                if !is_from || tokens.get(end).map(|t| t.text) != Some(",") {
+                    break;
+                }
+
+                start = end + 1;
+            }
+        }
+
+        for (i, token) in tokens.iter().enumerate() {
+            let previous = i.checked_sub(1).map(|i| tokens[i].text);
+            let next = tokens.get(i + 1).map(|t| t.text);
+
+            // `*` and `table.*` select all columns, other stars are `COUNT(*)` or multiplications
+            if token.text == "*" {
+                let tables = if previous == Some(".") {
+                    let qualifier = unquote(tokens[i - 2].text).to_lowercase();
+
+                    scope
+                        .iter()
+                        .filter(|(a, _)| *a == qualifier)
+                        .take(1)
+                        .collect::<Vec<_>>()
+                } else if i > 0
+                    && (previous == Some(",")
+                        || tokens[i - 1].is_keyword("SELECT")
+                        || tokens[i - 1].is_keyword("DISTINCT")
+                        || tokens[i - 1].is_keyword("ALL"))
+                {
+                    scope.iter().collect()
+                } else {
+                    vec![]
+                };
+                // Tables with an alias are in the scope twice
+                let mut table_names = tables
+                    .into_iter()
+                    .map(|(_, t)| t.clone())
+                    .collect::<Vec<_>>();
+
+                table_names.dedup();
+
+                for table_name in table_names {
+                    let columns = self.all_columns(&table_name);
+
+                    self.add(&table_name, AccessKind::Read, &columns);
+                }
+
+                continue;
+            }
+
+            if skip.contains(&i) || !is_identifier(token) {
+                continue;
+            }
+
+            // Functions and the column part of a qualified column
+            if next == Some("(") || previous == Some(".") {
+                continue;
+            }
+
+            let name = unquote(token.text);
+
+            if next == Some(".") {
+                let Some(column) = tokens.get(i + 2).filter(|t| is_identifier(t)) else {
+                    continue;
+                };
+                let column = unquote(column.text);
+                let qualifier = name.to_lowercase();
+
+                if qualifier == "new" || qualifier == "old" {
+                    if let Some(row_table) = row_table {
+                        self.add(row_table, AccessKind::Read, &[column]);
+                    }
+                } else if let Some((_, table_name)) = scope.iter().find(|(a, _)| *a == qualifier) {
+                    let table_name = table_name.clone();
+
+                    self.add(&table_name, AccessKind::Read, &[column]);
+                }
+            } else if let Some((_, table_name)) =
+                scope.iter().find(|(_, t)| self.has_column(t, &name))
+            {
+                let table_name = table_name.clone();
+
+                self.add(&table_name, AccessKind::Read, &[name]);
+            }
+        }
+    }
+}
+
+fn is_identifier(token: &Token) -> bool {
+    matches!(token.kind, TokenKind::Word | TokenKind::QuotedIdentifier)
+}
+
+/// The first INSERT, REPLACE, UPDATE, DELETE or SELECT outside of parentheses
+fn main_keyword(tokens: &[Token]) -> Option<usize> {
+    let mut depth = 0;
+
+    tokens.iter().position(|t| {
+        match t.text {
+            "(" => depth += 1,
+            ")" => depth -= 1,
+            _ => {}
+        }
+
+        depth == 0
+            && ["INSERT", "REPLACE", "UPDATE", "DELETE", "SELECT"]
+                .iter()
+                .any(|k| t.is_keyword(k))
+    })
+}
+
+/// The lowercase names of the common table expressions of `WITH`
+fn cte_names(tokens: &[Token]) -> Vec<String> {
+    if !tokens.first().is_some_and(|t| t.is_keyword("WITH")) {
+        return vec![];
+    }
+
+    tokens
+        .windows(2)
+        .filter(|w| is_identifier(&w[0]) && (w[1].is_keyword("AS") || w[1].text == "("))
+        .map(|w| unquote(w[0].text).to_lowercase())
+        .collect()
+}
+
+/// The table name at `start`, with an optional schema, and the index after it
+/// The tokens of the name are added to `skip`.
+fn table_reference(
+    tokens: &[Token],
+    start: usize,
+    skip: &mut HashSet<usize>,
+) -> (Option<String>, usize) {
+    let Some(token) = tokens.get(start).filter(|t| is_identifier(t)) else {
+        return (None, start);
+    };
+
+    if tokens.get(start + 1).map(|t| t.text) == Some(".") {
+        if let Some(name) = tokens.get(start + 2).filter(|t| is_identifier(t)) {
+            skip.extend(start..start + 3);
+
+            return (Some(unquote(name.text)), start + 3);
+        }
+    }
+
+    skip.insert(start);
+
+    (Some(unquote(token.text)), start + 1)
+}
+
+/// The alias at `start` (`AS alias` or a word that is not a keyword) and the index after it
+fn table_alias(
+    tokens: &[Token],
+    start: usize,
+    skip: &mut HashSet<usize>,
+) -> (Option<String>, usize) {
+    match tokens.get(start) {
+        Some(t) if t.is_keyword("AS") => match tokens.get(start + 1) {
+            Some(alias) if is_identifier(alias) => {
+                skip.extend([start, start + 1]);
+
+                (Some(unquote(alias.text)), start + 2)
+            }
+            _ => (None, start),
+        },
+        Some(t) if is_identifier(t) && !t.is_any_keyword() => {
+            skip.insert(start);
+
+            (Some(unquote(t.text)), start + 1)
+        }
+        _ => (None, start),
+    }
+}
+
+/// The names of the column list that opens at `start`
+fn column_list(tokens: &[Token], start: usize, skip: &mut HashSet<usize>) -> Vec<String> {
+    let mut columns = vec![];
+
+    for (i, token) in tokens.iter().enumerate().skip(start + 1) {
+        if token.text == ")" {
+            break;
+        }
+
+        if is_identifier(token) {
+            skip.insert(i);
+            columns.push(unquote(token.text));
+        }
+    }
+
+    columns
+}
+
+/// The columns on the left side of the assignments of `SET`, also `(a, b) = ...`
+fn assigned_columns(tokens: &[Token], start: usize, skip: &mut HashSet<usize>) -> Vec<String> {
+    let Some(set) = tokens[start.min(tokens.len())..]
+        .iter()
+        .position(|t| t.is_keyword("SET"))
+        .map(|s| s + start)
+    else {
+        return vec![];
+    };
+    let mut columns = vec![];
+    let mut depth = 0;
+    let mut i = set + 1;
+
+    while let Some(token) = tokens.get(i) {
+        match token.text {
+            "(" if depth == 0 => {
+                let close = tokens[i..]
+                    .iter()
+                    .position(|t| t.text == ")")
+                    .map(|c| c + i);
+
+                if let Some(close) = close {
+                    if tokens.get(close + 1).map(|t| t.text) == Some("=") {
+                        columns.extend(column_list(tokens, i, skip));
+                        i = close + 1;
+
+                        continue;
+                    }
+                }
+
+                depth += 1;
+            }
+            "(" => depth += 1,
+            ")" => depth -= 1,
+            _ if depth == 0
+                && ["FROM", "WHERE", "RETURNING"]
+                    .iter()
+                    .any(|k| token.is_keyword(k)) =>
+            {
+                break;
+            }
+            _ if depth == 0
+                && is_identifier(token)
+                && tokens.get(i + 1).map(|t| t.text) == Some("=") =>
+            {
+                skip.insert(i);
+                columns.push(unquote(token.text));
+            }
+            _ => {}
+        }
+
+        i += 1;
+    }
+
+    columns
+}
@@ -0,0 +1,719 @@
+//! Compares the structure of two schemas, e.g. of two versions of a database file
+
+use std::fmt::{Display, Formatter, Result};
+#[cfg(feature = "rusqlite")]
+use std::path::Path;
+
+#[cfg(feature = "rusqlite")]
+use rusqlite::{Connection, OpenFlags};
+
+use crate::ddl::{create_index_sql, explicit_indexes};
+use crate::{canonical_sql, Column, ForeignKey, Index, Metadata, Table};
+
+/// The changes to get from one schema to another, sorted by name
+/// Only the structure is compared, data like row counts and statistics is ignored
+#[derive(Debug, PartialEq, Clone, Eq, Default)]
+pub struct SchemaDiff {
+    pub added_tables: Vec<Table>,
+    pub removed_tables: Vec<Table>,
+    pub changed_tables: Vec<TableDiff>,
+}
+
+/// The changes of a table that is in both schemas
+/// Changed foreign keys and indexes are removed and added, since they are recreated anyway
+#[derive(Debug, PartialEq, Clone, Eq, Default)]
+pub struct TableDiff {
+    pub table_name: String,
+    pub added_columns: Vec<Column>,
+    pub removed_columns: Vec<Column>,
+    pub changed_columns: Vec<ColumnChange>,
+    pub added_foreign_keys: Vec<ForeignKey>,
+    pub removed_foreign_keys: Vec<ForeignKey>,
+    pub added_indexes: Vec<Index>,
+    pub removed_indexes: Vec<Index>,
+    /// If the CREATE TABLE statements differ after `canonical_sql`, also for changes the other
+    /// fields don't cover, like a CHECK constraint or a collation
+    pub definition_changed: bool,
+}
+
+/// A column with the same name but a different type, nullability, primary key or default value
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct ColumnChange {
+    pub old: Column,
+    pub new: Column,
+}
+
+impl SchemaDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_tables.is_empty()
+            && self.removed_tables.is_empty()
+            && self.changed_tables.is_empty()
+    }
+}
+
+impl TableDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_columns.is_empty()
+            && self.removed_columns.is_empty()
+            && self.changed_columns.is_empty()
+            && self.added_foreign_keys.is_empty()
+            && self.removed_foreign_keys.is_empty()
+            && self.added_indexes.is_empty()
+            && self.removed_indexes.is_empty()
+            && !self.definition_changed
+    }
+}
+
+impl Metadata {
+    /// The changes to get from this schema to the other schema
+    /// Tables and columns are matched by name with `Metadata::lookup` and `Table::lookup`, internal
+    /// tables like `sqlite_stat1` are skipped since SQLite maintains them
+    pub fn diff(&self, other: &Metadata) -> SchemaDiff {
+        let mut diff = SchemaDiff::default();
+
+        for table in self
+            .sorted_tables()
+            .into_iter()
+            .filter(|t| !t.is_internal())
+        {
+            match other.table(&table.table_name) {
+                Some(other_table) => {
+                    let table_diff = diff_table(table, other_table);
+
+                    if !table_diff.is_empty() {
+                        diff.changed_tables.push(table_diff);
+                    }
+                }
+                None => diff.removed_tables.push(table.clone()),
+            }
+        }
+
+        for table in other
+            .sorted_tables()
+            .into_iter()
+            .filter(|t| !t.is_internal())
+        {
+            if self.table(&table.table_name).is_none() {
+                diff.added_tables.push(table.clone());
+            }
+        }
+
+        diff
+    }
+
+    /// A hash of the structure, schemas without a `diff` have the same fingerprint
+    /// The CREATE TABLE and CREATE INDEX statements are hashed in the form of `canonical_sql` and
+    /// sorted by name, so formatting and the order of creation don't matter. The hash (FNV-1a) is
+    /// the same on every platform and version, so it can be stored.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hash = 0xcbf29ce484222325u64;
+
+        for table in self
+            .sorted_tables()
+            .into_iter()
+            .filter(|t| !t.is_internal())
+        {
+            let mut indexes = explicit_indexes(table).collect::<Vec<_>>();
+
+            indexes.sort_by(|a, b| a.name.cmp(&b.name));
+
+            let statements = [canonical_sql(&table.create_sql)].into_iter().chain(
+                indexes
+                    .iter()
+                    .map(|i| canonical_sql(&create_index_sql(table, i))),
+            );
+
+            for statement in statements {
+                for byte in statement.bytes().chain([b';']) {
+                    hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+                }
+            }
+        }
+
+        hash
+    }
+}
+
+fn diff_table(old: &Table, new: &Table) -> TableDiff {
+    let mut diff = TableDiff {
+        table_name: new.table_name.clone(),
+        ..Default::default()
+    };
+
+    for column in &old.columns {
+        match new.column(&column.name) {
+            Some(new_column) if !same_column(column, new_column) => {
+                diff.changed_columns.push(ColumnChange {
+                    old: column.clone(),
+                    new: new_column.clone(),
+                })
+            }
+            Some(_) => {}
+            None => diff.removed_columns.push(column.clone()),
+        }
+    }
+
+    diff.added_columns = new
+        .columns
+        .iter()
+        .filter(|c| old.column(&c.name).is_none())
+        .cloned()
+        .collect();
+    diff.removed_foreign_keys = missing(&old.foreign_keys, &new.foreign_keys, same_foreign_key);
+    diff.added_foreign_keys = missing(&new.foreign_keys, &old.foreign_keys, same_foreign_key);
+    diff.removed_indexes = missing(&old.indexes, &new.indexes, same_index);
+    diff.added_indexes = missing(&new.indexes, &old.indexes, same_index);
+    diff.definition_changed = canonical_sql(&old.create_sql) != canonical_sql(&new.create_sql);
+
+    diff
+}
+
+/// The elements that have no equal element in the others
+fn missing<T: Clone>(elements: &[T], others: &[T], same: fn(&T, &T) -> bool) -> Vec<T> {
+    elements
+        .iter()
+        .filter(|e| !others.iter().any(|o| same(e, o)))
+        .cloned()
+        .collect()
+}
+
+/// The id is ignored, it changes when an earlier column is dropped
+fn same_column(column: &Column, other: &Column) -> bool {
+    column.name.eq_ignore_ascii_case(&other.name)
+        && column.the_type == other.the_type
+        && column.nullable == other.nullable
+        && column.part_of_pk == other.part_of_pk
+        && column.default_value == other.default_value
+        && column.logical_type == other.logical_type
+}
+
+fn same_columns(columns: &[Column], other: &[Column]) -> bool {
+    columns.len() == other.len()
+        && columns
+            .iter()
+            .zip(other)
+            .all(|(c, o)| c.name.eq_ignore_ascii_case(&o.name))
+}
+
+/// The id is ignored, it depends on the order of the foreign keys
+fn same_foreign_key(foreign_key: &ForeignKey, other: &ForeignKey) -> bool {
+    foreign_key.table.eq_ignore_ascii_case(&other.table)
+        && same_columns(&foreign_key.from_column, &other.from_column)
+        && same_columns(&foreign_key.to_column, &other.to_column)
+        && foreign_key.on_update == other.on_update
+        && foreign_key.on_delete == other.on_delete
+}
+
+fn same_index(index: &Index, other: &Index) -> bool {
+    index.name.eq_ignore_ascii_case(&other.name)
+        && index.unique == other.unique
+        && same_columns(&index.columns, &other.columns)
+}
+
+/// The changes of two schemas that are both derived from a base schema, e.g. two branches that
+/// both added migrations
+#[derive(Debug, PartialEq, Clone, Eq, Default)]
+pub struct ThreeWayMerge {
+    /// The changes of both sides to the base schema, without the conflicting changes
+    pub changes: SchemaDiff,
+    pub conflicts: Vec<SchemaConflict>,
+}
+
+/// Changes of both sides that can't be combined
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub enum SchemaConflict {
+    /// Both sides added the table with a different structure
+    Table { table: String },
+    /// One side removed the table, the other side changed it
+    RemovedTable { table: String },
+    /// Both sides added or changed the column differently
+    Column { table: String, column: String },
+    /// One side removed the column, the other side changed it
+    RemovedColumn { table: String, column: String },
+    /// Both sides added an index with the same name but a different definition
+    Index { table: String, index: String },
+}
+
+/// Combines the changes of both derived schemas to the base schema
+/// Equal changes on both sides are taken once, conflicting changes are left out of the changes
+pub fn merge_three_way(base: &Metadata, ours: &Metadata, theirs: &Metadata) -> ThreeWayMerge {
+    let ours = base.diff(ours);
+    let theirs = base.diff(theirs);
+    let mut conflicts = vec![];
+    let mut changes = SchemaDiff {
+        added_tables: union(
+            &ours.added_tables,
+            &theirs.added_tables,
+            |t| &t.table_name,
+            |t, o| diff_table(t, o).is_empty(),
+            |t| {
+                conflicts.push(SchemaConflict::Table {
+                    table: t.table_name.clone(),
+                })
+            },
+        ),
+        removed_tables: union(
+            &ours.removed_tables,
+            &theirs.removed_tables,
+            |t| &t.table_name,
+            |_, _| true,
+            |_| {},
+        ),
+        changed_tables: vec![],
+    };
+
+    for (removed, changed) in [(&ours, &theirs), (&theirs, &ours)] {
+        for table in &removed.removed_tables {
+            if find(&changed.changed_tables, &table.table_name, |t| {
+                &t.table_name
+            })
+            .is_some()
+            {
+                conflicts.push(SchemaConflict::RemovedTable {
+                    table: table.table_name.clone(),
+                });
+                changes
+                    .removed_tables
+                    .retain(|t| !t.table_name.eq_ignore_ascii_case(&table.table_name));
+            }
+        }
+    }
+
+    for table in &ours.changed_tables {
+        if find(&theirs.removed_tables, &table.table_name, |t| &t.table_name).is_some() {
+            continue;
+        }
+
+        changes.changed_tables.push(
+            match find(&theirs.changed_tables, &table.table_name, |t| &t.table_name) {
+                Some(other) => merge_table_diff(table, other, &mut conflicts),
+                None => table.clone(),
+            },
+        );
+    }
+
+    for table in &theirs.changed_tables {
+        if find(&ours.removed_tables, &table.table_name, |t| &t.table_name).is_none()
+            && find(&ours.changed_tables, &table.table_name, |t| &t.table_name).is_none()
+        {
+            changes.changed_tables.push(table.clone());
+        }
+    }
+
+    changes.changed_tables.retain(|t| !t.is_empty());
+    changes
+        .changed_tables
+        .sort_by(|a, b| a.table_name.cmp(&b.table_name));
+
+    ThreeWayMerge { changes, conflicts }
+}
+
+fn merge_table_diff(
+    ours: &TableDiff,
+    theirs: &TableDiff,
+    conflicts: &mut Vec<SchemaConflict>,
+) -> TableDiff {
+    let table = &ours.table_name;
+    let mut column_conflict = |column: &str| {
+        conflicts.push(SchemaConflict::Column {
+            table: table.clone(),
+            column: column.to_string(),
+        })
+    };
+    let mut diff = TableDiff {
+        table_name: table.clone(),
+        added_columns: union(
+            &ours.added_columns,
+            &theirs.added_columns,
+            |c| &c.name,
+            same_column,
+            |c| column_conflict(&c.name),
+        ),
+        changed_columns: union(
+            &ours.changed_columns,
+            &theirs.changed_columns,
+            |c| &c.new.name,
+            |c, o| same_column(&c.new, &o.new),
+            |c| column_conflict(&c.new.name),
+        ),
+        removed_columns: union(
+            &ours.removed_columns,
+            &theirs.removed_columns,
+            |c| &c.name,
+            |_, _| true,
+            |_| {},
+        ),
+        added_foreign_keys: union(
+            &ours.added_foreign_keys,
+            &theirs.added_foreign_keys,
+            |_| "",
+            same_foreign_key,
+            |_| {},
+        ),
+        removed_foreign_keys: union(
+            &ours.removed_foreign_keys,
+            &theirs.removed_foreign_keys,
+            |_| "",
+            same_foreign_key,
+            |_| {},
+        ),
+        added_indexes: union(
+            &ours.added_indexes,
+            &theirs.added_indexes,
+            |i| &i.name,
+            same_index,
+            |i| {
+                conflicts.push(SchemaConflict::Index {
+                    table: table.clone(),
+                    index: i.name.clone(),
+                })
+            },
+        ),
+        removed_indexes: union(
+            &ours.removed_indexes,
+            &theirs.removed_indexes,
+            |i| &i.name,
+            |_, _| true,
+            |_| {},
+        ),
+        definition_changed: ours.definition_changed || theirs.definition_changed,
+    };
+
+    for (removed, changed) in [(ours, theirs), (theirs, ours)] {
+        for column in &removed.removed_columns {
+            if find(&changed.changed_columns, &column.name, |c| &c.new.name).is_some() {
+                conflicts.push(SchemaConflict::RemovedColumn {
+                    table: table.clone(),
+                    column: column.name.clone(),
+                });
+                diff.removed_columns
+                    .retain(|c| !c.name.eq_ignore_ascii_case(&column.name));
+                diff.changed_columns
+                    .retain(|c| !c.new.name.eq_ignore_ascii_case(&column.name));
+            }
+        }
+    }
+
+    diff
+}
+
+fn find<'a, T>(elements: &'a [T], name: &str, name_of: fn(&T) -> &str) -> Option<&'a T> {
+    elements
+        .iter()
+        .find(|e| name_of(e).eq_ignore_ascii_case(name))
+}
+
+/// The elements of both sides, elements with the same name are taken once when they are the
+/// same and are left out when they conflict
+/// Elements without a name are matched with `same`
+fn union<T: Clone>(
+    ours: &[T],
+    theirs: &[T],
+    name_of: fn(&T) -> &str,
+    same: fn(&T, &T) -> bool,
+    mut conflict: impl FnMut(&T),
+) -> Vec<T> {
+    let matches = |e: &T, o: &T| {
+        let name = name_of(e);
+
+        if name.is_empty() {
+            same(e, o)
+        } else {
+            name.eq_ignore_ascii_case(name_of(o))
+        }
+    };
+    let mut elements = vec![];
+
+    for element in ours {
+        match theirs.iter().find(|o| matches(element, o)) {
+            Some(other) if !same(element, other) => conflict(element),
+            _ => elements.push(element.clone()),
+        }
+    }
+
+    for element in theirs {
+        if !ours.iter().any(|o| matches(element, o)) {
+            elements.push(element.clone());
+        }
+    }
+
+    elements
+}
+
+/// Parses both database files and returns the changes to get from the first to the second
+/// The files are opened read only, so a missing file is an error instead of an empty database
+#[cfg(feature = "rusqlite")]
+pub fn diff_files<P: AsRef<Path>, Q: AsRef<Path>>(
+    path_a: P,
+    path_b: Q,
+) -> rusqlite::Result<SchemaDiff> {
+    Ok(parse_read_only(path_a)?.diff(&parse_read_only(path_b)?))
+}
+
+/// Parses the database file opened read only, a missing file is an error
+#[cfg(feature = "rusqlite")]
+pub(crate) fn parse_read_only<P: AsRef<Path>>(path: P) -> rusqlite::Result<Metadata> {
+    let connection = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+
+    // Opening doesn't validate the file, reading the schema does
+    connection.query_row("SELECT COUNT(*) FROM sqlite_master;", [], |row| {
+        row.get::<_, i64>(0)
+    })?;
+
+    Ok(crate::metadata_from_connection(
+        &connection,
+        Default::default(),
+    ))
+}
+
+fn names(columns: &[Column]) -> String {
+    columns
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl Display for SchemaDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        for table in &self.added_tables {
+            writeln!(f, "+ table {}", table.table_name)?;
+        }
+
+        for table in &self.removed_tables {
+            writeln!(f, "- table {}", table.table_name)?;
+        }
+
+        for table in &self.changed_tables {
+            write!(f, "{table}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for TableDiff {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        writeln!(f, "~ table {}", self.table_name)?;
+
+        for column in &self.added_columns {
+            writeln!(f, "  + column {} {}", column.name, column.the_type)?;
+        }
+
+        for column in &self.removed_columns {
+            writeln!(f, "  - column {}", column.name)?;
+        }
+
+        for change in &self.changed_columns {
+            writeln!(
+                f,
+                "  ~ column {}: {} -> {}",
+                change.new.name, change.old, change.new
+            )?;
+        }
+
+        for foreign_key in &self.added_foreign_keys {
+            writeln!(
+                f,
+                "  + foreign key ({}) -> {}",
+                names(&foreign_key.from_column),
+                foreign_key.table
+            )?;
+        }
+
+        for foreign_key in &self.removed_foreign_keys {
+            writeln!(
+                f,
+                "  - foreign key ({}) -> {}",
+                names(&foreign_key.from_column),
+                foreign_key.table
+            )?;
+        }
+
+        for index in &self.added_indexes {
+            writeln!(f, "  + index {} ({})", index.name, names(&index.columns))?;
+        }
+
+        for index in &self.removed_indexes {
+            writeln!(f, "  - index {}", index.name)?;
+        }
+
+        // Changed columns and foreign keys already explain a changed definition
+        let explained = !self.added_columns.is_empty()
+            || !self.removed_columns.is_empty()
+            || !self.changed_columns.is_empty()
+            || !self.added_foreign_keys.is_empty()
+            || !self.removed_foreign_keys.is_empty();
+
+        if self.definition_changed && !explained {
+            writeln!(f, "  ~ definition")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Display for SchemaConflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            SchemaConflict::Table { table } => {
+                write!(f, "table {table} is added differently on both sides")
+            }
+            SchemaConflict::RemovedTable { table } => {
+                write!(
+                    f,
+                    "table {table} is removed on one side and changed on the other"
+                )
+            }
+            SchemaConflict::Column { table, column } => {
+                write!(
+                    f,
+                    "column {table}.{column} is changed differently on both sides"
+                )
+            }
+            SchemaConflict::RemovedColumn { table, column } => write!(
+                f,
+                "column {table}.{column} is removed on one side and changed on the other"
+            ),
+            SchemaConflict::Index { table, index } => {
+                write!(
+                    f,
+                    "index {index} of {table} is added differently on both sides"
+                )
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::diff::{diff_files, merge_three_way, SchemaConflict};
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_diff_files() {
+        let dir = std::env::temp_dir();
+        let path_a = dir.join("sqlite_parser_diff_a.sqlite3");
+        let path_b = dir.join("sqlite_parser_diff_b.sqlite3");
+
+        for (path, sql) in [
+            (
+                &path_a,
+                "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT);
+                CREATE TABLE tag (tag_id INTEGER NOT NULL PRIMARY KEY);",
+            ),
+            (
+                &path_b,
+                "CREATE TABLE user (
+                    user_id INTEGER NOT NULL PRIMARY KEY,
+                    name TEXT NOT NULL,
+                    email TEXT
+                );
+                CREATE INDEX user_email ON user(email);
+                CREATE TABLE post (post_id INTEGER NOT NULL PRIMARY KEY);",
+            ),
+        ] {
+            let _ = std::fs::remove_file(path);
+
+            Connection::open(path).unwrap().execute_batch(sql).unwrap();
+        }
+
+        let diff = diff_files(&path_a, &path_b).unwrap();
+
+        assert_eq!(
+            "+ table post
+- table tag
+~ table user
+  + column email TEXT
+  ~ column name: name TEXT -> name TEXT NOT NULL
+  + index user_email (email)
+",
+            diff.to_string()
+        );
+        assert!(diff_files(&path_a, &path_a).unwrap().is_empty());
+        assert!(diff_files(&path_a, dir.join("sqlite_parser_diff_missing.sqlite3")).is_err());
+
+        std::fs::remove_file(path_a).unwrap();
+        std::fs::remove_file(path_b).unwrap();
+    }
+
+    #[test]
+    fn test_diff_definition() {
+        let old = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, age INTEGER CHECK (age > 0));",
+        );
+        // Only the formatting differs
+        let formatted = metadata_from_sql(
+            "create table \"user\" (
+                user_id integer not null primary key, -- the id
+                [age] INTEGER check(age>0)
+            );",
+        );
+
+        assert!(old.diff(&formatted).is_empty());
+
+        let new = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, age INTEGER CHECK (age >= 0));",
+        );
+        let diff = old.diff(&new);
+
+        assert!(diff.changed_tables[0].definition_changed);
+        assert_eq!("~ table user\n  ~ definition\n", diff.to_string());
+        assert_eq!(vec!["user"], old.migration_to(&new).rebuilt_tables);
+    }
+
+    #[test]
+    fn test_merge_three_way() {
+        let base = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT, age INTEGER);
+            CREATE TABLE tag (tag_id INTEGER NOT NULL PRIMARY KEY);",
+        );
+        let ours = metadata_from_sql(
+            "CREATE TABLE user (
+                user_id INTEGER NOT NULL PRIMARY KEY,
+                name TEXT NOT NULL,
+                email TEXT
+            );
+            CREATE TABLE tag (tag_id INTEGER NOT NULL PRIMARY KEY);
+            CREATE TABLE post (post_id INTEGER NOT NULL PRIMARY KEY);",
+        );
+        let theirs = metadata_from_sql(
+            "CREATE TABLE user (
+                user_id INTEGER NOT NULL PRIMARY KEY,
+                name TEXT NOT NULL,
+                age REAL,
+                email INTEGER
+            );
+            CREATE INDEX user_name ON user(name);",
+        );
+        let merge = merge_three_way(&base, &ours, &theirs);
+
+        assert_eq!(
+            vec![
+                SchemaConflict::Column {
+                    table: "user".to_string(),
+                    column: "email".to_string()
+                },
+                SchemaConflict::RemovedColumn {
+                    table: "user".to_string(),
+                    column: "age".to_string()
+                },
+            ],
+            merge.conflicts
+        );
+        assert_eq!(
+            "+ table post
+- table tag
+~ table user
+  ~ column name: name TEXT -> name TEXT NOT NULL
+  + index user_name (name)
+",
+            merge.changes.to_string()
+        );
+    }
+}
@@ -0,0 +1,206 @@
+//! Human readable forms of the schema
+//!
+//! The normal form is a single line, the alternate form (`{:#}`) of a table spans multiple lines.
+//! Names are printed as they are, without quotes.
+
+use std::fmt::{Display, Formatter, Result};
+
+use crate::{Column, ForeignKey, Index, LogicalType, Metadata, OnUpdateAndDelete, Table, Type};
+
+/// Numeric types are written as `NUMERIC(precision, scale)`
+impl Display for Type {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str(match self {
+            Type::Text => "TEXT",
+            Type::Integer => "INTEGER",
+            Type::String => "STRING",
+            Type::Real => "REAL",
+            Type::Blob => "BLOB",
+            Type::Numeric { precision, scale } => {
+                f.write_str("NUMERIC")?;
+
+                return match (precision, scale) {
+                    (Some(precision), Some(scale)) => write!(f, "({precision}, {scale})"),
+                    (Some(precision), None) => write!(f, "({precision})"),
+                    _ => Ok(()),
+                };
+            }
+            Type::Unknown => "UNKNOWN",
+        })
+    }
+}
+
+impl Display for LogicalType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str(match self {
+            LogicalType::Boolean => "BOOLEAN",
+            LogicalType::Date => "DATE",
+            LogicalType::DateTime => "DATETIME",
+            LogicalType::Timestamp => "TIMESTAMP",
+            LogicalType::Json => "JSON",
+            LogicalType::Uuid => "UUID",
+        })
+    }
+}
+
+impl Display for OnUpdateAndDelete {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_str(match self {
+            OnUpdateAndDelete::NoAction => "NO ACTION",
+            OnUpdateAndDelete::Restrict => "RESTRICT",
+            OnUpdateAndDelete::SetNull => "SET NULL",
+            OnUpdateAndDelete::SetDefault => "SET DEFAULT",
+            OnUpdateAndDelete::Cascade => "CASCADE",
+        })
+    }
+}
+
+/// E.g. `user_id INTEGER NOT NULL PRIMARY KEY`
+impl Display for Column {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{} {}", self.name, self.the_type)?;
+
+        if !self.nullable {
+            f.write_str(" NOT NULL")?;
+        }
+
+        if self.part_of_pk {
+            f.write_str(" PRIMARY KEY")?;
+        }
+
+        if let Some(default_value) = &self.default_value {
+            write!(f, " DEFAULT {default_value}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// E.g. `UNIQUE INDEX user_name (name)`
+impl Display for Index {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        if self.unique {
+            f.write_str("UNIQUE ")?;
+        }
+
+        write!(f, "INDEX {} ({})", self.name, column_names(&self.columns))
+    }
+}
+
+/// E.g. `FOREIGN KEY (user_id) REFERENCES user (user_id) ON DELETE CASCADE`
+/// Actions are only printed when they are not `NO ACTION`
+impl Display for ForeignKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(
+            f,
+            "FOREIGN KEY ({}) REFERENCES {} ({})",
+            column_names(&self.from_column),
+            self.table,
+            column_names(&self.to_column)
+        )?;
+
+        if self.on_update != OnUpdateAndDelete::NoAction {
+            write!(f, " ON UPDATE {}", self.on_update)?;
+        }
+
+        if self.on_delete != OnUpdateAndDelete::NoAction {
+            write!(f, " ON DELETE {}", self.on_delete)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The normal form is the table name with its columns, e.g. `user (user_id INTEGER NOT NULL)`
+/// The alternate form prints the columns, foreign keys and indexes on separate, indented lines
+impl Display for Table {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        if !f.alternate() {
+            return write!(
+                f,
+                "{} ({})",
+                self.table_name,
+                self.columns
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+
+        f.write_str(&self.table_name)?;
+
+        if let Some(row_count) = self.row_count {
+            write!(f, " ({row_count} rows)")?;
+        }
+
+        for column in &self.columns {
+            write!(f, "\n  {column}")?;
+        }
+
+        for foreign_key in &self.foreign_keys {
+            write!(f, "\n  {foreign_key}")?;
+        }
+
+        for index in &self.indexes {
+            write!(f, "\n  {index}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Metadata {
+    /// Prints every table in the alternate form, sorted by name and separated by an empty line
+    pub fn pretty_print(&self) -> String {
+        self.sorted_tables()
+            .into_iter()
+            .map(|t| format!("{t:#}"))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+}
+
+fn column_names(columns: &[Column]) -> String {
+    columns
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_display() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT DEFAULT 'x');
+        CREATE TABLE post (
+            post_id INTEGER NOT NULL PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            FOREIGN KEY(user_id) REFERENCES user(user_id) ON DELETE CASCADE
+        );
+        CREATE UNIQUE INDEX user_name ON user(name);",
+        );
+        let user = metadata.table("user").unwrap();
+
+        assert_eq!(
+            "user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT DEFAULT 'x')",
+            user.to_string()
+        );
+        assert_eq!(
+            "post
+  post_id INTEGER NOT NULL PRIMARY KEY
+  user_id INTEGER NOT NULL
+  FOREIGN KEY (user_id) REFERENCES user (user_id) ON DELETE CASCADE
+
+user
+  user_id INTEGER NOT NULL PRIMARY KEY
+  name TEXT DEFAULT 'x'
+  UNIQUE INDEX user_name (name)",
+            metadata.pretty_print()
+        );
+    }
+}
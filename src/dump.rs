@@ -0,0 +1,163 @@
+//! Parses the text output of the `sqlite3` shell commands `.dump` and `.schema`
+//!
+//! Only the CREATE statements are executed, transactions, pragmas and the data (INSERT and
+//! DELETE statements) are skipped. Virtual tables, which `.dump` writes directly into
+//! `sqlite_schema`, are skipped as well.
+
+use crate::tokenizer::{tokenize, Token, TokenKind};
+use crate::{parse_sql, Metadata};
+
+/// Parses the schema of a `.dump` or `.schema` export
+/// Example:
+///
+/// ```
+/// use sqlite_parser::dump::parse_dump;
+///
+/// let metadata = parse_dump(
+///     "PRAGMA foreign_keys=OFF;
+/// BEGIN TRANSACTION;
+/// CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY);
+/// INSERT INTO user VALUES(1);
+/// COMMIT;",
+/// );
+///
+/// assert!(metadata.table("user").is_some());
+/// ```
+pub fn parse_dump(dump: &str) -> Metadata {
+    parse_sql(&schema_statements(dump).join(";\n"))
+}
+
+/// The CREATE statements of the dump, without the terminating semicolon
+fn schema_statements(dump: &str) -> Vec<&str> {
+    split_statements(dump)
+        .into_iter()
+        .filter(|statement| {
+            let tokens = statement
+                .iter()
+                .filter(|t| !t.is_trivia())
+                .collect::<Vec<_>>();
+
+            if !tokens
+                .first()
+                .map(|t| t.is_keyword("CREATE"))
+                .unwrap_or(false)
+            {
+                return false;
+            }
+
+            // Internal tables like sqlite_sequence can't be created, SQLite creates them itself
+            let is_internal_table = tokens.get(1).map(|t| t.is_keyword("TABLE")) == Some(true)
+                && tokens
+                    .iter()
+                    .skip(2)
+                    .find(|t| {
+                        !t.is_keyword("IF") && !t.is_keyword("NOT") && !t.is_keyword("EXISTS")
+                    })
+                    .map(|t| {
+                        t.text
+                            .trim_start_matches(['"', '`', '[', '\''])
+                            .to_lowercase()
+                            .starts_with("sqlite_")
+                    })
+                    .unwrap_or(false);
+
+            !is_internal_table
+        })
+        .map(|statement| {
+            let first = statement.iter().find(|t| !t.is_trivia()).unwrap();
+            let last = statement.iter().rev().find(|t| !t.is_trivia()).unwrap();
+
+            &dump[first.offset..last.offset + last.text.len()]
+        })
+        .collect()
+}
+
+/// Splits the SQL on semicolons, empty statements are removed
+/// A trigger body contains semicolons, a trigger only ends with `END;`
+fn split_statements(sql: &str) -> Vec<Vec<Token<'_>>> {
+    let mut statements = vec![];
+    let mut current: Vec<Token> = vec![];
+
+    for token in tokenize(sql) {
+        if token.kind == TokenKind::Punctuation && token.text == ";" {
+            let mut words = current.iter().filter(|t| !t.is_trivia());
+            let is_trigger = words.next().map(|t| t.is_keyword("CREATE")) == Some(true)
+                && words.take(2).any(|t| t.is_keyword("TRIGGER"));
+            let ends_with_end = current
+                .iter()
+                .rev()
+                .find(|t| !t.is_trivia())
+                .map(|t| t.is_keyword("END"))
+                == Some(true);
+
+            if is_trigger && !ends_with_end {
+                current.push(token);
+
+                continue;
+            }
+
+            let statement = std::mem::take(&mut current);
+
+            if statement.iter().any(|t| !t.is_trivia()) {
+                statements.push(statement);
+            }
+        } else {
+            current.push(token);
+        }
+    }
+
+    if current.iter().any(|t| !t.is_trivia()) {
+        statements.push(current);
+    }
+
+    statements
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dump::parse_dump;
+
+    #[test]
+    fn test_parse_dump() {
+        let metadata = parse_dump(
+            "PRAGMA foreign_keys=OFF;
+BEGIN TRANSACTION;
+CREATE TABLE user (user_id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL);
+INSERT INTO user VALUES(1,'a; CREATE TABLE fake (id INTEGER)');
+CREATE TABLE post (
+    post_id INTEGER NOT NULL PRIMARY KEY, -- a comment;
+    user_id INTEGER NOT NULL REFERENCES user(user_id)
+);
+DELETE FROM sqlite_sequence;
+INSERT INTO sqlite_sequence VALUES('user',1);
+CREATE INDEX post_user_id ON post(user_id);
+CREATE TRIGGER user_deleted AFTER DELETE ON user BEGIN
+    DELETE FROM post WHERE user_id = old.user_id;
+    UPDATE post SET user_id = CASE WHEN user_id > 0 THEN user_id ELSE 0 END WHERE 0;
+END;
+COMMIT;",
+        );
+
+        assert_eq!(
+            vec!["post", "sqlite_sequence", "user"],
+            metadata
+                .sorted_tables()
+                .iter()
+                .map(|t| t.table_name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "post_user_id",
+            metadata.table("post").unwrap().indexes[0].name
+        );
+
+        // The output of .schema contains the internal table, which SQLite creates itself
+        let metadata = parse_dump(
+            "CREATE TABLE user (user_id INTEGER PRIMARY KEY AUTOINCREMENT);
+CREATE TABLE sqlite_sequence(name,seq);",
+        );
+
+        assert_eq!(2, metadata.tables.len());
+        assert!(metadata.table("sqlite_sequence").unwrap().is_internal());
+    }
+}
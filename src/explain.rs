@@ -0,0 +1,269 @@
+//! Runs `EXPLAIN QUERY PLAN` and links the steps of the plan to the tables and indexes of the
+//! metadata
+//!
+//! ```
+//! use sqlite_parser::explain::{explain, StepKind};
+//! use sqlite_parser::parse_sql;
+//! use rusqlite::Connection;
+//!
+//! let sql = "CREATE TABLE user (user_id INTEGER PRIMARY KEY, email TEXT);";
+//! let connection = Connection::open_in_memory().unwrap();
+//!
+//! connection.execute_batch(sql).unwrap();
+//!
+//! let metadata = parse_sql(sql);
+//! let plan = explain(&connection, "SELECT * FROM user u WHERE email = ?;", &metadata).unwrap();
+//!
+//! assert_eq!(StepKind::Scan, plan.steps[0].kind);
+//! assert_eq!("user", plan.steps[0].table.unwrap().table_name);
+//! ```
+
+use std::collections::HashMap;
+
+use rusqlite::Connection;
+
+use crate::tokenizer::{tokenize_without_trivia, unquote, Token, TokenKind};
+use crate::{Index, Metadata, Table};
+
+/// The steps of `EXPLAIN QUERY PLAN`, in the order SQLite returns them
+#[derive(Debug, PartialEq, Clone)]
+pub struct QueryPlan<'a> {
+    pub steps: Vec<PlanStep<'a>>,
+}
+
+/// A row of `EXPLAIN QUERY PLAN`
+#[derive(Debug, PartialEq, Clone)]
+pub struct PlanStep<'a> {
+    pub id: i32,
+    /// The id of the parent step, 0 for the steps at the top
+    pub parent: i32,
+    /// The description of SQLite, e.g. `SEARCH u USING INDEX user_email (email=?)`
+    pub detail: String,
+    pub kind: StepKind,
+    /// The name of the table in the plan, this is the alias if the query has one
+    /// Only set when the table is found in the metadata.
+    pub alias: Option<String>,
+    /// The table that is scanned or searched, `None` for subqueries, views and CTEs
+    pub table: Option<&'a Table>,
+    /// The index that is used, `None` for the primary key and automatic indexes
+    pub index: Option<&'a Index>,
+    /// Only the index is read, not the table
+    pub covering: bool,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Eq, Hash, PartialOrd, Ord)]
+pub enum StepKind {
+    /// Reads every row of the table or index
+    Scan,
+    /// Reads the rows of the table or index that match the constraints
+    Search,
+    /// Other steps, e.g. a temporary B-tree for ORDER BY or a subquery
+    Other,
+}
+
+impl<'a> QueryPlan<'a> {
+    /// The steps that scan a whole table of the metadata
+    pub fn full_scans(&self) -> Vec<&PlanStep<'a>> {
+        self.steps
+            .iter()
+            .filter(|s| s.kind == StepKind::Scan && s.table.is_some())
+            .collect()
+    }
+
+    /// The steps directly below the step
+    pub fn children(&self, id: i32) -> Vec<&PlanStep<'a>> {
+        self.steps.iter().filter(|s| s.parent == id).collect()
+    }
+
+    /// The indexes the plan uses
+    pub fn indexes(&self) -> Vec<&'a Index> {
+        self.steps.iter().filter_map(|s| s.index).collect()
+    }
+}
+
+/// The plan of the statement, the parameters of the statement don't need to be bound
+/// Tables in the plan are matched to the metadata by their name or alias in the SQL.
+pub fn explain<'a>(
+    connection: &Connection,
+    sql: &str,
+    metadata: &'a Metadata,
+) -> rusqlite::Result<QueryPlan<'a>> {
+    let aliases = aliases(&tokenize_without_trivia(sql), metadata);
+    let mut stmt = connection.prepare(&format!("EXPLAIN QUERY PLAN {sql}"))?;
+    // Unbound parameters are NULL, the plan doesn't depend on them
+    let rows = stmt
+        .raw_query()
+        .mapped(|row| Ok((row.get(0)?, row.get(1)?, row.get::<_, String>(3)?)))
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let steps = rows
+        .into_iter()
+        .map(|(id, parent, detail)| step(id, parent, detail, &aliases))
+        .collect();
+
+    Ok(QueryPlan { steps })
+}
+
+/// Parses a detail like `SCAN p USING COVERING INDEX post_title`
+fn step<'a>(
+    id: i32,
+    parent: i32,
+    detail: String,
+    aliases: &HashMap<String, &'a Table>,
+) -> PlanStep<'a> {
+    let (kind, rest) = if let Some(rest) = detail.strip_prefix("SCAN ") {
+        (StepKind::Scan, rest)
+    } else if let Some(rest) = detail.strip_prefix("SEARCH ") {
+        (StepKind::Search, rest)
+    } else {
+        (StepKind::Other, "")
+    };
+    let name = rest.split(' ').next().unwrap();
+    let table = aliases.get(&name.to_lowercase()).copied();
+    let index_name = ["USING COVERING INDEX ", "USING INDEX "]
+        .iter()
+        .find_map(|using| rest.split_once(using))
+        .map(|(_, index)| index.split(' ').next().unwrap());
+
+    PlanStep {
+        id,
+        parent,
+        kind,
+        alias: table.map(|_| name.to_string()),
+        table,
+        index: table.zip(index_name).and_then(|(t, i)| t.index(i)),
+        covering: rest.contains("COVERING INDEX"),
+        detail,
+    }
+}
+
+/// The tables of the FROM and JOIN clauses, keyed by the lowercase alias and table name
+pub(crate) fn aliases<'a>(tokens: &[Token], metadata: &'a Metadata) -> HashMap<String, &'a Table> {
+    let mut aliases = HashMap::new();
+
+    for (i, token) in tokens.iter().enumerate() {
+        if !is_identifier(token) {
+            continue;
+        }
+
+        let Some(table) = metadata.table(&unquote(token.text)) else {
+            continue;
+        };
+
+        aliases.insert(table.table_name.to_lowercase(), table);
+
+        let alias = match tokens.get(i + 1) {
+            Some(t) if t.is_keyword("AS") => tokens.get(i + 2),
+            Some(t) if is_identifier(t) => Some(t),
+            _ => None,
+        };
+
+        if let Some(alias) = alias.filter(|t| is_identifier(t)) {
+            aliases.insert(unquote(alias.text).to_lowercase(), table);
+        }
+    }
+
+    aliases
+}
+
+/// Keywords that end a table reference or a condition
+const CLAUSE_KEYWORDS: [&str; 24] = [
+    "SELECT",
+    "FROM",
+    "WHERE",
+    "JOIN",
+    "ON",
+    "USING",
+    "LEFT",
+    "RIGHT",
+    "FULL",
+    "INNER",
+    "OUTER",
+    "CROSS",
+    "NATURAL",
+    "GROUP",
+    "ORDER",
+    "LIMIT",
+    "HAVING",
+    "WINDOW",
+    "UNION",
+    "INTERSECT",
+    "EXCEPT",
+    "SET",
+    "VALUES",
+    "RETURNING",
+];
+
+/// Keywords inside a condition
+const OPERATOR_KEYWORDS: [&str; 10] = [
+    "AND", "OR", "NOT", "IN", "IS", "BETWEEN", "LIKE", "GLOB", "INDEXED", "AS",
+];
+
+pub(crate) fn is_identifier(token: &Token) -> bool {
+    token.kind == TokenKind::QuotedIdentifier
+        || (token.kind == TokenKind::Word
+            && !CLAUSE_KEYWORDS
+                .iter()
+                .chain(&OPERATOR_KEYWORDS)
+                .any(|k| token.is_keyword(k)))
+}
+
+pub(crate) fn is_clause_keyword(token: &Token) -> bool {
+    CLAUSE_KEYWORDS.iter().any(|k| token.is_keyword(k))
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::explain::{explain, StepKind};
+    use crate::metadata_from_connection;
+
+    #[test]
+    fn test_explain() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE user (user_id INTEGER PRIMARY KEY, email TEXT UNIQUE, age INTEGER);
+                CREATE TABLE post (post_id INTEGER PRIMARY KEY, user_id INTEGER, title TEXT);
+                CREATE INDEX post_title ON post(title, user_id);",
+            )
+            .unwrap();
+
+        let metadata = metadata_from_connection(&connection, Default::default());
+        let plan = explain(
+            &connection,
+            "SELECT p.user_id FROM post AS p JOIN user ON user.user_id = p.user_id
+            WHERE p.title = ? AND user.email IN (SELECT email FROM user WHERE age > ?)
+            ORDER BY age;",
+            &metadata,
+        )
+        .unwrap();
+        let steps = plan
+            .steps
+            .iter()
+            .map(|s| {
+                (
+                    s.kind,
+                    s.alias.as_deref(),
+                    s.index.map(|i| i.name.as_str()),
+                    s.covering,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                (StepKind::Search, Some("p"), Some("post_title"), true),
+                (StepKind::Search, Some("user"), None, false),
+                (StepKind::Other, None, None, false),
+                (StepKind::Scan, Some("user"), None, false),
+                (StepKind::Other, None, None, false),
+            ],
+            steps
+        );
+        assert_eq!(1, plan.full_scans().len());
+        assert_eq!(1, plan.children(plan.steps[2].id).len());
+        assert_eq!("post_title", plan.indexes()[0].name);
+    }
+}
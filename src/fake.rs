@@ -0,0 +1,341 @@
+//! Generates random rows that are valid for the schema, e.g. to seed a test database
+//!
+//! ```
+//! # #[cfg(feature = "rusqlite")]
+//! # {
+//! use sqlite_parser::fake::FakeData;
+//! use sqlite_parser::parse_sql;
+//! use sqlite_parser::sample::Value;
+//!
+//! let metadata = parse_sql(
+//!     "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT NOT NULL);
+//!     CREATE TABLE post (post_id INTEGER NOT NULL PRIMARY KEY, user_id INTEGER NOT NULL REFERENCES user);",
+//! );
+//! let rows = FakeData::new(&metadata)
+//!     .rows(5)
+//!     .column("user", "name", |i| Value::Text(format!("user {i}")))
+//!     .generate();
+//!
+//! assert_eq!(vec!["user", "post"], rows.iter().map(|(t, _)| t.table_name.as_str()).collect::<Vec<_>>());
+//! # }
+//! ```
+
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+
+#[cfg(feature = "rusqlite")]
+use rusqlite::{Connection, ToSql};
+
+use crate::sample::{SampleRow, Value};
+use crate::{Column, Metadata, Table, Type};
+
+type Generator = Box<dyn Fn(usize) -> Value>;
+
+/// Generates rows for every table of the metadata
+/// The values respect the type, NOT NULL, the primary key and unique indexes and refer to
+/// generated rows of the referenced table. CHECK constraints are not respected, use `column` to
+/// generate valid values for these columns. A foreign key in a cycle is NULL when it refers to a
+/// table that has no rows yet, which fails for NOT NULL columns.
+pub struct FakeData<'a> {
+    metadata: &'a Metadata,
+    rows: usize,
+    seed: u64,
+    /// Generators per table and column name
+    generators: HashMap<(String, String), Generator>,
+}
+
+impl<'a> FakeData<'a> {
+    /// Generates 10 rows per table with seed 0
+    pub fn new(metadata: &'a Metadata) -> Self {
+        Self {
+            metadata,
+            rows: 10,
+            seed: 0,
+            generators: HashMap::new(),
+        }
+    }
+
+    /// The amount of rows per table
+    /// Tables with a foreign key in a unique index get at most one row per referenced row.
+    pub fn rows(mut self, rows: usize) -> Self {
+        self.rows = rows;
+        self
+    }
+
+    /// The same seed generates the same rows
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Generates the values of the column with the function, which gets the index of the row
+    pub fn column(
+        mut self,
+        table_name: &str,
+        column_name: &str,
+        generator: impl Fn(usize) -> Value + 'static,
+    ) -> Self {
+        self.generators.insert(
+            (table_name.to_string(), column_name.to_string()),
+            Box::new(generator),
+        );
+        self
+    }
+
+    /// The rows per table, in the order of `Metadata::tables_in_dependency_order`
+    pub fn generate(&self) -> Vec<(&'a Table, Vec<SampleRow>)> {
+        let mut random = Random(self.seed);
+        let mut generated: HashMap<&str, Vec<SampleRow>> = HashMap::new();
+        let mut ordered = vec![];
+
+        for table in self.metadata.tables_in_dependency_order() {
+            let rows = self.generate_table(table, &generated, &mut random);
+
+            generated.insert(&table.table_name, rows);
+            ordered.push(table);
+        }
+
+        ordered
+            .into_iter()
+            .map(|t| (t, generated.remove(t.table_name.as_str()).unwrap()))
+            .collect()
+    }
+
+    /// Inserts the generated rows in a transaction
+    #[cfg(feature = "rusqlite")]
+    pub fn insert(&self, connection: &Connection) -> rusqlite::Result<()> {
+        let transaction = connection.unchecked_transaction()?;
+
+        for (table, rows) in self.generate() {
+            let mut stmt = transaction.prepare(&table.insert_sql())?;
+
+            for row in rows {
+                let params = row
+                    .values
+                    .iter()
+                    .map(|v| v as &dyn ToSql)
+                    .collect::<Vec<_>>();
+
+                stmt.execute(params.as_slice())?;
+            }
+        }
+
+        transaction.commit()
+    }
+
+    fn generate_table(
+        &self,
+        table: &Table,
+        generated: &HashMap<&str, Vec<SampleRow>>,
+        random: &mut Random,
+    ) -> Vec<SampleRow> {
+        let unique_columns = table
+            .indexes
+            .iter()
+            .filter(|i| i.unique)
+            .flat_map(|i| i.columns.iter())
+            .chain(table.primary_key())
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>();
+        let is_unique = |c: &Column| unique_columns.contains(&c.name.as_str());
+        let mut rows = vec![];
+
+        for i in 0..self.rows {
+            let mut values = vec![None; table.columns.len()];
+            // Unique foreign keys pick the referenced rows like digits of the row index
+            let mut remaining = i;
+            let mut unique_foreign_key = false;
+
+            for foreign_key in &table.foreign_keys {
+                let parent_rows = match generated.get(foreign_key.table.as_str()) {
+                    Some(parent_rows) if !parent_rows.is_empty() => parent_rows,
+                    // Self references can refer to an earlier row
+                    _ if foreign_key.table.eq_ignore_ascii_case(&table.table_name)
+                        && !rows.is_empty() =>
+                    {
+                        &rows
+                    }
+                    _ => {
+                        for from in &foreign_key.from_column {
+                            let from = table.columns.iter().position(|c| c.name == from.name);
+
+                            values[from.unwrap()] = Some(Value::Null);
+                        }
+
+                        continue;
+                    }
+                };
+                let parent = if foreign_key.from_column.iter().any(is_unique) {
+                    let parent = remaining % parent_rows.len();
+
+                    remaining /= parent_rows.len();
+                    unique_foreign_key = true;
+
+                    parent
+                } else {
+                    random.below(parent_rows.len())
+                };
+                let parent_table = self.metadata.table(&foreign_key.table).unwrap();
+
+                for (from, to) in foreign_key.from_column.iter().zip(&foreign_key.to_column) {
+                    let to = parent_table
+                        .columns
+                        .iter()
+                        .position(|c| c.name == to.name)
+                        .unwrap();
+                    let from = table
+                        .columns
+                        .iter()
+                        .position(|c| c.name == from.name)
+                        .unwrap();
+
+                    values[from] = Some(parent_rows[parent].values[to].clone());
+                }
+            }
+
+            // All combinations of the referenced rows are used
+            if unique_foreign_key && remaining > 0 {
+                break;
+            }
+
+            let values = table
+                .columns
+                .iter()
+                .zip(values)
+                .map(|(column, value)| {
+                    if let Some(generator) = self
+                        .generators
+                        .get(&(table.table_name.clone(), column.name.clone()))
+                    {
+                        return generator(i);
+                    }
+
+                    match value {
+                        Some(value) => value,
+                        None if is_unique(column) => unique_value(column, i),
+                        None if column.nullable && random.below(10) == 0 => Value::Null,
+                        None => random_value(column, random),
+                    }
+                })
+                .collect();
+
+            rows.push(SampleRow { values });
+        }
+
+        rows
+    }
+}
+
+impl Debug for FakeData<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FakeData")
+            .field("rows", &self.rows)
+            .field("seed", &self.seed)
+            .field("generators", &self.generators.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// A value that differs for every row
+fn unique_value(column: &Column, i: usize) -> Value {
+    match column.the_type {
+        Type::Integer | Type::Numeric { .. } => Value::Integer(i as i64 + 1),
+        Type::Real => Value::Real(i as f64 + 1.),
+        Type::Blob => Value::Blob((i as u64).to_be_bytes().to_vec()),
+        Type::Text | Type::String | Type::Unknown => Value::Text(format!("{}_{i}", column.name)),
+    }
+}
+
+fn random_value(column: &Column, random: &mut Random) -> Value {
+    match column.the_type {
+        Type::Integer => Value::Integer(random.below(1000) as i64),
+        Type::Real | Type::Numeric { .. } => Value::Real(random.below(100_000) as f64 / 100.),
+        Type::Blob => Value::Blob(random.next().to_be_bytes().to_vec()),
+        Type::Text | Type::String | Type::Unknown => {
+            Value::Text(format!("{}_{}", column.name, random.below(1000)))
+        }
+    }
+}
+
+/// SplitMix64, good enough for fake data and without a dependency
+struct Random(u64);
+
+impl Random {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+
+        let mut z = self.0;
+
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::fake::FakeData;
+    use crate::metadata_from_connection;
+    use crate::sample::Value;
+
+    #[test]
+    fn test_fake_data() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "PRAGMA foreign_keys = ON;
+                CREATE TABLE user (
+                    user_id INTEGER NOT NULL PRIMARY KEY,
+                    email TEXT NOT NULL UNIQUE,
+                    score REAL,
+                    parent_id INTEGER REFERENCES user
+                );
+                CREATE TABLE tag (tag_id INTEGER NOT NULL PRIMARY KEY, name TEXT NOT NULL CHECK (name != ''));
+                CREATE TABLE user_tag (
+                    user_id INTEGER NOT NULL REFERENCES user,
+                    tag_id INTEGER NOT NULL REFERENCES tag,
+                    PRIMARY KEY (user_id, tag_id)
+                );",
+            )
+            .unwrap();
+
+        let metadata = metadata_from_connection(&connection, Default::default());
+        let fake = FakeData::new(&metadata)
+            .rows(4)
+            .column("tag", "name", |i| Value::Text(format!("tag {i}")));
+
+        assert_eq!(fake.generate(), fake.generate());
+
+        fake.insert(&connection).unwrap();
+
+        let count = |table: &str| -> i64 {
+            connection
+                .query_row(&format!("SELECT COUNT(*) FROM {table};"), [], |row| {
+                    row.get(0)
+                })
+                .unwrap()
+        };
+
+        assert_eq!(4, count("user"));
+        // Every tag with the first user, since the primary key must be unique
+        assert_eq!(4, count("user_tag"));
+        assert!(!connection
+            .prepare("PRAGMA foreign_key_check;")
+            .unwrap()
+            .exists([])
+            .unwrap());
+
+        assert_ne!(
+            FakeData::new(&metadata).generate(),
+            FakeData::new(&metadata).seed(1).generate()
+        );
+    }
+}
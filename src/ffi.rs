@@ -0,0 +1,311 @@
+//! The C interface, to embed the parser as a shared library in non-Rust applications
+//!
+//! Build the library with `cargo rustc --lib --release --features ffi --crate-type cdylib`.
+//! Tables are walked by position in name order, columns and foreign keys by position in the
+//! table. Pointers to tables, columns and foreign keys are valid until the metadata is freed.
+//! Strings returned by the library are owned by the caller and freed with
+//! `sqlite_parser_string_free`. As an alternative to walking the metadata,
+//! `sqlite_parser_to_json` returns the complete metadata as JSON.
+
+use std::ffi::{c_char, CStr, CString};
+use std::panic::catch_unwind;
+use std::ptr::null_mut;
+
+use crate::diff::parse_read_only;
+use crate::{Column, ForeignKey, Metadata, Table};
+
+/// Parses the SQLite file at the UTF-8 path, returns NULL when parsing fails
+/// The file is opened read only, so a missing file returns NULL instead of creating a database.
+///
+/// # Safety
+/// `path` must be a valid NUL-terminated string. The result must be freed with
+/// `sqlite_parser_free`.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite_parser_parse(path: *const c_char) -> *mut Metadata {
+    if path.is_null() {
+        return null_mut();
+    }
+
+    let path = match CStr::from_ptr(path).to_str() {
+        Ok(path) => path.to_string(),
+        Err(_) => return null_mut(),
+    };
+
+    // Parsing panics on failure, which must not unwind into C
+    match catch_unwind(|| parse_read_only(path)) {
+        Ok(Ok(metadata)) => Box::into_raw(Box::new(metadata)),
+        Ok(Err(_)) | Err(_) => null_mut(),
+    }
+}
+
+/// Frees the metadata, NULL is ignored
+///
+/// # Safety
+/// `metadata` must be returned by `sqlite_parser_parse` and not be freed before.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite_parser_free(metadata: *mut Metadata) {
+    if !metadata.is_null() {
+        drop(Box::from_raw(metadata));
+    }
+}
+
+/// Frees a string returned by the library, NULL is ignored
+///
+/// # Safety
+/// `string` must be returned by this library and not be freed before.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite_parser_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// The metadata as JSON, NULL if it can't be serialized
+///
+/// # Safety
+/// `metadata` must be a valid pointer returned by `sqlite_parser_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite_parser_to_json(metadata: *const Metadata) -> *mut c_char {
+    match serde_json::to_string(&*metadata) {
+        Ok(json) => to_c_string(&json),
+        Err(_) => null_mut(),
+    }
+}
+
+/// # Safety
+/// `metadata` must be a valid pointer returned by `sqlite_parser_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite_parser_table_count(metadata: *const Metadata) -> usize {
+    (*metadata).tables.len()
+}
+
+/// The table at the position in name order, NULL if the position is out of bounds
+///
+/// # Safety
+/// `metadata` must be a valid pointer returned by `sqlite_parser_parse`.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite_parser_table(
+    metadata: *const Metadata,
+    position: usize,
+) -> *const Table {
+    (*metadata)
+        .sorted_tables()
+        .get(position)
+        .map_or(std::ptr::null(), |t| *t as *const Table)
+}
+
+/// # Safety
+/// `table` must be a valid pointer returned by `sqlite_parser_table`.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite_parser_table_name(table: *const Table) -> *mut c_char {
+    to_c_string(&(*table).table_name)
+}
+
+/// # Safety
+/// `table` must be a valid pointer returned by `sqlite_parser_table`.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite_parser_column_count(table: *const Table) -> usize {
+    (*table).columns.len()
+}
+
+/// The column at the position, NULL if the position is out of bounds
+///
+/// # Safety
+/// `table` must be a valid pointer returned by `sqlite_parser_table`.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite_parser_column(
+    table: *const Table,
+    position: usize,
+) -> *const Column {
+    element(&(*table).columns, position)
+}
+
+/// # Safety
+/// `column` must be a valid pointer returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite_parser_column_name(column: *const Column) -> *mut c_char {
+    to_c_string(&(*column).name)
+}
+
+/// The type as written in SQL, e.g. `INTEGER`
+///
+/// # Safety
+/// `column` must be a valid pointer returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite_parser_column_type(column: *const Column) -> *mut c_char {
+    to_c_string(&(*column).the_type.to_string())
+}
+
+/// # Safety
+/// `column` must be a valid pointer returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite_parser_column_nullable(column: *const Column) -> bool {
+    (*column).nullable
+}
+
+/// # Safety
+/// `column` must be a valid pointer returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite_parser_column_part_of_pk(column: *const Column) -> bool {
+    (*column).part_of_pk
+}
+
+/// # Safety
+/// `table` must be a valid pointer returned by `sqlite_parser_table`.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite_parser_foreign_key_count(table: *const Table) -> usize {
+    (*table).foreign_keys.len()
+}
+
+/// The foreign key at the position, NULL if the position is out of bounds
+///
+/// # Safety
+/// `table` must be a valid pointer returned by `sqlite_parser_table`.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite_parser_foreign_key(
+    table: *const Table,
+    position: usize,
+) -> *const ForeignKey {
+    element(&(*table).foreign_keys, position)
+}
+
+/// The name of the referenced table
+///
+/// # Safety
+/// `foreign_key` must be a valid pointer returned by `sqlite_parser_foreign_key`.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite_parser_foreign_key_table(
+    foreign_key: *const ForeignKey,
+) -> *mut c_char {
+    to_c_string(&(*foreign_key).table)
+}
+
+/// The amount of column pairs of the foreign key
+///
+/// # Safety
+/// `foreign_key` must be a valid pointer returned by `sqlite_parser_foreign_key`.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite_parser_foreign_key_column_count(
+    foreign_key: *const ForeignKey,
+) -> usize {
+    (*foreign_key).from_column.len()
+}
+
+/// The column of the own table, NULL if the position is out of bounds
+///
+/// # Safety
+/// `foreign_key` must be a valid pointer returned by `sqlite_parser_foreign_key`.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite_parser_foreign_key_from_column(
+    foreign_key: *const ForeignKey,
+    position: usize,
+) -> *const Column {
+    element(&(*foreign_key).from_column, position)
+}
+
+/// The column of the referenced table, NULL if the position is out of bounds
+///
+/// # Safety
+/// `foreign_key` must be a valid pointer returned by `sqlite_parser_foreign_key`.
+#[no_mangle]
+pub unsafe extern "C" fn sqlite_parser_foreign_key_to_column(
+    foreign_key: *const ForeignKey,
+    position: usize,
+) -> *const Column {
+    element(&(*foreign_key).to_column, position)
+}
+
+fn element<T>(elements: &[T], position: usize) -> *const T {
+    elements
+        .get(position)
+        .map_or(std::ptr::null(), |e| e as *const T)
+}
+
+/// SQLite identifiers can't contain NUL, but the conversion is lossy to be sure
+fn to_c_string(string: &str) -> *mut c_char {
+    CString::new(string.replace('\0', "")).unwrap().into_raw()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::{CStr, CString};
+
+    use crate::ffi::*;
+
+    #[test]
+    fn test_ffi() {
+        let path = std::env::temp_dir().join("sqlite_parser_ffi.sqlite3");
+        let _ = std::fs::remove_file(&path);
+
+        rusqlite::Connection::open(&path)
+            .unwrap()
+            .execute_batch(
+                "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY);
+                CREATE TABLE post (
+                    post_id INTEGER NOT NULL PRIMARY KEY,
+                    user_id INTEGER REFERENCES user(user_id)
+                );",
+            )
+            .unwrap();
+
+        let c_path = CString::new(path.to_str().unwrap()).unwrap();
+        let string = |s: *mut c_char| unsafe {
+            let result = CStr::from_ptr(s).to_str().unwrap().to_string();
+
+            sqlite_parser_string_free(s);
+
+            result
+        };
+
+        unsafe {
+            let metadata = sqlite_parser_parse(c_path.as_ptr());
+
+            assert_eq!(2, sqlite_parser_table_count(metadata));
+
+            let post = sqlite_parser_table(metadata, 0);
+
+            assert_eq!("post", string(sqlite_parser_table_name(post)));
+            assert_eq!(2, sqlite_parser_column_count(post));
+            assert!(sqlite_parser_column(post, 2).is_null());
+
+            let user_id = sqlite_parser_column(post, 1);
+
+            assert_eq!("user_id", string(sqlite_parser_column_name(user_id)));
+            assert_eq!("INTEGER", string(sqlite_parser_column_type(user_id)));
+            assert!(sqlite_parser_column_nullable(user_id));
+            assert!(!sqlite_parser_column_part_of_pk(user_id));
+
+            let foreign_key = sqlite_parser_foreign_key(post, 0);
+
+            assert_eq!("user", string(sqlite_parser_foreign_key_table(foreign_key)));
+            assert_eq!(1, sqlite_parser_foreign_key_column_count(foreign_key));
+            assert_eq!(
+                "user_id",
+                string(sqlite_parser_column_name(
+                    sqlite_parser_foreign_key_to_column(foreign_key, 0)
+                ))
+            );
+
+            let json: serde_json::Value =
+                serde_json::from_str(&string(sqlite_parser_to_json(metadata))).unwrap();
+
+            assert_eq!("user", json["tables"]["user"]["table_name"]);
+
+            sqlite_parser_free(metadata);
+
+            let missing = CString::new("/missing/directory/file.sqlite3").unwrap();
+
+            assert!(sqlite_parser_parse(missing.as_ptr()).is_null());
+        }
+
+        // A missing file in an existing directory is not created
+        let missing = std::env::temp_dir().join("sqlite_parser_ffi_missing.sqlite3");
+        let _ = std::fs::remove_file(&missing);
+        let c_missing = CString::new(missing.to_str().unwrap()).unwrap();
+
+        assert!(unsafe { sqlite_parser_parse(c_missing.as_ptr()) }.is_null());
+        assert!(!missing.exists());
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
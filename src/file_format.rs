@@ -0,0 +1,908 @@
+//! Reads the schema directly from the SQLite file format, without the SQLite library
+//!
+//! The database header is read for the `DatabaseInfo` and the `sqlite_master` b-tree for the SQL
+//! of the tables and indexes, which is parsed to build the metadata. This works for files that
+//! are too new or too old for the linked SQLite library, and doesn't need SQLite at all.
+//!
+//! Differences with parsing through SQLite:
+//! - `DatabaseInfo::sqlite_version` is the version that last wrote the file and the compile
+//!   options are empty
+//! - The WAL file is not read, changes that are not checkpointed are missing
+//! - The columns of virtual tables are read from the module arguments for fts3, fts4, fts5,
+//!   rtree and rtree_i32, virtual tables of other modules have no columns
+//! - Options that read the data (row counts, samples, profiles, storage, statistics) are not
+//!   supported, except for `ParseOptions::sequences`
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+use crate::backend::{parse_backend, IntrospectionBackend};
+use crate::database::{AutoVacuum, DatabaseInfo};
+use crate::ddl::{
+    automatic_indexes, parse_create_index, parse_create_table, parse_create_virtual_table,
+    TableDefinition,
+};
+use crate::report::ParseWarning;
+use crate::sample::Value;
+use crate::trigger::Trigger;
+use crate::view::View;
+use crate::{
+    declared_type, find_column, AutomaticIndex, Column, ForeignKey, Index, LookupMode, Metadata,
+    ParseOptions, Table,
+};
+
+/// The b-tree depth is limited to 20 by SQLite, corrupt files could contain loops
+const MAX_DEPTH: usize = 64;
+
+/// Parses the SQLite file without using the SQLite library
+pub fn parse_file<P: AsRef<Path>>(path: P) -> Result<Metadata> {
+    parse_file_bytes(&std::fs::read(path)?)
+}
+
+/// Same as `parse_file`, for a file that is held in memory
+pub fn parse_file_bytes(bytes: &[u8]) -> Result<Metadata> {
+    Ok(parse_backend(
+        &FileFormatBackend::new(bytes)?,
+        ParseOptions::default(),
+    ))
+}
+
+/// A backend that reads the schema from the bytes of a database file
+/// The file is read completely on creation, so errors are returned early
+pub struct FileFormatBackend {
+    tables: Vec<Table>,
+    database: DatabaseInfo,
+    triggers: Vec<Trigger>,
+    views: Vec<View>,
+    /// The values of `sqlite_sequence`, keyed by table name
+    sequences: HashMap<String, i64>,
+    /// The anomalies found while reading the schema, handed out per table while parsing
+    warnings: Vec<ParseWarning>,
+}
+
+impl FileFormatBackend {
+    /// Reads the header and the schema of the file
+    pub fn new(bytes: &[u8]) -> Result<Self> {
+        let file = File::new(bytes)?;
+        let mut rows = vec![];
+
+        file.table_rows(1, 0, &mut HashSet::new(), &mut rows)?;
+
+        let schema = rows
+            .into_iter()
+            .map(SchemaRow::new)
+            .collect::<Result<Vec<_>>>()?;
+        let mut warnings = vec![];
+        let mut sequences = HashMap::new();
+
+        if let Some(row) = schema
+            .iter()
+            .find(|r| r.kind == "table" && r.name == "sqlite_sequence")
+        {
+            let mut rows = vec![];
+
+            file.table_rows(row.root_page, 0, &mut HashSet::new(), &mut rows)?;
+
+            for row in rows {
+                if let [Value::Text(name), Value::Integer(sequence)] = row.as_slice() {
+                    sequences.insert(name.clone(), *sequence);
+                }
+            }
+        }
+
+        let mut triggers = schema
+            .iter()
+            .filter(|r| r.kind == "trigger")
+            .filter_map(|r| {
+                Some(Trigger::new(
+                    r.name.clone(),
+                    r.table_name.clone(),
+                    r.sql.clone()?,
+                ))
+            })
+            .collect::<Vec<_>>();
+
+        let mut views = schema
+            .iter()
+            .filter(|r| r.kind == "view")
+            .filter_map(|r| {
+                Some(View {
+                    name: r.name.clone(),
+                    columns: vec![],
+                    sql: r.sql.clone()?,
+                    accesses: vec![],
+                })
+            })
+            .collect::<Vec<_>>();
+
+        triggers.sort_by(|a, b| a.name.cmp(&b.name));
+        views.sort_by(|a, b| a.name.cmp(&b.name));
+
+        Ok(Self {
+            tables: tables(&schema, &mut warnings)?,
+            database: file.database_info(),
+            triggers,
+            views,
+            sequences,
+            warnings,
+        })
+    }
+
+    fn table(&self, table_name: &str) -> &Table {
+        self.tables
+            .iter()
+            .find(|t| t.table_name == table_name)
+            .unwrap_or_else(|| panic!("Unknown table {table_name}"))
+    }
+
+    /// Adds the warnings of the table that match the filter
+    fn warnings(
+        &self,
+        table_name: &str,
+        warnings: &mut Vec<ParseWarning>,
+        filter: fn(&ParseWarning) -> bool,
+    ) {
+        warnings.extend(
+            self.warnings
+                .iter()
+                .filter(|w| w.table() == table_name && filter(w))
+                .cloned(),
+        );
+    }
+}
+
+impl IntrospectionBackend for FileFormatBackend {
+    fn table_names(&self) -> Vec<String> {
+        self.tables.iter().map(|t| t.table_name.clone()).collect()
+    }
+
+    fn create_sql(&self, table_name: &str) -> String {
+        self.table(table_name).create_sql.clone()
+    }
+
+    fn columns(&self, table_name: &str, warnings: &mut Vec<ParseWarning>) -> Vec<Column> {
+        self.warnings(table_name, warnings, |w| {
+            matches!(w, ParseWarning::UnknownType { .. })
+        });
+
+        self.table(table_name).columns.clone()
+    }
+
+    fn foreign_keys(&self, table_name: &str, warnings: &mut Vec<ParseWarning>) -> Vec<ForeignKey> {
+        self.warnings(table_name, warnings, |w| {
+            matches!(
+                w,
+                ParseWarning::MissingForeignKeyTable { .. }
+                    | ParseWarning::MissingForeignKeyColumn { .. }
+            )
+        });
+
+        self.table(table_name).foreign_keys.clone()
+    }
+
+    fn indexes(
+        &self,
+        table_name: &str,
+        _: &[Column],
+        _: &[ForeignKey],
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Vec<Index> {
+        self.warnings(table_name, warnings, |w| {
+            matches!(
+                w,
+                ParseWarning::IndexExpression { .. } | ParseWarning::DuplicateIndex { .. }
+            )
+        });
+
+        self.table(table_name).indexes.clone()
+    }
+
+    fn automatic_indexes(&self, table_name: &str, _: &[Column]) -> Vec<AutomaticIndex> {
+        self.table(table_name).automatic_indexes.clone()
+    }
+
+    fn index_sql(&self, table_name: &str) -> Option<BTreeMap<String, String>> {
+        Some(self.table(table_name).index_sql.clone())
+    }
+
+    fn sequence(&self, table_name: &str) -> Option<i64> {
+        self.sequences.get(table_name).copied()
+    }
+
+    fn database_info(&self) -> DatabaseInfo {
+        self.database.clone()
+    }
+
+    fn triggers(&self) -> Vec<Trigger> {
+        self.triggers.clone()
+    }
+
+    fn views(&self) -> Vec<View> {
+        self.views.clone()
+    }
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
+
+struct File<'a> {
+    bytes: &'a [u8],
+    page_size: usize,
+    /// The page size without the reserved bytes at the end of every page
+    usable_size: usize,
+    encoding: TextEncoding,
+}
+
+/// The encoding of all text in the file, set by `PRAGMA encoding` before the first table
+#[derive(Debug, PartialEq, Copy, Clone, Eq)]
+enum TextEncoding {
+    Utf8,
+    Utf16le,
+    Utf16be,
+}
+
+impl TextEncoding {
+    fn decode(self, bytes: &[u8]) -> Result<String> {
+        let units = |to_u16: fn([u8; 2]) -> u16| {
+            bytes
+                .chunks_exact(2)
+                .map(|c| to_u16([c[0], c[1]]))
+                .collect::<Vec<_>>()
+        };
+        let text = match self {
+            TextEncoding::Utf8 => return Ok(String::from_utf8_lossy(bytes).to_string()),
+            TextEncoding::Utf16le => String::from_utf16(&units(u16::from_le_bytes)),
+            TextEncoding::Utf16be => String::from_utf16(&units(u16::from_be_bytes)),
+        };
+
+        text.map_err(|e| invalid(format!("Invalid UTF-16 text: {e}")))
+    }
+}
+
+impl<'a> File<'a> {
+    fn new(bytes: &'a [u8]) -> Result<Self> {
+        if bytes.len() < 100 || &bytes[..16] != b"SQLite format 3\0" {
+            return Err(invalid("Not a SQLite database".to_string()));
+        }
+
+        let page_size = match u16::from_be_bytes([bytes[16], bytes[17]]) {
+            1 => 65536,
+            size => size as usize,
+        };
+
+        if !page_size.is_power_of_two() || !(512..=65536).contains(&page_size) {
+            return Err(invalid(format!("Invalid page size {page_size}")));
+        }
+
+        Ok(Self {
+            bytes,
+            page_size,
+            usable_size: page_size - bytes[20] as usize,
+            encoding: match u32::from_be_bytes(bytes[56..60].try_into().unwrap()) {
+                2 => TextEncoding::Utf16le,
+                3 => TextEncoding::Utf16be,
+                _ => TextEncoding::Utf8,
+            },
+        })
+    }
+
+    fn header_u32(&self, offset: usize) -> u32 {
+        u32::from_be_bytes(self.bytes[offset..offset + 4].try_into().unwrap())
+    }
+
+    fn database_info(&self) -> DatabaseInfo {
+        let version = self.header_u32(96);
+
+        DatabaseInfo {
+            page_size: self.page_size as u32,
+            encoding: match self.encoding {
+                TextEncoding::Utf8 => "UTF-8",
+                TextEncoding::Utf16le => "UTF-16le",
+                TextEncoding::Utf16be => "UTF-16be",
+            }
+            .to_string(),
+            journal_mode: if self.bytes[18] == 2 { "wal" } else { "delete" }.to_string(),
+            auto_vacuum: match (self.header_u32(52), self.header_u32(64)) {
+                (0, _) => AutoVacuum::None,
+                (_, 0) => AutoVacuum::Full,
+                _ => AutoVacuum::Incremental,
+            },
+            user_version: self.header_u32(60) as i32,
+            application_id: self.header_u32(68) as i32,
+            schema_version: self.header_u32(40) as i32,
+            sqlite_version: format!(
+                "{}.{}.{}",
+                version / 1_000_000,
+                version / 1000 % 1000,
+                version % 1000
+            ),
+            compile_options: vec![],
+        }
+    }
+
+    /// The page, the first page has number 1
+    fn page(&self, number: u32) -> Result<&'a [u8]> {
+        let start = (number as usize)
+            .checked_sub(1)
+            .ok_or_else(|| invalid("Page 0 doesn't exist".to_string()))?
+            * self.page_size;
+
+        self.bytes
+            .get(start..start + self.page_size)
+            .ok_or_else(|| invalid(format!("Page {number} is out of bounds")))
+    }
+
+    /// Reads the records of the table b-tree in rowid order
+    /// Every page can be visited once, a page that is visited again means the b-tree has a loop.
+    fn table_rows(
+        &self,
+        number: u32,
+        depth: usize,
+        visited: &mut HashSet<u32>,
+        rows: &mut Vec<Vec<Value>>,
+    ) -> Result<()> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("The b-tree is too deep".to_string()));
+        }
+
+        if !visited.insert(number) {
+            return Err(invalid(format!("Page {number} is visited twice")));
+        }
+
+        let page = self.page(number)?;
+        // The first page starts with the database header
+        let header = if number == 1 { 100 } else { 0 };
+        let cells = read_u16(page, header + 3)? as usize;
+        let (is_leaf, pointers) = match page[header] {
+            0x0d => (true, header + 8),
+            0x05 => (false, header + 12),
+            kind => return Err(invalid(format!("Page {number} has page type {kind}"))),
+        };
+
+        for i in 0..cells {
+            let cell = read_u16(page, pointers + i * 2)? as usize;
+
+            if is_leaf {
+                let (size, n) = read_varint(page, cell)?;
+                // The rowid
+                let (_, m) = read_varint(page, cell + n)?;
+
+                rows.push(read_record(
+                    &self.payload(page, cell + n + m, size as usize)?,
+                    self.encoding,
+                )?);
+            } else {
+                self.table_rows(read_u32(page, cell)?, depth + 1, visited, rows)?;
+            }
+        }
+
+        if !is_leaf {
+            self.table_rows(read_u32(page, header + 8)?, depth + 1, visited, rows)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the payload of a cell, following the overflow pages
+    fn payload(&self, page: &[u8], start: usize, size: usize) -> Result<Vec<u8>> {
+        let u = self.usable_size;
+        let max_local = u - 35;
+        let local = if size <= max_local {
+            size
+        } else {
+            let min_local = (u - 12) * 32 / 255 - 23;
+            let k = min_local + (size - min_local) % (u - 4);
+
+            if k <= max_local {
+                k
+            } else {
+                min_local
+            }
+        };
+        let mut payload = slice(page, start, local)?.to_vec();
+        let mut next = if local < size {
+            read_u32(page, start + local)?
+        } else {
+            0
+        };
+
+        while payload.len() < size {
+            // Every page can be visited once, more means there is a loop
+            if next == 0 || payload.len() > self.bytes.len() {
+                return Err(invalid("Invalid overflow page".to_string()));
+            }
+
+            let overflow = self.page(next)?;
+            let amount = (size - payload.len()).min(u - 4);
+
+            payload.extend_from_slice(slice(overflow, 4, amount)?);
+            next = read_u32(overflow, 0)?;
+        }
+
+        Ok(payload)
+    }
+}
+
+fn slice(bytes: &[u8], start: usize, len: usize) -> Result<&[u8]> {
+    bytes
+        .get(start..start + len)
+        .ok_or_else(|| invalid("Unexpected end of page".to_string()))
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Result<u16> {
+    Ok(u16::from_be_bytes(
+        slice(bytes, offset, 2)?.try_into().unwrap(),
+    ))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Result<u32> {
+    Ok(u32::from_be_bytes(
+        slice(bytes, offset, 4)?.try_into().unwrap(),
+    ))
+}
+
+/// Reads a variable length integer, returns the value and the amount of bytes read
+fn read_varint(bytes: &[u8], offset: usize) -> Result<(u64, usize)> {
+    let mut value = 0u64;
+
+    for i in 0..9 {
+        let byte = *slice(bytes, offset + i, 1)?.first().unwrap();
+
+        // The ninth byte uses all 8 bits
+        if i == 8 {
+            return Ok(((value << 8) | byte as u64, 9));
+        }
+
+        value = (value << 7) | (byte & 0x7f) as u64;
+
+        if byte & 0x80 == 0 {
+            return Ok((value, i + 1));
+        }
+    }
+
+    unreachable!()
+}
+
+/// Decodes a record, text is decoded with the encoding of the file
+fn read_record(payload: &[u8], encoding: TextEncoding) -> Result<Vec<Value>> {
+    let (header_size, mut offset) = read_varint(payload, 0)?;
+    let mut serial_types = vec![];
+
+    while offset < header_size as usize {
+        let (serial_type, n) = read_varint(payload, offset)?;
+
+        serial_types.push(serial_type);
+        offset += n;
+    }
+
+    let mut values = vec![];
+
+    for serial_type in serial_types {
+        let (value, size) = match serial_type {
+            0 => (Value::Null, 0),
+            1..=6 => {
+                let size = [1, 2, 3, 4, 6, 8][serial_type as usize - 1];
+                let bytes = slice(payload, offset, size)?;
+                // Sign extend from the first byte
+                let mut value = (bytes[0] as i8) as i64;
+
+                for b in &bytes[1..] {
+                    value = (value << 8) | *b as i64;
+                }
+
+                (Value::Integer(value), size)
+            }
+            7 => (
+                Value::Real(f64::from_be_bytes(
+                    slice(payload, offset, 8)?.try_into().unwrap(),
+                )),
+                8,
+            ),
+            8 => (Value::Integer(0), 0),
+            9 => (Value::Integer(1), 0),
+            n if n >= 12 && n % 2 == 0 => {
+                let size = (n as usize - 12) / 2;
+
+                (Value::Blob(slice(payload, offset, size)?.to_vec()), size)
+            }
+            n if n >= 13 => {
+                let size = (n as usize - 13) / 2;
+
+                (
+                    Value::Text(encoding.decode(slice(payload, offset, size)?)?),
+                    size,
+                )
+            }
+            n => return Err(invalid(format!("Invalid serial type {n}"))),
+        };
+
+        values.push(value);
+        offset += size;
+    }
+
+    Ok(values)
+}
+
+/// A row of `sqlite_master`
+struct SchemaRow {
+    kind: String,
+    name: String,
+    table_name: String,
+    root_page: u32,
+    sql: Option<String>,
+}
+
+impl SchemaRow {
+    fn new(values: Vec<Value>) -> Result<Self> {
+        let text = |i: usize| match values.get(i) {
+            Some(Value::Text(text)) => Ok(Some(text.clone())),
+            Some(Value::Null) => Ok(None),
+            _ => Err(invalid("Invalid sqlite_master row".to_string())),
+        };
+        let required =
+            |i: usize| text(i)?.ok_or_else(|| invalid("Invalid sqlite_master row".to_string()));
+
+        Ok(Self {
+            kind: required(0)?,
+            name: required(1)?,
+            table_name: required(2)?,
+            root_page: match values.get(3) {
+                Some(Value::Integer(page)) => *page as u32,
+                // Views and triggers have no b-tree
+                _ => 0,
+            },
+            sql: text(4)?,
+        })
+    }
+}
+
+/// Builds the tables in the same way as parsing through SQLite does
+fn tables(schema: &[SchemaRow], warnings: &mut Vec<ParseWarning>) -> Result<Vec<Table>> {
+    let mut definitions = vec![];
+
+    for row in schema {
+        let sql = match &row.sql {
+            Some(sql) if row.kind == "table" => sql,
+            _ => continue,
+        };
+
+        let definition = parse_create_virtual_table(sql)
+            .or_else(|| parse_create_table(sql))
+            .ok_or_else(|| invalid(format!("Can't parse the SQL of table {}", row.name)))?;
+        let columns = definition
+            .columns
+            .iter()
+            .filter(|c| !c.generated)
+            .enumerate()
+            .map(|(id, c)| {
+                let (the_type, logical_type) =
+                    declared_type(&c.declared_type, &row.name, &c.name, warnings);
+                let part_of_pk = definition
+                    .primary_key
+                    .iter()
+                    .any(|pk| pk.eq_ignore_ascii_case(&c.name));
+
+                Column {
+                    id: id as i32,
+                    name: c.name.clone(),
+                    the_type,
+                    declared_type: c.declared_type.clone(),
+                    // The primary key of a WITHOUT ROWID table is implicitly NOT NULL
+                    nullable: !(c.not_null || definition.without_rowid && part_of_pk),
+                    part_of_pk,
+                    default_value: c.default_value.clone(),
+                    boolean_hint: false,
+                    logical_type,
+                    extension: None,
+                    comment: None,
+                    enum_values: None,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        definitions.push((row, definition, columns));
+    }
+
+    let mut tables = vec![];
+
+    for (row, definition, columns) in &definitions {
+        let foreign_keys = foreign_keys(&row.name, definition, columns, &definitions, warnings)?;
+        let mut indexes = vec![];
+        let mut index_sql = BTreeMap::new();
+
+        for index_row in schema {
+            let sql = match &index_row.sql {
+                Some(sql) if index_row.kind == "index" && index_row.table_name == row.name => sql,
+                _ => continue,
+            };
+
+            index_sql.insert(index_row.name.clone(), sql.clone());
+
+            let index = parse_create_index(sql).ok_or_else(|| {
+                invalid(format!("Can't parse the SQL of index {}", index_row.name))
+            })?;
+
+            if index.expressions {
+                warnings.push(ParseWarning::IndexExpression {
+                    table: row.name.clone(),
+                    index: index_row.name.clone(),
+                });
+
+                if index.columns.is_empty() {
+                    continue;
+                }
+            }
+
+            indexes.push(Index {
+                name: index_row.name.clone(),
+                columns: find_columns(columns, &index.columns, &row.name)?,
+                unique: index.unique,
+                storage: None,
+                statistics: None,
+            });
+        }
+
+        // The automatic indexes of UNIQUE constraints, newest first like pragma_index_list
+        let mut unique_columns: Vec<&String> = vec![];
+
+        for name in definition.unique.iter().rev().flatten() {
+            if !unique_columns.contains(&name) {
+                unique_columns.push(name);
+            }
+        }
+
+        for name in unique_columns {
+            let column = find_columns(columns, std::slice::from_ref(name), &row.name)?.remove(0);
+            let is_foreign_key = foreign_keys
+                .iter()
+                .any(|f| f.from_column.iter().any(|f| f.name == column.name));
+
+            // Primary and foreign keys are skipped, like parsing through SQLite does
+            if column.part_of_pk || is_foreign_key {
+                continue;
+            }
+
+            indexes.push(Index {
+                name: column.name.clone(),
+                columns: vec![column],
+                unique: true,
+                storage: None,
+                statistics: None,
+            });
+        }
+
+        // Indexes with the same columns as an earlier index are skipped, like parsing through
+        // SQLite does
+        let mut unique_indexes: Vec<Index> = vec![];
+
+        for index in indexes {
+            match unique_indexes.iter().find(|i| i.columns == index.columns) {
+                Some(duplicate_of) => warnings.push(ParseWarning::DuplicateIndex {
+                    table: row.name.clone(),
+                    index: index.name,
+                    duplicate_of: duplicate_of.name.clone(),
+                }),
+                None => unique_indexes.push(index),
+            }
+        }
+
+        tables.push(Table {
+            table_name: row.name.clone(),
+            columns: columns.clone(),
+            foreign_keys,
+            indexes: unique_indexes,
+            automatic_indexes: automatic_indexes(&row.name, &row.sql.clone().unwrap(), columns),
+            row_count: None,
+            sequence: None,
+            storage: None,
+            create_sql: row.sql.clone().unwrap(),
+            index_sql,
+            lookup: LookupMode::default(),
+        });
+    }
+
+    Ok(tables)
+}
+
+/// The foreign keys ordered by id, SQLite numbers them in reverse order of declaration
+/// Foreign keys to a missing table or column are skipped with a warning
+fn foreign_keys(
+    table_name: &str,
+    definition: &TableDefinition,
+    columns: &[Column],
+    tables: &[(&SchemaRow, TableDefinition, Vec<Column>)],
+    warnings: &mut Vec<ParseWarning>,
+) -> Result<Vec<ForeignKey>> {
+    let mut foreign_keys = vec![];
+
+    for (id, foreign_key) in definition.foreign_keys.iter().rev().enumerate() {
+        let Some((_, referenced, referenced_columns)) = tables
+            .iter()
+            .find(|(row, _, _)| row.name.eq_ignore_ascii_case(&foreign_key.table))
+        else {
+            warnings.push(ParseWarning::MissingForeignKeyTable {
+                table: table_name.to_string(),
+                referenced_table: foreign_key.table.clone(),
+            });
+
+            continue;
+        };
+        // A foreign key without columns refers to the primary key
+        let to_column = if foreign_key.to_column.is_empty() {
+            &referenced.primary_key
+        } else {
+            &foreign_key.to_column
+        };
+
+        if let Some(missing) = to_column
+            .iter()
+            .find(|c| find_column(referenced_columns, c).is_none())
+        {
+            warnings.push(ParseWarning::MissingForeignKeyColumn {
+                table: table_name.to_string(),
+                referenced_table: foreign_key.table.clone(),
+                column: missing.clone(),
+            });
+
+            continue;
+        }
+
+        foreign_keys.push(ForeignKey {
+            id: id as i32,
+            table: foreign_key.table.clone(),
+            from_column: find_columns(columns, &foreign_key.from_column, table_name)?,
+            to_column: find_columns(referenced_columns, to_column, &foreign_key.table)?,
+            on_update: foreign_key.on_update,
+            on_delete: foreign_key.on_delete,
+        });
+    }
+
+    Ok(foreign_keys)
+}
+
+fn find_columns(columns: &[Column], names: &[String], table_name: &str) -> Result<Vec<Column>> {
+    names
+        .iter()
+        .map(|name| {
+            find_column(columns, name)
+                .cloned()
+                .ok_or_else(|| invalid(format!("Unknown column {name} in table {table_name}")))
+        })
+        .collect()
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::backend::parse_backend;
+    use crate::file_format::{parse_file, parse_file_bytes, FileFormatBackend};
+    use crate::{parse_no_parser, parse_no_parser_with_options, ParseOptions};
+
+    #[test]
+    fn test_parse_file() {
+        let path = std::env::temp_dir().join("sqlite_parser_file_format.sqlite3");
+        let _ = std::fs::remove_file(&path);
+        let connection = Connection::open(&path).unwrap();
+        // Enough tables to make sqlite_master span multiple pages, and a create statement that
+        // is too big for a single page
+        let mut sql = (0..100)
+            .map(|i| format!("CREATE TABLE filler_{i} (id INTEGER NOT NULL PRIMARY KEY);"))
+            .collect::<String>();
+
+        sql.push_str(&format!(
+            "PRAGMA user_version = 7;
+            PRAGMA application_id = 12;
+            CREATE TABLE user (
+                user_id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL DEFAULT '{}',
+                email TEXT UNIQUE,
+                parent_id INTEGER REFERENCES user,
+                doubled INTEGER AS (user_id * 2),
+                age INTEGER DEFAULT (18)
+            );
+            CREATE TABLE [contacts] (
+                contact_id INTEGER NOT NULL,
+                \"first name\" TEXT NOT NULL,
+                user_id INTEGER,
+                phone TEXT,
+                CONSTRAINT fk FOREIGN KEY(user_id) REFERENCES user(user_id) ON DELETE CASCADE,
+                FOREIGN KEY(phone) REFERENCES user(email),
+                PRIMARY KEY (contact_id, \"first name\"),
+                UNIQUE (phone, user_id)
+            );
+            CREATE INDEX contacts_user_id ON contacts(user_id, \"first name\" DESC);
+            CREATE UNIQUE INDEX user_lower_name ON user(lower(name), age);
+            CREATE TABLE w (k TEXT, n INT, PRIMARY KEY (k, n)) WITHOUT ROWID;
+            CREATE TABLE w_ref (k TEXT, n INT, FOREIGN KEY (k, n) REFERENCES w(k, n));
+            CREATE VIRTUAL TABLE f USING fts5(title, \"body\" UNINDEXED, tokenize = 'porter');
+            CREATE VIRTUAL TABLE g USING fts4(a, b, tokenize=porter);
+            CREATE VIRTUAL TABLE r USING rtree(id, min_x, max_x, +aux TEXT);
+            CREATE VIRTUAL TABLE r_i32 USING rtree_i32(id, min_x, max_x);
+            INSERT INTO user (user_id, name) VALUES (41, 'a');",
+            "x".repeat(10_000)
+        ));
+
+        connection.execute_batch(&sql).unwrap();
+        drop(connection);
+
+        let expected = parse_no_parser(&path);
+        let metadata = parse_file(&path).unwrap();
+        let options = ParseOptions {
+            sequences: true,
+            ..Default::default()
+        };
+        let backend = FileFormatBackend::new(&std::fs::read(&path).unwrap()).unwrap();
+        let with_sequences = parse_backend(&backend, options.clone());
+
+        assert_eq!(
+            parse_no_parser_with_options(&path, options).tables,
+            with_sequences.tables
+        );
+        assert_eq!(Some(41), with_sequences.table("user").unwrap().sequence);
+        assert_eq!(None, with_sequences.table("contacts").unwrap().sequence);
+        assert_eq!(None, metadata.table("user").unwrap().sequence);
+
+        assert_eq!(expected.tables.len(), metadata.tables.len());
+
+        for table in metadata.tables.values() {
+            assert_eq!(expected.table(&table.table_name), Some(table));
+        }
+
+        let mut database = expected.database.clone();
+
+        database.sqlite_version = rusqlite::version().to_string();
+        database.compile_options = vec![];
+
+        assert_eq!(database, metadata.database);
+        assert_eq!(7, metadata.database.user_version);
+        assert!(!metadata.table("w").unwrap().column("k").unwrap().nullable);
+        assert_eq!(2, metadata.table("f").unwrap().columns.len());
+
+        // The right child of the first page points back to itself
+        let mut bytes = std::fs::read(&path).unwrap();
+
+        assert_eq!(0x05, bytes[100]);
+
+        bytes[108..112].copy_from_slice(&1u32.to_be_bytes());
+
+        assert!(parse_file_bytes(&bytes).is_err());
+
+        std::fs::remove_file(path).unwrap();
+
+        assert!(parse_file_bytes(b"not a database").is_err());
+    }
+
+    #[test]
+    fn test_parse_file_utf16() {
+        for encoding in ["UTF-16le", "UTF-16be"] {
+            let path = std::env::temp_dir().join(format!("sqlite_parser_{encoding}.sqlite3"));
+            let _ = std::fs::remove_file(&path);
+            let connection = Connection::open(&path).unwrap();
+
+            connection
+                .execute_batch(&format!(
+                    "PRAGMA encoding = '{encoding}';
+                    CREATE TABLE gebruiker (gebruiker_id INTEGER NOT NULL PRIMARY KEY, naam TEXT);
+                    CREATE TABLE \"café ☕\" (
+                        id INTEGER NOT NULL PRIMARY KEY,
+                        gebruiker_id INTEGER REFERENCES gebruiker
+                    );
+                    CREATE INDEX café_gebruiker ON \"café ☕\"(gebruiker_id);"
+                ))
+                .unwrap();
+            drop(connection);
+
+            let expected = parse_no_parser(&path);
+            let metadata = parse_file(&path).unwrap();
+
+            assert_eq!(encoding, metadata.database.encoding);
+            assert_eq!(expected.tables, metadata.tables);
+            assert!(metadata.table("café ☕").is_some());
+
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! FTS5 tables with external content, linked with their content table and the triggers that keep
+//! the index in sync
+//!
+//! ```
+//! # #[cfg(feature = "rusqlite")]
+//! # {
+//! use sqlite_parser::parse_sql;
+//!
+//! let metadata = parse_sql(
+//!     "CREATE TABLE post (post_id INTEGER NOT NULL PRIMARY KEY, title TEXT, body TEXT);
+//!     CREATE VIRTUAL TABLE post_fts USING fts5(title, body, content='post', content_rowid='post_id');
+//!     CREATE TRIGGER post_ai AFTER INSERT ON post BEGIN
+//!         INSERT INTO post_fts(rowid, title, body) VALUES (new.post_id, new.title, new.body);
+//!     END;",
+//! );
+//! let fts = &metadata.fts_tables()[0];
+//!
+//! assert_eq!("post", fts.content_table);
+//! assert_eq!("post_ai", fts.triggers[0].name);
+//! assert!(!fts.is_synchronized());
+//! # }
+//! ```
+
+use crate::tokenizer::{tokenize_without_trivia, unquote, Token, TokenKind};
+use crate::trigger::{Trigger, TriggerTiming};
+use crate::Metadata;
+
+/// The shadow tables that FTS5 creates, as suffix of the FTS table name
+const SHADOW_TABLES: [&str; 5] = ["data", "idx", "content", "docsize", "config"];
+
+/// An FTS5 table that indexes the rows of a content table (`content=`)
+#[derive(Debug, PartialEq, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FtsTable {
+    /// The name of the virtual table
+    pub table_name: String,
+    /// The table of the `content` option
+    pub content_table: String,
+    /// The column of the `content_rowid` option, `rowid` by default
+    pub content_rowid: String,
+    /// The columns of the FTS table, which are read from the content table
+    pub columns: Vec<String>,
+    /// The tables that FTS5 created for the index, sorted by name
+    pub shadow_tables: Vec<String>,
+    /// The AFTER triggers on the content table that write to the FTS table, sorted by name
+    pub triggers: Vec<Trigger>,
+}
+
+impl FtsTable {
+    /// Checks if there are triggers for an INSERT, UPDATE and DELETE of the content table
+    pub fn is_synchronized(&self) -> bool {
+        [Trigger::on_insert, Trigger::on_update, Trigger::on_delete]
+            .iter()
+            .all(|on| self.triggers.iter().any(on))
+    }
+
+    /// The names of the FTS table, its shadow tables and its triggers, e.g. to treat them as one
+    /// unit in generated code or a diff
+    pub fn objects(&self) -> Vec<&str> {
+        std::iter::once(&self.table_name)
+            .chain(&self.shadow_tables)
+            .chain(self.triggers.iter().map(|t| &t.name))
+            .map(|n| n.as_str())
+            .collect()
+    }
+}
+
+impl Metadata {
+    /// The FTS5 tables with external content, sorted by name
+    /// Contentless tables (`content=''`) and tables of which the content table doesn't exist are
+    /// skipped.
+    pub fn fts_tables(&self) -> Vec<FtsTable> {
+        let mut fts_tables = vec![];
+
+        for table in self.sorted_tables() {
+            let Some(options) = parse_fts5(&table.create_sql) else {
+                continue;
+            };
+            let Some(content_table) = options
+                .content
+                .filter(|c| !c.is_empty())
+                .and_then(|c| self.table(&c))
+            else {
+                continue;
+            };
+            let prefix = format!("{}_", table.table_name.to_lowercase());
+            let mut shadow_tables = self
+                .tables
+                .keys()
+                .filter(|n| {
+                    n.to_lowercase()
+                        .strip_prefix(&prefix)
+                        .is_some_and(|s| SHADOW_TABLES.contains(&s))
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+
+            shadow_tables.sort();
+
+            fts_tables.push(FtsTable {
+                table_name: table.table_name.clone(),
+                content_table: content_table.table_name.clone(),
+                content_rowid: options.content_rowid.unwrap_or_else(|| "rowid".to_string()),
+                columns: options.columns,
+                shadow_tables,
+                triggers: self
+                    .triggers_on(&content_table.table_name)
+                    .into_iter()
+                    .filter(|t| {
+                        t.timing == TriggerTiming::After && writes_to(&t.sql, &table.table_name)
+                    })
+                    .cloned()
+                    .collect(),
+            });
+        }
+
+        fts_tables
+    }
+
+    /// Checks if the table is a shadow table of one of the `fts_tables`
+    pub fn is_fts_shadow_table(&self, table_name: &str) -> bool {
+        self.fts_tables().iter().any(|f| {
+            f.shadow_tables
+                .iter()
+                .any(|s| s.eq_ignore_ascii_case(table_name))
+        })
+    }
+}
+
+struct Fts5Options {
+    columns: Vec<String>,
+    content: Option<String>,
+    content_rowid: Option<String>,
+}
+
+/// The columns and options of `CREATE VIRTUAL TABLE ... USING fts5(...)`
+fn parse_fts5(sql: &str) -> Option<Fts5Options> {
+    let tokens = tokenize_without_trivia(sql);
+    let using = tokens.iter().position(|t| t.is_keyword("USING"))?;
+
+    if !tokens.get(using + 1)?.is_keyword("fts5") || tokens.get(using + 2)?.text != "(" {
+        return None;
+    }
+
+    let mut options = Fts5Options {
+        columns: vec![],
+        content: None,
+        content_rowid: None,
+    };
+
+    for argument in tokens[using + 3..].split(|t| t.text == "," || t.text == ")") {
+        match argument {
+            [key, equals, value] if equals.text == "=" => {
+                let value = Some(unquote(value.text));
+
+                if key.is_keyword("content") {
+                    options.content = value;
+                } else if key.is_keyword("content_rowid") {
+                    options.content_rowid = value;
+                }
+            }
+            // A column, optionally followed by UNINDEXED
+            [column, ..] => options.columns.push(unquote(column.text)),
+            [] => {}
+        }
+    }
+
+    Some(options)
+}
+
+/// Checks if the body of the trigger inserts, updates or deletes rows of the table
+fn writes_to(sql: &str, table_name: &str) -> bool {
+    let tokens = tokenize_without_trivia(sql);
+    let Some(begin) = tokens.iter().position(|t| t.is_keyword("BEGIN")) else {
+        return false;
+    };
+    let is_table = |t: &Token| {
+        matches!(t.kind, TokenKind::Word | TokenKind::QuotedIdentifier)
+            && unquote(t.text).eq_ignore_ascii_case(table_name)
+    };
+
+    tokens[begin..].windows(2).any(|w| {
+        (w[0].is_keyword("INTO") || w[0].is_keyword("UPDATE") || w[0].is_keyword("FROM"))
+            && is_table(&w[1])
+    })
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_fts_tables() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE post (post_id INTEGER NOT NULL PRIMARY KEY, title TEXT, body TEXT);
+            CREATE VIRTUAL TABLE post_fts USING fts5(
+                title,
+                body UNINDEXED,
+                content = 'post',
+                content_rowid = \"post_id\"
+            );
+            CREATE VIRTUAL TABLE note_fts USING fts5(text, content = '');
+            CREATE TABLE log (message TEXT);
+            CREATE TRIGGER post_ai AFTER INSERT ON post BEGIN
+                INSERT INTO post_fts(rowid, title, body) VALUES (new.post_id, new.title, new.body);
+            END;
+            CREATE TRIGGER post_ad AFTER DELETE ON post BEGIN
+                INSERT INTO post_fts(post_fts, rowid, title, body)
+                VALUES ('delete', old.post_id, old.title, old.body);
+            END;
+            CREATE TRIGGER post_au AFTER UPDATE ON post BEGIN
+                INSERT INTO post_fts(post_fts, rowid, title, body)
+                VALUES ('delete', old.post_id, old.title, old.body);
+                INSERT INTO \"post_fts\"(rowid, title, body) VALUES (new.post_id, new.title, new.body);
+            END;
+            CREATE TRIGGER post_log AFTER INSERT ON post BEGIN
+                INSERT INTO log VALUES ('post_fts');
+            END;",
+        );
+        let fts_tables = metadata.fts_tables();
+
+        assert_eq!(1, fts_tables.len());
+
+        let fts = &fts_tables[0];
+
+        assert_eq!("post_fts", fts.table_name);
+        assert_eq!("post", fts.content_table);
+        assert_eq!("post_id", fts.content_rowid);
+        assert_eq!(vec!["title", "body"], fts.columns);
+        assert_eq!(
+            vec![
+                "post_fts_config",
+                "post_fts_data",
+                "post_fts_docsize",
+                "post_fts_idx"
+            ],
+            fts.shadow_tables
+        );
+        assert_eq!(
+            vec!["post_ad", "post_ai", "post_au"],
+            fts.triggers.iter().map(|t| &t.name).collect::<Vec<_>>()
+        );
+        assert!(fts.is_synchronized());
+        assert_eq!(8, fts.objects().len());
+        assert!(metadata.is_fts_shadow_table("POST_FTS_DATA"));
+        assert!(!metadata.is_fts_shadow_table("note_fts_data"));
+    }
+}
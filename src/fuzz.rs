@@ -0,0 +1,302 @@
+//! Implementations of `arbitrary::Arbitrary`, to fuzz code that consumes the metadata
+//!
+//! The generated schemas are internally consistent: names are unique, index columns exist and
+//! foreign keys refer to the complete primary key of a generated table. The `create_sql` of
+//! every table is a valid CREATE TABLE statement for the table.
+
+use std::collections::{BTreeMap, HashMap};
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::database::DatabaseInfo;
+use crate::ddl::{automatic_indexes, create_table_sql, generated_index_sql};
+use crate::{Column, ForeignKey, Index, LookupMode, Metadata, Table, Type};
+
+const MAX_TABLES: usize = 8;
+const MAX_COLUMNS: usize = 8;
+const MAX_INDEXES: usize = 3;
+const MAX_FOREIGN_KEYS: usize = 2;
+
+impl<'a> Arbitrary<'a> for Column {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let name = identifier(u)?;
+
+        arbitrary_column(u, 0, name)
+    }
+}
+
+/// Foreign keys of a single table only refer to the table itself
+impl<'a> Arbitrary<'a> for Table {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut names = vec![];
+        let mut table = arbitrary_table(u, &mut names)?;
+        let foreign_keys = arbitrary_foreign_keys(u, &table, std::slice::from_ref(&table))?;
+
+        table.foreign_keys = foreign_keys;
+        table.create_sql = create_table_sql(&table);
+        table.automatic_indexes =
+            automatic_indexes(&table.table_name, &table.create_sql, &table.columns);
+        table.index_sql = generated_index_sql(&table);
+
+        Ok(table)
+    }
+}
+
+impl<'a> Arbitrary<'a> for Metadata {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        // Tables and indexes share a namespace in SQLite
+        let mut names = vec![];
+        let mut tables = vec![];
+
+        for _ in 0..u.int_in_range(1..=MAX_TABLES)? {
+            tables.push(arbitrary_table(u, &mut names)?);
+        }
+
+        for i in 0..tables.len() {
+            tables[i].foreign_keys = arbitrary_foreign_keys(u, &tables[i], &tables)?;
+            tables[i].create_sql = create_table_sql(&tables[i]);
+            tables[i].automatic_indexes = automatic_indexes(
+                &tables[i].table_name,
+                &tables[i].create_sql,
+                &tables[i].columns,
+            );
+            tables[i].index_sql = generated_index_sql(&tables[i]);
+        }
+
+        Ok(Metadata {
+            tables: tables
+                .into_iter()
+                .map(|t| (t.table_name.clone(), t))
+                .collect(),
+            samples: HashMap::new(),
+            profiles: HashMap::new(),
+            integrity: None,
+            database: DatabaseInfo::default(),
+            lookup: LookupMode::default(),
+            triggers: vec![],
+            views: vec![],
+            annotations: Default::default(),
+        })
+    }
+}
+
+/// A short lowercase identifier, it never starts with the reserved `sqlite_` prefix
+fn identifier(u: &mut Unstructured) -> Result<String> {
+    let mut name = String::from("n");
+
+    for _ in 0..u.int_in_range(0..=7)? {
+        name.push(*u.choose(b"abcdefghijklmnopqrstuvwxyz0123456789")? as char);
+    }
+
+    Ok(name)
+}
+
+/// Makes the name unique by appending a number, names are compared case insensitive
+fn unique(name: String, names: &mut Vec<String>) -> String {
+    let mut candidate = name.clone();
+    let mut i = 2;
+
+    while names.iter().any(|n| n.eq_ignore_ascii_case(&candidate)) {
+        candidate = format!("{name}{i}");
+        i += 1;
+    }
+
+    names.push(candidate.clone());
+
+    candidate
+}
+
+fn arbitrary_column(u: &mut Unstructured, id: i32, name: String) -> Result<Column> {
+    let the_type = match Type::arbitrary(u)? {
+        // A scale can't be declared without a precision
+        Type::Numeric {
+            precision: None, ..
+        } => Type::Numeric {
+            precision: None,
+            scale: None,
+        },
+        the_type => the_type,
+    };
+    let default_value = if bool::arbitrary(u)? {
+        Some(
+            match the_type {
+                Type::Integer => "0",
+                Type::Real | Type::Numeric { .. } => "1.5",
+                Type::Text | Type::String => "'text'",
+                Type::Blob | Type::Unknown => "x'00'",
+            }
+            .to_string(),
+        )
+    } else {
+        None
+    };
+
+    // Other unknown names get the type of their affinity
+    let declared_type = match the_type {
+        Type::Unknown => "DECIMAL(1.5)".to_string(),
+        the_type => the_type.to_string(),
+    };
+
+    Ok(Column {
+        id,
+        name,
+        the_type,
+        declared_type,
+        nullable: bool::arbitrary(u)?,
+        part_of_pk: false,
+        default_value,
+        boolean_hint: false,
+        logical_type: None,
+        extension: None,
+        comment: None,
+        enum_values: None,
+    })
+}
+
+/// A table with columns and indexes, but without foreign keys and `create_sql`
+fn arbitrary_table(u: &mut Unstructured, names: &mut Vec<String>) -> Result<Table> {
+    let table_name = unique(identifier(u)?, names);
+    let mut column_names = vec![];
+    let mut columns = vec![];
+
+    for id in 0..u.int_in_range(1..=MAX_COLUMNS)? {
+        let name = unique(identifier(u)?, &mut column_names);
+
+        columns.push(arbitrary_column(u, id as i32, name)?);
+    }
+
+    for column in columns.iter_mut().take(u.int_in_range(0..=2)?) {
+        column.part_of_pk = true;
+    }
+
+    let mut indexes: Vec<Index> = vec![];
+
+    for _ in 0..u.int_in_range(0..=MAX_INDEXES)? {
+        let mut index_columns: Vec<Column> = vec![];
+
+        for _ in 0..u.int_in_range(1..=columns.len().min(3))? {
+            let column = u.choose(&columns)?;
+
+            if !index_columns.contains(column) {
+                index_columns.push(column.clone());
+            }
+        }
+
+        // SQLite allows it, but the parser rejects indexes on the same columns
+        if indexes.iter().any(|i| i.columns == index_columns) {
+            continue;
+        }
+
+        indexes.push(Index {
+            name: unique(format!("{table_name}_{}", identifier(u)?), names),
+            columns: index_columns,
+            unique: bool::arbitrary(u)?,
+            storage: None,
+            statistics: None,
+        });
+    }
+
+    Ok(Table {
+        table_name,
+        columns,
+        foreign_keys: vec![],
+        indexes,
+        automatic_indexes: vec![],
+        row_count: None,
+        sequence: None,
+        storage: None,
+        create_sql: String::new(),
+        index_sql: BTreeMap::new(),
+        lookup: LookupMode::default(),
+    })
+}
+
+/// Foreign keys from the table to the primary key of one of the tables
+fn arbitrary_foreign_keys(
+    u: &mut Unstructured,
+    table: &Table,
+    tables: &[Table],
+) -> Result<Vec<ForeignKey>> {
+    let mut foreign_keys = vec![];
+
+    for _ in 0..u.int_in_range(0..=MAX_FOREIGN_KEYS)? {
+        let referenced = u.choose(tables)?;
+        let to_column = referenced
+            .columns
+            .iter()
+            .filter(|c| c.part_of_pk)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if to_column.is_empty() || to_column.len() > table.columns.len() {
+            continue;
+        }
+
+        let mut from_column: Vec<Column> = vec![];
+
+        while from_column.len() < to_column.len() {
+            let mut column = u.choose(&table.columns)?;
+
+            if from_column.contains(column) {
+                // Take the first free column, so this always ends
+                column = table
+                    .columns
+                    .iter()
+                    .find(|c| !from_column.contains(c))
+                    .unwrap();
+            }
+
+            from_column.push(column.clone());
+        }
+
+        foreign_keys.push(ForeignKey {
+            id: foreign_keys.len() as i32,
+            table: referenced.table_name.clone(),
+            from_column,
+            to_column,
+            on_update: Arbitrary::arbitrary(u)?,
+            on_delete: Arbitrary::arbitrary(u)?,
+        });
+    }
+
+    Ok(foreign_keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    use crate::ddl::create_index_sql;
+    use crate::tests::metadata_from_sql;
+    use crate::Metadata;
+
+    #[test]
+    fn test_arbitrary_metadata() {
+        for seed in 0..50u32 {
+            let bytes = (0..4096u32)
+                .map(|i| (i.wrapping_mul(2654435761).wrapping_add(seed * 40503) >> 13) as u8)
+                .collect::<Vec<_>>();
+            let metadata = Metadata::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+            let mut sql = vec!["PRAGMA foreign_keys = OFF".to_string()];
+
+            for table in metadata.sorted_tables() {
+                sql.push(table.create_sql.clone());
+
+                for index in &table.indexes {
+                    sql.push(create_index_sql(table, index));
+                }
+            }
+
+            let parsed = metadata_from_sql(&(sql.join(";\n") + ";"));
+
+            for table in metadata.tables.values() {
+                let parsed = parsed.table(&table.table_name).unwrap();
+
+                assert_eq!(table.columns, parsed.columns);
+                assert_eq!(table.create_sql, parsed.create_sql);
+                assert_eq!(table.foreign_keys, parsed.foreign_keys);
+                assert_eq!(table.automatic_indexes, parsed.automatic_indexes);
+            }
+        }
+    }
+}
@@ -0,0 +1,508 @@
+//! The relations between tables, based on the foreign keys
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use petgraph::algo::tarjan_scc;
+use petgraph::graph::{DiGraph, NodeIndex};
+
+use crate::{Column, ForeignKey, Metadata, Table};
+
+/// The maximum amount of columns of a join table that are not part of its foreign keys
+const MAX_JOIN_TABLE_EXTRA_COLUMNS: usize = 2;
+
+/// A graph with a node per table and an edge per foreign key
+/// The edges point from the referencing table to the referenced table
+pub type DependencyGraph<'a> = DiGraph<&'a Table, &'a ForeignKey>;
+
+/// Tables that refer to each other through foreign keys, directly or indirectly
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct ForeignKeyCycle<'a> {
+    /// The tables in the cycle, sorted by name
+    pub tables: Vec<&'a Table>,
+    /// The foreign keys between the tables of the cycle, with the table that holds them
+    pub foreign_keys: Vec<(&'a Table, &'a ForeignKey)>,
+}
+
+/// The cardinality of a relation, see `ForeignKey::cardinality`
+#[derive(Debug, PartialEq, Copy, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Cardinality {
+    pub kind: RelationKind,
+    /// A row doesn't have to refer to a row, see `ForeignKey::is_optional`
+    pub optional: bool,
+}
+
+/// How many rows can refer to the same referenced row
+#[derive(Debug, PartialEq, Copy, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RelationKind {
+    /// The columns of the foreign key are unique, a row is referred to at most once
+    OneToOne,
+    /// A row can be referred to by many rows
+    OneToMany,
+}
+
+/// A table that links the rows of two tables, see `Metadata::many_to_many_relations`
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct ManyToMany<'a> {
+    pub join_table: &'a Table,
+    /// The foreign key to the first table, the foreign keys are sorted by the name of the
+    /// referenced table
+    pub left: &'a ForeignKey,
+    /// The foreign key to the second table, the same table as `left` for a self relation
+    pub right: &'a ForeignKey,
+    /// The columns that are not part of the foreign keys, e.g. the moment the rows were linked
+    pub extra_columns: Vec<&'a Column>,
+}
+
+impl ForeignKey {
+    /// The cardinality of the relation, `table` is the table that holds the foreign key
+    /// The relation is one-to-one when one of the `Table::conflict_targets` consists of columns
+    /// of the foreign key, it is optional when the foreign key `is_optional`.
+    pub fn cardinality(&self, table: &Table) -> Cardinality {
+        let is_foreign_key_column =
+            |column: &Column| self.from_column.iter().any(|c| c.name == column.name);
+        let unique = table
+            .conflict_targets()
+            .into_iter()
+            .any(|columns| columns.into_iter().all(is_foreign_key_column));
+
+        Cardinality {
+            kind: if unique {
+                RelationKind::OneToOne
+            } else {
+                RelationKind::OneToMany
+            },
+            optional: self.is_optional(),
+        }
+    }
+
+    /// Checks if a row doesn't have to refer to a row, since a column of the foreign key is
+    /// nullable
+    pub fn is_optional(&self) -> bool {
+        self.from_column.iter().any(|c| c.nullable)
+    }
+}
+
+impl<'a> ForeignKeyCycle<'a> {
+    /// Checks if the cycle is a table that refers to itself
+    pub fn is_self_reference(&self) -> bool {
+        self.tables.len() == 1
+    }
+}
+
+impl Metadata {
+    /// The foreign keys that refer to the table, with the table that holds them
+    /// Sorted by the name of the referencing table, self references are included
+    pub fn referencing(&self, table_name: &str) -> Vec<(&Table, &ForeignKey)> {
+        self.sorted_tables()
+            .into_iter()
+            .flat_map(|t| {
+                t.foreign_keys
+                    .iter()
+                    .filter(|f| self.lookup.matches(&f.table, table_name))
+                    .map(move |f| (t, f))
+            })
+            .collect()
+    }
+
+    /// The tables that don't refer to other tables and aren't referred to by other tables, sorted
+    /// by name. Self references are ignored.
+    pub fn unreferenced_tables(&self) -> Vec<&Table> {
+        self.sorted_tables()
+            .into_iter()
+            .filter(|t| {
+                t.foreign_keys
+                    .iter()
+                    .all(|f| self.lookup.matches(&f.table, &t.table_name))
+                    && self
+                        .referencing(&t.table_name)
+                        .iter()
+                        .all(|(referencing, _)| referencing.table_name == t.table_name)
+            })
+            .collect()
+    }
+
+    /// Builds the dependency graph of the tables
+    /// Nodes are added in table name order, foreign keys to tables that aren't parsed are skipped
+    /// Example:
+    ///
+    /// ```
+    /// use sqlite_parser::Metadata;
+    ///
+    /// fn print_relations(metadata: &Metadata) {
+    ///     let graph = metadata.dependency_graph();
+    ///
+    ///     for edge in graph.raw_edges() {
+    ///         println!(
+    ///             "{} -> {}",
+    ///             graph[edge.source()].table_name,
+    ///             graph[edge.target()].table_name
+    ///         );
+    ///     }
+    /// }
+    /// ```
+    pub fn dependency_graph(&self) -> DependencyGraph<'_> {
+        let mut graph = DependencyGraph::new();
+        let nodes: HashMap<&str, NodeIndex> = self
+            .sorted_tables()
+            .into_iter()
+            .map(|t| (t.table_name.as_str(), graph.add_node(t)))
+            .collect();
+
+        for table in self.sorted_tables() {
+            for foreign_key in &table.foreign_keys {
+                if let Some(referenced) = self
+                    .table(&foreign_key.table)
+                    .and_then(|t| nodes.get(t.table_name.as_str()))
+                {
+                    graph.add_edge(nodes[table.table_name.as_str()], *referenced, foreign_key);
+                }
+            }
+        }
+
+        graph
+    }
+
+    /// The tables sorted so that referenced tables come before the tables referencing them
+    /// Tables without a relation between them are sorted by name. Tables in a foreign key cycle
+    /// can't be ordered, they are placed next to each other (sorted by name) at the position of
+    /// the cycle as a whole. Self references are ignored.
+    pub fn tables_in_dependency_order(&self) -> Vec<&Table> {
+        let graph = self.dependency_graph();
+        let mut components = tarjan_scc(&graph)
+            .into_iter()
+            .map(|mut nodes| {
+                nodes.sort_by(|a, b| graph[*a].table_name.cmp(&graph[*b].table_name));
+                nodes
+            })
+            .collect::<Vec<_>>();
+
+        components.sort_by(|a, b| graph[a[0]].table_name.cmp(&graph[b[0]].table_name));
+
+        let component_of: HashMap<NodeIndex, usize> = components
+            .iter()
+            .enumerate()
+            .flat_map(|(i, nodes)| nodes.iter().map(move |n| (*n, i)))
+            .collect();
+        // The components each component refers to
+        let mut dependencies = vec![HashSet::new(); components.len()];
+
+        for edge in graph.raw_edges() {
+            let from = component_of[&edge.source()];
+            let to = component_of[&edge.target()];
+
+            if from != to {
+                dependencies[from].insert(to);
+            }
+        }
+
+        // Kahn's algorithm, the components are indexed by name so the lowest index is picked first
+        let mut ready = (0..components.len())
+            .filter(|c| dependencies[*c].is_empty())
+            .collect::<BTreeSet<_>>();
+        let mut ordered = vec![];
+
+        while let Some(component) = ready.pop_first() {
+            ordered.extend(components[component].iter().map(|n| graph[*n]));
+
+            for (other, deps) in dependencies.iter_mut().enumerate() {
+                if deps.remove(&component) && deps.is_empty() {
+                    ready.insert(other);
+                }
+            }
+        }
+
+        ordered
+    }
+
+    /// The join tables, sorted by name
+    /// A join table has two foreign keys of which the columns together are the primary key, has
+    /// at most two other columns and isn't referred to by other tables.
+    pub fn many_to_many_relations(&self) -> Vec<ManyToMany<'_>> {
+        let mut relations = vec![];
+
+        for table in self.sorted_tables() {
+            let [left, right] = &table.foreign_keys[..] else {
+                continue;
+            };
+            let (left, right) = if left.table <= right.table {
+                (left, right)
+            } else {
+                (right, left)
+            };
+            let is_foreign_key_column = |column: &Column| {
+                left.from_column
+                    .iter()
+                    .chain(&right.from_column)
+                    .any(|c| c.name == column.name)
+            };
+            let primary_key = table.primary_key();
+            let extra_columns = table
+                .columns
+                .iter()
+                .filter(|c| !is_foreign_key_column(c))
+                .collect::<Vec<_>>();
+
+            if primary_key.len() != left.from_column.len() + right.from_column.len()
+                || !primary_key.iter().all(|c| is_foreign_key_column(c))
+                || extra_columns.len() > MAX_JOIN_TABLE_EXTRA_COLUMNS
+                || !self.referencing(&table.table_name).is_empty()
+            {
+                continue;
+            }
+
+            relations.push(ManyToMany {
+                join_table: table,
+                left,
+                right,
+                extra_columns,
+            });
+        }
+
+        relations
+    }
+
+    /// Finds the foreign key cycles, including tables that refer to themselves
+    /// Tables that are part of multiple overlapping loops are reported as a single cycle
+    pub fn foreign_key_cycles(&self) -> Vec<ForeignKeyCycle<'_>> {
+        let graph = self.dependency_graph();
+        let mut cycles = vec![];
+
+        for nodes in tarjan_scc(&graph) {
+            let foreign_keys = graph
+                .raw_edges()
+                .iter()
+                .filter(|e| nodes.contains(&e.source()) && nodes.contains(&e.target()))
+                .map(|e| (graph[e.source()], e.weight))
+                .collect::<Vec<_>>();
+
+            // A single table without a self reference is not a cycle
+            if foreign_keys.is_empty() {
+                continue;
+            }
+
+            let mut tables = nodes.into_iter().map(|n| graph[n]).collect::<Vec<_>>();
+
+            tables.sort_by(|a, b| a.table_name.cmp(&b.table_name));
+
+            cycles.push(ForeignKeyCycle {
+                tables,
+                foreign_keys,
+            });
+        }
+
+        cycles.sort_by(|a, b| a.tables[0].table_name.cmp(&b.tables[0].table_name));
+
+        cycles
+    }
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::graph::{Cardinality, RelationKind};
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_cardinality() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY);
+            CREATE TABLE profile (user_id INTEGER NOT NULL PRIMARY KEY REFERENCES user);
+            CREATE TABLE avatar (
+                avatar_id INTEGER NOT NULL PRIMARY KEY,
+                user_id INTEGER UNIQUE REFERENCES user
+            );
+            CREATE TABLE post (
+                post_id INTEGER NOT NULL PRIMARY KEY,
+                user_id INTEGER NOT NULL REFERENCES user,
+                editor_id INTEGER REFERENCES user,
+                slug TEXT,
+                UNIQUE (editor_id, slug)
+            );
+            CREATE UNIQUE INDEX post_user_editor ON post (user_id, editor_id);
+            CREATE UNIQUE INDEX post_live_user ON post (user_id) WHERE slug IS NULL;",
+        );
+        let cardinality = |table_name: &str, i: usize| {
+            let table = metadata.table(table_name).unwrap();
+            let foreign_key = &table.foreign_keys[i];
+            let cardinality = foreign_key.cardinality(table);
+
+            assert_eq!(foreign_key.is_optional(), cardinality.optional);
+
+            cardinality
+        };
+        let one_to_one = |optional| Cardinality {
+            kind: RelationKind::OneToOne,
+            optional,
+        };
+        let one_to_many = |optional| Cardinality {
+            kind: RelationKind::OneToMany,
+            optional,
+        };
+
+        assert_eq!(one_to_one(false), cardinality("profile", 0));
+        assert_eq!(one_to_one(true), cardinality("avatar", 0));
+        // Partial indexes and part of a UNIQUE constraint don't make a column unique
+        assert_eq!(one_to_many(true), cardinality("post", 0));
+        assert_eq!(one_to_many(false), cardinality("post", 1));
+    }
+
+    #[test]
+    fn test_many_to_many_relations() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY);
+            CREATE TABLE group_ (group_id INTEGER NOT NULL PRIMARY KEY);
+            CREATE TABLE membership (
+                user_id INTEGER NOT NULL REFERENCES user,
+                group_id INTEGER NOT NULL REFERENCES group_,
+                joined TEXT,
+                PRIMARY KEY (user_id, group_id)
+            );
+            CREATE TABLE friend (
+                user_id INTEGER NOT NULL REFERENCES user,
+                friend_id INTEGER NOT NULL REFERENCES user,
+                PRIMARY KEY (user_id, friend_id)
+            );
+            CREATE TABLE post (
+                post_id INTEGER NOT NULL PRIMARY KEY,
+                user_id INTEGER NOT NULL REFERENCES user,
+                group_id INTEGER NOT NULL REFERENCES group_
+            );",
+        );
+        let relations = metadata.many_to_many_relations();
+
+        assert_eq!(
+            vec!["friend", "membership"],
+            relations
+                .iter()
+                .map(|r| &r.join_table.table_name)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("user", relations[0].left.table);
+        assert_eq!("user", relations[0].right.table);
+        assert_eq!("group_", relations[1].left.table);
+        assert_eq!("user", relations[1].right.table);
+        assert_eq!("joined", relations[1].extra_columns[0].name);
+    }
+
+    #[test]
+    fn test_dependency_graph() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (
+            user_id INTEGER NOT NULL PRIMARY KEY,
+            parent_id INTEGER,
+            FOREIGN KEY(parent_id) REFERENCES user(user_id)
+        );
+        CREATE TABLE contacts (
+            contact_id INTEGER NOT NULL PRIMARY KEY,
+            user_id INTEGER,
+            FOREIGN KEY(user_id) REFERENCES user(user_id)
+        );
+        CREATE TABLE unrelated (id INTEGER NOT NULL PRIMARY KEY);",
+        );
+        let graph = metadata.dependency_graph();
+
+        assert_eq!(3, graph.node_count());
+
+        let edges = graph
+            .raw_edges()
+            .iter()
+            .map(|e| {
+                (
+                    graph[e.source()].table_name.as_str(),
+                    graph[e.target()].table_name.as_str(),
+                    e.weight.from_column[0].name.as_str(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                ("contacts", "user", "user_id"),
+                ("user", "user", "parent_id")
+            ],
+            edges
+        );
+    }
+
+    #[test]
+    fn test_tables_in_dependency_order() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE a_book (
+            book_id INTEGER NOT NULL PRIMARY KEY,
+            author_id INTEGER,
+            FOREIGN KEY(author_id) REFERENCES z_author(author_id)
+        );
+        CREATE TABLE z_author (
+            author_id INTEGER NOT NULL PRIMARY KEY,
+            mentor_id INTEGER,
+            FOREIGN KEY(mentor_id) REFERENCES z_author(author_id)
+        );
+        CREATE TABLE m_chicken (
+            chicken_id INTEGER NOT NULL PRIMARY KEY,
+            egg_id INTEGER,
+            FOREIGN KEY(egg_id) REFERENCES m_egg(egg_id)
+        );
+        CREATE TABLE m_egg (
+            egg_id INTEGER NOT NULL PRIMARY KEY,
+            chicken_id INTEGER,
+            author_id INTEGER,
+            FOREIGN KEY(chicken_id) REFERENCES m_chicken(chicken_id),
+            FOREIGN KEY(author_id) REFERENCES z_author(author_id)
+        );
+        CREATE TABLE b_unrelated (id INTEGER NOT NULL PRIMARY KEY);",
+        );
+
+        assert_eq!(
+            vec!["b_unrelated", "z_author", "a_book", "m_chicken", "m_egg"],
+            metadata
+                .tables_in_dependency_order()
+                .into_iter()
+                .map(|t| t.table_name.as_str())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_foreign_key_cycles() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (
+            user_id INTEGER NOT NULL PRIMARY KEY,
+            parent_id INTEGER,
+            FOREIGN KEY(parent_id) REFERENCES user(user_id)
+        );
+        CREATE TABLE a (
+            a_id INTEGER NOT NULL PRIMARY KEY,
+            b_id INTEGER,
+            FOREIGN KEY(b_id) REFERENCES b(b_id)
+        );
+        CREATE TABLE b (
+            b_id INTEGER NOT NULL PRIMARY KEY,
+            c_id INTEGER,
+            FOREIGN KEY(c_id) REFERENCES c(c_id)
+        );
+        CREATE TABLE c (
+            c_id INTEGER NOT NULL PRIMARY KEY,
+            a_id INTEGER,
+            user_id INTEGER,
+            FOREIGN KEY(a_id) REFERENCES a(a_id),
+            FOREIGN KEY(user_id) REFERENCES user(user_id)
+        );",
+        );
+        let cycles = metadata.foreign_key_cycles();
+
+        assert_eq!(2, cycles.len());
+        assert_eq!(
+            vec!["a", "b", "c"],
+            cycles[0]
+                .tables
+                .iter()
+                .map(|t| t.table_name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(3, cycles[0].foreign_keys.len());
+        assert!(!cycles[0].is_self_reference());
+        assert!(cycles[1].is_self_reference());
+        assert_eq!("user", cycles[1].tables[0].table_name);
+    }
+}
@@ -0,0 +1,241 @@
+//! Renders the schema together with a profile of the data as a single self-contained HTML page,
+//! to quickly get to know an unfamiliar database
+//!
+//! Every table gets a card with its foreign keys and indexes and a row per column with the null
+//! ratio, the amount of distinct values, the range, the most common values and a histogram of the
+//! numbers. The page has no scripts and no external resources.
+
+use std::fmt::Write;
+
+use rusqlite::Connection;
+
+use crate::profile::{histogram, profile_table, top_values, HistogramBucket};
+use crate::sample::Value;
+use crate::{Column, Metadata, Table};
+
+/// The amount of most common values per column
+const TOP_VALUES: usize = 5;
+/// The amount of buckets of a histogram
+const BUCKETS: usize = 10;
+/// Longer values are cut off
+const MAX_VALUE_LENGTH: usize = 40;
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; background: #f5f5f5; color: #222; }
+.card { background: #fff; border-radius: 6px; padding: 1em 1.5em; margin-bottom: 2em; box-shadow: 0 1px 3px #0003; }
+table { border-collapse: collapse; width: 100%; }
+th, td { text-align: left; padding: 0.3em 0.6em; border-bottom: 1px solid #eee; vertical-align: top; }
+.bar { background: #e0e0e0; height: 0.8em; width: 8em; display: inline-block; }
+.bar span { background: #4a90d9; height: 100%; display: block; }
+.histogram { display: flex; align-items: flex-end; height: 3em; gap: 1px; }
+.histogram span { background: #4a90d9; width: 0.8em; min-height: 1px; }
+.muted { color: #888; }";
+
+/// Profiles every table and renders the report, this scans every table a few times
+/// The profiles of `Metadata::profiles` are used when available.
+pub fn profile_report(connection: &Connection, metadata: &Metadata) -> String {
+    let mut html = String::new();
+
+    writeln!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Database profile</title>\n<style>\n{STYLE}\n</style>\n</head>\n<body>\n<h1>Database profile</h1>"
+    )
+    .unwrap();
+    writeln!(
+        html,
+        "<p class=\"muted\">{} tables, SQLite {}, page size {}, encoding {}</p>",
+        metadata.tables.len(),
+        escape(&metadata.database.sqlite_version),
+        metadata.database.page_size,
+        escape(&metadata.database.encoding)
+    )
+    .unwrap();
+
+    for table in metadata.sorted_tables() {
+        table_card(&mut html, connection, metadata, table);
+    }
+
+    html.push_str("</body>\n</html>\n");
+
+    html
+}
+
+fn table_card(html: &mut String, connection: &Connection, metadata: &Metadata, table: &Table) {
+    let profile = metadata
+        .profiles
+        .get(&table.table_name)
+        .cloned()
+        .unwrap_or_else(|| profile_table(connection, table));
+
+    writeln!(
+        html,
+        "<div class=\"card\">\n<h2>{}</h2>\n<p class=\"muted\">{} rows</p>",
+        escape(&table.table_name),
+        profile.row_count
+    )
+    .unwrap();
+
+    for foreign_key in &table.foreign_keys {
+        writeln!(
+            html,
+            "<p>({}) &rarr; {} ({})</p>",
+            escape(&names(&foreign_key.from_column)),
+            escape(&foreign_key.table),
+            escape(&names(&foreign_key.to_column))
+        )
+        .unwrap();
+    }
+
+    for index in &table.indexes {
+        writeln!(
+            html,
+            "<p class=\"muted\">{}index {} ({})</p>",
+            if index.unique { "unique " } else { "" },
+            escape(&index.name),
+            escape(&names(&index.columns))
+        )
+        .unwrap();
+    }
+
+    html.push_str(
+        "<table>\n<tr><th>Column</th><th>Type</th><th>Nulls</th><th>Distinct</th><th>Range</th><th>Top values</th><th>Histogram</th></tr>\n",
+    );
+
+    for (column, column_profile) in table.columns.iter().zip(&profile.columns) {
+        let null_ratio = ratio(column_profile.null_count, profile.row_count);
+        let range = match (&column_profile.min, &column_profile.max) {
+            (Some(min), Some(max)) => {
+                format!(
+                    "{} &ndash; {}",
+                    escape(&value_text(min)),
+                    escape(&value_text(max))
+                )
+            }
+            _ => String::new(),
+        };
+        let top = top_values(connection, table, column, TOP_VALUES);
+        let top = top
+            .iter()
+            .map(|(value, count)| {
+                format!(
+                    "{} <span class=\"bar\"><span style=\"width: {:.0}%\"></span></span> {count}",
+                    escape(&value_text(value)),
+                    ratio(*count, column_profile.value_count) * 100.
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("<br>");
+
+        writeln!(
+            html,
+            "<tr><td>{}{}</td><td>{}</td><td><span class=\"bar\"><span style=\"width: {:.0}%\"></span></span> {:.1}%</td><td>{}</td><td>{range}</td><td>{top}</td><td>{}</td></tr>",
+            escape(&column.name),
+            if column.part_of_pk { " <span class=\"muted\">PK</span>" } else { "" },
+            escape(&column.declared_type),
+            null_ratio * 100.,
+            null_ratio * 100.,
+            column_profile.distinct_count,
+            histogram_html(&histogram(connection, table, column, BUCKETS))
+        )
+        .unwrap();
+    }
+
+    html.push_str("</table>\n</div>\n");
+}
+
+fn histogram_html(buckets: &[HistogramBucket]) -> String {
+    let Some(max) = buckets.iter().map(|b| b.count).max() else {
+        return String::new();
+    };
+    let bars = buckets
+        .iter()
+        .map(|b| {
+            format!(
+                "<span style=\"height: {:.0}%\" title=\"{} &ndash; {}: {}\"></span>",
+                ratio(b.count, max) * 100.,
+                b.lower,
+                b.upper,
+                b.count
+            )
+        })
+        .collect::<String>();
+
+    format!("<div class=\"histogram\">{bars}</div>")
+}
+
+fn ratio(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.
+    } else {
+        part as f64 / total as f64
+    }
+}
+
+fn names(columns: &[Column]) -> String {
+    columns
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The value as shown in the report, blobs as hex literal
+fn value_text(value: &Value) -> String {
+    let text = match value {
+        Value::Null => "NULL".to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Real(r) => r.to_string(),
+        Value::Text(t) => t.clone(),
+        Value::Blob(b) => format!(
+            "x'{}'",
+            b.iter().map(|b| format!("{b:02x}")).collect::<String>()
+        ),
+    };
+
+    match text.char_indices().nth(MAX_VALUE_LENGTH) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::html::profile_report;
+    use crate::metadata_from_connection;
+
+    #[test]
+    fn test_profile_report() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT, age INTEGER);
+                CREATE TABLE post (post_id INTEGER PRIMARY KEY, user_id INTEGER REFERENCES user);
+                CREATE INDEX post_user_id ON post(user_id);
+                INSERT INTO user VALUES (1, '<b>', 20), (2, 'b', NULL), (3, 'b', 40), (4, NULL, 60);",
+            )
+            .unwrap();
+
+        let metadata = metadata_from_connection(&connection, Default::default());
+        let html = profile_report(&connection, &metadata);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<h2>post</h2>"));
+        assert!(html.contains("<p>(user_id) &rarr; user (user_id)</p>"));
+        assert!(html.contains("index post_user_id (user_id)"));
+        // The names are escaped and the null ratio of name is 1 of 4
+        assert!(html.contains("&lt;b&gt;"));
+        assert!(!html.contains("<b>"));
+        assert!(html.contains("25.0%"));
+        // Only user_id and age of user have numbers, post is empty
+        assert_eq!(2, html.matches("<div class=\"histogram\">").count());
+    }
+}
@@ -0,0 +1,320 @@
+//! What depends on a column or table, to see what breaks when it changes or is dropped
+
+use crate::ddl::check_constraints;
+use crate::tokenizer::{tokenize_without_trivia, unquote, TokenKind};
+use crate::trigger::{Trigger, TriggerEvent};
+use crate::view::View;
+use crate::{quote_identifier, AutomaticIndex, ForeignKey, Index, Metadata, Table};
+
+/// Everything that mentions a column, see `Metadata::references_to_column`
+#[derive(Debug, PartialEq, Clone, Eq, Default)]
+pub struct ColumnReferences<'a> {
+    /// The foreign keys from or to the column, with the table that holds them, sorted by table
+    /// name
+    pub foreign_keys: Vec<(&'a Table, &'a ForeignKey)>,
+    /// The indexes of the table with the column
+    pub indexes: Vec<&'a Index>,
+    /// The PRIMARY KEY and UNIQUE constraints of the table with the column
+    pub automatic_indexes: Vec<&'a AutomaticIndex>,
+    /// The views that select the column, also with `*`
+    pub views: Vec<&'a View>,
+    /// The triggers that read or write the column, or run on an update of it
+    pub triggers: Vec<&'a Trigger>,
+    /// The expressions of the CHECK constraints of the table that mention the column
+    pub checks: Vec<&'a str>,
+}
+
+impl ColumnReferences<'_> {
+    /// Checks if nothing refers to the column, so it can be changed without other changes
+    pub fn is_empty(&self) -> bool {
+        self.foreign_keys.is_empty()
+            && self.indexes.is_empty()
+            && self.automatic_indexes.is_empty()
+            && self.views.is_empty()
+            && self.triggers.is_empty()
+            && self.checks.is_empty()
+    }
+}
+
+/// Everything that a DROP TABLE invalidates, see `Metadata::drop_impact`
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct DropImpact<'a> {
+    pub table: &'a Table,
+    /// The foreign keys of other tables that refer to the table, sorted by table name
+    pub foreign_keys: Vec<(&'a Table, &'a ForeignKey)>,
+    /// The views that select from the table, also through other views, in dependency order
+    pub views: Vec<&'a View>,
+    /// The triggers on the table and the triggers that read or write it
+    pub triggers: Vec<&'a Trigger>,
+    /// The indexes of the table, which are dropped together with it
+    pub indexes: Vec<&'a Index>,
+}
+
+impl DropImpact<'_> {
+    /// The DROP statements to drop the table without leaving broken views and triggers behind
+    /// The views are dropped in reverse dependency order, then the triggers that aren't dropped
+    /// together with the table or a view, then the table. The foreign keys that refer to the
+    /// table aren't changed, with `foreign_keys` enabled the DROP TABLE fails while rows refer to
+    /// it.
+    pub fn statements(&self) -> Vec<String> {
+        let is_dropped = |name: &str| {
+            self.table.table_name.eq_ignore_ascii_case(name)
+                || self.views.iter().any(|v| v.name.eq_ignore_ascii_case(name))
+        };
+        let views = self
+            .views
+            .iter()
+            .rev()
+            .map(|v| format!("DROP VIEW {};", quote_identifier(&v.name)));
+        let triggers = self
+            .triggers
+            .iter()
+            .filter(|t| !is_dropped(&t.table_name))
+            .map(|t| format!("DROP TRIGGER {};", quote_identifier(&t.name)));
+
+        views
+            .chain(triggers)
+            .chain(std::iter::once(format!(
+                "DROP TABLE {};",
+                quote_identifier(&self.table.table_name)
+            )))
+            .collect()
+    }
+}
+
+impl Metadata {
+    /// Everything that dropping the table invalidates, `None` if the table doesn't exist
+    pub fn drop_impact(&self, table_name: &str) -> Option<DropImpact<'_>> {
+        let table = self.table(table_name)?;
+        let is_table = |name: &str| self.lookup.matches(name, &table.table_name);
+        let mut views: Vec<&View> = vec![];
+
+        for view in self.views_in_dependency_order() {
+            if view
+                .references()
+                .any(|r| is_table(r) || views.iter().any(|v| self.lookup.matches(r, &v.name)))
+            {
+                views.push(view);
+            }
+        }
+
+        Some(DropImpact {
+            table,
+            foreign_keys: self
+                .referencing(&table.table_name)
+                .into_iter()
+                .filter(|(t, _)| !is_table(&t.table_name))
+                .collect(),
+            triggers: self
+                .triggers
+                .iter()
+                .filter(|t| {
+                    is_table(&t.table_name) || t.accesses.iter().any(|a| is_table(&a.table_name))
+                })
+                .collect(),
+            views,
+            indexes: table.indexes.iter().collect(),
+        })
+    }
+
+    /// Everything that mentions the column, `None` if the table or column doesn't exist
+    /// Views and triggers are found through `View::accesses` and `Trigger::accesses`.
+    pub fn references_to_column(
+        &self,
+        table_name: &str,
+        column_name: &str,
+    ) -> Option<ColumnReferences<'_>> {
+        let table = self.table(table_name)?;
+        let column = table.column(column_name)?;
+        let is_table = |name: &str| self.lookup.matches(name, &table.table_name);
+        let is_column = |name: &str| name.eq_ignore_ascii_case(&column.name);
+        let mut foreign_keys = table
+            .foreign_keys
+            .iter()
+            .filter(|f| f.from_column.iter().any(|c| is_column(&c.name)))
+            .map(|f| (table, f))
+            .collect::<Vec<_>>();
+
+        for (referencing, foreign_key) in self.referencing(&table.table_name) {
+            if foreign_key.to_column.iter().any(|c| is_column(&c.name))
+                && !foreign_keys.contains(&(referencing, foreign_key))
+            {
+                foreign_keys.push((referencing, foreign_key));
+            }
+        }
+
+        foreign_keys.sort_by(|a, b| a.0.table_name.cmp(&b.0.table_name));
+
+        Some(ColumnReferences {
+            foreign_keys,
+            indexes: table
+                .indexes
+                .iter()
+                .filter(|i| i.columns.iter().any(|c| is_column(&c.name)))
+                .collect(),
+            automatic_indexes: table
+                .automatic_indexes
+                .iter()
+                .filter(|i| i.columns.iter().any(|c| is_column(&c.name)))
+                .collect(),
+            views: self
+                .views
+                .iter()
+                .filter(|v| {
+                    v.accesses
+                        .iter()
+                        .any(|a| is_table(&a.table_name) && a.columns.iter().any(|c| is_column(c)))
+                })
+                .collect(),
+            triggers: self
+                .triggers
+                .iter()
+                .filter(|t| {
+                    let update_of = match &t.event {
+                        TriggerEvent::Update { columns } => columns.iter().any(|c| is_column(c)),
+                        _ => false,
+                    };
+
+                    (update_of && is_table(&t.table_name))
+                        || t.accesses.iter().any(|a| {
+                            is_table(&a.table_name) && a.columns.iter().any(|c| is_column(c))
+                        })
+                })
+                .collect(),
+            checks: check_constraints(&table.create_sql)
+                .into_iter()
+                .filter(|check| {
+                    tokenize_without_trivia(check).iter().any(|t| {
+                        matches!(t.kind, TokenKind::Word | TokenKind::QuotedIdentifier)
+                            && is_column(&unquote(t.text))
+                    })
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_references_to_column() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (
+                user_id INTEGER NOT NULL PRIMARY KEY,
+                email TEXT NOT NULL UNIQUE CHECK (email LIKE '%@%'),
+                name TEXT,
+                CHECK (length(\"EMAIL\") < 100),
+                CHECK (name != '')
+            );
+            CREATE TABLE post (
+                post_id INTEGER NOT NULL PRIMARY KEY,
+                user_id INTEGER NOT NULL REFERENCES user,
+                email TEXT,
+                FOREIGN KEY (email) REFERENCES user (email)
+            );
+            CREATE INDEX user_name_email ON user (name, email);
+            CREATE VIEW everything AS SELECT * FROM user;
+            CREATE VIEW names AS SELECT name FROM user;
+            CREATE VIEW post_emails AS SELECT p.email FROM post p;
+            CREATE TABLE log (message TEXT);
+            CREATE TRIGGER user_email AFTER UPDATE OF email ON user BEGIN
+                INSERT INTO log VALUES ('changed');
+            END;
+            CREATE TRIGGER user_name AFTER UPDATE OF name ON user BEGIN
+                INSERT INTO log VALUES (new.name);
+            END;",
+        );
+        let references = metadata.references_to_column("user", "EMAIL").unwrap();
+
+        assert_eq!(
+            vec![("post", vec!["email"])],
+            references
+                .foreign_keys
+                .iter()
+                .map(|(t, f)| (
+                    t.table_name.as_str(),
+                    f.from_column.iter().map(|c| c.name.as_str()).collect()
+                ))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("user_name_email", references.indexes[0].name);
+        assert_eq!(1, references.automatic_indexes.len());
+        assert_eq!(
+            vec!["everything"],
+            references.views.iter().map(|v| &v.name).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["user_email"],
+            references
+                .triggers
+                .iter()
+                .map(|t| &t.name)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["email LIKE '%@%'", "length(\"EMAIL\") < 100"],
+            references.checks
+        );
+
+        // Triggers that insert without a column list write every column
+        assert_eq!(
+            2,
+            metadata
+                .references_to_column("log", "message")
+                .unwrap()
+                .triggers
+                .len()
+        );
+        assert!(metadata
+            .references_to_column("post", "post_id")
+            .unwrap()
+            .is_empty());
+        assert_eq!(None, metadata.references_to_column("user", "unknown"));
+    }
+
+    #[test]
+    fn test_drop_impact() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT);
+            CREATE TABLE post (post_id INTEGER NOT NULL PRIMARY KEY, user_id INTEGER REFERENCES user);
+            CREATE TABLE log (message TEXT);
+            CREATE INDEX user_name ON user (name);
+            CREATE VIEW names AS SELECT name FROM user;
+            CREATE VIEW a_names AS SELECT * FROM names;
+            CREATE TRIGGER user_insert AFTER INSERT ON user BEGIN
+                INSERT INTO log VALUES (new.name);
+            END;
+            CREATE TRIGGER post_insert AFTER INSERT ON post BEGIN
+                UPDATE user SET name = name WHERE user_id = new.user_id;
+            END;
+            CREATE TRIGGER names_delete INSTEAD OF DELETE ON names BEGIN
+                DELETE FROM user WHERE name = old.name;
+            END;",
+        );
+        let impact = metadata.drop_impact("USER").unwrap();
+
+        assert_eq!("post", impact.foreign_keys[0].0.table_name);
+        assert_eq!(
+            vec!["names", "a_names"],
+            impact.views.iter().map(|v| &v.name).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["names_delete", "post_insert", "user_insert"],
+            impact.triggers.iter().map(|t| &t.name).collect::<Vec<_>>()
+        );
+        assert_eq!("user_name", impact.indexes[0].name);
+        assert_eq!(
+            vec![
+                "DROP VIEW \"a_names\";",
+                "DROP VIEW \"names\";",
+                "DROP TRIGGER \"post_insert\";",
+                "DROP TABLE \"user\";",
+            ],
+            impact.statements()
+        );
+        assert!(metadata.drop_impact("log").unwrap().views.is_empty());
+        assert_eq!(None, metadata.drop_impact("unknown"));
+    }
+}
@@ -0,0 +1,443 @@
+//! Proposes keys that are not declared in the schema by looking at the actual data
+//!
+//! Many databases omit foreign key declarations. A column named `<name>_id` is a candidate if
+//! there is a table with a single column primary key named `<name>_id`, or a table named `<name>`
+//! (or `<name>s`) with a single column primary key named `id`. The candidate is only proposed if
+//! every non-NULL value of the column exists in the referenced table.
+//!
+//! Tables without a primary key or unique index can be checked for candidate keys: combinations
+//! of columns that are unique and never NULL over all the rows.
+//!
+//! INTEGER columns that only store 0, 1 and NULL are probably booleans, see
+//! `ParseOptions::detect_booleans`.
+
+use rusqlite::Connection;
+
+use crate::{quote_identifier, Column, Metadata, Table, Type};
+
+/// A foreign key that is not declared, but probably exists
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct InferredForeignKey {
+    /// The table that holds the referring column
+    pub table: String,
+    /// The referring column
+    pub column: String,
+    /// The table it probably refers to
+    pub referenced_table: String,
+    /// The column it probably refers to
+    pub referenced_column: String,
+    /// The amount of non-NULL values that were checked for existence in the referenced table
+    pub checked_values: u64,
+}
+
+/// Proposes foreign keys for columns that aren't already part of a declared foreign key
+/// The suggestions are sorted by table name and column id
+pub fn infer_foreign_keys(connection: &Connection, metadata: &Metadata) -> Vec<InferredForeignKey> {
+    let tables = metadata.sorted_tables();
+
+    let mut inferred = vec![];
+
+    for table in &tables {
+        for column in &table.columns {
+            if !column.name.to_lowercase().ends_with("_id") {
+                continue;
+            }
+
+            let declared = table
+                .foreign_keys
+                .iter()
+                .any(|f| f.from_column.iter().any(|c| c.name == column.name));
+
+            if declared {
+                continue;
+            }
+
+            for (referenced_table, referenced_column) in candidates(&tables, table, column) {
+                if let Some(checked_values) = contained(
+                    connection,
+                    table,
+                    column,
+                    referenced_table,
+                    referenced_column,
+                ) {
+                    inferred.push(InferredForeignKey {
+                        table: table.table_name.clone(),
+                        column: column.name.clone(),
+                        referenced_table: referenced_table.table_name.clone(),
+                        referenced_column: referenced_column.name.clone(),
+                        checked_values,
+                    });
+                }
+            }
+        }
+    }
+
+    inferred
+}
+
+/// Finds the tables the column could refer to based on the naming conventions
+fn candidates<'a>(
+    tables: &[&'a Table],
+    table: &Table,
+    column: &Column,
+) -> Vec<(&'a Table, &'a Column)> {
+    let column_name = column.name.to_lowercase();
+    let prefix = &column_name[..column_name.len() - "_id".len()];
+    let mut candidates = vec![];
+
+    for candidate in tables {
+        let mut pk = candidate.columns.iter().filter(|c| c.part_of_pk);
+        let pk_column = match (pk.next(), pk.next()) {
+            (Some(pk_column), None) => pk_column,
+            _ => continue,
+        };
+
+        // A primary key doesn't refer to itself
+        if candidate.table_name == table.table_name && pk_column.name == column.name {
+            continue;
+        }
+
+        let pk_name = pk_column.name.to_lowercase();
+        let candidate_name = candidate.table_name.to_lowercase();
+
+        if pk_name == column_name
+            || (pk_name == "id"
+                && (candidate_name == prefix || candidate_name == format!("{prefix}s")))
+        {
+            candidates.push((*candidate, pk_column));
+        }
+    }
+
+    candidates
+}
+
+/// Checks if all the values of the column are present in the referenced column
+/// Returns the amount of values that were checked
+fn contained(
+    connection: &Connection,
+    table: &Table,
+    column: &Column,
+    referenced_table: &Table,
+    referenced_column: &Column,
+) -> Option<u64> {
+    let c = quote_identifier(&column.name);
+    let (checked, missing): (u64, u64) = connection
+        .query_row(
+            &format!(
+                "SELECT COUNT(*), IFNULL(SUM(NOT EXISTS (SELECT 1 FROM {} p WHERE p.{} = c.{c})), 0)
+FROM {} c
+WHERE c.{c} IS NOT NULL;",
+                quote_identifier(&referenced_table.table_name),
+                quote_identifier(&referenced_column.name),
+                quote_identifier(&table.table_name),
+            ),
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+
+    if missing == 0 {
+        Some(checked)
+    } else {
+        None
+    }
+}
+
+/// The INTEGER columns that store at least one value and only store 0, 1 and NULL in the first `n`
+/// rows of the table
+pub fn boolean_columns(connection: &Connection, table: &Table, n: usize) -> Vec<String> {
+    let columns = table
+        .columns
+        .iter()
+        .filter(|c| c.the_type == Type::Integer)
+        .collect::<Vec<_>>();
+
+    if columns.is_empty() {
+        return vec![];
+    }
+
+    // Per column if every value is a boolean and the amount of values
+    let checks = columns
+        .iter()
+        .map(|c| {
+            let c = quote_identifier(&c.name);
+
+            let boolean = format!("{c} IS NULL OR (typeof({c}) = 'integer' AND {c} IN (0, 1))");
+
+            format!("IFNULL(MIN({boolean}), 1), COUNT({c})")
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let names = columns
+        .iter()
+        .map(|c| quote_identifier(&c.name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!(
+        "SELECT {checks} FROM (SELECT {names} FROM {} LIMIT {n});",
+        quote_identifier(&table.table_name)
+    );
+
+    connection
+        .query_row(&sql, [], |row| {
+            let mut boolean_columns = vec![];
+
+            for (i, column) in columns.iter().enumerate() {
+                let only_booleans: bool = row.get(i * 2)?;
+                let values: u64 = row.get(i * 2 + 1)?;
+
+                if only_booleans && values > 0 {
+                    boolean_columns.push(column.name.clone());
+                }
+            }
+
+            Ok(boolean_columns)
+        })
+        .unwrap()
+}
+
+/// The limits of the candidate key search, the amount of queries grows fast with `max_columns`
+#[derive(Debug, PartialEq, Copy, Clone, Eq)]
+pub struct CandidateKeyOptions {
+    /// The maximum amount of columns in a candidate key
+    pub max_columns: usize,
+    /// Tables with more rows are skipped, `None` checks every table
+    pub max_rows: Option<u64>,
+}
+
+impl Default for CandidateKeyOptions {
+    fn default() -> Self {
+        Self {
+            max_columns: 2,
+            max_rows: Some(100_000),
+        }
+    }
+}
+
+/// A combination of columns that uniquely identifies every row
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct CandidateKey {
+    pub table: String,
+    /// The columns of the key, in the order of `Table::columns`
+    pub columns: Vec<String>,
+}
+
+/// Finds the minimal candidate keys of tables without a primary key or unique index
+/// Empty tables are skipped, since every combination would be unique
+pub fn candidate_keys(
+    connection: &Connection,
+    metadata: &Metadata,
+    options: CandidateKeyOptions,
+) -> Vec<CandidateKey> {
+    let tables = metadata.sorted_tables();
+
+    let mut keys = vec![];
+
+    for table in tables {
+        if table.columns.iter().any(|c| c.part_of_pk) || table.indexes.iter().any(|i| i.unique) {
+            continue;
+        }
+
+        let table_name = quote_identifier(&table.table_name);
+        let rows: u64 = connection
+            .query_row(&format!("SELECT COUNT(*) FROM {table_name};"), [], |row| {
+                row.get(0)
+            })
+            .unwrap();
+
+        if rows == 0 || options.max_rows.map(|max| rows > max).unwrap_or(false) {
+            continue;
+        }
+
+        let mut found: Vec<Vec<usize>> = vec![];
+
+        for size in 1..=options.max_columns.min(table.columns.len()) {
+            for combination in combinations(table.columns.len(), size) {
+                // Only minimal keys are reported
+                if found
+                    .iter()
+                    .any(|key| key.iter().all(|k| combination.contains(k)))
+                {
+                    continue;
+                }
+
+                let columns = combination
+                    .iter()
+                    .map(|i| quote_identifier(&table.columns[*i].name))
+                    .collect::<Vec<_>>();
+                let not_null = columns
+                    .iter()
+                    .map(|c| format!("{c} IS NOT NULL"))
+                    .collect::<Vec<_>>()
+                    .join(" AND ");
+                let distinct: u64 = connection
+                    .query_row(
+                        &format!(
+                            "SELECT COUNT(*) FROM (SELECT DISTINCT {} FROM {table_name} WHERE {not_null});",
+                            columns.join(", ")
+                        ),
+                        [],
+                        |row| row.get(0),
+                    )
+                    .unwrap();
+
+                if distinct == rows {
+                    found.push(combination);
+                }
+            }
+        }
+
+        for key in found {
+            keys.push(CandidateKey {
+                table: table.table_name.clone(),
+                columns: key
+                    .into_iter()
+                    .map(|i| table.columns[i].name.clone())
+                    .collect(),
+            });
+        }
+    }
+
+    keys
+}
+
+/// All the combinations of `size` indexes out of `0..amount`, in lexicographic order
+fn combinations(amount: usize, size: usize) -> Vec<Vec<usize>> {
+    if size == 0 {
+        return vec![vec![]];
+    }
+
+    let mut result = vec![];
+
+    for first in 0..amount {
+        for rest in combinations(amount - first - 1, size - 1) {
+            let mut combination = vec![first];
+
+            combination.extend(rest.into_iter().map(|r| r + first + 1));
+            result.push(combination);
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::inference::{
+        boolean_columns, candidate_keys, infer_foreign_keys, CandidateKey, CandidateKeyOptions,
+        InferredForeignKey,
+    };
+    use crate::tests::metadata_from_sql;
+    use crate::{metadata_from_connection, Metadata, ParseOptions};
+
+    #[test]
+    fn test_infer_foreign_keys() {
+        let sql = "CREATE TABLE users (id INTEGER NOT NULL PRIMARY KEY);
+        CREATE TABLE team (team_id INTEGER NOT NULL PRIMARY KEY);
+        CREATE TABLE post (
+            post_id INTEGER NOT NULL PRIMARY KEY,
+            user_id INTEGER,
+            team_id INTEGER,
+            declared_id INTEGER,
+            FOREIGN KEY(declared_id) REFERENCES team(team_id)
+        );
+        INSERT INTO users VALUES (1), (2);
+        INSERT INTO team VALUES (1);
+        INSERT INTO post VALUES (1, 1, 1, 1), (2, 2, 5, NULL), (3, NULL, NULL, NULL);";
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection.execute_batch(sql).unwrap();
+
+        let metadata = metadata_from_sql(sql);
+
+        // team_id contains a value that is not in team, so it isn't proposed
+        assert_eq!(
+            vec![InferredForeignKey {
+                table: "post".to_string(),
+                column: "user_id".to_string(),
+                referenced_table: "users".to_string(),
+                referenced_column: "id".to_string(),
+                checked_values: 2,
+            }],
+            infer_foreign_keys(&connection, &metadata)
+        );
+    }
+
+    #[test]
+    fn test_candidate_keys() {
+        let sql = "CREATE TABLE log (
+            code TEXT,
+            day INTEGER,
+            slot INTEGER,
+            message TEXT
+        );
+        CREATE TABLE keyed (id INTEGER NOT NULL PRIMARY KEY, value TEXT);
+        INSERT INTO log VALUES ('a', 1, 1, 'x'), ('a', 2, 2, 'x'), ('b', 1, 3, 'y'), ('b', 2, NULL, 'z');
+        INSERT INTO keyed VALUES (1, 'a'), (2, 'b');";
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection.execute_batch(sql).unwrap();
+
+        let metadata = metadata_from_sql(sql);
+        let key = |columns: &[&str]| CandidateKey {
+            table: "log".to_string(),
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+        };
+
+        assert_eq!(
+            vec![key(&["code", "day"]), key(&["day", "message"])],
+            candidate_keys(&connection, &metadata, CandidateKeyOptions::default())
+        );
+        assert!(candidate_keys(
+            &connection,
+            &metadata,
+            CandidateKeyOptions {
+                max_columns: 1,
+                max_rows: None
+            }
+        )
+        .is_empty());
+    }
+
+    #[test]
+    fn test_boolean_columns() {
+        let sql = "CREATE TABLE user (
+            user_id INTEGER NOT NULL PRIMARY KEY,
+            active INTEGER NOT NULL,
+            admin INTEGER,
+            never INTEGER,
+            score INTEGER,
+            flag TEXT
+        );
+        INSERT INTO user VALUES (1, 1, NULL, NULL, 0, '1'), (2, 0, 1, NULL, 1, '0'), (3, 1, 0, NULL, 2, '1');";
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection.execute_batch(sql).unwrap();
+
+        let metadata = metadata_from_connection(
+            &connection,
+            ParseOptions {
+                detect_booleans: Some(2),
+                ..Default::default()
+            },
+        );
+        let hints = |metadata: &Metadata| {
+            metadata.tables["user"]
+                .columns
+                .iter()
+                .filter(|c| c.boolean_hint)
+                .map(|c| c.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        // Only the first 2 rows are read, so score looks like a boolean
+        assert_eq!(vec!["active", "admin", "score"], hints(&metadata));
+        assert_eq!(
+            vec!["active", "admin"],
+            boolean_columns(&connection, &metadata.tables["user"], 10)
+        );
+        assert!(hints(&metadata_from_connection(&connection, Default::default())).is_empty());
+    }
+}
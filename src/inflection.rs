@@ -0,0 +1,549 @@
+//! Naming utilities for code generators: the case conversions, singular and plural forms and the
+//! escaping of reserved words of the target language
+//!
+//! ```
+//! use sqlite_parser::inflection::{Inflector, Language};
+//!
+//! let inflector = Inflector::default();
+//!
+//! assert_eq!("UserRole", inflector.type_name("user_roles", Language::Rust));
+//! assert_eq!("r#type", inflector.field_name("type", Language::Rust));
+//! assert_eq!("createdAt", inflector.field_name("created_at", Language::TypeScript));
+//! ```
+//!
+//! The singular and plural forms are made with a few English rules, names the rules get wrong can
+//! be added to `Inflector::plurals`.
+
+use std::collections::BTreeMap;
+
+/// The words that are the same in singular and plural
+const UNCOUNTABLE: [&str; 12] = [
+    "data",
+    "deer",
+    "equipment",
+    "fish",
+    "information",
+    "media",
+    "metadata",
+    "money",
+    "news",
+    "series",
+    "sheep",
+    "species",
+];
+
+/// The built-in irregular plurals, singular first
+const IRREGULAR: [(&str, &str); 10] = [
+    ("child", "children"),
+    ("foot", "feet"),
+    ("goose", "geese"),
+    ("man", "men"),
+    ("mouse", "mice"),
+    ("ox", "oxen"),
+    ("person", "people"),
+    ("quiz", "quizzes"),
+    ("tooth", "teeth"),
+    ("woman", "women"),
+];
+
+/// Singulars that end with `ie` after a consonant, the plurals of other words ending with `ies`
+/// end with `y`
+const IE_SINGULARS: [&str; 10] = [
+    "brownie", "calorie", "cookie", "hippie", "movie", "pie", "rookie", "selfie", "tie", "zombie",
+];
+
+/// The singulars of the plurals that end with `ives` and have a singular that ends with `fe`
+const FE_SINGULARS: [&str; 3] = ["knife", "life", "wife"];
+
+/// A language to generate code for, for the reserved words and naming conventions
+#[derive(Debug, PartialEq, Copy, Clone, Eq, Hash, PartialOrd, Ord)]
+pub enum Language {
+    Rust,
+    TypeScript,
+    Python,
+    Kotlin,
+    Swift,
+    Go,
+}
+
+impl Language {
+    /// The words that can't be used as an identifier without escaping
+    pub fn reserved_words(&self) -> &'static [&'static str] {
+        match self {
+            Language::Rust => &[
+                "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else",
+                "enum", "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match",
+                "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct",
+                "super", "trait", "true", "type", "unsafe", "use", "where", "while", "abstract",
+                "become", "box", "do", "final", "gen", "macro", "override", "priv", "try",
+                "typeof", "unsized", "virtual", "yield",
+            ],
+            Language::TypeScript => &[
+                "break",
+                "case",
+                "catch",
+                "class",
+                "const",
+                "continue",
+                "debugger",
+                "default",
+                "delete",
+                "do",
+                "else",
+                "enum",
+                "export",
+                "extends",
+                "false",
+                "finally",
+                "for",
+                "function",
+                "if",
+                "import",
+                "in",
+                "instanceof",
+                "new",
+                "null",
+                "return",
+                "super",
+                "switch",
+                "this",
+                "throw",
+                "true",
+                "try",
+                "typeof",
+                "var",
+                "void",
+                "while",
+                "with",
+            ],
+            Language::Python => &[
+                "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class",
+                "continue", "def", "del", "elif", "else", "except", "finally", "for", "from",
+                "global", "if", "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass",
+                "raise", "return", "try", "while", "with", "yield",
+            ],
+            Language::Kotlin => &[
+                "as",
+                "break",
+                "class",
+                "continue",
+                "do",
+                "else",
+                "false",
+                "for",
+                "fun",
+                "if",
+                "in",
+                "interface",
+                "is",
+                "null",
+                "object",
+                "package",
+                "return",
+                "super",
+                "this",
+                "throw",
+                "true",
+                "try",
+                "typealias",
+                "typeof",
+                "val",
+                "var",
+                "when",
+                "while",
+            ],
+            Language::Swift => &[
+                "associatedtype",
+                "class",
+                "deinit",
+                "enum",
+                "extension",
+                "fileprivate",
+                "func",
+                "import",
+                "init",
+                "inout",
+                "internal",
+                "let",
+                "open",
+                "operator",
+                "private",
+                "protocol",
+                "public",
+                "rethrows",
+                "static",
+                "struct",
+                "subscript",
+                "typealias",
+                "var",
+                "break",
+                "case",
+                "continue",
+                "default",
+                "defer",
+                "do",
+                "else",
+                "fallthrough",
+                "for",
+                "guard",
+                "if",
+                "in",
+                "repeat",
+                "return",
+                "switch",
+                "where",
+                "while",
+                "as",
+                "Any",
+                "catch",
+                "false",
+                "is",
+                "nil",
+                "super",
+                "self",
+                "Self",
+                "throw",
+                "throws",
+                "true",
+                "try",
+            ],
+            Language::Go => &[
+                "break",
+                "case",
+                "chan",
+                "const",
+                "continue",
+                "default",
+                "defer",
+                "else",
+                "fallthrough",
+                "for",
+                "func",
+                "go",
+                "goto",
+                "if",
+                "import",
+                "interface",
+                "map",
+                "package",
+                "range",
+                "return",
+                "select",
+                "struct",
+                "switch",
+                "type",
+                "var",
+            ],
+        }
+    }
+
+    /// Escapes the name if it's a reserved word: a raw identifier in Rust, backticks in Kotlin and
+    /// Swift and a trailing `_` otherwise
+    /// `self`, `Self`, `super` and `crate` can't be raw identifiers in Rust and get a `_`.
+    pub fn escape(&self, name: &str) -> String {
+        if !self.reserved_words().contains(&name) {
+            return name.to_string();
+        }
+
+        match self {
+            Language::Rust if !matches!(name, "self" | "Self" | "super" | "crate") => {
+                format!("r#{name}")
+            }
+            Language::Kotlin | Language::Swift => format!("`{name}`"),
+            _ => format!("{name}_"),
+        }
+    }
+}
+
+/// Singular and plural forms with overrides, and the names of types and fields
+#[derive(Debug, PartialEq, Clone, Eq, Default)]
+pub struct Inflector {
+    /// Irregular plurals by singular, consulted before the built-in rules in both directions,
+    /// e.g. `("cactus", "cacti")`
+    pub plurals: BTreeMap<String, String>,
+}
+
+impl Inflector {
+    /// The plural of the last word, e.g. `user_roles` for `user_role`
+    pub fn pluralize(&self, name: &str) -> String {
+        map_last_word(name, |word| {
+            if let Some(plural) = self.plurals.get(word) {
+                return plural.clone();
+            }
+
+            if UNCOUNTABLE.contains(&word) || self.plurals.values().any(|p| p == word) {
+                return word.to_string();
+            }
+
+            if let Some((_, plural)) = IRREGULAR.iter().find(|(s, _)| *s == word) {
+                return plural.to_string();
+            }
+
+            if ["s", "x", "z", "ch", "sh"]
+                .iter()
+                .any(|s| word.ends_with(s))
+            {
+                format!("{word}es")
+            } else if word.ends_with('y') && !ends_with_vowel(&word[..word.len() - 1]) {
+                format!("{}ies", &word[..word.len() - 1])
+            } else if word.ends_with("lf") {
+                format!("{}ves", &word[..word.len() - 1])
+            } else if word.ends_with("ife") {
+                format!("{}ves", &word[..word.len() - 2])
+            } else {
+                format!("{word}s")
+            }
+        })
+    }
+
+    /// The singular of the last word, e.g. `user_role` for `user_roles`
+    pub fn singularize(&self, name: &str) -> String {
+        map_last_word(name, |word| {
+            if let Some((singular, _)) = self.plurals.iter().find(|(_, p)| *p == word) {
+                return singular.clone();
+            }
+
+            if UNCOUNTABLE.contains(&word) || self.plurals.contains_key(word) {
+                return word.to_string();
+            }
+
+            if let Some((singular, _)) = IRREGULAR.iter().find(|(_, p)| *p == word) {
+                return singular.to_string();
+            }
+
+            let strip = |n: usize| {
+                word.char_indices()
+                    .rev()
+                    .nth(n - 1)
+                    .map_or("", |(i, _)| &word[..i])
+            };
+
+            if IE_SINGULARS.contains(&strip(1)) {
+                strip(1).to_string()
+            } else if word.ends_with("ies") && word.len() > 3 && !ends_with_vowel(strip(3)) {
+                format!("{}y", strip(3))
+            } else if word.ends_with("lves") {
+                format!("{}f", strip(3))
+            } else if word.ends_with("ives")
+                && FE_SINGULARS.contains(&format!("{}fe", strip(3)).as_str())
+            {
+                format!("{}fe", strip(3))
+            } else if ["sses", "shes", "ches", "xes", "zzes"]
+                .iter()
+                .any(|s| word.ends_with(s))
+                || (word.ends_with("uses") && !ends_with_vowel(strip(4)))
+            {
+                strip(2).to_string()
+            } else if word.ends_with('s') && !["ss", "us", "is"].iter().any(|s| word.ends_with(s)) {
+                strip(1).to_string()
+            } else {
+                word.to_string()
+            }
+        })
+    }
+
+    /// The name of the type for the rows of a table, the singular in PascalCase
+    pub fn type_name(&self, table_name: &str, language: Language) -> String {
+        language.escape(&pascal_case(&self.singularize(table_name)))
+    }
+
+    /// The name of the field for a column in the naming convention of the language: snake_case
+    /// for Rust and Python, PascalCase for Go and camelCase otherwise
+    pub fn field_name(&self, column_name: &str, language: Language) -> String {
+        let name = match language {
+            Language::Rust | Language::Python => snake_case(column_name),
+            Language::Go => pascal_case(column_name),
+            Language::TypeScript | Language::Kotlin | Language::Swift => camel_case(column_name),
+        };
+
+        language.escape(&name)
+    }
+}
+
+/// The words of a name, split at characters that aren't letters or digits and at the start of
+/// words in camelCase, e.g. `HTTPServer` is `HTTP` and `Server`
+pub fn words(name: &str) -> Vec<&str> {
+    let mut words = vec![];
+
+    for part in name.split(|c: char| !c.is_alphanumeric()) {
+        let chars = part.char_indices().collect::<Vec<_>>();
+        let mut start = 0;
+
+        for (i, &(offset, c)) in chars.iter().enumerate().skip(1) {
+            let previous = chars[i - 1].1;
+            let next_is_lower = chars.get(i + 1).is_some_and(|(_, n)| n.is_lowercase());
+
+            if c.is_uppercase()
+                && (previous.is_lowercase()
+                    || previous.is_ascii_digit()
+                    || (previous.is_uppercase() && next_is_lower))
+            {
+                words.push(&part[start..offset]);
+                start = offset;
+            }
+        }
+
+        if start < part.len() {
+            words.push(&part[start..]);
+        }
+    }
+
+    words
+}
+
+/// `user_role` for `UserRole`
+pub fn snake_case(name: &str) -> String {
+    words(name)
+        .iter()
+        .map(|w| w.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+/// `USER_ROLE` for `UserRole`
+pub fn screaming_snake_case(name: &str) -> String {
+    snake_case(name).to_uppercase()
+}
+
+/// `userRole` for `user_role`
+pub fn camel_case(name: &str) -> String {
+    let pascal = pascal_case(name);
+    let mut chars = pascal.chars();
+
+    match chars.next() {
+        Some(first) => first.to_lowercase().chain(chars).collect(),
+        None => pascal,
+    }
+}
+
+/// `UserRole` for `user_role`
+pub fn pascal_case(name: &str) -> String {
+    words(name).iter().map(|w| capitalize(w)).collect()
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+
+    match chars.next() {
+        Some(first) => first
+            .to_uppercase()
+            .chain(chars.flat_map(|c| c.to_lowercase()))
+            .collect(),
+        None => String::new(),
+    }
+}
+
+/// Replaces the last word with `f` of its lowercase form, the case of the word is kept: all
+/// uppercase stays uppercase, otherwise the letters the forms have in common keep their case
+fn map_last_word(name: &str, f: impl Fn(&str) -> String) -> String {
+    let Some(word) = words(name).last().copied() else {
+        return name.to_string();
+    };
+    let start = word.as_ptr() as usize - name.as_ptr() as usize;
+    let end = start + word.len();
+    let lower = word.to_lowercase();
+    let mapped = f(&lower);
+    let mapped = if word.chars().count() > 1 && !word.chars().any(char::is_lowercase) {
+        mapped.to_uppercase()
+    } else if lower.len() == word.len() {
+        let common = lower
+            .char_indices()
+            .zip(mapped.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((i, a), _)| i + a.len_utf8());
+
+        format!("{}{}", &word[..common], &mapped[common..])
+    } else {
+        mapped
+    };
+
+    format!("{}{mapped}{}", &name[..start], &name[end..])
+}
+
+fn ends_with_vowel(s: &str) -> bool {
+    s.ends_with(['a', 'e', 'i', 'o', 'u'])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::inflection::{
+        camel_case, pascal_case, screaming_snake_case, snake_case, words, Inflector, Language,
+    };
+
+    #[test]
+    fn test_case_conversions() {
+        assert_eq!(vec!["HTTP", "Server", "2", "id"], words("HTTPServer 2_id"));
+        assert_eq!(vec!["user", "Id2", "Name"], words("userId2Name"));
+        assert_eq!("user_role_id", snake_case("UserRoleID"));
+        assert_eq!("USER_ROLE", screaming_snake_case("userRole"));
+        assert_eq!("userRoleId", camel_case("user_role_id"));
+        assert_eq!("UserRole", pascal_case("user-role"));
+        assert_eq!("", pascal_case("__"));
+    }
+
+    #[test]
+    fn test_inflector() {
+        let mut inflector = Inflector::default();
+
+        for (singular, plural) in [
+            ("user_role", "user_roles"),
+            ("category", "categories"),
+            ("day", "days"),
+            ("address", "addresses"),
+            ("status", "statuses"),
+            ("box", "boxes"),
+            ("Person", "People"),
+            ("shelf", "shelves"),
+            ("wife", "wives"),
+            ("knife", "knives"),
+            ("life", "lives"),
+            ("house", "houses"),
+            ("warehouse", "warehouses"),
+            ("bus", "buses"),
+            ("movie", "movies"),
+            ("cookie", "cookies"),
+            ("archive", "archives"),
+            ("drive", "drives"),
+            ("objective", "objectives"),
+            ("quiz", "quizzes"),
+            ("buzz", "buzzes"),
+            ("size", "sizes"),
+            ("metadata", "metadata"),
+            ("USER", "USERS"),
+        ] {
+            assert_eq!(plural, inflector.pluralize(singular));
+            assert_eq!(singular, inflector.singularize(plural));
+        }
+
+        assert_eq!("analysis", inflector.singularize("analysis"));
+        assert_eq!("cactuses", inflector.pluralize("cactus"));
+
+        inflector
+            .plurals
+            .insert("cactus".to_string(), "cacti".to_string());
+
+        assert_eq!("big_cacti", inflector.pluralize("big_cactus"));
+        assert_eq!("Cactus", inflector.singularize("Cacti"));
+        assert_eq!("cacti", inflector.pluralize("cacti"));
+
+        assert_eq!("Self_", inflector.type_name("selves", Language::Rust));
+        assert_eq!("Class", inflector.type_name("classes", Language::Python));
+        assert_eq!("`class`", inflector.field_name("class", Language::Kotlin));
+        assert_eq!("UserId", inflector.field_name("user_id", Language::Go));
+        assert_eq!("class_", inflector.field_name("class", Language::Python));
+        assert_eq!("type", inflector.field_name("type", Language::TypeScript));
+    }
+
+    #[test]
+    fn test_inflector_non_ascii() {
+        let inflector = Inflector::default();
+
+        assert_eq!("café", inflector.singularize("cafés"));
+        assert_eq!("café", inflector.singularize("café"));
+        assert_eq!("ü", inflector.singularize("ü"));
+        assert_eq!("größe", inflector.singularize("größe"));
+        assert_eq!("straße", inflector.singularize("straßes"));
+        assert_eq!("cafés", inflector.pluralize("café"));
+    }
+}
@@ -0,0 +1,542 @@
+//! Builds the columns, foreign keys and indexes from the rows of the schema pragmas
+//!
+//! The rows are either queried per table (the public `query_*` functions and the `Parser` hooks)
+//! or for all tables at once with a few queries that join the pragmas over `sqlite_master`, see
+//! `Schema`. Both ways share the building, so the result is the same.
+
+use std::collections::HashMap;
+
+use rusqlite::{Connection, Row};
+
+use crate::report::ParseWarning;
+use crate::{
+    declared_type, find_column, AutomaticIndex, Column, ForeignKey, Index, IndexOrigin,
+    OnUpdateAndDelete,
+};
+
+/// Builds a column from a row of `pragma_table_info`, starting at the offset
+pub(crate) fn column(
+    row: &Row,
+    offset: usize,
+    table_name: &str,
+    warnings: &mut Vec<ParseWarning>,
+) -> Column {
+    // Parse the type first
+    let t: String = row.get(offset + 2).unwrap();
+    let is_non_null: bool = row.get(offset + 3).unwrap();
+    let name: String = row.get(offset + 1).unwrap();
+    let (the_type, logical_type) = declared_type(&t, table_name, &name, warnings);
+
+    Column {
+        id: row.get(offset).unwrap(),
+        name,
+        the_type,
+        declared_type: t,
+        nullable: !is_non_null,
+        part_of_pk: row.get(offset + 5).unwrap(),
+        default_value: row.get(offset + 4).unwrap(),
+        boolean_hint: false,
+        logical_type,
+        extension: None,
+        comment: None,
+        enum_values: None,
+    }
+}
+
+/// A row of `pragma_foreign_key_list`, a foreign key has a row per column
+#[derive(Debug, Clone)]
+pub(crate) struct ForeignKeyRow {
+    id: i32,
+    seq: usize,
+    table: String,
+    from_column: String,
+    /// `None` if the foreign key refers to the primary key
+    to_column: Option<String>,
+    on_update: String,
+    on_delete: String,
+}
+
+impl ForeignKeyRow {
+    /// Reads the row, starting at the offset
+    pub(crate) fn new(row: &Row, offset: usize) -> Self {
+        Self {
+            id: row.get(offset).unwrap(),
+            seq: row.get(offset + 1).unwrap(),
+            table: row.get(offset + 2).unwrap(),
+            from_column: row.get(offset + 3).unwrap(),
+            to_column: row.get(offset + 4).unwrap(),
+            on_update: row.get(offset + 5).unwrap(),
+            on_delete: row.get(offset + 6).unwrap(),
+        }
+    }
+}
+
+/// Builds the foreign keys of a table from its rows
+/// The columns and primary key columns of referenced tables are read with the functions, no
+/// columns means the table doesn't exist
+pub(crate) fn foreign_keys(
+    table_name: &str,
+    rows: Vec<ForeignKeyRow>,
+    own_columns: &[Column],
+    referenced_columns: impl Fn(&str) -> Vec<Column>,
+    pk_columns: impl Fn(&str) -> Vec<String>,
+    warnings: &mut Vec<ParseWarning>,
+) -> Vec<ForeignKey> {
+    let mut foreign_keys: Vec<ForeignKey> = vec![];
+    // The ids of the foreign keys that refer to a missing table or column
+    let mut skipped = vec![];
+
+    for row in rows {
+        let id = row.id;
+
+        if skipped.contains(&id) {
+            continue;
+        }
+
+        let table = row.table;
+        let other_table_columns = referenced_columns(&table);
+
+        if other_table_columns.is_empty() {
+            warnings.push(ParseWarning::MissingForeignKeyTable {
+                table: table_name.to_string(),
+                referenced_table: table,
+            });
+            skipped.push(id);
+
+            continue;
+        }
+
+        let from_column = row.from_column;
+        // A foreign key without columns refers to the primary key of the other table
+        let to_column = match row.to_column {
+            Some(to_column) => to_column,
+            None => pk_columns(&table).get(row.seq).cloned().unwrap_or_default(),
+        };
+        let to_column = match find_column(&other_table_columns, &to_column) {
+            Some(column) => column.clone(),
+            None => {
+                warnings.push(ParseWarning::MissingForeignKeyColumn {
+                    table: table_name.to_string(),
+                    referenced_table: table,
+                    column: to_column,
+                });
+                // Earlier columns of the foreign key are already added
+                foreign_keys.retain(|f| f.id != id);
+                skipped.push(id);
+
+                continue;
+            }
+        };
+
+        let mut foreign_key = ForeignKey {
+            id,
+            from_column: vec![find_column(own_columns, &from_column)
+                .unwrap_or_else(|| {
+                    panic!(
+                        "Expected to find {} in {:#?}",
+                        from_column,
+                        own_columns.iter().map(|c| &c.name).collect::<Vec<_>>()
+                    )
+                })
+                .clone()],
+            to_column: vec![to_column],
+            table,
+            on_update: OnUpdateAndDelete::from_str(&row.on_update),
+            on_delete: OnUpdateAndDelete::from_str(&row.on_delete),
+        };
+
+        if let Some(fk) = foreign_keys.iter_mut().find(|f| f.id == id) {
+            fk.from_column.push(foreign_key.from_column.remove(0));
+            fk.to_column.push(foreign_key.to_column.remove(0));
+        } else {
+            foreign_keys.push(foreign_key);
+        }
+    }
+
+    foreign_keys
+}
+
+/// An index created with CREATE INDEX
+#[derive(Debug, Clone)]
+pub(crate) struct IndexRow {
+    pub name: String,
+    pub unique: bool,
+    /// The key columns in index order, expressions are `None`
+    pub columns: Vec<Option<String>>,
+}
+
+/// Builds the indexes of a table from the created indexes and the columns of UNIQUE constraints
+pub(crate) fn indexes(
+    table_name: &str,
+    index_rows: Vec<IndexRow>,
+    unique_columns: Vec<String>,
+    columns: &[Column],
+    foreign_keys: &[ForeignKey],
+    warnings: &mut Vec<ParseWarning>,
+) -> Vec<Index> {
+    let mut indexes: Vec<Index> = vec![];
+
+    for IndexRow {
+        name,
+        unique,
+        columns: index_columns,
+    } in index_rows
+    {
+        // Expressions are not a column of the table, only the columns are kept
+        if index_columns.iter().any(Option::is_none) {
+            warnings.push(ParseWarning::IndexExpression {
+                table: table_name.to_string(),
+                index: name.clone(),
+            });
+
+            if index_columns.iter().all(Option::is_none) {
+                continue;
+            }
+        }
+
+        indexes.push(Index {
+            columns: index_columns
+                .into_iter()
+                .flatten()
+                .map(|c| {
+                    find_column(columns, &c)
+                        .unwrap_or_else(|| {
+                            panic!("Could not find column {c} of index {name} in columns: {columns:#?}")
+                        })
+                        .clone()
+                })
+                .collect(),
+            name,
+            unique,
+            storage: None,
+            statistics: None,
+        });
+    }
+
+    for name in unique_columns {
+        let index_column = match columns.iter().find(|c| c.name == name) {
+            // PK's always have unique indexes, skip these
+            Some(column) if !column.part_of_pk => column.clone(),
+            _ => continue,
+        };
+        let contains = foreign_keys
+            .iter()
+            .find(|f| f.from_column.iter().any(|f| f.name == index_column.name));
+
+        if contains.is_some() {
+            // Foreign keys always have unique constraints, ignore
+            continue;
+        }
+
+        indexes.push(Index {
+            name,
+            columns: vec![index_column],
+            unique: true,
+            storage: None,
+            statistics: None,
+        })
+    }
+
+    // Indexes with the same columns as an earlier index are skipped
+    let mut unique_indexes: Vec<Index> = vec![];
+
+    for index in indexes {
+        match unique_indexes.iter().find(|i| i.columns == index.columns) {
+            Some(duplicate_of) => warnings.push(ParseWarning::DuplicateIndex {
+                table: table_name.to_string(),
+                index: index.name,
+                duplicate_of: duplicate_of.name.clone(),
+            }),
+            None => unique_indexes.push(index),
+        }
+    }
+
+    unique_indexes
+}
+
+/// An index of `pragma_index_list` with origin `pk` or `u`
+#[derive(Debug, Clone)]
+pub(crate) struct AutomaticIndexRow {
+    pub name: String,
+    pub origin: String,
+    pub columns: Vec<String>,
+}
+
+/// Builds the automatic indexes, ordered by the position in their name
+/// Indexes on hidden columns are skipped, like the columns themselves.
+pub(crate) fn automatic_indexes(
+    rows: Vec<AutomaticIndexRow>,
+    columns: &[Column],
+) -> Vec<AutomaticIndex> {
+    let mut indexes = rows
+        .into_iter()
+        .filter_map(|row| {
+            Some(AutomaticIndex {
+                position: row.name.rsplit('_').next()?.parse().ok()?,
+                origin: if row.origin == "pk" {
+                    IndexOrigin::PrimaryKey
+                } else {
+                    IndexOrigin::Unique
+                },
+                columns: row
+                    .columns
+                    .iter()
+                    .map(|c| find_column(columns, c).cloned())
+                    .collect::<Option<_>>()?,
+                name: row.name,
+                storage: None,
+                statistics: None,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    indexes.sort_by_key(|i| i.position);
+
+    indexes
+}
+
+/// The schema pragmas of all tables, read with a query per pragma instead of a query per table
+/// Tables are keyed by their lowercase name, since SQLite compares ASCII names case insensitive
+#[derive(Debug, Default)]
+pub(crate) struct Schema {
+    tables: HashMap<String, SchemaTable>,
+}
+
+#[derive(Debug, Default)]
+struct SchemaTable {
+    columns: Vec<Column>,
+    /// The warnings of the columns, added when the columns of the table are requested
+    column_warnings: Vec<ParseWarning>,
+    /// The primary key columns with their position in the primary key
+    pk_columns: Vec<(i32, String)>,
+    foreign_keys: Vec<ForeignKeyRow>,
+    indexes: Vec<IndexRow>,
+    unique_columns: Vec<String>,
+    automatic_indexes: Vec<AutomaticIndexRow>,
+}
+
+impl Schema {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "query_schema", level = "trace", skip(connection))
+    )]
+    pub(crate) fn query(connection: &Connection) -> Self {
+        let mut schema = Schema::default();
+
+        schema.query_columns(connection);
+        schema.query_foreign_keys(connection);
+        schema.query_indexes(connection);
+        schema.query_unique_columns(connection);
+        schema.query_automatic_indexes(connection);
+
+        schema
+    }
+
+    fn table(&mut self, row: &Row) -> &mut SchemaTable {
+        let name: String = row.get(0).unwrap();
+
+        self.tables.entry(name.to_ascii_lowercase()).or_default()
+    }
+
+    fn query_columns(&mut self, connection: &Connection) {
+        let mut stmt = connection
+            .prepare(
+                "SELECT m.name, p.*
+FROM sqlite_master AS m
+JOIN pragma_table_info(m.name) AS p
+WHERE m.type = 'table';",
+            )
+            .unwrap();
+        let mut rows = stmt.query([]).unwrap();
+
+        while let Some(row) = rows.next().unwrap() {
+            let table_name: String = row.get(0).unwrap();
+            let table = self.table(row);
+            let column = column(row, 1, &table_name, &mut table.column_warnings);
+            let pk: i32 = row.get(6).unwrap();
+
+            if pk > 0 {
+                table.pk_columns.push((pk, column.name.clone()));
+            }
+
+            table.columns.push(column);
+        }
+    }
+
+    fn query_foreign_keys(&mut self, connection: &Connection) {
+        let mut stmt = connection
+            .prepare(
+                "SELECT m.name, f.*
+FROM sqlite_master AS m
+JOIN pragma_foreign_key_list(m.name) AS f
+WHERE m.type = 'table';",
+            )
+            .unwrap();
+        let mut rows = stmt.query([]).unwrap();
+
+        while let Some(row) = rows.next().unwrap() {
+            let foreign_key = ForeignKeyRow::new(row, 1);
+
+            self.table(row).foreign_keys.push(foreign_key);
+        }
+    }
+
+    fn query_indexes(&mut self, connection: &Connection) {
+        // Indexes created with CREATE INDEX, in the order they were created
+        let mut stmt = connection
+            .prepare(
+                "SELECT m.tbl_name, m.name, il.[unique], ii.name
+FROM sqlite_master AS m
+JOIN pragma_index_list(m.tbl_name) AS il ON il.name = m.name
+JOIN pragma_index_info(m.name) AS ii
+WHERE m.type = 'index' AND m.sql IS NOT NULL
+ORDER BY m.rowid, ii.seqno;",
+            )
+            .unwrap();
+        let mut rows = stmt.query([]).unwrap();
+
+        while let Some(row) = rows.next().unwrap() {
+            let name: String = row.get(1).unwrap();
+            let unique = row.get(2).unwrap();
+            let column = row.get(3).unwrap();
+            let indexes = &mut self.table(row).indexes;
+
+            match indexes.last_mut() {
+                Some(index) if index.name == name => index.columns.push(column),
+                _ => indexes.push(IndexRow {
+                    name,
+                    unique,
+                    columns: vec![column],
+                }),
+            }
+        }
+    }
+
+    fn query_unique_columns(&mut self, connection: &Connection) {
+        // https://stackoverflow.com/a/53629321/7715250
+        let mut stmt = connection
+            .prepare(
+                "SELECT DISTINCT m.name, ii.name as column_name
+FROM sqlite_master AS m,
+     pragma_index_list(m.name) AS il,
+     pragma_index_info(il.name) AS ii
+WHERE m.type = 'table' AND il.[unique] = 1 AND il.origin = 'u' AND ii.name IS NOT NULL;",
+            )
+            .unwrap();
+        let mut rows = stmt.query([]).unwrap();
+
+        while let Some(row) = rows.next().unwrap() {
+            let column = row.get(1).unwrap();
+
+            self.table(row).unique_columns.push(column);
+        }
+    }
+
+    fn query_automatic_indexes(&mut self, connection: &Connection) {
+        let mut stmt = connection
+            .prepare(
+                "SELECT m.name, il.name, il.origin, ii.name
+FROM sqlite_master AS m
+JOIN pragma_index_list(m.name) AS il
+JOIN pragma_index_info(il.name) AS ii
+WHERE m.type = 'table' AND il.origin IN ('pk', 'u')
+ORDER BY m.name, il.name, ii.seqno;",
+            )
+            .unwrap();
+        let mut rows = stmt.query([]).unwrap();
+
+        while let Some(row) = rows.next().unwrap() {
+            let name: String = row.get(1).unwrap();
+            let column = row.get(3).unwrap();
+            let indexes = &mut self.table(row).automatic_indexes;
+
+            match indexes.last_mut() {
+                Some(index) if index.name == name => index.columns.push(column),
+                _ => indexes.push(AutomaticIndexRow {
+                    name,
+                    origin: row.get(2).unwrap(),
+                    columns: vec![column],
+                }),
+            }
+        }
+    }
+
+    fn get(&self, table_name: &str) -> Option<&SchemaTable> {
+        self.tables.get(&table_name.to_ascii_lowercase())
+    }
+
+    pub(crate) fn automatic_indexes(
+        &self,
+        table_name: &str,
+        columns: &[Column],
+    ) -> Vec<AutomaticIndex> {
+        match self.get(table_name) {
+            Some(table) => automatic_indexes(table.automatic_indexes.clone(), columns),
+            None => vec![],
+        }
+    }
+
+    pub(crate) fn columns(
+        &self,
+        table_name: &str,
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Vec<Column> {
+        match self.get(table_name) {
+            Some(table) => {
+                warnings.extend(table.column_warnings.iter().cloned());
+
+                table.columns.clone()
+            }
+            None => vec![],
+        }
+    }
+
+    pub(crate) fn foreign_keys(
+        &self,
+        table_name: &str,
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Vec<ForeignKey> {
+        let Some(table) = self.get(table_name) else {
+            return vec![];
+        };
+
+        foreign_keys(
+            table_name,
+            table.foreign_keys.clone(),
+            &table.columns,
+            |t| self.get(t).map(|t| t.columns.clone()).unwrap_or_default(),
+            |t| {
+                let mut pk_columns = self
+                    .get(t)
+                    .map(|t| t.pk_columns.clone())
+                    .unwrap_or_default();
+
+                pk_columns.sort();
+                pk_columns.into_iter().map(|(_, name)| name).collect()
+            },
+            warnings,
+        )
+    }
+
+    pub(crate) fn indexes(
+        &self,
+        table_name: &str,
+        columns: &[Column],
+        foreign_keys: &[ForeignKey],
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Vec<Index> {
+        let (index_rows, unique_columns) = match self.get(table_name) {
+            Some(table) => (table.indexes.clone(), table.unique_columns.clone()),
+            None => (vec![], vec![]),
+        };
+
+        indexes(
+            table_name,
+            index_rows,
+            unique_columns,
+            columns,
+            foreign_keys,
+            warnings,
+        )
+    }
+}
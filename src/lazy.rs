@@ -0,0 +1,223 @@
+//! Metadata that only parses the tables that are used, for tools that inspect a few tables of a
+//! big schema
+
+use std::cell::OnceCell;
+use std::path::Path;
+
+use rusqlite::Connection;
+
+use crate::database::{query_database_info, DatabaseInfo};
+use crate::{parse_table_from_connection, Metadata, ParseOptions, Table, DEFAULT_TABLES_QUERY};
+
+/// Lists the table names when it is created, a table is parsed the first time it is accessed
+/// Like `parse_table`, only the row count and storage options are used. The connection is held
+/// until the metadata is dropped, tables that are dropped before they are accessed are `None`.
+#[derive(Debug)]
+pub struct LazyMetadata {
+    connection: Connection,
+    options: ParseOptions,
+    tables: Vec<(String, OnceCell<Option<Table>>)>,
+    database: OnceCell<DatabaseInfo>,
+}
+
+impl LazyMetadata {
+    pub fn open<P: AsRef<Path>>(path: P, options: ParseOptions) -> rusqlite::Result<Self> {
+        Self::from_connection(Connection::open(path)?, options)
+    }
+
+    /// Lists the tables of the connection, with the same tables as `parse_no_parser`
+    /// Tables that the annotations ignore are not listed.
+    pub fn from_connection(
+        connection: Connection,
+        options: ParseOptions,
+    ) -> rusqlite::Result<Self> {
+        let mut tables = vec![];
+
+        {
+            let mut stmt = connection.prepare(DEFAULT_TABLES_QUERY)?;
+            let mut rows = stmt.query([])?;
+
+            while let Some(row) = rows.next()? {
+                let table_name: String = row.get(0)?;
+
+                if options
+                    .annotations
+                    .as_ref()
+                    .is_some_and(|a| a.ignores_table(options.lookup, &table_name))
+                {
+                    continue;
+                }
+
+                tables.push((table_name, OnceCell::new()));
+            }
+        }
+
+        Ok(Self {
+            connection,
+            options,
+            tables,
+            database: OnceCell::new(),
+        })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    pub fn table_names(&self) -> Vec<&str> {
+        self.tables.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    /// Parses the table on first access, names are compared with `ParseOptions::lookup`
+    pub fn table(&self, table_name: &str) -> Option<&Table> {
+        let (name, table) = self
+            .tables
+            .iter()
+            .find(|(name, _)| name == table_name)
+            .or_else(|| {
+                self.tables
+                    .iter()
+                    .find(|(name, _)| self.options.lookup.matches(name, table_name))
+            })?;
+
+        table
+            .get_or_init(|| {
+                match parse_table_from_connection(&self.connection, name, self.options.clone()) {
+                    Ok(table) => Some(table),
+                    Err(rusqlite::Error::QueryReturnedNoRows) => None,
+                    Err(e) => panic!("{e}"),
+                }
+            })
+            .as_ref()
+    }
+
+    /// If the table is already parsed
+    pub fn is_parsed(&self, table_name: &str) -> bool {
+        self.tables
+            .iter()
+            .any(|(name, table)| name == table_name && table.get().is_some())
+    }
+
+    pub fn database(&self) -> &DatabaseInfo {
+        self.database
+            .get_or_init(|| query_database_info(&self.connection))
+    }
+
+    /// Parses the tables that are not parsed yet
+    pub fn into_metadata(self) -> Metadata {
+        for (name, _) in &self.tables {
+            self.table(name);
+        }
+
+        let database = self.database().clone();
+        let triggers = crate::trigger::query_triggers(&self.connection, None);
+        let views = crate::view::query_views(&self.connection);
+
+        let mut metadata = Metadata {
+            tables: self
+                .tables
+                .into_iter()
+                .filter_map(|(name, table)| Some((name, table.into_inner().unwrap()?)))
+                .collect(),
+            samples: Default::default(),
+            profiles: Default::default(),
+            integrity: None,
+            database,
+            lookup: self.options.lookup,
+            triggers,
+            views,
+            annotations: Default::default(),
+        };
+
+        metadata.analyze_dependencies();
+
+        if let Some(annotations) = &self.options.annotations {
+            metadata.annotate(annotations);
+        }
+
+        metadata
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::annotations::{Annotations, TableAnnotation};
+    use crate::lazy::LazyMetadata;
+    use crate::{parse_sql, ParseOptions};
+
+    #[test]
+    fn test_lazy_metadata() {
+        let sql = "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY);
+            CREATE TABLE post (
+                post_id INTEGER NOT NULL PRIMARY KEY,
+                user_id INTEGER NOT NULL REFERENCES user(user_id)
+            );";
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection.execute_batch(sql).unwrap();
+
+        let lazy = LazyMetadata::from_connection(connection, ParseOptions::default()).unwrap();
+
+        assert_eq!(vec!["user", "post"], lazy.table_names());
+        assert!(!lazy.is_parsed("post"));
+
+        // The table is parsed on access, so changes until then are seen
+        lazy.connection()
+            .execute_batch("ALTER TABLE post ADD COLUMN title TEXT;")
+            .unwrap();
+
+        assert!(lazy.table("POST").unwrap().column("title").is_some());
+        assert!(lazy.is_parsed("post"));
+        assert!(!lazy.is_parsed("user"));
+        assert!(lazy.table("missing").is_none());
+
+        let expected = parse_sql(&format!("{sql} ALTER TABLE post ADD COLUMN title TEXT;"));
+
+        assert_eq!(expected.tables, lazy.into_metadata().tables);
+    }
+
+    #[test]
+    fn test_lazy_metadata_removed_tables() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE a (a_id INTEGER NOT NULL PRIMARY KEY);
+            CREATE TABLE b (b_id INTEGER NOT NULL PRIMARY KEY);
+            CREATE TABLE c (c_id INTEGER NOT NULL PRIMARY KEY);",
+            )
+            .unwrap();
+
+        let annotations = Annotations {
+            tables: [(
+                "b".to_string(),
+                TableAnnotation {
+                    ignore: true,
+                    ..Default::default()
+                },
+            )]
+            .into(),
+        };
+        let lazy = LazyMetadata::from_connection(
+            connection,
+            ParseOptions {
+                annotations: Some(annotations),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(vec!["a", "c"], lazy.table_names());
+        assert!(lazy.table("b").is_none());
+
+        lazy.connection().execute_batch("DROP TABLE c;").unwrap();
+
+        assert!(lazy.table("c").is_none());
+
+        let metadata = lazy.into_metadata();
+
+        assert_eq!(vec!["a"], metadata.tables.keys().collect::<Vec<_>>());
+    }
+}
@@ -1,18 +1,215 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "rusqlite")]
 use std::path::Path;
 
-use rusqlite::{Connection, ToSql};
+#[cfg(feature = "rusqlite")]
+use rusqlite::{Connection, OptionalExtension, ToSql};
+
+use crate::annotations::Annotations;
+#[cfg(feature = "rusqlite")]
+use crate::backend::{parse_backend_report, RusqliteBackend};
+use crate::check::{IntegrityCheck, IntegrityReport};
+use crate::database::DatabaseInfo;
+#[cfg(feature = "rusqlite")]
+use crate::introspection::{AutomaticIndexRow, ForeignKeyRow, IndexRow};
+use crate::mapper::{Extension, SharedTypeMapper};
+use crate::profile::TableProfile;
+use crate::report::ParseWarning;
+#[cfg(feature = "rusqlite")]
+use crate::report::{ParseReport, StrictError};
+use crate::sample::SampleRow;
+use crate::statistics::IndexStatistics;
+use crate::storage::StorageStats;
+use crate::tokenizer::tokenize_without_trivia;
+use crate::trigger::Trigger;
+use crate::view::View;
+
+pub use ddl::canonical_sql;
+pub use petgraph;
+
+#[cfg(feature = "rusqlite")]
+pub mod advisor;
+pub mod annotations;
+pub mod anonymize;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod assert;
+#[cfg(feature = "sqlparser")]
+pub mod ast;
+#[cfg(feature = "rusqlite")]
+pub mod audit;
+pub mod backend;
+pub mod builder;
+#[cfg(feature = "rusqlite")]
+pub mod cache;
+pub mod cascade;
+pub mod catalog;
+pub mod check;
+pub mod crud;
+pub mod database;
+pub mod dbt;
+mod ddl;
+pub mod dependency;
+pub mod diff;
+mod display;
+#[cfg(feature = "rusqlite")]
+pub mod dump;
+#[cfg(feature = "rusqlite")]
+pub mod explain;
+pub mod fake;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod file_format;
+pub mod fts;
+#[cfg(feature = "arbitrary")]
+mod fuzz;
+pub mod graph;
+#[cfg(feature = "rusqlite")]
+pub mod html;
+pub mod impact;
+#[cfg(feature = "rusqlite")]
+pub mod inference;
+pub mod inflection;
+#[cfg(feature = "rusqlite")]
+mod introspection;
+#[cfg(feature = "rusqlite")]
+pub mod lazy;
+pub mod limits;
+pub mod lint;
+#[cfg(feature = "rusqlite")]
+pub mod lookup_table;
+pub mod mapper;
+pub mod merge;
+pub mod migration;
+#[cfg(feature = "openapi")]
+pub mod openapi;
+#[cfg(feature = "polars")]
+pub mod polars;
+#[cfg(feature = "rusqlite")]
+pub mod pool;
+pub mod profile;
+#[cfg(feature = "pyo3")]
+mod python;
+pub mod report;
+pub mod sample;
+#[cfg(feature = "sea-schema")]
+pub mod sea_schema;
+pub mod statistics;
+pub mod storage;
+#[cfg(feature = "rusqlite")]
+pub mod strict;
+#[cfg(feature = "tera")]
+pub mod template;
+mod tokenizer;
+pub mod tree;
+pub mod trigger;
+pub mod validate;
+#[cfg(feature = "rusqlite")]
+pub mod versioning;
+pub mod view;
+#[cfg(feature = "wasm")]
+mod wasm;
 
 #[derive(Debug, PartialEq, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Metadata {
     pub tables: HashMap<String, Table>,
+    /// Example rows per table name, only available when `ParseOptions::samples` is set
+    pub samples: HashMap<String, Vec<SampleRow>>,
+    /// Data statistics per table name, only available when `ParseOptions::profile` is set
+    pub profiles: HashMap<String, TableProfile>,
+    /// The result of the integrity check, only available when `ParseOptions::integrity_check` is set
+    pub integrity: Option<IntegrityReport>,
+    /// The settings of the database file
+    pub database: DatabaseInfo,
+    /// How `table` compares table names, copied from `ParseOptions::lookup`
+    pub lookup: LookupMode,
+    /// The triggers of the tables and views, sorted by name
+    pub triggers: Vec<Trigger>,
+    /// The views, sorted by name
+    pub views: Vec<View>,
+    /// The annotations that were merged with `Metadata::annotate`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub annotations: Annotations,
 }
 
 impl Metadata {
     pub fn table(&self, table_name: &str) -> Option<&Table> {
-        self.tables
-            .values()
-            .find(|table| table.table_name == table_name)
+        self.tables.get(table_name).or_else(|| match self.lookup {
+            LookupMode::Exact => None,
+            _ => self
+                .sorted_tables()
+                .into_iter()
+                .find(|table| self.lookup.matches(&table.table_name, table_name)),
+        })
+    }
+
+    /// The triggers on the table or view, the name is compared with `lookup`
+    pub fn triggers_on(&self, table_name: &str) -> Vec<&Trigger> {
+        self.triggers
+            .iter()
+            .filter(|t| self.lookup.matches(&t.table_name, table_name))
+            .collect()
+    }
+
+    /// Finds the columns with the name in all the tables, sorted by table name
+    pub fn find_columns(&self, column_name: &str) -> Vec<(&Table, &Column)> {
+        self.sorted_tables()
+            .into_iter()
+            .filter_map(|t| t.column(column_name).map(|c| (t, c)))
+            .collect()
+    }
+
+    /// Finds the columns with the type in all the tables, sorted by table name and column id
+    pub fn columns_of_type(&self, the_type: Type) -> Vec<(&Table, &Column)> {
+        self.sorted_tables()
+            .into_iter()
+            .flat_map(|t| {
+                t.columns
+                    .iter()
+                    .filter(move |c| c.the_type == the_type)
+                    .map(move |c| (t, c))
+            })
+            .collect()
+    }
+
+    /// The tables sorted by name, for deterministic output
+    pub(crate) fn sorted_tables(&self) -> Vec<&Table> {
+        let mut tables = self.tables.values().collect::<Vec<_>>();
+
+        tables.sort_by(|a, b| a.table_name.cmp(&b.table_name));
+
+        tables
+    }
+
+    /// The CREATE TABLE and CREATE INDEX statements of the tables, sorted by table name
+    /// The statements are the `create_sql` and `index_sql` of the tables, indexes of UNIQUE
+    /// constraints are part of the CREATE TABLE statement. Internal tables are skipped, SQLite
+    /// creates them when they are needed.
+    pub fn schema_sql(&self) -> String {
+        let mut sql = String::new();
+
+        for table in self
+            .sorted_tables()
+            .into_iter()
+            .filter(|t| !t.is_internal())
+        {
+            sql.push_str(&table.create_sql);
+            sql.push_str(";\n");
+
+            for index_sql in table.index_sql.values() {
+                sql.push_str(index_sql);
+                sql.push_str(";\n");
+            }
+        }
+
+        sql
+    }
+
+    /// Checks if the database passed the integrity check, `None` if no check was done
+    /// Metadata parsed from a corrupted database shouldn't be trusted
+    pub fn integrity_ok(&self) -> Option<bool> {
+        self.integrity.as_ref().map(|i| i.is_ok())
     }
 }
 
@@ -44,19 +241,27 @@ impl Metadata {
 /// /// Remove the SQLite file for the doc test
 /// std::fs::remove_file(&my_sqlite_file_location).unwrap();
 /// ```
+#[cfg(feature = "rusqlite")]
 pub fn parse<P: AsRef<Path>, Parse: Parser>(path: P, parser: &mut Parse) {
-    let (query, params) = parser.query_all_tables();
     let connection = Connection::open(&path).unwrap();
 
-    // Get the tables
-    let tables = query_tables(query, params, &connection);
+    parse_connection(&connection, parser);
+}
 
-    parser.process_tables(Metadata {
-        tables: tables
-            .into_iter()
-            .map(|t| (t.table_name.clone(), t))
-            .collect(),
-    });
+/// Parses the tables of an already opened connection
+#[cfg(feature = "rusqlite")]
+fn parse_connection<Parse: Parser>(connection: &Connection, parser: &mut Parse) {
+    let report = {
+        let (query, params) = parser.query_all_tables();
+        let params = params.iter().map(|p| p.as_ref()).collect::<Vec<_>>();
+        let backend =
+            RusqliteBackend::with_tables_query(connection, &query, &params).with_parser(parser);
+
+        parse_backend_report(&backend, parser.parse_options()).unwrap_or_else(|e| panic!("{e}"))
+    };
+
+    parser.process_warnings(report.warnings);
+    parser.process_tables(report.metadata);
 }
 
 /// Convenience method to get the tables
@@ -78,35 +283,331 @@ pub fn parse<P: AsRef<Path>, Parse: Parser>(path: P, parser: &mut Parse) {
 /// /// Remove the SQLite file for the doc test
 /// std::fs::remove_file(&my_sqlite_file_location).unwrap();
 /// ```
+#[cfg(feature = "rusqlite")]
 pub fn parse_no_parser<P: AsRef<Path>>(path: P) -> Metadata {
-    struct Parse {
-        tables: Option<Metadata>,
-    }
+    parse_no_parser_with_options(path, ParseOptions::default())
+}
 
-    impl Parser for Parse {
-        fn process_tables(&mut self, tables: Metadata) {
-            self.tables = Some(tables)
-        }
+/// Same as `parse_no_parser`, but with custom options
+#[cfg(feature = "rusqlite")]
+pub fn parse_no_parser_with_options<P: AsRef<Path>>(path: P, options: ParseOptions) -> Metadata {
+    let connection = Connection::open(&path).unwrap();
+
+    metadata_from_connection(&connection, options)
+}
+
+/// Same as `parse_no_parser_with_options`, with the anomalies that were recovered from
+/// Without a report, the anomalies are only visible in the metadata (e.g. `Type::Unknown`)
+#[cfg(feature = "rusqlite")]
+pub fn parse_report<P: AsRef<Path>>(
+    path: P,
+    options: ParseOptions,
+) -> Result<ParseReport, StrictError> {
+    let connection = Connection::open(&path).unwrap();
+
+    report_from_connection(&connection, options)
+}
+
+/// Parses the schema of DDL statements, e.g. a `schema.sql` file, without a database file
+/// The statements are executed on a temporary in-memory database
+/// Example:
+///
+/// ```
+/// use sqlite_parser::parse_sql;
+///
+/// let metadata = parse_sql("CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY);");
+///
+/// assert!(metadata.table("user").is_some());
+/// ```
+#[cfg(feature = "rusqlite")]
+pub fn parse_sql(ddl: &str) -> Metadata {
+    parse_sql_with_options(ddl, ParseOptions::default())
+}
+
+/// Same as `parse_sql`, but with custom options
+/// Options that read data only see the rows inserted by the statements
+#[cfg(feature = "rusqlite")]
+pub fn parse_sql_with_options(ddl: &str, options: ParseOptions) -> Metadata {
+    let connection = Connection::open_in_memory().unwrap();
+
+    connection.execute_batch(ddl).unwrap();
+
+    metadata_from_connection(&connection, options)
+}
+
+/// Same as `parse_sql_with_options`, with the anomalies that were recovered from
+#[cfg(feature = "rusqlite")]
+pub fn parse_sql_report(ddl: &str, options: ParseOptions) -> Result<ParseReport, StrictError> {
+    let connection = Connection::open_in_memory().unwrap();
+
+    connection.execute_batch(ddl).unwrap();
+
+    report_from_connection(&connection, options)
+}
+
+/// Parses a database file that is held in memory, e.g. an uploaded file
+/// Returns an error if the bytes are not a SQLite database
+#[cfg(feature = "rusqlite")]
+pub fn parse_bytes(bytes: &[u8]) -> rusqlite::Result<Metadata> {
+    parse_bytes_with_options(bytes, ParseOptions::default())
+}
+
+/// Same as `parse_bytes`, but with custom options
+#[cfg(feature = "rusqlite")]
+pub fn parse_bytes_with_options(bytes: &[u8], options: ParseOptions) -> rusqlite::Result<Metadata> {
+    let mut connection = Connection::open_in_memory()?;
+
+    if !bytes.is_empty() {
+        // SQLite takes ownership of the buffer, so it must be allocated by SQLite
+        let data = unsafe {
+            let ptr = rusqlite::ffi::sqlite3_malloc64(bytes.len() as u64) as *mut u8;
+            let ptr = std::ptr::NonNull::new(ptr).ok_or_else(|| {
+                rusqlite::Error::SqliteFailure(
+                    rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_NOMEM),
+                    None,
+                )
+            })?;
+
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr.as_ptr(), bytes.len());
+
+            let buffer = std::slice::from_raw_parts_mut(ptr.as_ptr(), bytes.len());
+
+            // A database in WAL mode can't be read from memory, the header is changed to the
+            // rollback journal mode. The WAL file isn't part of the bytes anyway.
+            if buffer.len() > 19 && buffer[18] == 2 && buffer[19] == 2 {
+                buffer[18] = 1;
+                buffer[19] = 1;
+            }
+
+            rusqlite::serialize::OwnedData::from_raw_nonnull(ptr, bytes.len())
+        };
+
+        connection.deserialize(rusqlite::DatabaseName::Main, data, true)?;
     }
 
-    let mut p = Parse { tables: None };
+    // Deserializing doesn't validate the bytes, reading the schema does
+    connection.query_row("SELECT COUNT(*) FROM sqlite_master;", [], |row| {
+        row.get::<_, i64>(0)
+    })?;
+
+    Ok(metadata_from_connection(&connection, options))
+}
+
+/// Parses a single table of the SQLite file, without parsing the other tables
+/// The name is compared case insensitive, returns `QueryReturnedNoRows` if the table doesn't exist
+#[cfg(feature = "rusqlite")]
+pub fn parse_table<P: AsRef<Path>>(path: P, table_name: &str) -> rusqlite::Result<Table> {
+    let connection = Connection::open(path)?;
 
-    parse(path, &mut p);
+    parse_table_from_connection(&connection, table_name, ParseOptions::default())
+}
 
-    p.tables.unwrap()
+/// Same as `parse_table`, for an already opened connection and with custom options
+/// Only the triggers, index statistics and storage of the table are read, the options that are
+/// not part of the table (samples, profiles and the integrity check) are ignored
+/// Returns `QueryReturnedNoRows` if the annotations ignore the table, and the anomalies as a
+/// `SqliteFailure` in `ParseMode::Strict`
+#[cfg(feature = "rusqlite")]
+pub fn parse_table_from_connection(
+    connection: &Connection,
+    table_name: &str,
+    options: ParseOptions,
+) -> rusqlite::Result<Table> {
+    let table_name: String = connection.query_row(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name = ? COLLATE NOCASE;",
+        [table_name],
+        |row| row.get(0),
+    )?;
+    let params: [&dyn ToSql; 1] = [&table_name];
+    let backend = RusqliteBackend::with_tables_query(connection, "SELECT ?;", &params)
+        .with_table(&table_name);
+    let options = ParseOptions {
+        samples: None,
+        profile: false,
+        integrity_check: None,
+        ..options
+    };
+    let report = parse_backend_report(&backend, options).map_err(|e| {
+        rusqlite::Error::SqliteFailure(
+            rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_ERROR),
+            Some(e.to_string()),
+        )
+    })?;
+
+    report
+        .metadata
+        .tables
+        .into_values()
+        .find(|t| t.table_name == table_name)
+        .ok_or(rusqlite::Error::QueryReturnedNoRows)
 }
 
+#[cfg(feature = "rusqlite")]
+fn metadata_from_connection(connection: &Connection, options: ParseOptions) -> Metadata {
+    report_from_connection(connection, options)
+        .unwrap_or_else(|e| panic!("{e}"))
+        .metadata
+}
+
+#[cfg(feature = "rusqlite")]
+fn report_from_connection(
+    connection: &Connection,
+    options: ParseOptions,
+) -> Result<ParseReport, StrictError> {
+    parse_backend_report(&RusqliteBackend::new(connection), options)
+}
+
+/// Selects all tables
+#[cfg(feature = "rusqlite")]
+const DEFAULT_TABLES_QUERY: &str = "SELECT name FROM sqlite_master WHERE type='table';";
+
 /// Implement this trait to parse your own types
+#[cfg(feature = "rusqlite")]
 pub trait Parser {
-    fn query_all_tables(&self) -> (&'static str, &'static [&'static dyn ToSql]) {
-        ("SELECT name FROM sqlite_master WHERE type='table';", &[])
+    /// The query that selects the names of the tables to parse, with its parameters
+    /// The query can be built at runtime and the parameters can borrow from the parser, e.g.
+    /// `(format!("... WHERE name IN ({placeholders})"), vec![Box::new(&self.name)])`
+    fn query_all_tables(&self) -> (String, Vec<Box<dyn ToSql + '_>>) {
+        (DEFAULT_TABLES_QUERY.to_string(), vec![])
+    }
+
+    /// The options to use while parsing, by default only the structure is parsed
+    fn parse_options(&self) -> ParseOptions {
+        ParseOptions::default()
+    }
+
+    /// Called before a table is parsed, e.g. to show a progress bar for big schemas
+    fn progress(&self, _tables_done: usize, _tables_total: usize, _current_table: &str) {}
+
+    /// Queries the columns of a table, override to e.g. filter columns or to read hidden columns
+    /// with `pragma_table_xinfo`
+    /// Foreign keys and indexes refer to the columns, so they must be able to find them
+    /// Recoverable anomalies are added to the warnings
+    fn query_columns(
+        &self,
+        connection: &Connection,
+        table_name: &str,
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Vec<Column> {
+        query_columns(connection, table_name, warnings)
+    }
+
+    /// Queries the foreign keys of a table
+    fn query_foreign_keys(
+        &self,
+        connection: &Connection,
+        table_name: &str,
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Vec<ForeignKey> {
+        query_fk(connection, table_name, warnings)
     }
 
+    /// Queries the indexes of a table, the columns and foreign keys are the result of the other
+    /// hooks
+    fn query_indexes(
+        &self,
+        connection: &Connection,
+        table_name: &str,
+        columns: &[Column],
+        foreign_keys: &[ForeignKey],
+        warnings: &mut Vec<ParseWarning>,
+    ) -> Vec<Index> {
+        query_indexes(connection, table_name, columns, foreign_keys, warnings)
+    }
+
+    /// Called with the anomalies that were recovered from, before `process_tables`
+    /// By default the warnings are ignored
+    fn process_warnings(&mut self, _warnings: Vec<ParseWarning>) {}
+
     fn process_tables(&mut self, tables: Metadata);
 }
 
+/// Options to parse more than the structure of the database
+/// Note: options that read the actual data can be slow on big databases
+#[derive(Debug, PartialEq, Clone, Eq, Default)]
+pub struct ParseOptions {
+    /// How to determine the amount of rows of each table, `None` skips counting
+    pub row_counts: Option<RowCount>,
+    /// The maximum amount of example rows to read per table, `None` skips sampling
+    pub samples: Option<usize>,
+    /// Computes statistics (null count, distinct count, min/max, length) of every column
+    pub profile: bool,
+    /// Reads the disk usage of tables and indexes from the `dbstat` virtual table
+    /// Ignored when SQLite is compiled without `dbstat`
+    pub storage_stats: bool,
+    /// Runs an integrity check before parsing, see `Metadata::integrity_ok`
+    pub integrity_check: Option<IntegrityCheck>,
+    /// How table, column and index names are compared by the lookup methods
+    pub lookup: LookupMode,
+    /// What to do with anomalies that can be recovered from, see `ParseWarning`
+    pub mode: ParseMode,
+    /// The maximum amount of rows to read per table to set `Column::boolean_hint`, `None` skips
+    /// the detection
+    pub detect_booleans: Option<usize>,
+    /// Consulted before the built-in mapping of the declared types, see `TypeMapper`
+    pub type_mapper: Option<SharedTypeMapper>,
+    /// Reads the current AUTOINCREMENT value of every table from `sqlite_sequence`
+    pub sequences: bool,
+    /// Stores `Table::create_sql` in the form of `canonical_sql`, the column comments are read
+    /// before
+    pub canonical_sql: bool,
+    /// Merged into the metadata with `Metadata::annotate`, the unknown tables and columns are
+    /// reported as warnings
+    pub annotations: Option<Annotations>,
+}
+
+/// How anomalies in the schema are handled
+#[derive(Debug, PartialEq, Copy, Clone, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParseMode {
+    /// The anomalies are recovered from and reported as warnings, e.g. to explore files found in
+    /// the wild
+    #[default]
+    Lenient,
+    /// Parsing fails on any anomaly, e.g. to check a schema in CI
+    /// Functions that return a `ParseReport` return a `StrictError`, the others panic
+    Strict,
+}
+
+/// How names are compared when looking up tables, columns and indexes
+#[derive(Debug, PartialEq, Copy, Clone, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LookupMode {
+    /// The names must be equal
+    Exact,
+    /// ASCII letters are compared case insensitive, this is how SQLite compares identifiers
+    #[default]
+    AsciiCaseInsensitive,
+    /// The lowercase forms are compared, this also folds non ASCII letters
+    UnicodeCaseInsensitive,
+}
+
+impl LookupMode {
+    pub fn matches(&self, name: &str, other: &str) -> bool {
+        match self {
+            LookupMode::Exact => name == other,
+            LookupMode::AsciiCaseInsensitive => name.eq_ignore_ascii_case(other),
+            LookupMode::UnicodeCaseInsensitive => name.to_lowercase() == other.to_lowercase(),
+        }
+    }
+}
+
+/// The way the amount of rows of a table is determined
+#[derive(Debug, PartialEq, Copy, Clone, Eq)]
+pub enum RowCount {
+    /// Runs `SELECT COUNT(*)` for each table, this scans the whole table
+    Exact,
+    /// Reads the estimate from `sqlite_stat1` (filled by `ANALYZE`)
+    /// Falls back to `Exact` for tables without statistics
+    Statistics,
+}
+
 /// Represents a table in SQLite
-#[derive(Debug, PartialEq, Clone, Eq)]
+/// Ordered by table name first, the fields are compared in the order they are declared
+#[derive(Debug, PartialEq, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Table {
     /// The table name
     pub table_name: String,
@@ -115,26 +616,148 @@ pub struct Table {
     /// The foreign keys of the table
     pub foreign_keys: Vec<ForeignKey>,
     pub indexes: Vec<Index>,
+    /// The indexes SQLite creates for the PRIMARY KEY and UNIQUE constraints, ordered by position
+    pub automatic_indexes: Vec<AutomaticIndex>,
+    /// The amount of rows, only available when `ParseOptions::row_counts` is set
+    pub row_count: Option<u64>,
+    /// The largest rowid ever used by an AUTOINCREMENT table, only available when
+    /// `ParseOptions::sequences` is set and a row has been inserted
+    pub sequence: Option<i64>,
+    /// The disk usage, only available when `ParseOptions::storage_stats` is set
+    pub storage: Option<StorageStats>,
+    /// The CREATE TABLE statement as stored by SQLite
+    pub create_sql: String,
+    /// The CREATE INDEX statements as stored by SQLite keyed by index name, also of the partial
+    /// and expression indexes that `indexes` can't describe
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub index_sql: BTreeMap<String, String>,
+    /// How `column` and `index` compare names, copied from `ParseOptions::lookup`
+    pub lookup: LookupMode,
 }
 
 /// Represents an index in SQLite
-#[derive(Debug, PartialEq, Clone, Eq)]
+/// Ordered by name first, the fields are compared in the order they are declared
+#[derive(Debug, PartialEq, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Index {
     pub name: String,
     pub columns: Vec<Column>,
     pub unique: bool,
+    /// The disk usage, only available when `ParseOptions::storage_stats` is set
+    /// Note: not available for indexes created by a UNIQUE column constraint, see
+    /// `Table::automatic_indexes`
+    pub storage: Option<StorageStats>,
+    /// The planner statistics, only available when `ANALYZE` has been run
+    pub statistics: Option<IndexStatistics>,
+}
+
+/// An index that SQLite creates for a PRIMARY KEY or UNIQUE constraint, named
+/// `sqlite_autoindex_<table>_<position>`
+/// An INTEGER PRIMARY KEY is the rowid and has no index, constraints with the same columns as an
+/// earlier constraint share its index.
+#[derive(Debug, PartialEq, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AutomaticIndex {
+    pub name: String,
+    pub origin: IndexOrigin,
+    /// The columns of the constraint, in constraint order
+    pub columns: Vec<Column>,
+    /// Starts at 1, the indexes are numbered in the order of their constraints in the CREATE
+    /// TABLE statement
+    pub position: u32,
+    /// The disk usage, only available when `ParseOptions::storage_stats` is set
+    /// Note: the primary key of a WITHOUT ROWID table is stored in the table itself
+    pub storage: Option<StorageStats>,
+    /// The planner statistics, only available when `ANALYZE` has been run
+    pub statistics: Option<IndexStatistics>,
+}
+
+/// The constraint that an `AutomaticIndex` backs, the `origin` of `pragma index_list`
+#[derive(Debug, PartialEq, Clone, Copy, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum IndexOrigin {
+    PrimaryKey,
+    Unique,
 }
 
 impl Table {
+    /// If SQLite creates and maintains the table itself, like `sqlite_sequence` and `sqlite_stat1`
+    /// These tables can't be created with a CREATE TABLE statement.
+    pub fn is_internal(&self) -> bool {
+        self.table_name
+            .get(..7)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("sqlite_"))
+    }
+
     pub fn column(&self, column_name: &str) -> Option<&Column> {
         self.columns
             .iter()
-            .find(|c| c.name.to_lowercase() == column_name.to_lowercase())
+            .find(|c| self.lookup.matches(&c.name, column_name))
+    }
+
+    pub fn index(&self, index_name: &str) -> Option<&Index> {
+        self.indexes
+            .iter()
+            .find(|i| self.lookup.matches(&i.name, index_name))
+    }
+
+    /// The columns of the primary key, in the order of `columns`
+    pub fn primary_key(&self) -> Vec<&Column> {
+        self.columns.iter().filter(|c| c.part_of_pk).collect()
+    }
+
+    /// The columns that are not part of the primary key
+    pub fn non_pk_columns(&self) -> Vec<&Column> {
+        self.columns.iter().filter(|c| !c.part_of_pk).collect()
+    }
+
+    pub fn column_by_id(&self, id: i32) -> Option<&Column> {
+        self.columns.iter().find(|c| c.id == id)
+    }
+
+    pub fn nullable_columns(&self) -> Vec<&Column> {
+        self.columns.iter().filter(|c| c.nullable).collect()
+    }
+
+    /// Checks if the primary key is declared with AUTOINCREMENT
+    pub fn has_autoincrement(&self) -> bool {
+        tokenize_without_trivia(&self.create_sql)
+            .iter()
+            .any(|t| t.is_keyword("AUTOINCREMENT"))
+    }
+
+    /// The best index to look up the columns without reading the table, see `Index::covers`
+    /// The index with the least columns is preferred, then a unique index.
+    pub fn covering_index_for(&self, columns: &[&str]) -> Option<&Index> {
+        self.indexes
+            .iter()
+            .filter(|i| i.covers_with(columns, self.lookup))
+            .min_by_key(|i| (i.columns.len(), !i.unique))
+    }
+}
+
+impl Index {
+    /// Checks if the columns are the leading columns of the index, in the same order
+    /// A lookup on these columns can be done with the index only, a unique index that has
+    /// exactly these columns finds at most one row. Names are compared like SQLite does.
+    pub fn covers(&self, columns: &[&str]) -> bool {
+        self.covers_with(columns, LookupMode::default())
+    }
+
+    fn covers_with(&self, columns: &[&str], lookup: LookupMode) -> bool {
+        !columns.is_empty()
+            && columns.len() <= self.columns.len()
+            && columns
+                .iter()
+                .zip(&self.columns)
+                .all(|(name, c)| lookup.matches(&c.name, name))
     }
 }
 
 /// Represents a column in SQLite
-#[derive(Debug, PartialEq, Clone, Eq)]
+/// Ordered by id first, which is the order of the columns in the table
+#[derive(Debug, PartialEq, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Column {
     /// The id of the column (starts with 0 and is incremented for each column)
     pub id: i32,
@@ -142,14 +765,66 @@ pub struct Column {
     pub name: String,
     /// The type of the column
     pub the_type: Type,
+    /// The type as written in the CREATE TABLE statement, empty if there is no type
+    pub declared_type: String,
     /// Checks if the column is nullable
     pub nullable: bool,
     /// Checks if the column is part of the primary key
     pub part_of_pk: bool,
+    /// The default value of the column as written in the DDL, if any
+    pub default_value: Option<String>,
+    /// Only 0, 1 and NULL are stored in this INTEGER column, so it is probably a boolean
+    /// Only detected when `ParseOptions::detect_booleans` is set
+    pub boolean_hint: bool,
+    /// The meaning of the declared type, e.g. `DATETIME` is stored as `Type::Text`
+    pub logical_type: Option<LogicalType>,
+    /// The payload of `MappedType::Extension`, only set by a `ParseOptions::type_mapper`
+    pub extension: Option<Extension>,
+    /// The comments next to the column definition in the CREATE TABLE statement
+    pub comment: Option<String>,
+    /// The values of a `CHECK (column IN (...))` constraint in the order they are written, so code
+    /// generators can emit an enum instead of the raw type
+    pub enum_values: Option<Vec<String>>,
+}
+
+impl Column {
+    /// The numbers between the parentheses of the declared type, e.g. `[10, 2]` for
+    /// `DECIMAL(10, 2)`
+    /// Empty without parentheses or when a parameter isn't a number.
+    pub fn type_parameters(&self) -> Vec<i64> {
+        split_declared_type(&self.declared_type)
+            .map(|(_, parameters)| parameters)
+            .unwrap_or_default()
+    }
+
+    /// The type affinity of the declared type, which is how SQLite converts the stored values
+    pub fn affinity(&self) -> Affinity {
+        Affinity::from_declared(&self.declared_type)
+    }
+
+    /// The maximum length of a character or binary type, e.g. 255 for `VARCHAR(255)`
+    /// SQLite stores longer values as well, the length is only declared.
+    pub fn max_length(&self) -> Option<i64> {
+        let (name, parameters) = split_declared_type(&self.declared_type)?;
+        let name = name.to_lowercase();
+
+        match parameters[..] {
+            [length]
+                if ["char", "clob", "text", "binary", "blob"]
+                    .iter()
+                    .any(|n| name.contains(n)) =>
+            {
+                Some(length)
+            }
+            _ => None,
+        }
+    }
 }
 
 /// Represents a foreign key in SQLite
-#[derive(Debug, PartialEq, Clone, Eq)]
+/// Ordered by id first, the fields are compared in the order they are declared
+#[derive(Debug, PartialEq, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ForeignKey {
     /// The id of the foreign key
     /// Starts with 0 and is incremented for each unique foreign key
@@ -165,7 +840,9 @@ pub struct ForeignKey {
     pub on_delete: OnUpdateAndDelete,
 }
 
-#[derive(Debug, PartialEq, Copy, Clone, Eq)]
+#[derive(Debug, PartialEq, Copy, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OnUpdateAndDelete {
     NoAction,
     Restrict,
@@ -188,64 +865,317 @@ impl OnUpdateAndDelete {
 }
 
 /// Represents a type in SQLite
-#[derive(Debug, PartialEq, Copy, Clone, Eq)]
+#[derive(Debug, PartialEq, Copy, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Type {
     Text,
     Integer,
     String,
     Real,
     Blob,
+    /// `NUMERIC` or `DECIMAL`, optionally with the precision and scale like `DECIMAL(10, 2)`
+    /// SQLite stores the values as INTEGER or REAL, the parameters are not enforced
+    Numeric {
+        precision: Option<u32>,
+        scale: Option<u32>,
+    },
+    /// A declared type that can't be interpreted, see `ParseWarning::UnknownType`
+    Unknown,
 }
 
-impl From<String> for Type {
-    fn from(s: String) -> Self {
+impl Type {
+    /// The type of a declared type, `None` if the declared type is unknown
+    pub fn from_declared(s: &str) -> Option<Self> {
         let lower_cased = s.to_lowercase();
 
         if &lower_cased == "text" {
-            Type::Text
+            Some(Type::Text)
         } else if &lower_cased == "integer" || &lower_cased == "int" {
-            Type::Integer
+            Some(Type::Integer)
         } else if &lower_cased == "string" {
-            Type::String
+            Some(Type::String)
         } else if &lower_cased == "real" {
-            Type::Real
+            Some(Type::Real)
         } else if &lower_cased == "blob" {
-            Type::Blob
+            Some(Type::Blob)
         } else {
-            panic!("Unknown type: {}", s)
+            Self::numeric(&lower_cased)
+        }
+    }
+
+    /// Parses `numeric`, `decimal(p)` and `decimal(p, s)`
+    fn numeric(lower_cased: &str) -> Option<Self> {
+        let (name, parameters) = split_declared_type(lower_cased)?;
+
+        if name != "numeric" && name != "decimal" {
+            return None;
         }
+
+        let mut parameters = parameters
+            .into_iter()
+            .map(|p| u32::try_from(p).ok())
+            .collect::<Option<Vec<_>>>()?
+            .into_iter();
+        let numeric = Type::Numeric {
+            precision: parameters.next(),
+            scale: parameters.next(),
+        };
+
+        if parameters.next().is_some() {
+            return None;
+        }
+
+        Some(numeric)
     }
 }
 
-/// Queries the tables from the SQLite file
-fn query_tables(query: &str, params: &[&dyn ToSql], connection: &Connection) -> Vec<Table> {
-    let mut tables = vec![];
-    let mut stmt = connection.prepare(query).unwrap();
-    let mut rows = stmt.query(params).unwrap();
+/// The name and the parameters of a declared type, e.g. `VARCHAR` and `[255]` for `VARCHAR(255)`
+/// `None` when a parameter isn't a (signed) number.
+fn split_declared_type(declared_type: &str) -> Option<(&str, Vec<i64>)> {
+    let Some((name, parameters)) = declared_type.split_once('(') else {
+        return Some((declared_type.trim(), vec![]));
+    };
+    let parameters = parameters
+        .trim_end()
+        .strip_suffix(')')?
+        .split(',')
+        .map(|p| p.trim().parse().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    Some((name.trim(), parameters))
+}
 
-    while let Some(row) = rows.next().unwrap() {
-        // The name is available here
-        let table_name: String = row.get(0).unwrap();
-
-        // Get the columns
-        let columns = query_columns(connection, &table_name);
-        // Get the foreign keys
-        let foreign_keys = query_fk(connection, &table_name);
-        let indexes = query_indexes(connection, &table_name, &columns, &foreign_keys);
-
-        tables.push(Table {
-            table_name,
-            columns,
-            foreign_keys,
-            indexes,
-        });
+/// The type affinity of a column, see https://www.sqlite.org/datatype3.html#type_affinity
+#[derive(Debug, PartialEq, Copy, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Affinity {
+    Integer,
+    Text,
+    /// Also the affinity of columns without a declared type
+    Blob,
+    Real,
+    Numeric,
+}
+
+impl Affinity {
+    /// Follows the rules of SQLite, which look for parts of the declared type like `INT`, so
+    /// `VARCHAR(255)` has TEXT affinity and `DATETIME` has NUMERIC affinity
+    pub fn from_declared(s: &str) -> Self {
+        let upper_cased = s.to_uppercase();
+        let contains = |parts: &[&str]| parts.iter().any(|p| upper_cased.contains(p));
+
+        if contains(&["INT"]) {
+            Affinity::Integer
+        } else if contains(&["CHAR", "CLOB", "TEXT"]) {
+            Affinity::Text
+        } else if contains(&["BLOB"]) || upper_cased.trim().is_empty() {
+            Affinity::Blob
+        } else if contains(&["REAL", "FLOA", "DOUB"]) {
+            Affinity::Real
+        } else {
+            Affinity::Numeric
+        }
     }
 
-    tables
+    /// The type of declared types that are not one of the types, e.g. `BIGINT` is an `INTEGER`
+    pub fn storage_type(&self) -> Type {
+        match self {
+            Affinity::Integer => Type::Integer,
+            Affinity::Text => Type::Text,
+            Affinity::Blob => Type::Blob,
+            Affinity::Real => Type::Real,
+            Affinity::Numeric => Type::Numeric {
+                precision: None,
+                scale: None,
+            },
+        }
+    }
+}
+
+/// A declared type that is common but not a SQLite type, the value is stored as `storage_type`
+#[derive(Debug, PartialEq, Copy, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LogicalType {
+    /// `BOOLEAN` or `BOOL`
+    Boolean,
+    Date,
+    DateTime,
+    Timestamp,
+    /// `JSON` or `JSONB`
+    Json,
+    /// `UUID` or `GUID`
+    Uuid,
+}
+
+impl LogicalType {
+    /// The logical type of a declared type, parameters like `DATETIME(6)` are ignored
+    pub fn from_declared(s: &str) -> Option<Self> {
+        let name = s.split('(').next().unwrap().trim().to_lowercase();
+
+        match name.as_str() {
+            "boolean" | "bool" => Some(LogicalType::Boolean),
+            "date" => Some(LogicalType::Date),
+            "datetime" => Some(LogicalType::DateTime),
+            "timestamp" => Some(LogicalType::Timestamp),
+            "json" | "jsonb" => Some(LogicalType::Json),
+            "uuid" | "guid" => Some(LogicalType::Uuid),
+            _ => None,
+        }
+    }
+
+    /// How the values are usually stored
+    pub fn storage_type(&self) -> Type {
+        match self {
+            LogicalType::Boolean => Type::Integer,
+            LogicalType::Date
+            | LogicalType::DateTime
+            | LogicalType::Timestamp
+            | LogicalType::Json
+            | LogicalType::Uuid => Type::Text,
+        }
+    }
+}
+
+/// The type and logical type of a declared type
+/// Other declared types like `VARCHAR(255)`, `BIGINT` or no type at all get the type of their
+/// affinity. Declared types with parameters that aren't integers, like `DECIMAL(1.5)`, are added
+/// to the warnings and become `Type::Unknown`.
+pub(crate) fn declared_type(
+    declared_type: &str,
+    table_name: &str,
+    column_name: &str,
+    warnings: &mut Vec<ParseWarning>,
+) -> (Type, Option<LogicalType>) {
+    if let Some(the_type) = Type::from_declared(declared_type) {
+        return (the_type, None);
+    }
+
+    if let Some(logical_type) = LogicalType::from_declared(declared_type) {
+        return (logical_type.storage_type(), Some(logical_type));
+    }
+
+    match split_declared_type(declared_type) {
+        Some(_) => (Affinity::from_declared(declared_type).storage_type(), None),
+        None => {
+            warnings.push(ParseWarning::UnknownType {
+                table: table_name.to_string(),
+                column: column_name.to_string(),
+                declared_type: declared_type.to_string(),
+            });
+
+            (Type::Unknown, None)
+        }
+    }
+}
+
+impl From<String> for Type {
+    fn from(s: String) -> Self {
+        Type::from_declared(&s).unwrap_or_else(|| panic!("Unknown type: {}", s))
+    }
+}
+
+/// Queries the amount of rows of the table
+#[cfg(feature = "rusqlite")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip(connection))
+)]
+pub(crate) fn query_row_count(
+    connection: &Connection,
+    table_name: &str,
+    row_count: RowCount,
+) -> u64 {
+    if row_count == RowCount::Statistics && table_exists(connection, "sqlite_stat1") {
+        let stat: Option<String> = connection
+            .query_row(
+                "SELECT stat FROM sqlite_stat1 WHERE tbl = ? LIMIT 1;",
+                [table_name],
+                |row| row.get(0),
+            )
+            .optional()
+            .unwrap();
+
+        // The first number of the stat column is the (estimated) amount of rows
+        if let Some(rows) = stat.and_then(|s| s.split(' ').next()?.parse().ok()) {
+            return rows;
+        }
+    }
+
+    connection
+        .query_row(
+            &format!("SELECT COUNT(*) FROM {};", quote_identifier(table_name)),
+            [],
+            |row| row.get(0),
+        )
+        .unwrap()
+}
+
+/// Queries the CREATE INDEX statements of the table, keyed by index name
+#[cfg(feature = "rusqlite")]
+pub(crate) fn query_index_sql(
+    connection: &Connection,
+    table_name: &str,
+) -> BTreeMap<String, String> {
+    let mut stmt = connection
+        .prepare(
+            "SELECT name, sql FROM sqlite_master WHERE type = 'index' AND tbl_name = ? AND sql IS NOT NULL;",
+        )
+        .unwrap();
+
+    stmt.query_map([table_name], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .collect::<rusqlite::Result<_>>()
+        .unwrap()
+}
+
+/// Queries the value of the table in `sqlite_sequence`
+#[cfg(feature = "rusqlite")]
+pub(crate) fn query_sequence(connection: &Connection, table_name: &str) -> Option<i64> {
+    if !table_exists(connection, "sqlite_sequence") {
+        return None;
+    }
+
+    connection
+        .query_row(
+            "SELECT seq FROM sqlite_sequence WHERE name = ?;",
+            [table_name],
+            |row| row.get(0),
+        )
+        .optional()
+        .unwrap()
+}
+
+/// Checks if a table (including internal tables like sqlite_stat1) exists
+#[cfg(feature = "rusqlite")]
+pub(crate) fn table_exists(connection: &Connection, table_name: &str) -> bool {
+    connection
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table' AND name = ?;",
+            [table_name],
+            |row| row.get(0),
+        )
+        .unwrap()
+}
+
+/// Quotes an identifier so it can safely be used in a query
+pub(crate) fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
 }
 
 /// Queries the columns from the table name
-fn query_columns(connection: &Connection, table_name: &str) -> Vec<Column> {
+/// This is the default implementation of `Parser::query_columns`
+#[cfg(feature = "rusqlite")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip(connection, warnings))
+)]
+pub fn query_columns(
+    connection: &Connection,
+    table_name: &str,
+    warnings: &mut Vec<ParseWarning>,
+) -> Vec<Column> {
     let mut columns = vec![];
     let mut stmt = connection
         .prepare("SELECT * FROM pragma_table_info(?);")
@@ -253,217 +1183,229 @@ fn query_columns(connection: &Connection, table_name: &str) -> Vec<Column> {
     let mut rows = stmt.query([&table_name]).unwrap();
 
     while let Some(row) = rows.next().unwrap() {
-        // Parse the type first
-        let t: String = row.get(2).unwrap();
-        let is_non_null: bool = row.get(3).unwrap();
-        let name: String = row.get(1).unwrap();
-
-        columns.push(Column {
-            id: row.get(0).unwrap(),
-            name,
-            the_type: Type::from(t),
-            nullable: !is_non_null,
-            part_of_pk: row.get(5).unwrap(),
-        });
+        columns.push(introspection::column(row, 0, table_name, warnings));
     }
 
     columns
 }
 
 /// Queries the indexes from the table name
-fn query_indexes(
+/// This is the default implementation of `Parser::query_indexes`
+#[cfg(feature = "rusqlite")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip(connection, columns, foreign_keys, warnings))
+)]
+pub fn query_indexes(
     connection: &Connection,
     table_name: &str,
     columns: &[Column],
     foreign_keys: &[ForeignKey],
+    warnings: &mut Vec<ParseWarning>,
 ) -> Vec<Index> {
-    let mut indexes = vec![];
+    let mut index_rows = vec![];
+    // Indexes created with CREATE INDEX, in the order they were created
     let mut stmt = connection
         .prepare(
             "SELECT
-  name, sql
-FROM sqlite_master
-WHERE type = 'index' AND tbl_name = ? AND sql is not null;",
+  m.name, il.[unique]
+FROM sqlite_master AS m
+JOIN pragma_index_list(?1) AS il ON il.name = m.name
+WHERE m.type = 'index' AND m.tbl_name = ?1 AND m.sql IS NOT NULL
+ORDER BY m.rowid;",
         )
         .unwrap();
     let mut rows = stmt.query([&table_name]).unwrap();
 
     while let Some(row) = rows.next().unwrap() {
         let name: String = row.get(0).unwrap();
-        let sql: String = row.get(1).unwrap();
-        let columns_used = sql
-            .split('(')
-            .collect::<Vec<_>>()
-            .get(1)
-            .unwrap()
-            .split(')')
-            .collect::<Vec<_>>()
-            .first()
-            .unwrap()
-            .split(", ")
-            .map(|c| {
-                c.to_string()
-                    .strip_suffix(" desc")
-                    .map(|c| c.to_string())
-                    .unwrap_or(c.to_string())
-            });
 
-        indexes.push(Index {
-            name: name.clone(),
-            columns: columns_used
-                .clone()
-                .map(|c| {
-                    columns
-                        .iter()
-                        .find(|co| c.to_lowercase() == co.name.to_lowercase())
-                        .unwrap_or_else(|| panic!("Could not find index with name {name} in columns_used: {:#?} columns: {:#?}", columns_used, columns))
-                        .clone()
-                })
-                .collect(),
-            unique: false,
+        index_rows.push(IndexRow {
+            columns: query_index_columns(connection, &name),
+            name,
+            unique: row.get(1).unwrap(),
         });
     }
 
+    let mut unique_columns = vec![];
+    // https://stackoverflow.com/a/53629321/7715250
     let mut stmt = connection
         .prepare(
-            // https://stackoverflow.com/a/53629321/7715250
-            &format!(
-                "SELECT DISTINCT ii.name as column_name
-FROM sqlite_master AS m,
-     pragma_index_list(m.name) AS il,
+            "SELECT DISTINCT ii.name as column_name
+FROM pragma_index_list(?) AS il,
      pragma_index_info(il.name) AS ii
-WHERE m.type='table' AND il.[unique] = 1 and m.name = '{table_name}';"
-            ),
+WHERE il.[unique] = 1 AND il.origin = 'u' AND ii.name IS NOT NULL;",
         )
         .unwrap();
-    let mut rows = stmt.query([]).unwrap();
+    let mut rows = stmt.query([&table_name]).unwrap();
 
     while let Some(row) = rows.next().unwrap() {
-        let name: String = row.get(0).unwrap();
-        let mut index_column = None;
+        unique_columns.push(row.get(0).unwrap());
+    }
 
-        for column in columns {
-            if column.name == name {
-                if !column.part_of_pk {
-                    index_column = Some(column.clone());
-                }
+    introspection::indexes(
+        table_name,
+        index_rows,
+        unique_columns,
+        columns,
+        foreign_keys,
+        warnings,
+    )
+}
 
-                break;
-            }
-        }
+/// Queries the indexes of the PRIMARY KEY and UNIQUE constraints from the table name
+#[cfg(feature = "rusqlite")]
+pub(crate) fn query_automatic_indexes(
+    connection: &Connection,
+    table_name: &str,
+    columns: &[Column],
+) -> Vec<AutomaticIndex> {
+    let mut stmt = connection
+        .prepare("SELECT name, origin FROM pragma_index_list(?) WHERE origin IN ('pk', 'u');")
+        .unwrap();
+    let mut rows = stmt.query([table_name]).unwrap();
+    let mut index_rows = vec![];
 
-        if index_column.is_none() {
-            // PK's always have unique indexes, skip these
-            continue;
-        }
+    while let Some(row) = rows.next().unwrap() {
+        let name: String = row.get(0).unwrap();
 
-        let index_column = index_column.unwrap();
-        let contains = foreign_keys
-            .iter()
-            .find(|f| f.from_column.iter().any(|f| f.name == index_column.name));
+        index_rows.push(AutomaticIndexRow {
+            columns: query_index_columns(connection, &name)
+                .into_iter()
+                .flatten()
+                .collect(),
+            name,
+            origin: row.get(1).unwrap(),
+        });
+    }
 
-        if contains.is_some() {
-            // Foreign keys always have unique constraints, ignore
-            continue;
-        }
+    introspection::automatic_indexes(index_rows, columns)
+}
 
-        indexes.push(Index {
-            name,
-            columns: vec![index_column],
-            unique: true,
-        })
+/// Queries the names of the key columns of the index, in index order
+/// Expressions are `None`, since they are not a column of the table
+#[cfg(feature = "rusqlite")]
+fn query_index_columns(connection: &Connection, index_name: &str) -> Vec<Option<String>> {
+    let mut stmt = connection
+        .prepare("SELECT name FROM pragma_index_info(?) ORDER BY seqno;")
+        .unwrap();
+    let mut rows = stmt.query([index_name]).unwrap();
+    let mut names = vec![];
+
+    while let Some(row) = rows.next().unwrap() {
+        names.push(row.get(0).unwrap());
     }
 
-    // Check for duplicates
-    for (i, index) in indexes.iter().enumerate() {
-        for (i_inner, index_inner) in indexes.iter().enumerate() {
-            if i == i_inner {
-                assert_eq!(index, index_inner);
+    names
+}
 
-                continue;
-            }
+/// Queries the names of the primary key columns of the table, in primary key order
+#[cfg(feature = "rusqlite")]
+fn query_pk_columns(connection: &Connection, table_name: &str) -> Vec<String> {
+    let mut stmt = connection
+        .prepare("SELECT name FROM pragma_table_info(?) WHERE pk > 0 ORDER BY pk;")
+        .unwrap();
+    let mut rows = stmt.query([table_name]).unwrap();
+    let mut names = vec![];
 
-            assert_ne!(
-                index.columns, index_inner.columns,
-                "Duplicate index: {:#?}",
-                index_inner.columns
-            );
-        }
+    while let Some(row) = rows.next().unwrap() {
+        names.push(row.get(0).unwrap());
     }
 
-    indexes
+    names
+}
+
+/// Finds a column by name, SQLite compares identifiers case insensitive for ASCII characters
+pub(crate) fn find_column<'a>(columns: &'a [Column], name: &str) -> Option<&'a Column> {
+    columns
+        .iter()
+        .find(|c| c.name == name)
+        .or_else(|| columns.iter().find(|c| c.name.eq_ignore_ascii_case(name)))
 }
 
 /// Queries the foreign keys from the table name
-fn query_fk(connection: &Connection, table_name: &str) -> Vec<ForeignKey> {
-    let mut foreign_keys: Vec<ForeignKey> = vec![];
+/// This is the default implementation of `Parser::query_foreign_keys`
+#[cfg(feature = "rusqlite")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip(connection, warnings))
+)]
+pub fn query_fk(
+    connection: &Connection,
+    table_name: &str,
+    warnings: &mut Vec<ParseWarning>,
+) -> Vec<ForeignKey> {
+    let mut foreign_key_rows = vec![];
     let mut stmt = connection
         .prepare("SELECT * FROM pragma_foreign_key_list(?);")
         .unwrap();
     let mut rows = stmt.query([&table_name]).unwrap();
 
     while let Some(row) = rows.next().unwrap() {
-        let table: String = row.get(2).unwrap();
-        let other_table_columns = query_columns(connection, &table);
-        let from_column: String = row.get(3).unwrap();
-        let to_column: String = row.get(4).unwrap();
-        let on_update: String = row.get(5).unwrap();
-        let on_delete: String = row.get(6).unwrap();
-        let own_columns = query_columns(connection, table_name);
-
-        let mut foreign_key = ForeignKey {
-            id: row.get(0).unwrap(),
-            table,
-            from_column: vec![own_columns
-                .clone()
-                .into_iter()
-                .find(|c| c.name.to_lowercase() == from_column.to_lowercase())
-                .unwrap_or_else(|| {
-                    panic!(
-                        "Expected to find {} in {:#?}",
-                        from_column.to_lowercase(),
-                        own_columns
-                            .iter()
-                            .map(|c| c.name.to_lowercase())
-                            .collect::<Vec<_>>()
-                    )
-                })],
-            to_column: vec![other_table_columns
-                .clone()
-                .into_iter()
-                .find(|c| c.name.to_lowercase() == to_column.to_lowercase())
-                .unwrap()],
-            on_update: OnUpdateAndDelete::from_str(&on_update),
-            on_delete: OnUpdateAndDelete::from_str(&on_delete),
-        };
+        foreign_key_rows.push(ForeignKeyRow::new(row, 0));
+    }
 
-        if let Some(fk) = foreign_keys
-            .iter_mut()
-            .find(|f| f.id == row.get(0).unwrap())
-        {
-            fk.from_column.push(foreign_key.from_column.remove(0));
-            fk.to_column.push(foreign_key.to_column.remove(0));
-        } else {
-            foreign_keys.push(foreign_key);
-        }
+    if foreign_key_rows.is_empty() {
+        return vec![];
     }
 
-    foreign_keys
+    // The types of the columns are already reported when the tables themselves are parsed
+    introspection::foreign_keys(
+        table_name,
+        foreign_key_rows,
+        &query_columns(connection, table_name, &mut vec![]),
+        |table| query_columns(connection, table, &mut vec![]),
+        |table| query_pk_columns(connection, table),
+        warnings,
+    )
 }
 
-#[cfg(test)]
-mod tests {
-    use std::collections::HashMap;
+#[cfg(all(test, feature = "rusqlite"))]
+pub(crate) mod tests {
+    use std::cell::RefCell;
+    use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
-    use rusqlite::Connection;
+    use rusqlite::{Connection, ToSql};
 
+    use crate::report::ParseWarning;
     use crate::Type::{Blob, Integer, Real, Text};
     use crate::{
-        parse, Column, ForeignKey, Index, Metadata, OnUpdateAndDelete, Parser, Table, Type,
+        metadata_from_connection, parse, parse_bytes, parse_connection, parse_sql,
+        parse_sql_report, parse_table_from_connection, query_columns, query_indexes,
+        AutomaticIndex, Column, ForeignKey, Index, IndexOrigin, LogicalType, LookupMode, Metadata,
+        OnUpdateAndDelete, ParseMode, ParseOptions, Parser, RowCount, Table, Type,
     };
 
+    /// Creates an in-memory database from the given DDL and parses it
+    pub(crate) fn metadata_from_sql(sql: &str) -> Metadata {
+        parse_sql(sql)
+    }
+
     #[test]
     fn test_parse() {
+        const USER_SQL: &str = "CREATE TABLE user (
+            user_id INTEGER NOT NULL PRIMARY KEY,
+            parent_id INTEGER,
+            FOREIGN KEY(parent_id) REFERENCES user(user_id)
+        );";
+        const CONTACTS_SQL: &str = "CREATE TABLE contacts (
+            contact_id INTEGER NOT NULL,
+            first_name TEXT NOT NULL,
+            user_id INTEGER,
+            FOREIGN KEY(user_id) REFERENCES user(user_id),
+            PRIMARY KEY (contact_id, first_name)
+        );";
+        const BOOK_SQL: &str = "CREATE TABLE book (
+            contact_id INTEGER NOT NULL,
+            first_name TEXT NOT NULL,
+            real REAL UNIQUE NOT NULL,
+            blob BLOB NOT NULL,
+            user_id INTEGER,
+            FOREIGN KEY(contact_id, first_name) REFERENCES contacts(contact_id, first_name),
+            FOREIGN KEY(user_id) REFERENCES user(user_id),
+            PRIMARY KEY (contact_id, first_name)
+        );";
+
         let current = std::env::current_dir().unwrap().join("test_sqlite.sqlite3");
 
         // Create the sqlite3 file
@@ -472,29 +1414,9 @@ mod tests {
         // Connect and add some tables to assert the data on
         let connect = Connection::open(&current).unwrap();
 
-        connect
-            .execute(
-                "CREATE TABLE user (
-            user_id INTEGER NOT NULL PRIMARY KEY,
-            parent_id INTEGER,
-            FOREIGN KEY(parent_id) REFERENCES user(user_id)
-        );",
-                [],
-            )
-            .unwrap();
+        connect.execute(USER_SQL, []).unwrap();
 
-        connect
-            .execute(
-                "CREATE TABLE contacts (
-            contact_id INTEGER NOT NULL,
-            first_name TEXT NOT NULL,
-            user_id INTEGER,
-            FOREIGN KEY(user_id) REFERENCES user(user_id),
-            PRIMARY KEY (contact_id, first_name)
-        );",
-                [],
-            )
-            .unwrap();
+        connect.execute(CONTACTS_SQL, []).unwrap();
 
         connect
             .execute(
@@ -510,21 +1432,7 @@ mod tests {
             )
             .unwrap();
 
-        connect
-            .execute(
-                "CREATE TABLE book (
-            contact_id INTEGER NOT NULL,
-            first_name TEXT NOT NULL,
-            real REAL UNIQUE NOT NULL,
-            blob BLOB NOT NULL,
-            user_id INTEGER,
-            FOREIGN KEY(contact_id, first_name) REFERENCES contacts(contact_id, first_name),
-            FOREIGN KEY(user_id) REFERENCES user(user_id),
-            PRIMARY KEY (contact_id, first_name)
-        );",
-                [],
-            )
-            .unwrap();
+        connect.execute(BOOK_SQL, []).unwrap();
 
         // Create a parser
         struct Parse;
@@ -535,33 +1443,79 @@ mod tests {
                     id: 0,
                     name: "user_id".to_string(),
                     the_type: Type::Integer,
+                    declared_type: "INTEGER".to_string(),
                     nullable: false,
                     part_of_pk: true,
+                    default_value: None,
+                    boolean_hint: false,
+                    logical_type: None,
+                    extension: None,
+                    comment: None,
+                    enum_values: None,
                 };
 
-                let contacts = Table {
+                let mut contacts = Table {
                     table_name: "contacts".to_string(),
+                    create_sql: CONTACTS_SQL.trim_end_matches(';').to_string(),
+                    index_sql: BTreeMap::from([
+                        (
+                            "contact_id_reversed".to_string(),
+                            "CREATE INDEX contact_id_reversed on contacts(contact_id desc)"
+                                .to_string(),
+                        ),
+                        (
+                            "contacts_user_id".to_string(),
+                            "CREATE INDEX contacts_user_id on contacts(user_id, first_name)"
+                                .to_string(),
+                        ),
+                    ]),
+                    lookup: LookupMode::default(),
+                    row_count: None,
+                    sequence: None,
+                    storage: None,
+                    automatic_indexes: vec![],
                     columns: vec![
                         Column {
                             id: 0,
                             name: "contact_id".to_string(),
                             the_type: Integer,
+                            declared_type: "INTEGER".to_string(),
                             nullable: false,
                             part_of_pk: true,
+                            default_value: None,
+                            boolean_hint: false,
+                            logical_type: None,
+                            extension: None,
+                            comment: None,
+                            enum_values: None,
                         },
                         Column {
                             id: 1,
                             name: "first_name".to_string(),
                             the_type: Text,
+                            declared_type: "TEXT".to_string(),
                             nullable: false,
                             part_of_pk: true,
+                            default_value: None,
+                            boolean_hint: false,
+                            logical_type: None,
+                            extension: None,
+                            comment: None,
+                            enum_values: None,
                         },
                         Column {
                             id: 2,
                             name: "user_id".to_string(),
                             the_type: Integer,
+                            declared_type: "INTEGER".to_string(),
                             nullable: true,
                             part_of_pk: false,
+                            default_value: None,
+                            boolean_hint: false,
+                            logical_type: None,
+                            extension: None,
+                            comment: None,
+                            enum_values: None,
                         },
                     ],
                     foreign_keys: vec![ForeignKey {
@@ -571,8 +1525,15 @@ mod tests {
                             id: 2,
                             name: "user_id".to_string(),
                             the_type: Integer,
+                            declared_type: "INTEGER".to_string(),
                             nullable: true,
                             part_of_pk: false,
+                            default_value: None,
+                            boolean_hint: false,
+                            logical_type: None,
+                            extension: None,
+                            comment: None,
+                            enum_values: None,
                         }],
                         to_column: vec![user_id_column.clone()],
                         on_update: OnUpdateAndDelete::NoAction,
@@ -586,18 +1547,34 @@ mod tests {
                                     id: 2,
                                     name: "user_id".to_string(),
                                     the_type: Integer,
+                                    declared_type: "INTEGER".to_string(),
                                     nullable: true,
                                     part_of_pk: false,
+                                    default_value: None,
+                                    boolean_hint: false,
+                                    logical_type: None,
+                                    extension: None,
+                                    comment: None,
+                                    enum_values: None,
                                 },
                                 Column {
                                     id: 1,
                                     name: "first_name".to_string(),
                                     the_type: Text,
+                                    declared_type: "TEXT".to_string(),
                                     nullable: false,
                                     part_of_pk: true,
+                                    default_value: None,
+                                    boolean_hint: false,
+                                    logical_type: None,
+                                    extension: None,
+                                    comment: None,
+                                    enum_values: None,
                                 },
                             ],
                             unique: false,
+                            storage: None,
+                            statistics: None,
                         },
                         Index {
                             name: "contact_id_reversed".to_string(),
@@ -605,23 +1582,46 @@ mod tests {
                                 id: 0,
                                 name: "contact_id".to_string(),
                                 the_type: Integer,
+                                declared_type: "INTEGER".to_string(),
                                 nullable: false,
                                 part_of_pk: true,
+                                default_value: None,
+                                boolean_hint: false,
+                                logical_type: None,
+                                extension: None,
+                                comment: None,
+                                enum_values: None,
                             }],
                             unique: false,
+                            storage: None,
+                            statistics: None,
                         },
                     ],
                 };
                 let user = Table {
                     table_name: "user".to_string(),
+                    create_sql: USER_SQL.trim_end_matches(';').to_string(),
+                    index_sql: BTreeMap::new(),
+                    lookup: LookupMode::default(),
+                    row_count: None,
+                    sequence: None,
+                    storage: None,
+                    automatic_indexes: vec![],
                     columns: vec![
                         user_id_column,
                         Column {
                             id: 1,
                             name: "parent_id".to_string(),
                             the_type: Integer,
+                            declared_type: "INTEGER".to_string(),
                             nullable: true,
                             part_of_pk: false,
+                            default_value: None,
+                            boolean_hint: false,
+                            logical_type: None,
+                            extension: None,
+                            comment: None,
+                            enum_values: None,
                         },
                     ],
                     foreign_keys: vec![ForeignKey {
@@ -631,15 +1631,29 @@ mod tests {
                             id: 1,
                             name: "parent_id".to_string(),
                             the_type: Integer,
+                            declared_type: "INTEGER".to_string(),
                             nullable: true,
                             part_of_pk: false,
+                            default_value: None,
+                            boolean_hint: false,
+                            logical_type: None,
+                            extension: None,
+                            comment: None,
+                            enum_values: None,
                         }],
                         to_column: vec![Column {
                             id: 0,
                             name: "user_id".to_string(),
                             the_type: Integer,
+                            declared_type: "INTEGER".to_string(),
                             nullable: false,
                             part_of_pk: true,
+                            default_value: None,
+                            boolean_hint: false,
+                            logical_type: None,
+                            extension: None,
+                            comment: None,
+                            enum_values: None,
                         }],
                         on_update: OnUpdateAndDelete::NoAction,
                         on_delete: OnUpdateAndDelete::NoAction,
@@ -647,43 +1661,85 @@ mod tests {
                     indexes: vec![],
                 };
 
-                let book = Table {
+                let mut book = Table {
                     table_name: "book".to_string(),
+                    create_sql: BOOK_SQL.trim_end_matches(';').to_string(),
+                    index_sql: BTreeMap::new(),
+                    lookup: LookupMode::default(),
+                    row_count: None,
+                    sequence: None,
+                    storage: None,
+                    automatic_indexes: vec![],
                     columns: vec![
                         Column {
                             id: 0,
                             name: "contact_id".to_string(),
                             the_type: Integer,
+                            declared_type: "INTEGER".to_string(),
                             nullable: false,
                             part_of_pk: true,
+                            default_value: None,
+                            boolean_hint: false,
+                            logical_type: None,
+                            extension: None,
+                            comment: None,
+                            enum_values: None,
                         },
                         Column {
                             id: 1,
                             name: "first_name".to_string(),
                             the_type: Text,
+                            declared_type: "TEXT".to_string(),
                             nullable: false,
                             part_of_pk: true,
+                            default_value: None,
+                            boolean_hint: false,
+                            logical_type: None,
+                            extension: None,
+                            comment: None,
+                            enum_values: None,
                         },
                         Column {
                             id: 2,
                             name: "real".to_string(),
                             the_type: Real,
+                            declared_type: "REAL".to_string(),
                             nullable: false,
                             part_of_pk: false,
+                            default_value: None,
+                            boolean_hint: false,
+                            logical_type: None,
+                            extension: None,
+                            comment: None,
+                            enum_values: None,
                         },
                         Column {
                             id: 3,
                             name: "blob".to_string(),
                             the_type: Blob,
+                            declared_type: "BLOB".to_string(),
                             nullable: false,
                             part_of_pk: false,
+                            default_value: None,
+                            boolean_hint: false,
+                            logical_type: None,
+                            extension: None,
+                            comment: None,
+                            enum_values: None,
                         },
                         Column {
                             id: 4,
                             name: "user_id".to_string(),
                             the_type: Integer,
+                            declared_type: "INTEGER".to_string(),
                             nullable: true,
                             part_of_pk: false,
+                            default_value: None,
+                            boolean_hint: false,
+                            logical_type: None,
+                            extension: None,
+                            comment: None,
+                            enum_values: None,
                         },
                     ],
                     foreign_keys: vec![
@@ -694,15 +1750,29 @@ mod tests {
                                 id: 4,
                                 name: "user_id".to_string(),
                                 the_type: Type::Integer,
+                                declared_type: "INTEGER".to_string(),
                                 nullable: true,
                                 part_of_pk: false,
+                                default_value: None,
+                                boolean_hint: false,
+                                logical_type: None,
+                                extension: None,
+                                comment: None,
+                                enum_values: None,
                             }],
                             to_column: vec![Column {
                                 id: 0,
                                 name: "user_id".to_string(),
                                 the_type: Type::Integer,
+                                declared_type: "INTEGER".to_string(),
                                 nullable: false,
                                 part_of_pk: true,
+                                default_value: None,
+                                boolean_hint: false,
+                                logical_type: None,
+                                extension: None,
+                                comment: None,
+                                enum_values: None,
                             }],
                             on_update: OnUpdateAndDelete::NoAction,
                             on_delete: OnUpdateAndDelete::NoAction,
@@ -715,15 +1785,29 @@ mod tests {
                                     id: 0,
                                     name: "contact_id".to_string(),
                                     the_type: Type::Integer,
+                                    declared_type: "INTEGER".to_string(),
                                     nullable: false,
                                     part_of_pk: true,
+                                    default_value: None,
+                                    boolean_hint: false,
+                                    logical_type: None,
+                                    extension: None,
+                                    comment: None,
+                                    enum_values: None,
                                 },
                                 Column {
                                     id: 1,
                                     name: "first_name".to_string(),
                                     the_type: Type::Text,
+                                    declared_type: "TEXT".to_string(),
                                     nullable: false,
                                     part_of_pk: true,
+                                    default_value: None,
+                                    boolean_hint: false,
+                                    logical_type: None,
+                                    extension: None,
+                                    comment: None,
+                                    enum_values: None,
                                 },
                             ],
                             to_column: vec![
@@ -731,15 +1815,29 @@ mod tests {
                                     id: 0,
                                     name: "contact_id".to_string(),
                                     the_type: Type::Integer,
+                                    declared_type: "INTEGER".to_string(),
                                     nullable: false,
                                     part_of_pk: true,
+                                    default_value: None,
+                                    boolean_hint: false,
+                                    logical_type: None,
+                                    extension: None,
+                                    comment: None,
+                                    enum_values: None,
                                 },
                                 Column {
                                     id: 1,
                                     name: "first_name".to_string(),
                                     the_type: Type::Text,
+                                    declared_type: "TEXT".to_string(),
                                     nullable: false,
                                     part_of_pk: true,
+                                    default_value: None,
+                                    boolean_hint: false,
+                                    logical_type: None,
+                                    extension: None,
+                                    comment: None,
+                                    enum_values: None,
                                 },
                             ],
                             on_update: OnUpdateAndDelete::NoAction,
@@ -752,13 +1850,43 @@ mod tests {
                             id: 2,
                             name: "real".to_string(),
                             the_type: Real,
+                            declared_type: "REAL".to_string(),
                             nullable: false,
                             part_of_pk: false,
+                            default_value: None,
+                            boolean_hint: false,
+                            logical_type: None,
+                            extension: None,
+                            comment: None,
+                            enum_values: None,
                         }],
                         unique: true,
+                        storage: None,
+                        statistics: None,
                     }],
                 };
 
+                let automatic_index =
+                    |table: &Table, position, origin, columns: &[usize]| AutomaticIndex {
+                        name: format!("sqlite_autoindex_{}_{position}", table.table_name),
+                        origin,
+                        columns: columns.iter().map(|c| table.columns[*c].clone()).collect(),
+                        position,
+                        storage: None,
+                        statistics: None,
+                    };
+
+                contacts.automatic_indexes = vec![automatic_index(
+                    &contacts,
+                    1,
+                    IndexOrigin::PrimaryKey,
+                    &[0, 1],
+                )];
+                book.automatic_indexes = vec![
+                    automatic_index(&book, 1, IndexOrigin::Unique, &[2]),
+                    automatic_index(&book, 2, IndexOrigin::PrimaryKey, &[0, 1]),
+                ];
+
                 let map: HashMap<String, Table> = vec![contacts, user, book]
                     .into_iter()
                     .map(|v| (v.table_name.clone(), v))
@@ -778,4 +1906,678 @@ mod tests {
 
         std::fs::remove_file(current).unwrap();
     }
+
+    #[test]
+    fn test_row_counts() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT NOT NULL);
+            CREATE INDEX user_name ON user(name);
+            INSERT INTO user (name) VALUES ('a'), ('b'), ('c');
+            CREATE TABLE \"empty table\" (id INTEGER NOT NULL PRIMARY KEY);",
+            )
+            .unwrap();
+
+        struct Parse(RowCount, HashMap<String, Option<u64>>);
+
+        impl Parser for Parse {
+            fn parse_options(&self) -> ParseOptions {
+                ParseOptions {
+                    row_counts: Some(self.0),
+                    ..Default::default()
+                }
+            }
+
+            fn process_tables(&mut self, tables: Metadata) {
+                self.1 = tables
+                    .tables
+                    .into_iter()
+                    .map(|(name, table)| (name, table.row_count))
+                    .collect();
+            }
+        }
+
+        let mut parse = Parse(RowCount::Exact, HashMap::new());
+
+        parse_connection(&connection, &mut parse);
+
+        assert_eq!(Some(&Some(3)), parse.1.get("user"));
+        assert_eq!(Some(&Some(0)), parse.1.get("empty table"));
+
+        // Make the statistics differ from the real amount to see where the number comes from
+        connection
+            .execute_batch(
+                "ANALYZE;
+            UPDATE sqlite_stat1 SET stat = '10 1' WHERE tbl = 'user';",
+            )
+            .unwrap();
+
+        let mut parse = Parse(RowCount::Statistics, HashMap::new());
+
+        parse_connection(&connection, &mut parse);
+
+        assert_eq!(Some(&Some(10)), parse.1.get("user"));
+        assert_eq!(Some(&Some(0)), parse.1.get("empty table"));
+    }
+
+    #[test]
+    fn test_query_all_tables_with_runtime_params() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY);
+            CREATE TABLE post (post_id INTEGER NOT NULL PRIMARY KEY);
+            CREATE TABLE tag (tag_id INTEGER NOT NULL PRIMARY KEY);",
+            )
+            .unwrap();
+
+        struct Parse(Vec<String>, Vec<String>);
+
+        impl Parser for Parse {
+            fn query_all_tables(&self) -> (String, Vec<Box<dyn ToSql + '_>>) {
+                let placeholders = vec!["?"; self.0.len()].join(", ");
+
+                (
+                    format!("SELECT name FROM sqlite_master WHERE name IN ({placeholders});"),
+                    self.0
+                        .iter()
+                        .map(|n| Box::new(n) as Box<dyn ToSql>)
+                        .collect(),
+                )
+            }
+
+            fn process_tables(&mut self, tables: Metadata) {
+                self.1 = tables.tables.into_keys().collect();
+                self.1.sort();
+            }
+        }
+
+        let mut parse = Parse(vec!["user".to_string(), "tag".to_string()], vec![]);
+
+        parse_connection(&connection, &mut parse);
+
+        assert_eq!(vec!["tag", "user"], parse.1);
+    }
+
+    #[test]
+    fn test_parser_hooks() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE user (
+                user_id INTEGER NOT NULL PRIMARY KEY,
+                name TEXT NOT NULL,
+                _internal BLOB
+            );
+            CREATE INDEX user_name ON user(name);",
+            )
+            .unwrap();
+
+        struct Parse(Option<Metadata>);
+
+        impl Parser for Parse {
+            fn query_columns(
+                &self,
+                connection: &Connection,
+                table_name: &str,
+                warnings: &mut Vec<ParseWarning>,
+            ) -> Vec<Column> {
+                query_columns(connection, table_name, warnings)
+                    .into_iter()
+                    .filter(|c| !c.name.starts_with('_'))
+                    .collect()
+            }
+
+            fn query_indexes(
+                &self,
+                connection: &Connection,
+                table_name: &str,
+                columns: &[Column],
+                foreign_keys: &[ForeignKey],
+                warnings: &mut Vec<ParseWarning>,
+            ) -> Vec<Index> {
+                assert_eq!(2, columns.len());
+
+                query_indexes(connection, table_name, columns, foreign_keys, warnings)
+                    .into_iter()
+                    .map(|mut i| {
+                        i.name = i.name.to_uppercase();
+                        i
+                    })
+                    .collect()
+            }
+
+            fn process_tables(&mut self, tables: Metadata) {
+                self.0 = Some(tables);
+            }
+        }
+
+        let mut parse = Parse(None);
+
+        parse_connection(&connection, &mut parse);
+
+        let metadata = parse.0.unwrap();
+        let user = metadata.table("user").unwrap();
+
+        assert!(user.column("_internal").is_none());
+        assert_eq!("USER_NAME", user.indexes[0].name);
+    }
+
+    #[test]
+    fn test_parse_table() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY);
+            CREATE TABLE post (
+                post_id INTEGER NOT NULL PRIMARY KEY,
+                user_id INTEGER NOT NULL REFERENCES user(user_id)
+            );
+            CREATE INDEX post_user_id ON post(user_id, post_id);
+            INSERT INTO user VALUES (1);
+            INSERT INTO post VALUES (1, 1), (2, 1);",
+            )
+            .unwrap();
+
+        struct Parse(Option<Metadata>, ParseOptions);
+
+        impl Parser for Parse {
+            fn parse_options(&self) -> ParseOptions {
+                self.1.clone()
+            }
+
+            fn process_tables(&mut self, tables: Metadata) {
+                self.0 = Some(tables);
+            }
+        }
+
+        let options = ParseOptions {
+            row_counts: Some(RowCount::Exact),
+            ..Default::default()
+        };
+        let post = parse_table_from_connection(&connection, "POST", options.clone()).unwrap();
+        let mut parse = Parse(None, options);
+
+        parse_connection(&connection, &mut parse);
+
+        assert_eq!(parse.0.unwrap().table("post"), Some(&post));
+        assert_eq!(Some(2), post.row_count);
+        assert_eq!(
+            Err(rusqlite::Error::QueryReturnedNoRows),
+            parse_table_from_connection(&connection, "missing", ParseOptions::default())
+        );
+
+        connection
+            .execute_batch("CREATE TABLE orphan (user_id INTEGER REFERENCES missing);")
+            .unwrap();
+
+        let strict = ParseOptions {
+            mode: ParseMode::Strict,
+            ..Default::default()
+        };
+
+        assert!(parse_table_from_connection(&connection, "post", strict.clone()).is_ok());
+        assert!(matches!(
+            parse_table_from_connection(&connection, "orphan", strict),
+            Err(rusqlite::Error::SqliteFailure(_, Some(_)))
+        ));
+    }
+
+    #[test]
+    fn test_progress() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY);
+            CREATE TABLE post (post_id INTEGER NOT NULL PRIMARY KEY);",
+            )
+            .unwrap();
+
+        struct Parse(RefCell<Vec<(usize, usize, String)>>);
+
+        impl Parser for Parse {
+            fn progress(&self, tables_done: usize, tables_total: usize, current_table: &str) {
+                self.0
+                    .borrow_mut()
+                    .push((tables_done, tables_total, current_table.to_string()));
+            }
+
+            fn process_tables(&mut self, _tables: Metadata) {}
+        }
+
+        let mut parse = Parse(RefCell::new(vec![]));
+
+        parse_connection(&connection, &mut parse);
+
+        assert_eq!(
+            vec![(0, 2, "user".to_string()), (1, 2, "post".to_string())],
+            parse.0.into_inner()
+        );
+    }
+
+    #[test]
+    fn test_table_accessors() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (
+            user_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            email TEXT
+        );
+        CREATE TABLE contacts (
+            contact_id INTEGER NOT NULL,
+            first_name TEXT NOT NULL,
+            note TEXT DEFAULT 'no autoincrement here',
+            PRIMARY KEY (contact_id, first_name)
+        );",
+        );
+        let names = |columns: Vec<&Column>| {
+            columns
+                .into_iter()
+                .map(|c| c.name.clone())
+                .collect::<Vec<_>>()
+        };
+        let user = metadata.table("user").unwrap();
+        let contacts = metadata.table("contacts").unwrap();
+
+        assert_eq!(vec!["user_id"], names(user.primary_key()));
+        assert_eq!(vec!["name", "email"], names(user.non_pk_columns()));
+        assert_eq!(vec!["email"], names(user.nullable_columns()));
+        assert_eq!(Some("name"), user.column_by_id(1).map(|c| c.name.as_str()));
+        assert_eq!(None, user.column_by_id(3));
+        assert_eq!(
+            vec!["contact_id", "first_name"],
+            names(contacts.primary_key())
+        );
+        assert!(user.has_autoincrement());
+        assert!(!contacts.has_autoincrement());
+    }
+
+    #[test]
+    fn test_cross_table_column_search() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT NOT NULL);
+        CREATE TABLE contacts (contact_id INTEGER NOT NULL PRIMARY KEY, User_Id INTEGER);
+        CREATE TABLE note (note_id INTEGER NOT NULL PRIMARY KEY, body TEXT);",
+        );
+        let names = |columns: Vec<(&Table, &Column)>| {
+            columns
+                .into_iter()
+                .map(|(t, c)| format!("{}.{}", t.table_name, c.name))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            vec!["contacts.User_Id", "user.user_id"],
+            names(metadata.find_columns("user_id"))
+        );
+        assert_eq!(
+            vec!["note.body", "user.name"],
+            names(metadata.columns_of_type(Type::Text))
+        );
+        assert!(metadata.find_columns("unknown").is_empty());
+    }
+
+    #[test]
+    fn test_hostile_identifiers() {
+        let metadata = metadata_from_sql(
+            r#"CREATE TABLE "my ""table""" (
+            [select] INTEGER NOT NULL PRIMARY KEY,
+            "it's, (a) column" TEXT UNIQUE,
+            `naïve desc` TEXT NOT NULL
+        );
+        CREATE TABLE 'order' (
+            order_id INTEGER NOT NULL PRIMARY KEY,
+            "table ref" INTEGER REFERENCES "my ""table""",
+            FOREIGN KEY(order_id) REFERENCES "my ""table"""([select])
+        );
+        CREATE UNIQUE INDEX "idx ""quoted"", (x)" ON "my ""table"""(`naïve desc` DESC, "it's, (a) column");
+        CREATE INDEX [order index] ON 'order'("table ref", lower("table ref"));"#,
+        );
+        let table = metadata.table("my \"table\"").unwrap();
+
+        assert_eq!(
+            vec![
+                (
+                    "idx \"quoted\", (x)",
+                    true,
+                    vec!["naïve desc", "it's, (a) column"]
+                ),
+                ("it's, (a) column", true, vec!["it's, (a) column"]),
+            ],
+            table
+                .indexes
+                .iter()
+                .map(|i| (
+                    i.name.as_str(),
+                    i.unique,
+                    i.columns
+                        .iter()
+                        .map(|c| c.name.as_str())
+                        .collect::<Vec<_>>()
+                ))
+                .collect::<Vec<_>>()
+        );
+
+        let order = metadata.table("order").unwrap();
+
+        // The expression is not a column of the table
+        assert_eq!("order index", order.indexes[0].name);
+        assert_eq!(1, order.indexes[0].columns.len());
+
+        // Without columns, the foreign key refers to the primary key
+        for foreign_key in &order.foreign_keys {
+            assert_eq!("my \"table\"", foreign_key.table);
+            assert_eq!("select", foreign_key.to_column[0].name);
+        }
+
+        assert_eq!(2, order.foreign_keys.len());
+    }
+
+    #[test]
+    fn test_lookup_mode() {
+        let mut metadata = metadata_from_sql(
+            "CREATE TABLE User (User_Id INTEGER NOT NULL PRIMARY KEY, Ärger TEXT);
+        CREATE INDEX User_Ärger ON User(Ärger);",
+        );
+
+        assert!(metadata.table("user").is_some());
+        assert!(metadata.table("USER").unwrap().column("user_id").is_some());
+        assert!(metadata.table("User").unwrap().column("ärger").is_none());
+
+        metadata.lookup = LookupMode::Exact;
+
+        for table in metadata.tables.values_mut() {
+            table.lookup = LookupMode::UnicodeCaseInsensitive;
+        }
+
+        assert!(metadata.table("user").is_none());
+
+        let table = metadata.table("User").unwrap();
+
+        assert!(table.column("ärger").is_some());
+        assert!(table.index("user_ärger").is_some());
+    }
+
+    #[test]
+    fn test_hash_and_ord() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT);
+        CREATE TABLE contacts (contact_id INTEGER NOT NULL PRIMARY KEY);",
+        );
+        let mut tables = metadata.tables.values().cloned().collect::<Vec<_>>();
+
+        tables.sort();
+
+        assert_eq!("contacts", tables[0].table_name);
+        assert_eq!("user", tables[1].table_name);
+
+        let user = metadata.table("user").unwrap();
+        let columns = user.columns.iter().rev().cloned().collect::<BTreeSet<_>>();
+
+        assert_eq!(user.columns, columns.into_iter().collect::<Vec<_>>());
+
+        let set = tables.iter().chain(&tables).collect::<HashSet<_>>();
+
+        assert_eq!(2, set.len());
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch("CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY);")
+            .unwrap();
+
+        let bytes = connection
+            .serialize(rusqlite::DatabaseName::Main)
+            .unwrap()
+            .to_vec();
+        let metadata = parse_bytes(&bytes).unwrap();
+
+        assert_eq!(
+            parse_sql("CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY);").tables,
+            metadata.tables
+        );
+        assert!(parse_bytes(b"not a database, but long enough to have a header").is_err());
+        assert!(parse_bytes(&[]).unwrap().tables.is_empty());
+    }
+
+    #[test]
+    fn test_logical_types() {
+        let report = parse_sql_report(
+            "CREATE TABLE event (
+                event_id UUID NOT NULL PRIMARY KEY,
+                active BOOLEAN,
+                happened DATETIME(6),
+                payload jsonb,
+                other MONEY
+            );",
+            ParseOptions::default(),
+        )
+        .unwrap();
+        let types = report.metadata.tables["event"]
+            .columns
+            .iter()
+            .map(|c| (c.the_type, c.logical_type))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                (Type::Text, Some(LogicalType::Uuid)),
+                (Type::Integer, Some(LogicalType::Boolean)),
+                (Type::Text, Some(LogicalType::DateTime)),
+                (Type::Text, Some(LogicalType::Json)),
+                (
+                    Type::Numeric {
+                        precision: None,
+                        scale: None
+                    },
+                    None
+                ),
+            ],
+            types
+        );
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_numeric_type() {
+        let numeric = |precision, scale| Type::Numeric { precision, scale };
+
+        assert_eq!(Some(numeric(None, None)), Type::from_declared("NUMERIC"));
+        assert_eq!(
+            Some(numeric(Some(10), None)),
+            Type::from_declared("decimal(10)")
+        );
+        assert_eq!(
+            Some(numeric(Some(10), Some(2))),
+            Type::from_declared("DECIMAL ( 10, 2 )")
+        );
+        assert_eq!(None, Type::from_declared("DECIMAL(10, 2, 1)"));
+        assert_eq!(None, Type::from_declared("DECIMAL(x)"));
+
+        let metadata =
+            parse_sql("CREATE TABLE price (amount DECIMAL(10,2) NOT NULL, rate NUMERIC);");
+        let price = metadata.table("price").unwrap();
+
+        assert_eq!(numeric(Some(10), Some(2)), price.columns[0].the_type);
+        assert_eq!("NUMERIC(10, 2)", price.columns[0].the_type.to_string());
+        assert_eq!(numeric(None, None), price.columns[1].the_type);
+    }
+
+    #[test]
+    fn test_covering_index() {
+        let metadata = parse_sql(
+            "CREATE TABLE user (
+                user_id INTEGER NOT NULL PRIMARY KEY,
+                email TEXT NOT NULL,
+                name TEXT,
+                age INTEGER
+            );
+            CREATE INDEX user_name_age ON user(name, age, email);
+            CREATE INDEX user_email ON user(email);
+            CREATE UNIQUE INDEX user_email_name ON user(email, name);",
+        );
+        let user = metadata.table("user").unwrap();
+        let name = |index: Option<&Index>| index.map(|i| i.name.clone());
+
+        let name_age = user.index("user_name_age").unwrap();
+
+        assert!(name_age.covers(&["NAME", "age"]));
+        assert!(!name_age.covers(&["age", "name"]));
+        assert_eq!(
+            Some("user_email".to_string()),
+            name(user.covering_index_for(&["email"]))
+        );
+        assert_eq!(
+            Some("user_email_name".to_string()),
+            name(user.covering_index_for(&["email", "name"]))
+        );
+        assert_eq!(
+            Some("user_name_age".to_string()),
+            name(user.covering_index_for(&["name", "age", "email"]))
+        );
+        assert_eq!(None, name(user.covering_index_for(&["age"])));
+        assert_eq!(None, name(user.covering_index_for(&[])));
+    }
+
+    #[test]
+    fn test_schema_sql() {
+        let metadata = parse_sql(
+            "CREATE TABLE t (id INTEGER PRIMARY KEY, a TEXT, b TEXT, c TEXT, UNIQUE (a, b));
+            CREATE UNIQUE INDEX t_live ON t(a) WHERE c IS NULL;
+            CREATE INDEX t_lower ON t(lower(b), a);",
+        );
+
+        assert_eq!(
+            "CREATE TABLE t (id INTEGER PRIMARY KEY, a TEXT, b TEXT, c TEXT, UNIQUE (a, b));
+CREATE UNIQUE INDEX t_live ON t(a) WHERE c IS NULL;
+CREATE INDEX t_lower ON t(lower(b), a);
+",
+            metadata.schema_sql()
+        );
+        assert_eq!(metadata, parse_sql(&metadata.schema_sql()));
+    }
+
+    #[test]
+    fn test_type_parameters() {
+        let metadata = parse_sql(
+            "CREATE TABLE user (
+                name VARCHAR(255),
+                country CHARACTER (2),
+                hash BINARY(32),
+                amount DECIMAL(10, 2),
+                offset_ INT(-1),
+                id INTEGER
+            );",
+        );
+        let column = |name: &str| metadata.table("user").unwrap().column(name).unwrap();
+
+        assert_eq!(vec![255], column("name").type_parameters());
+        assert_eq!(Some(255), column("name").max_length());
+        assert_eq!(Some(2), column("country").max_length());
+        assert_eq!(Some(32), column("hash").max_length());
+        assert_eq!(vec![10, 2], column("amount").type_parameters());
+        assert_eq!(None, column("amount").max_length());
+        assert_eq!(vec![-1], column("offset_").type_parameters());
+        assert!(column("id").type_parameters().is_empty());
+    }
+
+    #[test]
+    fn test_adversarial_names() {
+        // Names with quotes, parameter markers and SQL, every internal query must quote or bind them
+        let ddl = r#"CREATE TABLE "it's ""quoted""" (
+                "a""b" INTEGER NOT NULL PRIMARY KEY,
+                [select] TEXT NOT NULL UNIQUE,
+                `with space` INTEGER
+            );
+            CREATE TABLE "x'); DROP TABLE y; --" (
+                "?" INTEGER REFERENCES "it's ""quoted""" ("a""b"),
+                ":name" TEXT,
+                "[br]" BLOB
+            );
+            CREATE INDEX "idx ""1""" ON "x'); DROP TABLE y; --" ("?", ":name");
+            CREATE TABLE y (id INTEGER);"#;
+        let connection = Connection::open_in_memory().unwrap();
+        let column_names = |table: &Table| {
+            table
+                .columns
+                .iter()
+                .map(|c| c.name.clone())
+                .collect::<Vec<_>>()
+        };
+
+        connection.execute_batch(ddl).unwrap();
+        crate::fake::FakeData::new(&parse_sql(ddl))
+            .rows(3)
+            .insert(&connection)
+            .unwrap();
+        connection.execute_batch("ANALYZE;").unwrap();
+
+        for row_counts in [RowCount::Exact, RowCount::Statistics] {
+            let metadata = metadata_from_connection(
+                &connection,
+                ParseOptions {
+                    row_counts: Some(row_counts),
+                    samples: Some(5),
+                    profile: true,
+                    storage_stats: true,
+                    integrity_check: Some(crate::IntegrityCheck::Full),
+                    detect_booleans: Some(10),
+                    ..Default::default()
+                },
+            );
+            let quoted = metadata.table("it's \"quoted\"").unwrap();
+            let other = metadata.table("x'); DROP TABLE y; --").unwrap();
+
+            assert_eq!(Some(3), quoted.row_count);
+            assert_eq!(vec!["a\"b", "select", "with space"], column_names(quoted));
+            assert_eq!(vec!["?", ":name", "[br]"], column_names(other));
+            assert_eq!("idx \"1\"", other.indexes[0].name);
+            assert_eq!("a\"b", other.foreign_keys[0].to_column[0].name);
+            assert_eq!(3, metadata.samples[&quoted.table_name].len());
+            assert!(parse_sql(&metadata.schema_sql()).diff(&metadata).is_empty());
+
+            crate::audit::type_audit(&connection, &metadata, 10);
+            crate::inference::infer_foreign_keys(&connection, &metadata);
+            crate::inference::candidate_keys(&connection, &metadata, Default::default());
+        }
+
+        assert_eq!(
+            vec!["a\"b", "select", "with space"],
+            column_names(
+                &parse_table_from_connection(&connection, "IT'S \"QUOTED\"", Default::default())
+                    .unwrap()
+            )
+        );
+
+        // A migration that rebuilds a table and adds a column
+        let old = metadata_from_connection(&connection, Default::default());
+        let new = parse_sql(&ddl.replace("\"?\" INTEGER", "\"?\" TEXT").replace(
+            "`with space` INTEGER",
+            "`with space` INTEGER, \"new \"\"\" TEXT",
+        ));
+
+        old.migration_to(&new).apply(&connection).unwrap();
+
+        assert!(metadata_from_connection(&connection, Default::default())
+            .diff(&new)
+            .is_empty());
+        assert_eq!(
+            3,
+            metadata_from_connection(&connection, Default::default())
+                .tables
+                .values()
+                .filter(|t| !t.is_internal())
+                .count(),
+            "table y is not dropped"
+        );
+    }
 }
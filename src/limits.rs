@@ -0,0 +1,259 @@
+//! Compares the schema with the limits of SQLite, e.g. of an embedded build that is compiled
+//! with lower limits than the defaults
+
+#[cfg(feature = "rusqlite")]
+use rusqlite::limits::Limit;
+#[cfg(feature = "rusqlite")]
+use rusqlite::Connection;
+
+use crate::Metadata;
+
+/// The limits to check against, the defaults are the defaults of SQLite
+#[derive(Debug, PartialEq, Clone)]
+pub struct Limits {
+    /// `SQLITE_MAX_COLUMN`, the columns of a table and of a view
+    pub max_columns: usize,
+    /// The columns of an index, `SQLITE_MAX_COLUMN` as well in SQLite
+    pub max_index_columns: usize,
+    /// The length of the names of tables, columns, indexes, views and triggers
+    /// SQLite itself has no limit, `None` skips the check.
+    pub max_identifier_length: Option<usize>,
+    /// `SQLITE_MAX_SQL_LENGTH`, the bytes of a CREATE statement
+    pub max_sql_length: usize,
+    /// `SQLITE_MAX_ATTACHED`, the databases that can be attached to a connection
+    pub max_attached: usize,
+    /// Values from this fraction of a limit are reported, to see what is close to a limit
+    pub warning_threshold: f64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_columns: 2000,
+            max_index_columns: 2000,
+            max_identifier_length: None,
+            max_sql_length: 1_000_000_000,
+            max_attached: 10,
+            warning_threshold: 0.9,
+        }
+    }
+}
+
+impl Limits {
+    /// The limits of the connection, which can be lower than the compiled limits
+    #[cfg(feature = "rusqlite")]
+    pub fn from_connection(connection: &Connection) -> Self {
+        let limit = |limit| connection.limit(limit).max(0) as usize;
+
+        Self {
+            max_columns: limit(Limit::SQLITE_LIMIT_COLUMN),
+            max_index_columns: limit(Limit::SQLITE_LIMIT_COLUMN),
+            max_sql_length: limit(Limit::SQLITE_LIMIT_SQL_LENGTH),
+            max_attached: limit(Limit::SQLITE_LIMIT_ATTACHED),
+            ..Default::default()
+        }
+    }
+
+    /// Checks a value, `None` if it is below the warning threshold
+    fn check(
+        &self,
+        object: &str,
+        kind: LimitKind,
+        value: usize,
+        max: usize,
+    ) -> Option<LimitFinding> {
+        (value as f64 >= max as f64 * self.warning_threshold).then(|| LimitFinding {
+            object: object.to_string(),
+            kind,
+            value,
+            max,
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Eq, Hash, PartialOrd, Ord)]
+pub enum LimitKind {
+    Columns,
+    IndexColumns,
+    IdentifierLength,
+    SqlLength,
+    AttachedDatabases,
+}
+
+/// A value that is close to or over a limit
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct LimitFinding {
+    /// The name of the table, index, view or trigger, or the qualified name of a column
+    pub object: String,
+    pub kind: LimitKind,
+    pub value: usize,
+    pub max: usize,
+}
+
+impl LimitFinding {
+    /// Checks if the value is over the limit, otherwise it is only close to it
+    pub fn is_exceeded(&self) -> bool {
+        self.value > self.max
+    }
+}
+
+/// The values of the schema that are close to or over a limit, by table sorted by name followed by
+/// the views and triggers
+pub fn check_limits(metadata: &Metadata, limits: &Limits) -> Vec<LimitFinding> {
+    let mut findings = vec![];
+    let identifier = |findings: &mut Vec<LimitFinding>, object: &str, name: &str| {
+        if let Some(max) = limits.max_identifier_length {
+            findings.extend(limits.check(object, LimitKind::IdentifierLength, name.len(), max));
+        }
+    };
+
+    for table in metadata.sorted_tables() {
+        let name = &table.table_name;
+
+        identifier(&mut findings, name, name);
+        findings.extend(limits.check(
+            name,
+            LimitKind::Columns,
+            table.columns.len(),
+            limits.max_columns,
+        ));
+        findings.extend(limits.check(
+            name,
+            LimitKind::SqlLength,
+            table.create_sql.len(),
+            limits.max_sql_length,
+        ));
+
+        for column in &table.columns {
+            identifier(
+                &mut findings,
+                &format!("{name}.{}", column.name),
+                &column.name,
+            );
+        }
+
+        for index in &table.indexes {
+            identifier(&mut findings, &index.name, &index.name);
+            findings.extend(limits.check(
+                &index.name,
+                LimitKind::IndexColumns,
+                index.columns.len(),
+                limits.max_index_columns,
+            ));
+        }
+    }
+
+    for view in &metadata.views {
+        identifier(&mut findings, &view.name, &view.name);
+        findings.extend(limits.check(
+            &view.name,
+            LimitKind::Columns,
+            view.columns.len(),
+            limits.max_columns,
+        ));
+        findings.extend(limits.check(
+            &view.name,
+            LimitKind::SqlLength,
+            view.sql.len(),
+            limits.max_sql_length,
+        ));
+    }
+
+    for trigger in &metadata.triggers {
+        identifier(&mut findings, &trigger.name, &trigger.name);
+        findings.extend(limits.check(
+            &trigger.name,
+            LimitKind::SqlLength,
+            trigger.sql.len(),
+            limits.max_sql_length,
+        ));
+    }
+
+    findings
+}
+
+/// Checks the amount of databases that are attached to the connection, the object of the finding
+/// is `main`
+#[cfg(feature = "rusqlite")]
+pub fn check_attached(connection: &Connection, limits: &Limits) -> Option<LimitFinding> {
+    let attached: usize = connection
+        .query_row(
+            "SELECT COUNT(*) FROM pragma_database_list WHERE name NOT IN ('main', 'temp');",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+
+    limits.check(
+        "main",
+        LimitKind::AttachedDatabases,
+        attached,
+        limits.max_attached,
+    )
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::limits::{check_limits, LimitKind, Limits};
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_check_limits() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER PRIMARY KEY, name TEXT, email TEXT, a_very_long_column TEXT);
+            CREATE INDEX user_name_email ON user (name, email);
+            CREATE TABLE tag (tag TEXT);",
+        );
+        let limits = Limits {
+            max_columns: 3,
+            max_index_columns: 2,
+            max_identifier_length: Some(16),
+            ..Default::default()
+        };
+        let findings = check_limits(&metadata, &limits);
+
+        assert_eq!(
+            vec![
+                ("user", LimitKind::Columns, 4, true),
+                (
+                    "user.a_very_long_column",
+                    LimitKind::IdentifierLength,
+                    18,
+                    true
+                ),
+                ("user_name_email", LimitKind::IdentifierLength, 15, false),
+                ("user_name_email", LimitKind::IndexColumns, 2, false),
+            ],
+            findings
+                .iter()
+                .map(|f| (f.object.as_str(), f.kind, f.value, f.is_exceeded()))
+                .collect::<Vec<_>>()
+        );
+        assert!(check_limits(&metadata, &Limits::default()).is_empty());
+    }
+
+    #[cfg(feature = "rusqlite")]
+    #[test]
+    fn test_limits_from_connection() {
+        use rusqlite::limits::Limit;
+        use rusqlite::Connection;
+
+        use crate::limits::check_attached;
+
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection.set_limit(Limit::SQLITE_LIMIT_ATTACHED, 1);
+        connection
+            .execute_batch("ATTACH ':memory:' AS other;")
+            .unwrap();
+
+        let limits = Limits::from_connection(&connection);
+
+        assert_eq!(2000, limits.max_columns);
+        assert_eq!(1, limits.max_attached);
+        assert_eq!(
+            Some(1),
+            check_attached(&connection, &limits).map(|f| f.value)
+        );
+    }
+}
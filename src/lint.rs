@@ -0,0 +1,409 @@
+//! Lint rules that flag suspicious patterns in a parsed schema
+//! Example:
+//!
+//! ```
+//! use sqlite_parser::lint::{builtin_rules, lint};
+//! use sqlite_parser::Metadata;
+//!
+//! fn check(metadata: &Metadata) {
+//!     for finding in lint(metadata, &builtin_rules()) {
+//!         println!("{}: {}", finding.rule, finding.message);
+//!     }
+//! }
+//! ```
+
+use crate::inflection::Inflector;
+use crate::{Metadata, OnUpdateAndDelete, Table};
+
+/// A single problem found by a lint rule
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct Finding {
+    /// The name of the rule that produced this finding
+    pub rule: &'static str,
+    /// The table the finding is about
+    pub table: String,
+    /// The column the finding is about, if any
+    pub column: Option<String>,
+    /// Human readable description of the problem
+    pub message: String,
+}
+
+/// Implement this trait to write your own lint rule
+pub trait Rule {
+    /// The name of the rule, this is copied in every finding
+    fn name(&self) -> &'static str;
+
+    /// Checks a single table, pushing a finding for every problem found
+    fn check(&self, metadata: &Metadata, table: &Table, findings: &mut Vec<Finding>);
+}
+
+/// Runs the rules against every table, the findings are sorted by table name
+pub fn lint(metadata: &Metadata, rules: &[Box<dyn Rule + '_>]) -> Vec<Finding> {
+    let tables = metadata.sorted_tables();
+
+    let mut findings = vec![];
+
+    for table in tables {
+        for rule in rules {
+            rule.check(metadata, table, &mut findings);
+        }
+    }
+
+    findings
+}
+
+/// The rules that don't need any configuration
+pub fn builtin_rules() -> Vec<Box<dyn Rule>> {
+    vec![
+        Box::new(NullableUniqueColumn),
+        Box::new(ForeignKeyNullability),
+    ]
+}
+
+/// Flags nullable columns in unique indexes
+/// SQLite treats every NULL as distinct, so the index doesn't prevent duplicate NULL values
+pub struct NullableUniqueColumn;
+
+impl Rule for NullableUniqueColumn {
+    fn name(&self) -> &'static str {
+        "nullable_unique_column"
+    }
+
+    fn check(&self, _metadata: &Metadata, table: &Table, findings: &mut Vec<Finding>) {
+        for index in table.indexes.iter().filter(|i| i.unique) {
+            for column in index.columns.iter().filter(|c| c.nullable) {
+                findings.push(Finding {
+                    rule: self.name(),
+                    table: table.table_name.clone(),
+                    column: Some(column.name.clone()),
+                    message: format!(
+                        "Column {} is nullable but part of unique index {}, multiple rows can hold NULL",
+                        column.name, index.name
+                    ),
+                });
+            }
+        }
+    }
+}
+
+/// Flags NOT NULL columns without a default that were added to a table that already existed in
+/// the previous version of the schema, existing rows have no value to fill the column with
+pub struct NotNullWithoutDefault<'a> {
+    /// The schema before the change
+    pub previous: &'a Metadata,
+}
+
+impl<'a> Rule for NotNullWithoutDefault<'a> {
+    fn name(&self) -> &'static str {
+        "not_null_without_default"
+    }
+
+    fn check(&self, _metadata: &Metadata, table: &Table, findings: &mut Vec<Finding>) {
+        let previous = match self.previous.table(&table.table_name) {
+            Some(previous) => previous,
+            // A new table has no existing rows
+            None => return,
+        };
+
+        for column in &table.columns {
+            if column.nullable || column.default_value.is_some() {
+                continue;
+            }
+
+            if previous.column(&column.name).is_some() {
+                continue;
+            }
+
+            findings.push(Finding {
+                rule: self.name(),
+                table: table.table_name.clone(),
+                column: Some(column.name.clone()),
+                message: format!(
+                    "Column {} is NOT NULL without a default but was added to existing table {}",
+                    column.name, table.table_name
+                ),
+            });
+        }
+    }
+}
+
+/// Flags foreign keys where the ON DELETE action can't be carried out because of the nullability
+/// of the referring columns, e.g. ON DELETE SET NULL on a NOT NULL column
+pub struct ForeignKeyNullability;
+
+impl Rule for ForeignKeyNullability {
+    fn name(&self) -> &'static str {
+        "foreign_key_nullability"
+    }
+
+    fn check(&self, _metadata: &Metadata, table: &Table, findings: &mut Vec<Finding>) {
+        for foreign_key in &table.foreign_keys {
+            for column in &foreign_key.from_column {
+                let problem = match foreign_key.on_delete {
+                    OnUpdateAndDelete::SetNull if !column.nullable => "ON DELETE SET NULL",
+                    OnUpdateAndDelete::SetDefault
+                        if !column.nullable && column.default_value.is_none() =>
+                    {
+                        "ON DELETE SET DEFAULT without a default"
+                    }
+                    _ => continue,
+                };
+
+                findings.push(Finding {
+                    rule: self.name(),
+                    table: table.table_name.clone(),
+                    column: Some(column.name.clone()),
+                    message: format!(
+                        "Column {} is NOT NULL but the foreign key to {} uses {problem}",
+                        column.name, foreign_key.table
+                    ),
+                });
+            }
+        }
+    }
+}
+
+/// The casing an identifier should be written in
+#[derive(Debug, PartialEq, Copy, Clone, Eq)]
+pub enum Case {
+    /// e.g. `user_contact`
+    Snake,
+    /// e.g. `userContact`
+    Camel,
+    /// e.g. `UserContact`
+    Pascal,
+}
+
+impl Case {
+    /// Checks if the identifier is written in this casing
+    pub fn matches(&self, identifier: &str) -> bool {
+        let mut chars = identifier.chars();
+        let first = match chars.next() {
+            Some(first) => first,
+            None => return false,
+        };
+
+        match self {
+            Case::Snake => {
+                first.is_ascii_lowercase()
+                    && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+                    && !identifier.ends_with('_')
+                    && !identifier.contains("__")
+            }
+            Case::Camel => first.is_ascii_lowercase() && chars.all(|c| c.is_ascii_alphanumeric()),
+            Case::Pascal => first.is_ascii_uppercase() && chars.all(|c| c.is_ascii_alphanumeric()),
+        }
+    }
+}
+
+/// Whether table names should be singular (`user`) or plural (`users`)
+#[derive(Debug, PartialEq, Copy, Clone, Eq)]
+pub enum Plurality {
+    Singular,
+    Plural,
+}
+
+impl Plurality {
+    /// Checks the last word of the table name with the default `Inflector`, uncountable words like
+    /// `metadata` are both singular and plural
+    pub fn matches(&self, table_name: &str) -> bool {
+        let inflector = Inflector::default();
+        let lower_cased = table_name.to_lowercase();
+        let singular = inflector.singularize(&lower_cased);
+
+        match self {
+            Plurality::Singular => singular == lower_cased,
+            Plurality::Plural => inflector.pluralize(&singular) == lower_cased,
+        }
+    }
+}
+
+/// Checks table, column and index names against the configured conventions
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct NamingConvention {
+    /// The casing of table, column and index names
+    pub case: Case,
+    /// The plurality of table names, `None` allows both
+    pub tables: Option<Plurality>,
+    /// The prefix the name of every CREATE INDEX statement should start with, e.g. `idx_`
+    pub index_prefix: Option<String>,
+}
+
+impl Default for NamingConvention {
+    fn default() -> Self {
+        Self {
+            case: Case::Snake,
+            tables: None,
+            index_prefix: None,
+        }
+    }
+}
+
+impl Rule for NamingConvention {
+    fn name(&self) -> &'static str {
+        "naming_convention"
+    }
+
+    fn check(&self, _metadata: &Metadata, table: &Table, findings: &mut Vec<Finding>) {
+        let mut push = |column: Option<&str>, message: String| {
+            findings.push(Finding {
+                rule: self.name(),
+                table: table.table_name.clone(),
+                column: column.map(|c| c.to_string()),
+                message,
+            })
+        };
+
+        if !self.case.matches(&table.table_name) {
+            push(
+                None,
+                format!(
+                    "Table name {} is not written in {:?} case",
+                    table.table_name, self.case
+                ),
+            );
+        }
+
+        if let Some(plurality) = self.tables {
+            if !plurality.matches(&table.table_name) {
+                push(
+                    None,
+                    format!("Table name {} is not {:?}", table.table_name, plurality),
+                );
+            }
+        }
+
+        for column in &table.columns {
+            if !self.case.matches(&column.name) {
+                push(
+                    Some(&column.name),
+                    format!(
+                        "Column name {} is not written in {:?} case",
+                        column.name, self.case
+                    ),
+                );
+            }
+        }
+
+        // The indexes of UNIQUE constraints are named after the column, they can't be renamed
+        for index in table
+            .indexes
+            .iter()
+            .filter(|i| table.index_sql.contains_key(&i.name))
+        {
+            if !self.case.matches(&index.name) {
+                push(
+                    None,
+                    format!(
+                        "Index name {} is not written in {:?} case",
+                        index.name, self.case
+                    ),
+                );
+            }
+
+            if let Some(prefix) = &self.index_prefix {
+                if !index.name.starts_with(prefix.as_str()) {
+                    push(
+                        None,
+                        format!("Index name {} doesn't start with {prefix}", index.name),
+                    );
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::lint::{
+        builtin_rules, lint, Case, NamingConvention, NotNullWithoutDefault, Plurality, Rule,
+    };
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_nullability_rules() {
+        let previous = metadata_from_sql(
+            "CREATE TABLE user (
+            user_id INTEGER NOT NULL PRIMARY KEY
+        );",
+        );
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (
+            user_id INTEGER NOT NULL PRIMARY KEY,
+            email TEXT UNIQUE,
+            name TEXT NOT NULL,
+            age INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE TABLE contacts (
+            contact_id INTEGER NOT NULL PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            FOREIGN KEY(user_id) REFERENCES user(user_id) ON DELETE SET NULL
+        );",
+        );
+
+        let findings = lint(&metadata, &builtin_rules());
+
+        assert_eq!(
+            vec![
+                ("foreign_key_nullability", "contacts", "user_id"),
+                ("nullable_unique_column", "user", "email")
+            ],
+            findings
+                .iter()
+                .map(|f| (f.rule, f.table.as_str(), f.column.as_deref().unwrap()))
+                .collect::<Vec<_>>()
+        );
+
+        let rules: Vec<Box<dyn Rule + '_>> = vec![Box::new(NotNullWithoutDefault {
+            previous: &previous,
+        })];
+        let findings = lint(&metadata, &rules);
+
+        assert_eq!(1, findings.len());
+        assert_eq!(Some("name"), findings[0].column.as_deref());
+    }
+
+    #[test]
+    fn test_naming_convention() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE users (
+            user_id INTEGER NOT NULL PRIMARY KEY,
+            firstName TEXT NOT NULL,
+            email TEXT UNIQUE
+        );
+        CREATE TABLE address (
+            address_id INTEGER NOT NULL PRIMARY KEY,
+            user_id INTEGER NOT NULL
+        );
+        CREATE INDEX address_user_id ON address(user_id);
+        CREATE INDEX idx_users_first_name ON users(firstName);",
+        );
+        let rules: Vec<Box<dyn Rule>> = vec![Box::new(NamingConvention {
+            case: Case::Snake,
+            tables: Some(Plurality::Plural),
+            index_prefix: Some("idx_".to_string()),
+        })];
+
+        let messages = lint(&metadata, &rules)
+            .into_iter()
+            .map(|f| f.message)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            vec![
+                "Table name address is not Plural",
+                "Index name address_user_id doesn't start with idx_",
+                "Column name firstName is not written in Snake case",
+            ],
+            messages
+        );
+
+        assert!(Plurality::Singular.matches("status"));
+        assert!(!Plurality::Plural.matches("status"));
+        assert!(Plurality::Plural.matches("user_People"));
+        assert!(!Plurality::Singular.matches("houses"));
+        assert!(Plurality::Singular.matches("metadata") && Plurality::Plural.matches("metadata"));
+        assert!(Plurality::Singular.matches("café") && Plurality::Plural.matches("cafés"));
+        assert!(!Plurality::Plural.matches("ü"));
+    }
+}
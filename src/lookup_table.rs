@@ -0,0 +1,265 @@
+//! Finds small static tables that other tables refer to, like `status (status_id, name)`, so code
+//! generators can emit their rows as an enum instead of a foreign key
+//!
+//! A table is a candidate when other tables refer to a single column of it, it has a few columns
+//! and a few rows, and its key or one of its other columns is text to name the variants. The
+//! heuristic can be overruled per table with `LookupTableOptions::confirmed` and
+//! `LookupTableOptions::rejected`.
+
+use rusqlite::Connection;
+
+use crate::sample::Value;
+use crate::{quote_identifier, Affinity, Column, Metadata, Table};
+
+/// The column names that are preferred as label of a variant, in order of preference
+const LABEL_NAMES: [&str; 5] = ["name", "label", "code", "title", "description"];
+
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct LookupTableOptions {
+    /// Tables with more rows are no candidate
+    pub max_rows: u64,
+    /// Tables with more columns are no candidate
+    pub max_columns: usize,
+    /// Tables that are always returned when they have a key, regardless of the heuristic and the
+    /// amount of rows
+    pub confirmed: Vec<String>,
+    /// Tables that are never returned
+    pub rejected: Vec<String>,
+}
+
+impl Default for LookupTableOptions {
+    fn default() -> Self {
+        Self {
+            max_rows: 50,
+            max_columns: 3,
+            confirmed: vec![],
+            rejected: vec![],
+        }
+    }
+}
+
+/// A table of which the rows can be an enum
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct LookupTable {
+    pub table: String,
+    /// The column that the foreign keys refer to, or the primary key column
+    pub key_column: String,
+    /// The text column that names the variants, `None` if the key is the name
+    pub label_column: Option<String>,
+    /// The rows, sorted by key
+    pub variants: Vec<EnumVariant>,
+    /// The tables with a foreign key to the table, sorted by name
+    pub referenced_by: Vec<String>,
+    /// Listed in `LookupTableOptions::confirmed`
+    pub confirmed: bool,
+}
+
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct EnumVariant {
+    /// The value of the key column
+    pub key: Value,
+    /// The value of the label column as text
+    pub label: Option<String>,
+}
+
+impl EnumVariant {
+    /// The label, or the key when it's text or an integer
+    pub fn name(&self) -> Option<String> {
+        match (&self.label, &self.key) {
+            (Some(label), _) => Some(label.clone()),
+            (None, Value::Text(text)) => Some(text.clone()),
+            (None, Value::Integer(i)) => Some(i.to_string()),
+            _ => None,
+        }
+    }
+}
+
+/// The lookup tables of the database, sorted by name
+pub fn lookup_tables(
+    connection: &Connection,
+    metadata: &Metadata,
+    options: &LookupTableOptions,
+) -> Vec<LookupTable> {
+    let is_listed = |names: &[String], table: &Table| {
+        names
+            .iter()
+            .any(|n| metadata.lookup.matches(n, &table.table_name))
+    };
+    let mut lookup_tables = vec![];
+
+    for table in metadata.sorted_tables() {
+        if is_listed(&options.rejected, table) {
+            continue;
+        }
+
+        let confirmed = is_listed(&options.confirmed, table);
+        let referencing = metadata
+            .referencing(&table.table_name)
+            .into_iter()
+            .filter(|(t, _)| t.table_name != table.table_name)
+            .collect::<Vec<_>>();
+        let referenced_columns = referencing
+            .iter()
+            .filter_map(|(_, f)| match &f.to_column[..] {
+                [column] => Some(column.name.to_lowercase()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let key_column = match &referenced_columns[..] {
+            [first, rest @ ..] if rest.iter().all(|c| c == first) => table.column(first),
+            _ => match &table.primary_key()[..] {
+                [column] if confirmed => table.column(&column.name),
+                _ => None,
+            },
+        };
+        let Some(key_column) = key_column else {
+            continue;
+        };
+        let label_column = label_column(table, key_column);
+
+        if !confirmed
+            && (table.columns.len() > options.max_columns
+                || (label_column.is_none() && key_column.affinity() != Affinity::Text))
+        {
+            continue;
+        }
+
+        let limit = if confirmed {
+            String::new()
+        } else {
+            format!(" LIMIT {}", options.max_rows + 1)
+        };
+        let variants = query_variants(connection, table, key_column, label_column, &limit);
+
+        if !confirmed && (variants.is_empty() || variants.len() as u64 > options.max_rows) {
+            continue;
+        }
+
+        let mut referenced_by = referencing
+            .iter()
+            .map(|(t, _)| t.table_name.clone())
+            .collect::<Vec<_>>();
+
+        referenced_by.dedup();
+
+        lookup_tables.push(LookupTable {
+            table: table.table_name.clone(),
+            key_column: key_column.name.clone(),
+            label_column: label_column.map(|c| c.name.clone()),
+            variants,
+            referenced_by,
+            confirmed,
+        });
+    }
+
+    lookup_tables
+}
+
+/// The text column other than the key with a name of `LABEL_NAMES`, or else the first one
+fn label_column<'a>(table: &'a Table, key_column: &Column) -> Option<&'a Column> {
+    let text_columns = table
+        .columns
+        .iter()
+        .filter(|c| c.name != key_column.name && c.affinity() == Affinity::Text)
+        .collect::<Vec<_>>();
+
+    LABEL_NAMES
+        .iter()
+        .find_map(|name| {
+            text_columns
+                .iter()
+                .find(|c| c.name.eq_ignore_ascii_case(name))
+        })
+        .or_else(|| text_columns.first())
+        .copied()
+}
+
+fn query_variants(
+    connection: &Connection,
+    table: &Table,
+    key_column: &Column,
+    label_column: Option<&Column>,
+    limit: &str,
+) -> Vec<EnumVariant> {
+    let key = quote_identifier(&key_column.name);
+    let label = label_column
+        .map(|c| format!("CAST({} AS TEXT)", quote_identifier(&c.name)))
+        .unwrap_or_else(|| "NULL".to_string());
+    let mut stmt = connection
+        .prepare(&format!(
+            "SELECT {key}, {label} FROM {} ORDER BY {key}{limit};",
+            quote_identifier(&table.table_name)
+        ))
+        .unwrap();
+
+    stmt.query_map([], |row| {
+        Ok(EnumVariant {
+            key: Value::from_stored(row.get_ref(0)?, key_column.the_type),
+            label: row.get(1)?,
+        })
+    })
+    .unwrap()
+    .collect::<rusqlite::Result<_>>()
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::lookup_table::{lookup_tables, LookupTableOptions};
+    use crate::metadata_from_connection;
+    use crate::sample::Value;
+
+    #[test]
+    fn test_lookup_tables() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE status (status_id INTEGER NOT NULL PRIMARY KEY, name TEXT NOT NULL);
+                CREATE TABLE country (code TEXT NOT NULL PRIMARY KEY);
+                CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT, country TEXT REFERENCES country);
+                CREATE TABLE task (
+                    task_id INTEGER NOT NULL PRIMARY KEY,
+                    status_id INTEGER NOT NULL REFERENCES status,
+                    user_id INTEGER REFERENCES user
+                );
+                INSERT INTO status VALUES (2, 'done'), (1, 'new');
+                INSERT INTO country VALUES ('NL');
+                INSERT INTO user VALUES (1, 'a', 'NL');",
+            )
+            .unwrap();
+
+        let metadata = metadata_from_connection(&connection, Default::default());
+        let tables = lookup_tables(&connection, &metadata, &LookupTableOptions::default());
+
+        assert_eq!(
+            vec!["country", "status", "user"],
+            tables.iter().map(|t| &t.table).collect::<Vec<_>>()
+        );
+        assert_eq!(Value::Integer(1), tables[1].variants[0].key);
+        assert_eq!(Some("new".to_string()), tables[1].variants[0].name());
+        assert_eq!(vec!["task"], tables[1].referenced_by);
+        assert_eq!(Some("NL".to_string()), tables[0].variants[0].name());
+
+        let tables = lookup_tables(
+            &connection,
+            &metadata,
+            &LookupTableOptions {
+                max_rows: 1,
+                confirmed: vec!["STATUS".to_string()],
+                rejected: vec!["user".to_string()],
+                ..Default::default()
+            },
+        );
+
+        assert_eq!(
+            vec![("country", false), ("status", true)],
+            tables
+                .iter()
+                .map(|t| (t.table.as_str(), t.confirmed))
+                .collect::<Vec<_>>()
+        );
+    }
+}
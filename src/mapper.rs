@@ -0,0 +1,281 @@
+//! Custom mapping of the declared types, e.g. for code generators with project specific type
+//! conventions
+//!
+//! ```
+//! # #[cfg(feature = "rusqlite")]
+//! # {
+//! use sqlite_parser::mapper::{Extension, MappedType, SharedTypeMapper};
+//! use sqlite_parser::{parse_sql_with_options, Column, ParseOptions, Type};
+//!
+//! #[derive(Debug)]
+//! struct RustType(&'static str);
+//!
+//! let options = ParseOptions {
+//!     type_mapper: Some(SharedTypeMapper::new(|_table: &str, column: &Column| {
+//!         match column.declared_type.to_lowercase().as_str() {
+//!             "money" => Some(MappedType::Builtin(Type::Integer)),
+//!             "point" => Some(MappedType::Extension(Extension::new(RustType("geo::Point")))),
+//!             _ => None,
+//!         }
+//!     })),
+//!     ..Default::default()
+//! };
+//! let metadata = parse_sql_with_options("CREATE TABLE place (location POINT);", options);
+//! let column = metadata.table("place").unwrap().column("location").unwrap();
+//! let rust_type = column.extension.as_ref().unwrap().downcast_ref::<RustType>().unwrap();
+//!
+//! assert_eq!("geo::Point", rust_type.0);
+//! # }
+//! ```
+
+use std::any::Any;
+use std::cmp::Ordering;
+use std::fmt::{Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::panic::{RefUnwindSafe, UnwindSafe};
+use std::sync::Arc;
+
+use crate::report::ParseWarning;
+use crate::{Column, Type};
+
+/// Converts the declared type of a column, consulted before the built-in mapping
+/// The column is built with the built-in mapping, so the name, declared type and constraints
+/// can be used to decide.
+pub trait TypeMapper: Send + Sync {
+    /// The type of the column, `None` keeps the built-in mapping
+    fn map_type(&self, table_name: &str, column: &Column) -> Option<MappedType>;
+}
+
+impl<F> TypeMapper for F
+where
+    F: Fn(&str, &Column) -> Option<MappedType> + Send + Sync,
+{
+    fn map_type(&self, table_name: &str, column: &Column) -> Option<MappedType> {
+        self(table_name, column)
+    }
+}
+
+/// The result of a `TypeMapper`
+#[derive(Debug, PartialEq, Clone, Eq, Hash, PartialOrd, Ord)]
+pub enum MappedType {
+    /// Replaces `Column::the_type` and clears `Column::logical_type`
+    Builtin(Type),
+    /// Sets `Column::extension`, the type of the built-in mapping is kept
+    Extension(Extension),
+}
+
+/// A value of any type that a `TypeMapper` attaches to a column, e.g. the resolved type of the
+/// target language of a code generator
+/// Extensions are compared, hashed and serialized by the `Debug` output of the value, a
+/// deserialized extension holds that output as a `String`.
+#[derive(Clone)]
+pub struct Extension {
+    value: Arc<dyn Any + Send + Sync>,
+    description: String,
+}
+
+impl Extension {
+    pub fn new<T: Any + Send + Sync + RefUnwindSafe + Debug>(value: T) -> Self {
+        Self {
+            description: format!("{value:?}"),
+            value: Arc::new(value),
+        }
+    }
+
+    /// The value, `None` if it has another type
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.value.downcast_ref()
+    }
+
+    /// The `Debug` output of the value
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+/// A `TypeMapper` that can be stored in `ParseOptions`
+/// Options are equal when they share the same mapper.
+#[derive(Clone)]
+pub struct SharedTypeMapper(pub Arc<dyn TypeMapper>);
+
+impl SharedTypeMapper {
+    pub fn new(mapper: impl TypeMapper + 'static) -> Self {
+        Self(Arc::new(mapper))
+    }
+
+    /// Maps the columns of the table
+    /// Columns with an unknown type that are mapped are removed from the warnings.
+    pub(crate) fn map_columns(
+        &self,
+        table_name: &str,
+        columns: &mut [Column],
+        warnings: &mut Vec<ParseWarning>,
+    ) {
+        for column in columns {
+            let Some(mapped) = self.0.map_type(table_name, column) else {
+                continue;
+            };
+
+            match mapped {
+                MappedType::Builtin(the_type) => {
+                    column.the_type = the_type;
+                    column.logical_type = None;
+                }
+                MappedType::Extension(extension) => column.extension = Some(extension),
+            }
+
+            warnings.retain(|w| {
+                !matches!(w, ParseWarning::UnknownType { table, column: name, .. }
+                    if table == table_name && name == &column.name)
+            });
+        }
+    }
+}
+
+impl Debug for SharedTypeMapper {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SharedTypeMapper")
+    }
+}
+
+impl PartialEq for SharedTypeMapper {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for SharedTypeMapper {}
+
+impl Debug for Extension {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.description)
+    }
+}
+
+// The values are `RefUnwindSafe` and only shared, so the metadata stays unwind safe
+impl UnwindSafe for Extension {}
+
+impl RefUnwindSafe for Extension {}
+
+impl PartialEq for Extension {
+    fn eq(&self, other: &Self) -> bool {
+        self.description == other.description
+    }
+}
+
+impl Eq for Extension {}
+
+impl Hash for Extension {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.description.hash(state);
+    }
+}
+
+impl PartialOrd for Extension {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Extension {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.description.cmp(&other.description)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Extension {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.description)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Extension {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let description = String::deserialize(deserializer)?;
+
+        Ok(Self {
+            value: Arc::new(description.clone()),
+            description,
+        })
+    }
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::mapper::{Extension, MappedType, SharedTypeMapper};
+    use crate::report::ParseWarning;
+    use crate::{parse_sql_report, Column, LogicalType, ParseOptions, Type};
+
+    #[derive(Debug, PartialEq)]
+    struct RustType(&'static str);
+
+    #[test]
+    fn test_type_mapper() {
+        let sql = "CREATE TABLE user (
+            user_id INTEGER NOT NULL PRIMARY KEY,
+            balance MONEY,
+            location POINT,
+            created DATETIME,
+            rate DECIMAL(1.5)
+        );";
+        let options = ParseOptions {
+            type_mapper: Some(SharedTypeMapper::new(
+                |_: &str, column: &Column| match column.declared_type.as_str() {
+                    "MONEY" => Some(MappedType::Builtin(Type::Integer)),
+                    "DECIMAL(1.5)" => Some(MappedType::Builtin(Type::Real)),
+                    "POINT" => Some(MappedType::Extension(Extension::new(RustType(
+                        "geo::Point",
+                    )))),
+                    _ if column.name == "created" => Some(MappedType::Builtin(Type::Integer)),
+                    _ => None,
+                },
+            )),
+            ..Default::default()
+        };
+        let report = parse_sql_report(sql, options).unwrap();
+        let user = report.metadata.table("user").unwrap();
+        let column = |name: &str| user.column(name).unwrap();
+
+        // The mapped columns don't have warnings anymore
+        assert!(report.warnings.is_empty());
+        assert_eq!(Type::Integer, column("balance").the_type);
+        assert_eq!(Type::Real, column("rate").the_type);
+        // POINT contains INT, so it has INTEGER affinity
+        assert_eq!(Type::Integer, column("location").the_type);
+
+        let extension = column("location").extension.as_ref().unwrap();
+
+        assert_eq!(Some(&RustType("geo::Point")), extension.downcast_ref());
+        assert_eq!(None, extension.downcast_ref::<String>());
+        assert_eq!("RustType(\"geo::Point\")", extension.description());
+        assert_eq!(None, column("created").logical_type);
+        assert_eq!(None, column("user_id").extension);
+
+        let unmapped = parse_sql_report(sql, ParseOptions::default()).unwrap();
+
+        assert_eq!(
+            vec![ParseWarning::UnknownType {
+                table: "user".to_string(),
+                column: "rate".to_string(),
+                declared_type: "DECIMAL(1.5)".to_string(),
+            }],
+            unmapped.warnings
+        );
+
+        let user = unmapped.metadata.table("user").unwrap();
+
+        assert_eq!(
+            Type::Numeric {
+                precision: None,
+                scale: None
+            },
+            user.column("balance").unwrap().the_type
+        );
+        assert_eq!(
+            Some(LogicalType::DateTime),
+            user.column("created").unwrap().logical_type
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Combines the metadata of several databases into a single catalog, e.g. of per-tenant shards
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+use crate::{Metadata, Table};
+
+/// Which table is kept when tables with the same name have a different structure
+#[derive(Debug, PartialEq, Copy, Clone, Eq, Hash, Default)]
+pub enum MergeStrategy {
+    /// The table of the earliest metadata is kept
+    #[default]
+    KeepFirst,
+    /// The table of the latest metadata is kept
+    KeepLast,
+    /// Merging fails with the conflicts
+    Fail,
+}
+
+/// Tables with the same name but a different structure
+/// Sources are numbered in the order they are merged, `self` is 0
+#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+pub struct MergeConflict {
+    pub table: String,
+    /// The source of the table that was merged first
+    pub first_source: usize,
+    /// The source of the table that differs from it
+    pub other_source: usize,
+}
+
+/// The merged metadata with the conflicts that were resolved by the strategy
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct MergedMetadata {
+    pub metadata: Metadata,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl Display for MergeConflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "table {} of source {} differs from source {}",
+            self.table, self.other_source, self.first_source
+        )
+    }
+}
+
+impl Metadata {
+    /// Adds the tables of the other metadata, tables with the same name (compared with
+    /// `Metadata::lookup`) are merged when they have the same structure
+    /// The structure is the columns, foreign keys and indexes, data like row counts can differ.
+    /// The database info and integrity check of `self` are kept, samples and profiles follow the
+    /// table that is kept. Views and triggers are added when `self` has none with the same name.
+    pub fn merge(
+        mut self,
+        others: impl IntoIterator<Item = Metadata>,
+        strategy: MergeStrategy,
+    ) -> Result<MergedMetadata, Vec<MergeConflict>> {
+        // The name of every merged table with the source it is taken from
+        let mut sources = self
+            .tables
+            .keys()
+            .map(|name| (name.clone(), 0))
+            .collect::<Vec<_>>();
+        let mut conflicts = vec![];
+
+        for (source, mut other) in others.into_iter().enumerate() {
+            let source = source + 1;
+
+            for trigger in other.triggers {
+                if !self
+                    .triggers
+                    .iter()
+                    .any(|t| t.name.eq_ignore_ascii_case(&trigger.name))
+                {
+                    self.triggers.push(trigger);
+                }
+            }
+
+            for view in other.views {
+                if self.view(&view.name).is_none() {
+                    self.views.push(view);
+                }
+            }
+
+            for (name, table) in other.tables {
+                let existing = sources
+                    .iter()
+                    .position(|(n, _)| self.lookup.matches(n, &name));
+                let Some(existing) = existing else {
+                    move_data(&mut other.samples, &mut self.samples, &name);
+                    move_data(&mut other.profiles, &mut self.profiles, &name);
+                    sources.push((name.clone(), source));
+                    self.tables.insert(name, table);
+
+                    continue;
+                };
+                let (existing_name, first_source) = sources[existing].clone();
+
+                if same_structure(&self.tables[&existing_name], &table) {
+                    continue;
+                }
+
+                conflicts.push(MergeConflict {
+                    table: existing_name.clone(),
+                    first_source,
+                    other_source: source,
+                });
+
+                if strategy == MergeStrategy::KeepLast {
+                    self.tables.remove(&existing_name);
+                    self.samples.remove(&existing_name);
+                    self.profiles.remove(&existing_name);
+                    move_data(&mut other.samples, &mut self.samples, &name);
+                    move_data(&mut other.profiles, &mut self.profiles, &name);
+                    sources[existing] = (name.clone(), source);
+                    self.tables.insert(name, table);
+                }
+            }
+        }
+
+        self.triggers.sort_by(|a, b| a.name.cmp(&b.name));
+        self.views.sort_by(|a, b| a.name.cmp(&b.name));
+        // The tables of other sources can resolve more of the views and triggers
+        self.analyze_dependencies();
+
+        if strategy == MergeStrategy::Fail && !conflicts.is_empty() {
+            return Err(conflicts);
+        }
+
+        Ok(MergedMetadata {
+            metadata: self,
+            conflicts,
+        })
+    }
+}
+
+/// Moves the samples or profile of the table
+fn move_data<T>(from: &mut HashMap<String, T>, to: &mut HashMap<String, T>, table_name: &str) {
+    if let Some(data) = from.remove(table_name) {
+        to.insert(table_name.to_string(), data);
+    }
+}
+
+/// Compares the columns, foreign keys and indexes, without the statistics and storage
+fn same_structure(table: &Table, other: &Table) -> bool {
+    let indexes = |t: &Table| {
+        let mut indexes = t
+            .indexes
+            .iter()
+            .map(|i| (i.name.clone(), i.columns.clone(), i.unique))
+            .collect::<Vec<_>>();
+
+        indexes.sort();
+
+        indexes
+    };
+
+    table.columns == other.columns
+        && table.foreign_keys == other.foreign_keys
+        && indexes(table) == indexes(other)
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::merge::{MergeConflict, MergeStrategy};
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_merge() {
+        let user = "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY);";
+        let tenants = || {
+            vec![
+                metadata_from_sql(&format!(
+                    "{user} CREATE TABLE post (post_id INTEGER NOT NULL PRIMARY KEY);"
+                )),
+                metadata_from_sql(&format!(
+                    "{user} CREATE TABLE post (post_id INTEGER NOT NULL PRIMARY KEY, title TEXT);"
+                )),
+                metadata_from_sql("CREATE TABLE tag (tag_id INTEGER NOT NULL PRIMARY KEY);"),
+            ]
+        };
+        let first = metadata_from_sql(user);
+        let conflict = MergeConflict {
+            table: "post".to_string(),
+            first_source: 1,
+            other_source: 2,
+        };
+
+        let merged = first
+            .clone()
+            .merge(tenants(), MergeStrategy::KeepFirst)
+            .unwrap();
+
+        assert_eq!(vec![conflict.clone()], merged.conflicts);
+        assert_eq!(3, merged.metadata.tables.len());
+        assert_eq!(1, merged.metadata.table("post").unwrap().columns.len());
+
+        let merged = first
+            .clone()
+            .merge(tenants(), MergeStrategy::KeepLast)
+            .unwrap();
+
+        assert_eq!(2, merged.metadata.table("post").unwrap().columns.len());
+        assert_eq!(
+            Err(vec![conflict]),
+            first.merge(tenants(), MergeStrategy::Fail)
+        );
+    }
+}
@@ -0,0 +1,701 @@
+//! Generates the statements to migrate a schema to another schema
+//!
+//! Changes that `ALTER TABLE` can express are done with `ALTER TABLE`, other changes rebuild the
+//! table with the procedure of https://www.sqlite.org/lang_altertable.html#otheralter: the new
+//! table is created under a temporary name, the data is copied, the old table is dropped, the new
+//! table is renamed and the indexes are recreated from their `Table::index_sql`. Dropped columns
+//! always rebuild the table, since `ALTER TABLE DROP COLUMN` needs SQLite 3.35 and fails for
+//! indexed or constrained columns.
+
+use std::fmt::{Display, Formatter};
+
+#[cfg(feature = "rusqlite")]
+use rusqlite::{ffi, Connection};
+
+use crate::ddl::{
+    column_sql, create_index_sql, is_constraint, rename_column_in_create_index,
+    rename_column_in_create_table, rename_column_reference, rename_create_table,
+    rename_referenced_column, with_added_columns,
+};
+use crate::dependency::TableAccess;
+use crate::diff::TableDiff;
+use crate::view::View;
+use crate::{canonical_sql, quote_identifier, Metadata, Table};
+
+/// The statements of a migration, see `Metadata::migration_to`
+/// Display writes the statements as a script that disables the foreign keys and runs the
+/// statements in a transaction.
+#[derive(Debug, PartialEq, Clone, Eq, Default)]
+pub struct Migration {
+    /// The statements to run inside the transaction, in order
+    pub statements: Vec<String>,
+    /// The tables that are rebuilt, the triggers of these tables are dropped with the old table
+    /// and need to be recreated
+    pub rebuilt_tables: Vec<String>,
+}
+
+impl Migration {
+    pub fn is_empty(&self) -> bool {
+        self.statements.is_empty()
+    }
+
+    /// Runs the migration in a transaction and recreates the triggers of the rebuilt tables,
+    /// except the triggers that the statements recreated
+    /// When foreign keys are enforced, the migration is rolled back if it violates a foreign key.
+    /// The `foreign_keys` and `legacy_alter_table` pragmas are restored afterwards.
+    #[cfg(feature = "rusqlite")]
+    pub fn apply(&self, connection: &Connection) -> rusqlite::Result<()> {
+        let foreign_keys: bool =
+            connection.query_row("PRAGMA foreign_keys;", [], |row| row.get(0))?;
+        let legacy_alter_table: bool =
+            connection.query_row("PRAGMA legacy_alter_table;", [], |row| row.get(0))?;
+        let mut triggers = vec![];
+
+        for table in &self.rebuilt_tables {
+            let mut stmt = connection.prepare(
+                "SELECT name, sql FROM sqlite_master WHERE type = 'trigger' AND tbl_name = ? COLLATE NOCASE;",
+            )?;
+            let rows = stmt.query_map([table], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+            })?;
+
+            for row in rows {
+                triggers.push(row?);
+            }
+        }
+
+        // Foreign keys can't be disabled inside a transaction
+        connection.execute_batch(
+            "PRAGMA foreign_keys = OFF; PRAGMA legacy_alter_table = ON; BEGIN TRANSACTION;",
+        )?;
+
+        let result = (|| {
+            for statement in &self.statements {
+                connection.execute_batch(statement)?;
+            }
+
+            for (name, sql) in &triggers {
+                let exists = connection
+                    .prepare("SELECT 1 FROM sqlite_master WHERE type = 'trigger' AND name = ?;")?
+                    .exists([name])?;
+
+                if !exists {
+                    connection.execute_batch(sql)?;
+                }
+            }
+
+            if foreign_keys
+                && connection
+                    .prepare("PRAGMA foreign_key_check;")?
+                    .exists([])?
+            {
+                return Err(rusqlite::Error::SqliteFailure(
+                    ffi::Error::new(ffi::SQLITE_CONSTRAINT_FOREIGNKEY),
+                    Some("FOREIGN KEY constraint failed".to_string()),
+                ));
+            }
+
+            Ok(())
+        })();
+
+        connection.execute_batch(if result.is_ok() {
+            "COMMIT;"
+        } else {
+            "ROLLBACK;"
+        })?;
+        connection.execute_batch(&format!(
+            "PRAGMA foreign_keys = {foreign_keys}; PRAGMA legacy_alter_table = {legacy_alter_table};"
+        ))?;
+
+        result
+    }
+}
+
+impl Display for Migration {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "PRAGMA foreign_keys = OFF;")?;
+        // Renaming a table checks the views, which fail while the old table is dropped
+        writeln!(f, "PRAGMA legacy_alter_table = ON;")?;
+        writeln!(f, "BEGIN TRANSACTION;")?;
+
+        for statement in &self.statements {
+            writeln!(f, "{statement}")?;
+        }
+
+        writeln!(f, "PRAGMA foreign_key_check;")?;
+        writeln!(f, "COMMIT;")?;
+        writeln!(f, "PRAGMA legacy_alter_table = OFF;")?;
+        writeln!(f, "PRAGMA foreign_keys = ON;")
+    }
+}
+
+impl Metadata {
+    /// The statements to get from this schema to the other schema, based on `Metadata::diff`
+    /// Tables and indexes are created with the CREATE TABLE and CREATE INDEX statements of the
+    /// other schema.
+    pub fn migration_to(&self, other: &Metadata) -> Migration {
+        let diff = self.diff(other);
+        let mut migration = Migration::default();
+
+        for table in &diff.added_tables {
+            migration.statements.push(format!("{};", table.create_sql));
+            migration.statements.extend(index_statements(table));
+        }
+
+        for table_diff in &diff.changed_tables {
+            let old = self.table(&table_diff.table_name).unwrap();
+            let new = other.table(&table_diff.table_name).unwrap();
+
+            match alter_table(table_diff, old, new) {
+                Some(statements) => migration.statements.extend(statements),
+                None => {
+                    migration
+                        .statements
+                        .extend(rebuild_table(old, new, &[], false));
+                    migration.rebuilt_tables.push(new.table_name.clone());
+                }
+            }
+        }
+
+        for table in &diff.removed_tables {
+            migration.statements.push(format!(
+                "DROP TABLE {};",
+                quote_identifier(&table.table_name)
+            ));
+        }
+
+        migration
+    }
+}
+
+impl Metadata {
+    /// The statements to rename a column, `None` if the table or column doesn't exist or the table
+    /// has another column with the new name
+    /// `ALTER TABLE ... RENAME COLUMN` is used when `database` supports it, otherwise the table is
+    /// rebuilt together with the tables that have a foreign key to the column. The views and
+    /// triggers of `references_to_column` are dropped first and recreated with the new name
+    /// afterwards, since `legacy_alter_table` keeps SQLite from renaming the column in them.
+    pub fn rename_column_migration(
+        &self,
+        table_name: &str,
+        column_name: &str,
+        new_name: &str,
+    ) -> Option<Migration> {
+        let table = self.table(table_name)?;
+        let column = table.column(column_name)?;
+
+        if table
+            .column(new_name)
+            .is_some_and(|c| c.name != column.name)
+        {
+            return None;
+        }
+
+        let references = self.references_to_column(table_name, column_name)?;
+        let is_table = |name: &str| self.lookup.matches(name, &table.table_name);
+        let is_column = |name: &String| name.eq_ignore_ascii_case(&column.name);
+        // The views that select from a recreated view are recreated as well
+        let mut views: Vec<&View> = vec![];
+
+        for view in self.views_in_dependency_order() {
+            if references.views.contains(&view)
+                || view
+                    .references()
+                    .any(|r| views.iter().any(|v| self.lookup.matches(r, &v.name)))
+            {
+                views.push(view);
+            }
+        }
+
+        // Unqualified names are only renamed when no other table or view of the statement has
+        // the column
+        let unqualified = |accesses: &[TableAccess], source: &str| {
+            !accesses.iter().any(|a| {
+                !self.lookup.matches(&a.table_name, source)
+                    && (self
+                        .table(&a.table_name)
+                        .is_some_and(|t| t.column(&column.name).is_some())
+                        || self
+                            .view(&a.table_name)
+                            .is_some_and(|v| v.columns.iter().any(is_column)))
+            })
+        };
+        let mut migration = Migration::default();
+
+        for view in views.iter().rev() {
+            migration
+                .statements
+                .push(format!("DROP VIEW {};", quote_identifier(&view.name)));
+        }
+
+        for trigger in &references.triggers {
+            migration
+                .statements
+                .push(format!("DROP TRIGGER {};", quote_identifier(&trigger.name)));
+        }
+
+        if self.database.supports_rename_column() {
+            migration.statements.push(format!(
+                "ALTER TABLE {} RENAME COLUMN {} TO {};",
+                quote_identifier(&table.table_name),
+                quote_identifier(&column.name),
+                quote_identifier(new_name)
+            ));
+        } else {
+            let mut renamed = table.clone();
+
+            renamed.create_sql = rename_column_in_create_table(
+                &table.create_sql,
+                &table.table_name,
+                &column.name,
+                new_name,
+            );
+
+            for index_column in renamed
+                .columns
+                .iter_mut()
+                .chain(renamed.indexes.iter_mut().flat_map(|i| &mut i.columns))
+                .filter(|c| c.name == column.name)
+            {
+                index_column.name = new_name.to_string();
+            }
+
+            for sql in renamed.index_sql.values_mut() {
+                *sql = rename_column_in_create_index(sql, &column.name, new_name);
+            }
+
+            migration.statements.extend(rebuild_table(
+                table,
+                &renamed,
+                &[(new_name, &column.name)],
+                false,
+            ));
+            migration.rebuilt_tables.push(table.table_name.clone());
+
+            for (referencing, _) in &references.foreign_keys {
+                if is_table(&referencing.table_name)
+                    || migration.rebuilt_tables.contains(&referencing.table_name)
+                {
+                    continue;
+                }
+
+                let mut new_referencing = (*referencing).clone();
+
+                new_referencing.create_sql = rename_referenced_column(
+                    &referencing.create_sql,
+                    &table.table_name,
+                    &column.name,
+                    new_name,
+                );
+                migration.statements.extend(rebuild_table(
+                    referencing,
+                    &new_referencing,
+                    &[],
+                    false,
+                ));
+                migration
+                    .rebuilt_tables
+                    .push(referencing.table_name.clone());
+            }
+        }
+
+        // The column is renamed in the views that select it from the table or a recreated view
+        for view in &views {
+            let mut sql = view.sql.clone();
+            let sources = std::iter::once(table.table_name.as_str())
+                .chain(views.iter().map(|v| v.name.as_str()));
+
+            for source in sources {
+                if view.accesses.iter().any(|a| {
+                    self.lookup.matches(&a.table_name, source) && a.columns.iter().any(is_column)
+                }) {
+                    sql = rename_column_reference(
+                        &sql,
+                        source,
+                        &column.name,
+                        new_name,
+                        unqualified(&view.accesses, source),
+                        false,
+                    );
+                }
+            }
+
+            migration.statements.push(format!("{sql};"));
+        }
+
+        for trigger in &references.triggers {
+            migration.statements.push(format!(
+                "{};",
+                rename_column_reference(
+                    &trigger.sql,
+                    &table.table_name,
+                    &column.name,
+                    new_name,
+                    unqualified(&trigger.accesses, &table.table_name),
+                    is_table(&trigger.table_name)
+                )
+            ));
+        }
+
+        Some(migration)
+    }
+}
+
+/// The ALTER TABLE, CREATE INDEX and DROP INDEX statements for the changes, `None` if the table
+/// must be rebuilt
+fn alter_table(diff: &TableDiff, old: &Table, new: &Table) -> Option<Vec<String>> {
+    if !diff.removed_columns.is_empty()
+        || !diff.changed_columns.is_empty()
+        || !diff.added_foreign_keys.is_empty()
+        || !diff.removed_foreign_keys.is_empty()
+        || diff.removed_indexes.iter().any(|i| is_constraint(old, i))
+        || diff.added_indexes.iter().any(|i| is_constraint(new, i))
+    {
+        return None;
+    }
+
+    let added_columns = diff
+        .added_columns
+        .iter()
+        .map(|c| column_sql(&new.create_sql, &c.name))
+        .collect::<Option<Vec<_>>>()?;
+
+    // The definition must be the same as the one ADD COLUMN results in, e.g. a changed CHECK
+    // constraint or an added column that isn't the last one needs a rebuild
+    if diff.definition_changed
+        && canonical_sql(&with_added_columns(&old.create_sql, &added_columns))
+            != canonical_sql(&new.create_sql)
+    {
+        return None;
+    }
+
+    let mut statements = diff
+        .removed_indexes
+        .iter()
+        .map(|i| format!("DROP INDEX {};", quote_identifier(&i.name)))
+        .collect::<Vec<_>>();
+
+    for (column, sql) in diff.added_columns.iter().zip(added_columns) {
+        // Added columns need a value for the existing rows
+        if column.part_of_pk || (!column.nullable && column.default_value.is_none()) {
+            return None;
+        }
+
+        statements.push(format!(
+            "ALTER TABLE {} ADD COLUMN {sql};",
+            quote_identifier(&new.table_name)
+        ));
+    }
+
+    statements.extend(
+        diff.added_indexes
+            .iter()
+            .map(|i| match new.index_sql.get(&i.name) {
+                Some(sql) => format!("{sql};"),
+                None => create_index_sql(new, i),
+            }),
+    );
+
+    Some(statements)
+}
+
+/// Steps 4 to 8 of the procedure, the other steps are done by `Migration`
+/// The data of a column is copied from the old column with the same name, or the old name of
+/// `renamed` (pairs of new and old name). With `cast`, the values of columns of which the declared
+/// type changed are converted with `CAST`, except for the type ANY.
+pub(crate) fn rebuild_table(
+    old: &Table,
+    new: &Table,
+    renamed: &[(&str, &str)],
+    cast: bool,
+) -> Vec<String> {
+    let temporary = format!("new_{}", new.table_name);
+    let (to_columns, from_columns): (Vec<_>, Vec<_>) = new
+        .columns
+        .iter()
+        .filter_map(|c| {
+            let old_name = renamed
+                .iter()
+                .find(|(new_name, _)| *new_name == c.name)
+                .map_or(c.name.as_str(), |(_, old_name)| old_name);
+            let old_column = old.column(old_name)?;
+            let from = quote_identifier(&old_column.name);
+            let changed = !c
+                .declared_type
+                .eq_ignore_ascii_case(&old_column.declared_type);
+
+            Some((
+                quote_identifier(&c.name),
+                if cast && changed && !c.declared_type.eq_ignore_ascii_case("ANY") {
+                    format!("CAST({from} AS {})", c.declared_type)
+                } else {
+                    from
+                },
+            ))
+        })
+        .unzip();
+    let mut statements = vec![
+        format!("{};", rename_create_table(&new.create_sql, &temporary)),
+        format!(
+            "INSERT INTO {} ({}) SELECT {} FROM {};",
+            quote_identifier(&temporary),
+            to_columns.join(", "),
+            from_columns.join(", "),
+            quote_identifier(&old.table_name)
+        ),
+        format!("DROP TABLE {};", quote_identifier(&old.table_name)),
+        format!(
+            "ALTER TABLE {} RENAME TO {};",
+            quote_identifier(&temporary),
+            quote_identifier(&new.table_name)
+        ),
+    ];
+
+    statements.extend(index_statements(new));
+
+    statements
+}
+
+/// The CREATE INDEX statements as stored by SQLite, so partial and expression indexes are
+/// recreated as they were
+fn index_statements(table: &Table) -> impl Iterator<Item = String> + '_ {
+    table.index_sql.values().map(|sql| format!("{sql};"))
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_alter_table() {
+        let old = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT);
+            CREATE INDEX user_name ON user(name);",
+        );
+        let new = metadata_from_sql(
+            "CREATE TABLE user (
+                user_id INTEGER NOT NULL PRIMARY KEY,
+                name TEXT,
+                email TEXT NOT NULL DEFAULT '' COLLATE NOCASE
+            );
+            CREATE INDEX user_email ON user(email, name);",
+        );
+        let migration = old.migration_to(&new);
+
+        assert_eq!(
+            vec![
+                "DROP INDEX \"user_name\";",
+                "ALTER TABLE \"user\" ADD COLUMN email TEXT NOT NULL DEFAULT '' COLLATE NOCASE;",
+                "CREATE INDEX user_email ON user(email, name);",
+            ],
+            migration.statements
+        );
+        assert!(migration.rebuilt_tables.is_empty());
+        assert!(new.migration_to(&new).is_empty());
+    }
+
+    #[cfg(feature = "rusqlite")]
+    #[test]
+    fn test_rebuild_table() {
+        use rusqlite::Connection;
+
+        use crate::metadata_from_connection;
+
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "PRAGMA foreign_keys = ON;
+                CREATE TABLE user (
+                    user_id INTEGER NOT NULL PRIMARY KEY,
+                    name TEXT NOT NULL,
+                    age INTEGER
+                );
+                CREATE INDEX user_age ON user(age, name);
+                CREATE TABLE post (post_id INTEGER PRIMARY KEY, user_id INTEGER REFERENCES user);
+                CREATE TABLE log (message TEXT);
+                CREATE TRIGGER user_log AFTER INSERT ON user BEGIN INSERT INTO log VALUES (NEW.name); END;
+                CREATE VIEW user_name AS SELECT name FROM user;
+                INSERT INTO user VALUES (1, 'a', 30);
+                INSERT INTO post VALUES (1, 1);",
+            )
+            .unwrap();
+
+        let old = metadata_from_connection(&connection, Default::default());
+        let new = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT UNIQUE);
+            CREATE TABLE post (post_id INTEGER PRIMARY KEY, user_id INTEGER REFERENCES user);
+            CREATE TABLE log (message TEXT);",
+        );
+        let migration = old.migration_to(&new);
+
+        assert_eq!(vec!["user"], migration.rebuilt_tables);
+        assert!(migration.to_string().contains("DROP TABLE \"user\";"));
+
+        migration.apply(&connection).unwrap();
+
+        let migrated = metadata_from_connection(&connection, Default::default());
+
+        assert!(migrated.diff(&new).is_empty());
+        assert!(migrated.migration_to(&new).is_empty());
+
+        // The data, trigger, view and foreign key enforcement are kept
+        connection
+            .execute_batch("INSERT INTO user VALUES (2, 'b');")
+            .unwrap();
+
+        let count: i64 = connection
+            .query_row(
+                "SELECT COUNT(*) FROM user_name JOIN log ON name = message;",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+
+        assert_eq!(2, count);
+        assert!(connection
+            .execute_batch("INSERT INTO post VALUES (2, 3);")
+            .is_err());
+
+        // Migrations that violate a foreign key are rolled back
+        let account = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT UNIQUE);
+            CREATE TABLE account (account_id INTEGER PRIMARY KEY);
+            CREATE TABLE post (post_id INTEGER PRIMARY KEY, user_id INTEGER REFERENCES account);
+            CREATE TABLE log (message TEXT);",
+        );
+
+        assert!(migrated.migration_to(&account).apply(&connection).is_err());
+        assert_eq!(
+            migrated,
+            metadata_from_connection(&connection, Default::default())
+        );
+    }
+
+    #[cfg(feature = "rusqlite")]
+    #[test]
+    fn test_rebuild_table_indexes() {
+        use rusqlite::Connection;
+
+        use crate::metadata_from_connection;
+
+        let schema = |c_type: &str| {
+            format!(
+                "CREATE TABLE t (id INTEGER PRIMARY KEY, a TEXT, b TEXT, c {c_type}, UNIQUE (a, b));
+                CREATE UNIQUE INDEX t_live ON t(a) WHERE c IS NULL;
+                CREATE INDEX t_lower ON t(lower(b), a);"
+            )
+        };
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection.execute_batch(&schema("TEXT")).unwrap();
+        connection
+            .execute_batch("INSERT INTO t VALUES (1, 'a', 'b', 'c'), (2, 'a', 'c', 'd');")
+            .unwrap();
+
+        let old = metadata_from_connection(&connection, Default::default());
+        let new = metadata_from_sql(&schema("INTEGER"));
+        let migration = old.migration_to(&new);
+
+        assert_eq!(vec!["t"], migration.rebuilt_tables);
+
+        migration.apply(&connection).unwrap();
+
+        let migrated = metadata_from_connection(&connection, Default::default());
+        let t = migrated.table("t").unwrap();
+
+        assert_eq!(new.table("t").unwrap().index_sql, t.index_sql);
+        assert_eq!(
+            vec!["t_live", "t_lower"],
+            t.index_sql.keys().collect::<Vec<_>>()
+        );
+        assert!(migrated.migration_to(&new).is_empty());
+
+        // The partial index only applies to rows without c
+        connection
+            .execute_batch("INSERT INTO t VALUES (3, 'x', 'y', NULL);")
+            .unwrap();
+        assert!(connection
+            .execute_batch("INSERT INTO t VALUES (4, 'x', 'z', NULL);")
+            .is_err());
+    }
+
+    #[cfg(feature = "rusqlite")]
+    #[test]
+    fn test_rename_column_migration() {
+        use rusqlite::Connection;
+
+        use crate::metadata_from_connection;
+
+        let schema = "PRAGMA foreign_keys = ON;
+            CREATE TABLE user (
+                user_id INTEGER NOT NULL PRIMARY KEY,
+                email TEXT NOT NULL UNIQUE CHECK (email LIKE '%@%')
+            );
+            CREATE INDEX user_email ON user(email);
+            CREATE TABLE post (post_id INTEGER PRIMARY KEY, email TEXT REFERENCES user (email) ON UPDATE CASCADE);
+            CREATE TABLE log (message TEXT);
+            CREATE VIEW emails AS SELECT u.email FROM user u;
+            CREATE VIEW post_emails AS SELECT e.email FROM emails e;
+            CREATE TRIGGER user_log AFTER UPDATE OF email ON user BEGIN
+                INSERT INTO log VALUES (new.email);
+            END;
+            INSERT INTO user VALUES (1, 'a@b');
+            INSERT INTO post VALUES (1, 'a@b');";
+
+        for sqlite_version in ["3.44.0", "3.24.0"] {
+            let connection = Connection::open_in_memory().unwrap();
+
+            connection.execute_batch(schema).unwrap();
+
+            let mut metadata = metadata_from_connection(&connection, Default::default());
+
+            metadata.database.sqlite_version = sqlite_version.to_string();
+
+            assert_eq!(
+                None,
+                metadata.rename_column_migration("user", "email", "USER_ID")
+            );
+            assert_eq!(
+                None,
+                metadata.rename_column_migration("user", "unknown", "mail")
+            );
+
+            let migration = metadata
+                .rename_column_migration("user", "email", "mail")
+                .unwrap();
+
+            assert_eq!("DROP VIEW \"post_emails\";", migration.statements[0]);
+            assert_eq!(
+                sqlite_version == "3.24.0",
+                migration.rebuilt_tables == ["user", "post"]
+            );
+
+            migration.apply(&connection).unwrap();
+
+            let migrated = metadata_from_connection(&connection, Default::default());
+            let user = migrated.table("user").unwrap();
+
+            assert!(user.column("email").is_none());
+            assert_eq!("mail", user.indexes[0].columns[0].name);
+            assert_eq!(
+                "mail",
+                migrated.table("post").unwrap().foreign_keys[0].to_column[0].name
+            );
+            assert_eq!(vec!["mail"], migrated.view("post_emails").unwrap().columns);
+
+            connection
+                .execute_batch("UPDATE user SET mail = 'c@d' WHERE user_id = 1;")
+                .unwrap();
+
+            let message: String = connection
+                .query_row("SELECT message FROM log;", [], |row| row.get(0))
+                .unwrap();
+
+            assert_eq!("c@d", message);
+            assert!(connection
+                .execute_batch("INSERT INTO post VALUES (2, 'x@y');")
+                .is_err());
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! Generates the `components` section of an OpenAPI 3.0 document with a schema per table
+//!
+//! ```
+//! use sqlite_parser::openapi::components;
+//! use sqlite_parser::parse_sql;
+//!
+//! let metadata = parse_sql("CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, email VARCHAR(255));");
+//! let components = components(&metadata);
+//!
+//! assert_eq!(
+//!     "string",
+//!     components["schemas"]["user"]["properties"]["email"]["type"]
+//! );
+//! ```
+
+use serde_json::{json, Map, Value};
+
+use crate::{Affinity, Column, LogicalType, Metadata, Table, Type};
+
+/// The `components` object with a schema per table under `schemas`, keyed by table name
+/// The NOT NULL columns are required, nullable columns have `nullable: true`.
+pub fn components(metadata: &Metadata) -> Value {
+    let schemas = metadata
+        .sorted_tables()
+        .into_iter()
+        .map(|t| (t.table_name.clone(), table_schema(t)))
+        .collect::<Map<_, _>>();
+
+    json!({ "schemas": schemas })
+}
+
+/// The object schema of a table, with a property per column
+pub fn table_schema(table: &Table) -> Value {
+    let properties = table
+        .columns
+        .iter()
+        .map(|c| (c.name.clone(), column_schema(c)))
+        .collect::<Map<_, _>>();
+    let required = table
+        .columns
+        .iter()
+        .filter(|c| !c.nullable)
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>();
+    let mut schema = json!({
+        "type": "object",
+        "properties": properties,
+    });
+
+    if !required.is_empty() {
+        schema["required"] = json!(required);
+    }
+
+    schema
+}
+
+/// The schema of a column, the logical type decides the type and format if the column has one
+/// Unknown types are mapped by their affinity, columns without a type accept any value like JSON
+/// columns.
+pub fn column_schema(column: &Column) -> Value {
+    let mut schema = match column.logical_type {
+        Some(LogicalType::Boolean) => json!({ "type": "boolean" }),
+        Some(LogicalType::Date) => json!({ "type": "string", "format": "date" }),
+        Some(LogicalType::DateTime | LogicalType::Timestamp) => {
+            json!({ "type": "string", "format": "date-time" })
+        }
+        Some(LogicalType::Json) => json!({}),
+        Some(LogicalType::Uuid) => json!({ "type": "string", "format": "uuid" }),
+        None if column.boolean_hint => json!({ "type": "boolean" }),
+        None => match column.the_type {
+            Type::Integer => json!({ "type": "integer", "format": "int64" }),
+            Type::Real => json!({ "type": "number", "format": "double" }),
+            Type::Numeric { .. } => json!({ "type": "number" }),
+            Type::Text | Type::String => json!({ "type": "string" }),
+            Type::Blob => json!({ "type": "string", "format": "byte" }),
+            Type::Unknown => match column.affinity() {
+                Affinity::Integer => json!({ "type": "integer", "format": "int64" }),
+                Affinity::Text => json!({ "type": "string" }),
+                Affinity::Real => json!({ "type": "number", "format": "double" }),
+                Affinity::Numeric => json!({ "type": "number" }),
+                Affinity::Blob => json!({}),
+            },
+        },
+    };
+
+    if schema["type"] == "string" && schema.get("format").is_none() {
+        if let Some(max_length) = column.max_length() {
+            schema["maxLength"] = json!(max_length);
+        }
+
+        if let Some(enum_values) = &column.enum_values {
+            schema["enum"] = json!(enum_values);
+        }
+    }
+
+    if column.nullable {
+        schema["nullable"] = json!(true);
+    }
+
+    if let Some(comment) = &column.comment {
+        schema["description"] = json!(comment);
+    }
+
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::openapi::components;
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_components() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (
+                user_id INTEGER NOT NULL PRIMARY KEY,
+                -- The login
+                email VARCHAR(100) NOT NULL,
+                score REAL,
+                active BOOLEAN NOT NULL,
+                created DATETIME,
+                settings JSON,
+                photo BLOB,
+                role TEXT NOT NULL CHECK (role IN ('admin', 'member'))
+            );",
+        );
+
+        assert_eq!(
+            json!({
+                "schemas": {
+                    "user": {
+                        "type": "object",
+                        "properties": {
+                            "user_id": { "type": "integer", "format": "int64" },
+                            "email": { "type": "string", "maxLength": 100, "description": "The login" },
+                            "score": { "type": "number", "format": "double", "nullable": true },
+                            "active": { "type": "boolean" },
+                            "created": { "type": "string", "format": "date-time", "nullable": true },
+                            "settings": { "nullable": true },
+                            "photo": { "type": "string", "format": "byte", "nullable": true },
+                            "role": { "type": "string", "enum": ["admin", "member"] },
+                        },
+                        "required": ["user_id", "email", "active", "role"],
+                    }
+                }
+            }),
+            components(&metadata)
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Converts the tables to `polars` schemas, to read the rows of a table into a typed data frame
+//!
+//! ```
+//! use polars::prelude::{DataType, Schema};
+//! use sqlite_parser::parse_sql;
+//!
+//! let metadata = parse_sql("CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, created DATE);");
+//! let schema = Schema::from(metadata.table("user").unwrap());
+//!
+//! assert_eq!(Some(&DataType::Date), schema.get("created"));
+//! ```
+//!
+//! The types are mapped like the `arrow` feature does, Polars has no nullability in its schema.
+//! For DataFusion, convert the Arrow schema of the table with
+//! `DFSchema::try_from_qualified_schema(table_name, &schema)`.
+
+use std::collections::HashMap;
+
+use ::polars::prelude::{DataType, Field, Schema, TimeUnit};
+
+use crate::{Affinity, Column, LogicalType, Metadata, Table, Type};
+
+impl From<&Table> for Schema {
+    /// The fields are in the order of the columns
+    fn from(table: &Table) -> Self {
+        Schema::from_iter(
+            table
+                .columns
+                .iter()
+                .map(|c| Field::new(c.name.as_str().into(), data_type(c))),
+        )
+    }
+}
+
+/// The schemas of the tables, keyed by table name
+pub fn polars_schemas(metadata: &Metadata) -> HashMap<String, Schema> {
+    metadata
+        .tables
+        .iter()
+        .map(|(name, table)| (name.clone(), Schema::from(table)))
+        .collect()
+}
+
+/// The Polars type of a column, the logical type decides the type if the column has one
+/// Unknown types are mapped by their affinity, NUMERIC and DECIMAL with a precision up to 38
+/// become `Decimal`.
+pub fn data_type(column: &Column) -> DataType {
+    match column.logical_type {
+        Some(LogicalType::Boolean) => return DataType::Boolean,
+        Some(LogicalType::Date) => return DataType::Date,
+        Some(LogicalType::DateTime | LogicalType::Timestamp) => {
+            return DataType::Datetime(TimeUnit::Microseconds, None)
+        }
+        Some(LogicalType::Json | LogicalType::Uuid) => return DataType::String,
+        None if column.boolean_hint => return DataType::Boolean,
+        None => {}
+    }
+
+    match column.the_type {
+        Type::Integer => DataType::Int64,
+        Type::Real => DataType::Float64,
+        Type::Numeric {
+            precision: Some(precision),
+            scale,
+        } if precision <= 38 => {
+            DataType::Decimal(Some(precision as usize), Some(scale.unwrap_or(0) as usize))
+        }
+        Type::Numeric { .. } => DataType::Float64,
+        Type::Text | Type::String => DataType::String,
+        Type::Blob => DataType::Binary,
+        Type::Unknown => match column.affinity() {
+            Affinity::Integer => DataType::Int64,
+            Affinity::Text => DataType::String,
+            Affinity::Real | Affinity::Numeric => DataType::Float64,
+            Affinity::Blob => DataType::Binary,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::polars::prelude::{DataType, Schema, TimeUnit};
+
+    use crate::polars::polars_schemas;
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_polars_schema() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (
+                user_id INTEGER NOT NULL PRIMARY KEY,
+                name VARCHAR(100) NOT NULL,
+                balance DECIMAL(10, 2),
+                active BOOLEAN,
+                created TIMESTAMP,
+                photo BLOB
+            );",
+        );
+        let schema = Schema::from(metadata.table("user").unwrap());
+
+        assert_eq!(
+            vec![
+                ("user_id", DataType::Int64),
+                ("name", DataType::String),
+                ("balance", DataType::Decimal(Some(10), Some(2))),
+                ("active", DataType::Boolean),
+                ("created", DataType::Datetime(TimeUnit::Microseconds, None)),
+                ("photo", DataType::Binary),
+            ],
+            schema
+                .iter()
+                .map(|(name, data_type)| (name.as_str(), data_type.clone()))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(schema, polars_schemas(&metadata)["user"]);
+    }
+}
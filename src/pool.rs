@@ -0,0 +1,390 @@
+//! Parses many databases concurrently with bounded parallelism, e.g. for a platform with a
+//! database file per tenant
+//!
+//! ```
+//! use std::path::PathBuf;
+//!
+//! use sqlite_parser::pool::parse_concurrently;
+//!
+//! fn parse_tenants(paths: &[PathBuf]) {
+//!     let results = parse_concurrently(paths.iter().map(|p| (p.clone(), p)), 4, &Default::default());
+//!
+//!     for (path, result) in results {
+//!         match result {
+//!             Ok(metadata) => println!("{}: {} tables", path.display(), metadata.tables.len()),
+//!             Err(e) => println!("{}: {e}", path.display()),
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! A database is read through a `ConnectionSource`, which is implemented for paths (opened read
+//! only) and, with the `r2d2` feature, for `r2d2` pools of rusqlite connections. `parse_dir` parses
+//! the database files of a directory, `same_schemas` groups the results by
+//! `Metadata::fingerprint`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Display, Formatter};
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use rusqlite::{Connection, OpenFlags};
+
+use crate::report::StrictError;
+use crate::{report_from_connection, Metadata, ParseOptions};
+
+/// Lends a connection to a database
+pub trait ConnectionSource: Sync {
+    type Error: Send;
+
+    /// Calls the function with a connection, fails if no connection can be made
+    fn with_connection<R>(&self, f: impl FnOnce(&Connection) -> R) -> Result<R, Self::Error>;
+}
+
+impl ConnectionSource for Path {
+    type Error = rusqlite::Error;
+
+    /// Opens the file read only, so a missing file is an error instead of an empty database
+    fn with_connection<R>(&self, f: impl FnOnce(&Connection) -> R) -> Result<R, Self::Error> {
+        let connection = Connection::open_with_flags(
+            self,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+
+        // Opening doesn't validate the file, reading the schema does
+        connection.query_row("SELECT COUNT(*) FROM sqlite_master;", [], |row| {
+            row.get::<_, i64>(0)
+        })?;
+
+        Ok(f(&connection))
+    }
+}
+
+impl ConnectionSource for PathBuf {
+    type Error = rusqlite::Error;
+
+    fn with_connection<R>(&self, f: impl FnOnce(&Connection) -> R) -> Result<R, Self::Error> {
+        self.as_path().with_connection(f)
+    }
+}
+
+#[cfg(feature = "r2d2")]
+impl<M: r2d2::ManageConnection<Connection = Connection>> ConnectionSource for r2d2::Pool<M> {
+    type Error = r2d2::Error;
+
+    /// Waits for a connection of the pool, at most the connection timeout of the pool
+    fn with_connection<R>(&self, f: impl FnOnce(&Connection) -> R) -> Result<R, Self::Error> {
+        let connection = self.get()?;
+
+        Ok(f(&connection))
+    }
+}
+
+/// Why a database of `parse_concurrently` couldn't be parsed
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub enum PoolError<E> {
+    /// No connection could be made
+    Connection(E),
+    /// Parsing failed in `ParseMode::Strict`
+    Strict(StrictError),
+}
+
+/// Parses every source with at most `parallelism` threads, the results are keyed like the sources
+/// A `parallelism` of 0 is treated as 1.
+pub fn parse_concurrently<'a, K, S>(
+    sources: impl IntoIterator<Item = (K, &'a S)>,
+    parallelism: usize,
+    options: &ParseOptions,
+) -> HashMap<K, Result<Metadata, PoolError<S::Error>>>
+where
+    K: Eq + Hash + Send,
+    S: ConnectionSource + ?Sized + 'a,
+{
+    run_bounded(sources.into_iter().collect(), parallelism, |source| {
+        source
+            .with_connection(|connection| report_from_connection(connection, options.clone()))
+            .map_err(PoolError::Connection)?
+            .map(|report| report.metadata)
+            .map_err(PoolError::Strict)
+    })
+    .into_iter()
+    .collect()
+}
+
+/// Parses the files in the directory of which the name matches the glob, like `*.sqlite3`
+/// The glob supports `*` and `?`. Files of the journal and WAL (`-journal`, `-wal` and `-shm`) are
+/// skipped, subdirectories aren't searched. Fails if the directory can't be read.
+pub fn parse_dir<P: AsRef<Path>>(
+    path: P,
+    glob: &str,
+) -> std::io::Result<HashMap<PathBuf, Result<Metadata, PoolError<rusqlite::Error>>>> {
+    parse_dir_with_options(path, glob, &ParseOptions::default())
+}
+
+/// Same as `parse_dir`, but with custom options
+/// The files are parsed with a thread per available CPU.
+pub fn parse_dir_with_options<P: AsRef<Path>>(
+    path: P,
+    glob: &str,
+    options: &ParseOptions,
+) -> std::io::Result<HashMap<PathBuf, Result<Metadata, PoolError<rusqlite::Error>>>> {
+    let mut paths = vec![];
+
+    for entry in std::fs::read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+
+        if entry.file_type()?.is_file()
+            && matches_glob(glob.as_bytes(), name.as_bytes())
+            && !["-journal", "-wal", "-shm"]
+                .iter()
+                .any(|s| name.ends_with(s))
+        {
+            paths.push(entry.path());
+        }
+    }
+
+    let parallelism = std::thread::available_parallelism().map_or(1, |p| p.get());
+
+    Ok(parse_concurrently(
+        paths.iter().map(|p| (p.clone(), p)),
+        parallelism,
+        options,
+    ))
+}
+
+/// The keys of the parsed databases grouped by `Metadata::fingerprint`, the keys of a group are
+/// sorted
+/// Groups with more than one key share an identical schema, failed databases are left out.
+pub fn same_schemas<K: Ord + Clone, E>(
+    results: &HashMap<K, Result<Metadata, E>>,
+) -> BTreeMap<u64, Vec<K>> {
+    let mut groups = BTreeMap::<u64, Vec<K>>::new();
+
+    for (key, metadata) in results {
+        if let Ok(metadata) = metadata {
+            groups
+                .entry(metadata.fingerprint())
+                .or_default()
+                .push(key.clone());
+        }
+    }
+
+    for keys in groups.values_mut() {
+        keys.sort();
+    }
+
+    groups
+}
+
+/// Matches `*` to any amount of characters and `?` to a single one
+fn matches_glob(glob: &[u8], name: &[u8]) -> bool {
+    match (glob.first(), name.first()) {
+        (None, _) => name.is_empty(),
+        (Some(b'*'), _) => {
+            matches_glob(&glob[1..], name) || (!name.is_empty() && matches_glob(glob, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => matches_glob(&glob[1..], &name[1..]),
+        (Some(g), Some(n)) if g == n => matches_glob(&glob[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+/// Calls the function for every value with at most `parallelism` threads, the results are in
+/// the order of the items
+pub(crate) fn run_bounded<K: Send, V: Send, R: Send>(
+    items: Vec<(K, V)>,
+    parallelism: usize,
+    f: impl Fn(&V) -> R + Sync,
+) -> Vec<(K, R)> {
+    let amount = items.len();
+    let remaining = Mutex::new(items.into_iter().enumerate());
+    let results = Mutex::new(Vec::with_capacity(amount));
+
+    std::thread::scope(|scope| {
+        for _ in 0..parallelism.clamp(1, amount.max(1)) {
+            scope.spawn(|| loop {
+                let Some((i, (key, value))) = remaining.lock().unwrap().next() else {
+                    break;
+                };
+                let result = f(&value);
+
+                results.lock().unwrap().push((i, key, result));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+
+    results.sort_by_key(|(i, _, _)| *i);
+    results.into_iter().map(|(_, k, r)| (k, r)).collect()
+}
+
+impl<E: Display> Display for PoolError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PoolError::Connection(e) => write!(f, "no connection: {e}"),
+            PoolError::Strict(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl<E: Debug + Display> std::error::Error for PoolError<E> {}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::pool::{parse_concurrently, parse_dir, run_bounded, same_schemas, PoolError};
+    use crate::{ParseMode, ParseOptions};
+
+    #[test]
+    fn test_parse_concurrently() {
+        let dir = std::env::temp_dir().join("sqlite_parser_pool");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        std::fs::create_dir(&dir).unwrap();
+
+        let paths = (0..5)
+            .map(|i| dir.join(format!("tenant_{i}.sqlite3")))
+            .collect::<Vec<_>>();
+
+        for (i, path) in paths.iter().enumerate() {
+            Connection::open(path)
+                .unwrap()
+                .execute_batch(&format!(
+                    "CREATE TABLE tenant_{i} (id INTEGER PRIMARY KEY, x DECIMAL(1.5));"
+                ))
+                .unwrap();
+        }
+
+        let missing = dir.join("missing.sqlite3");
+        let results = parse_concurrently(
+            paths.iter().chain([&missing]).map(|p| (p.clone(), p)),
+            2,
+            &Default::default(),
+        );
+
+        assert_eq!(6, results.len());
+        assert!(results[&paths[3]]
+            .as_ref()
+            .unwrap()
+            .table("tenant_3")
+            .is_some());
+        assert!(matches!(results[&missing], Err(PoolError::Connection(_))));
+
+        // The unknown type fails in strict mode
+        let strict = ParseOptions {
+            mode: ParseMode::Strict,
+            ..Default::default()
+        };
+        let results = parse_concurrently([(0, &paths[0])], 0, &strict);
+
+        assert!(matches!(results[&0], Err(PoolError::Strict(_))));
+
+        #[cfg(feature = "r2d2")]
+        {
+            let pool = r2d2::Pool::builder()
+                .max_size(2)
+                .build(FileManager(paths[1].clone()))
+                .unwrap();
+            let results = parse_concurrently([("tenant", &pool)], 4, &Default::default());
+
+            assert!(results["tenant"]
+                .as_ref()
+                .unwrap()
+                .table("tenant_1")
+                .is_some());
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_dir() {
+        let dir = std::env::temp_dir().join("sqlite_parser_parse_dir");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        std::fs::create_dir(&dir).unwrap();
+
+        for (name, sql) in [
+            (
+                "a.sqlite3",
+                "CREATE TABLE user (id INTEGER PRIMARY KEY, name TEXT);",
+            ),
+            (
+                "b.sqlite3",
+                "create table user (\n  id integer primary key,\n  name text\n);",
+            ),
+            ("c.sqlite3", "CREATE TABLE post (id INTEGER PRIMARY KEY);"),
+        ] {
+            Connection::open(dir.join(name))
+                .unwrap()
+                .execute_batch(sql)
+                .unwrap();
+        }
+
+        std::fs::write(dir.join("a.sqlite3-wal"), "").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a database").unwrap();
+
+        let results = parse_dir(&dir, "*.sqlite3*").unwrap();
+        let mut names = results
+            .keys()
+            .map(|p| p.file_name().unwrap().to_str().unwrap())
+            .collect::<Vec<_>>();
+
+        names.sort();
+
+        assert_eq!(vec!["a.sqlite3", "b.sqlite3", "c.sqlite3"], names);
+
+        let groups = same_schemas(&results);
+
+        assert_eq!(2, groups.len());
+        assert!(groups
+            .values()
+            .any(|g| g == &vec![dir.join("a.sqlite3"), dir.join("b.sqlite3")]));
+        assert!(parse_dir(dir.join("missing"), "*").is_err());
+        assert!(parse_dir(&dir, "*.txt").unwrap()[&dir.join("notes.txt")].is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_run_bounded() {
+        let running = std::sync::atomic::AtomicUsize::new(0);
+        let results = run_bounded((0..20).map(|i| (i, i * 2)).collect(), 3, |value| {
+            let now = running.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+
+            assert!(now < 3);
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            running.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+
+            value + 1
+        });
+
+        assert_eq!((0..20).map(|i| (i, i * 2 + 1)).collect::<Vec<_>>(), results);
+    }
+
+    /// A minimal manager, `r2d2_sqlite` depends on another rusqlite version
+    #[cfg(feature = "r2d2")]
+    struct FileManager(std::path::PathBuf);
+
+    #[cfg(feature = "r2d2")]
+    impl r2d2::ManageConnection for FileManager {
+        type Connection = Connection;
+        type Error = rusqlite::Error;
+
+        fn connect(&self) -> Result<Connection, rusqlite::Error> {
+            Connection::open(&self.0)
+        }
+
+        fn is_valid(&self, connection: &mut Connection) -> Result<(), rusqlite::Error> {
+            connection.execute_batch("")
+        }
+
+        fn has_broken(&self, _: &mut Connection) -> bool {
+            false
+        }
+    }
+}
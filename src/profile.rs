@@ -0,0 +1,366 @@
+//! Computes statistics over the actual data of the tables
+
+#[cfg(feature = "rusqlite")]
+use rusqlite::Connection;
+
+use crate::sample::Value;
+use crate::LookupMode;
+#[cfg(feature = "rusqlite")]
+use crate::{quote_identifier, Column, Table};
+
+/// Statistics of all the columns of a table
+#[derive(Debug, PartialEq, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TableProfile {
+    /// The amount of rows that were profiled
+    pub row_count: u64,
+    /// The statistics per column, in the same order as `Table::columns`
+    pub columns: Vec<ColumnProfile>,
+    /// How `column` compares names, copied from `Table::lookup`
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub lookup: LookupMode,
+}
+
+impl TableProfile {
+    /// Finds the statistics of the column, the name is compared with `lookup`
+    pub fn column(&self, column_name: &str) -> Option<&ColumnProfile> {
+        self.columns
+            .iter()
+            .find(|c| self.lookup.matches(&c.column, column_name))
+    }
+}
+
+/// Statistics of a single column
+#[derive(Debug, PartialEq, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColumnProfile {
+    /// The name of the column
+    pub column: String,
+    /// The amount of rows where the column is NULL
+    pub null_count: u64,
+    /// The amount of rows where the column is not NULL
+    pub value_count: u64,
+    /// The amount of distinct non-NULL values
+    pub distinct_count: u64,
+    /// The smallest value, `None` if all the values are NULL
+    pub min: Option<Value>,
+    /// The largest value, `None` if all the values are NULL
+    pub max: Option<Value>,
+    /// The sum of `length()` of all the non-NULL values
+    pub total_length: u64,
+}
+
+impl ColumnProfile {
+    /// The average `length()` of the non-NULL values
+    pub fn average_length(&self) -> Option<f64> {
+        if self.value_count == 0 {
+            None
+        } else {
+            Some(self.total_length as f64 / self.value_count as f64)
+        }
+    }
+}
+
+/// A range of values of `histogram`, the upper bound is only inclusive for the last bucket
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HistogramBucket {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: u64,
+}
+
+/// The `n` most common non-NULL values of the column with their count, the most common first
+/// Values with the same count are sorted by value.
+#[cfg(feature = "rusqlite")]
+pub fn top_values(
+    connection: &Connection,
+    table: &Table,
+    column: &Column,
+    n: usize,
+) -> Vec<(Value, u64)> {
+    let c = quote_identifier(&column.name);
+    let mut stmt = connection
+        .prepare(&format!(
+            "SELECT {c}, COUNT(*) FROM {} WHERE {c} IS NOT NULL GROUP BY 1 ORDER BY 2 DESC, 1 LIMIT {n};",
+            quote_identifier(&table.table_name)
+        ))
+        .unwrap();
+    let mut rows = stmt.query([]).unwrap();
+    let mut values = vec![];
+
+    while let Some(row) = rows.next().unwrap() {
+        values.push((
+            Value::from_stored(row.get_ref(0).unwrap(), column.the_type),
+            row.get(1).unwrap(),
+        ));
+    }
+
+    values
+}
+
+/// Divides the numbers stored in the column in `buckets` ranges of equal width between the
+/// smallest and largest number
+/// Text and blobs are skipped, empty if the column has no numbers.
+#[cfg(feature = "rusqlite")]
+pub fn histogram(
+    connection: &Connection,
+    table: &Table,
+    column: &Column,
+    buckets: usize,
+) -> Vec<HistogramBucket> {
+    let c = quote_identifier(&column.name);
+    let numbers = format!(
+        "SELECT {c} AS n FROM {} WHERE typeof({c}) IN ('integer', 'real')",
+        quote_identifier(&table.table_name)
+    );
+    let (min, max): (Option<f64>, Option<f64>) = connection
+        .query_row(
+            &format!("SELECT MIN(n), MAX(n) FROM ({numbers});"),
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .unwrap();
+    let (Some(min), Some(max)) = (min, max) else {
+        return vec![];
+    };
+    let buckets = if min == max { 1 } else { buckets.max(1) };
+    let width = (max - min) / buckets as f64;
+    let mut histogram = (0..buckets)
+        .map(|i| HistogramBucket {
+            lower: min + width * i as f64,
+            upper: if i + 1 == buckets {
+                max
+            } else {
+                min + width * (i + 1) as f64
+            },
+            count: 0,
+        })
+        .collect::<Vec<_>>();
+    let mut stmt = connection
+        .prepare(&format!(
+            "SELECT MIN(CAST((n - ?1) / ?2 AS INTEGER), ?3), COUNT(*) FROM ({numbers}) GROUP BY 1;"
+        ))
+        .unwrap();
+    // A width of 0 divides to NULL, which puts every number in the first bucket
+    let mut rows = stmt.query((min, width, buckets as i64 - 1)).unwrap();
+
+    while let Some(row) = rows.next().unwrap() {
+        let bucket: Option<usize> = row.get(0).unwrap();
+
+        histogram[bucket.unwrap_or(0)].count = row.get(1).unwrap();
+    }
+
+    histogram
+}
+
+/// The amount of aggregates per column of `profile_table`
+#[cfg(feature = "rusqlite")]
+const AGGREGATES_PER_COLUMN: usize = 6;
+
+/// Profiles every column of the table, this scans the whole table once
+/// Wide tables are scanned once per batch of columns, since a query can't return more columns
+/// than `SQLITE_LIMIT_COLUMN`.
+#[cfg(feature = "rusqlite")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all, fields(table = %table.table_name)))]
+pub fn profile_table(connection: &Connection, table: &Table) -> TableProfile {
+    let max_columns = connection.limit(rusqlite::limits::Limit::SQLITE_LIMIT_COLUMN) as usize;
+    // The first result column is the row count
+    let batch_size = (max_columns.saturating_sub(1) / AGGREGATES_PER_COLUMN).max(1);
+    let mut profile = TableProfile {
+        row_count: 0,
+        columns: vec![],
+        lookup: table.lookup,
+    };
+
+    if table.columns.is_empty() {
+        profile.row_count = connection
+            .query_row(
+                &format!(
+                    "SELECT COUNT(*) FROM {};",
+                    quote_identifier(&table.table_name)
+                ),
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+    }
+
+    for batch in table.columns.chunks(batch_size) {
+        let (row_count, columns) = profile_columns(connection, table, batch);
+
+        profile.row_count = row_count;
+        profile.columns.extend(columns);
+    }
+
+    profile
+}
+
+/// Profiles the columns of the table in one query, returns the row count and the profiles
+#[cfg(feature = "rusqlite")]
+fn profile_columns(
+    connection: &Connection,
+    table: &Table,
+    columns: &[Column],
+) -> (u64, Vec<ColumnProfile>) {
+    let aggregates = columns
+        .iter()
+        .map(|c| {
+            let c = quote_identifier(&c.name);
+
+            format!(
+                "COUNT(*) - COUNT({c}), COUNT({c}), COUNT(DISTINCT {c}), MIN({c}), MAX({c}), IFNULL(SUM(LENGTH({c})), 0)"
+            )
+        })
+        .collect::<Vec<_>>();
+    let query = format!(
+        "SELECT COUNT(*), {} FROM {};",
+        aggregates.join(", "),
+        quote_identifier(&table.table_name)
+    );
+
+    connection
+        .query_row(&query, [], |row| {
+            let mut profiles = vec![];
+
+            for (i, column) in columns.iter().enumerate() {
+                let offset = 1 + i * AGGREGATES_PER_COLUMN;
+                let value = |index: usize| -> rusqlite::Result<Option<Value>> {
+                    Ok(
+                        match Value::from_stored(row.get_ref(index)?, column.the_type) {
+                            Value::Null => None,
+                            v => Some(v),
+                        },
+                    )
+                };
+
+                profiles.push(ColumnProfile {
+                    column: column.name.clone(),
+                    null_count: row.get(offset)?,
+                    value_count: row.get(offset + 1)?,
+                    distinct_count: row.get(offset + 2)?,
+                    min: value(offset + 3)?,
+                    max: value(offset + 4)?,
+                    total_length: row.get(offset + 5)?,
+                });
+            }
+
+            Ok((row.get(0)?, profiles))
+        })
+        .unwrap()
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::profile::{histogram, profile_table, top_values};
+    use crate::sample::Value;
+    use crate::tests::metadata_from_sql;
+    use crate::LookupMode;
+
+    #[test]
+    fn test_profile_table() {
+        let sql = "CREATE TABLE user (
+            user_id INTEGER NOT NULL PRIMARY KEY,
+            status TEXT,
+            score REAL
+        );
+        INSERT INTO user VALUES (1, 'new', 1), (2, NULL, 2.5), (3, 'done', NULL), (4, 'new', 3);";
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection.execute_batch(sql).unwrap();
+
+        let metadata = metadata_from_sql(sql);
+        let profile = profile_table(&connection, metadata.table("user").unwrap());
+
+        assert_eq!(4, profile.row_count);
+
+        let status = profile.column("status").unwrap();
+
+        assert_eq!(1, status.null_count);
+        assert_eq!(2, status.distinct_count);
+        assert_eq!(Some(Value::Text("done".to_string())), status.min);
+        assert_eq!(Some(Value::Text("new".to_string())), status.max);
+        assert_eq!(Some(10. / 3.), status.average_length());
+
+        let score = profile.column("score").unwrap();
+
+        assert_eq!(Some(Value::Real(1.)), score.min);
+        assert_eq!(Some(Value::Real(3.)), score.max);
+        assert!(profile.column("SCORE").is_some());
+
+        let mut table = metadata.table("user").unwrap().clone();
+
+        table.lookup = LookupMode::Exact;
+
+        let profile = profile_table(&connection, &table);
+
+        assert!(profile.column("score").is_some());
+        assert!(profile.column("SCORE").is_none());
+    }
+
+    #[test]
+    fn test_top_values_and_histogram() {
+        let sql = "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, status TEXT, age);
+        INSERT INTO user VALUES
+            (1, 'new', 10), (2, 'done', 20), (3, 'new', 'unknown'), (4, NULL, 29), (5, 'done', 30),
+            (6, 'new', 30.0);";
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection.execute_batch(sql).unwrap();
+
+        let metadata = metadata_from_sql(sql);
+        let table = metadata.table("user").unwrap();
+
+        assert_eq!(
+            vec![
+                (Value::Text("new".to_string()), 3),
+                (Value::Text("done".to_string()), 2)
+            ],
+            top_values(&connection, table, table.column("status").unwrap(), 5)
+        );
+
+        let buckets = histogram(&connection, table, table.column("age").unwrap(), 2);
+
+        assert_eq!(
+            vec![(10., 20., 1), (20., 30., 4)],
+            buckets
+                .iter()
+                .map(|b| (b.lower, b.upper, b.count))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            1,
+            histogram(&connection, table, table.column("user_id").unwrap(), 0).len()
+        );
+        assert!(histogram(&connection, table, table.column("status").unwrap(), 5).is_empty());
+    }
+
+    #[test]
+    fn test_profile_wide_table() {
+        let columns = (0..400)
+            .map(|i| format!("c{i} INTEGER"))
+            .collect::<Vec<_>>();
+        let values = (0..400).map(|i| i.to_string()).collect::<Vec<_>>();
+        let sql = format!(
+            "CREATE TABLE wide ({});
+            INSERT INTO wide VALUES ({});",
+            columns.join(", "),
+            values.join(", ")
+        );
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection.execute_batch(&sql).unwrap();
+
+        let metadata = metadata_from_sql(&sql);
+        let profile = profile_table(&connection, metadata.table("wide").unwrap());
+
+        assert_eq!(1, profile.row_count);
+        assert_eq!(400, profile.columns.len());
+        assert_eq!(
+            Some(Value::Integer(399)),
+            profile.column("c399").unwrap().max
+        );
+    }
+}
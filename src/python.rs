@@ -0,0 +1,86 @@
+//! The Python bindings, `parse(path)` returns the metadata as nested dicts and lists with the
+//! same field names as the Rust types
+//!
+//! Build the module with maturin, which enables the `extension-module` feature of pyo3.
+
+use std::panic::catch_unwind;
+
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+use crate::diff::parse_read_only;
+
+/// Parses the SQLite file, raises a `ValueError` if the file can't be opened or parsed
+/// The file is opened read only, so a missing file raises instead of creating a database.
+#[pyfunction]
+fn parse(py: Python<'_>, path: &str) -> PyResult<PyObject> {
+    let metadata = catch_unwind(|| parse_read_only(path))
+        .map_err(|panic| {
+            panic
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| panic.downcast_ref::<&str>().map(|m| m.to_string()))
+                .unwrap_or_default()
+        })
+        .and_then(|result| result.map_err(|e| e.to_string()))
+        .map_err(|message| PyValueError::new_err(format!("Can't parse {path}: {message}")))?;
+
+    // The JSON module builds the dicts, so the field names match the JSON of the ffi module
+    let json = serde_json::to_string(&metadata).unwrap();
+
+    Ok(py.import("json")?.call_method1("loads", (json,))?.unbind())
+}
+
+#[pymodule]
+fn sqlite_parser(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(parse, m)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::prelude::*;
+    use pyo3::types::PyDict;
+
+    use crate::python::parse;
+
+    #[test]
+    fn test_parse() {
+        let path = std::env::temp_dir().join("sqlite_parser_python.sqlite3");
+        let _ = std::fs::remove_file(&path);
+
+        rusqlite::Connection::open(&path)
+            .unwrap()
+            .execute_batch("CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY);")
+            .unwrap();
+
+        pyo3::prepare_freethreaded_python();
+
+        Python::with_gil(|py| {
+            let metadata = parse(py, path.to_str().unwrap()).unwrap();
+            let metadata = metadata.bind(py).downcast::<PyDict>().unwrap();
+            let column_name = metadata
+                .get_item("tables")
+                .unwrap()
+                .unwrap()
+                .get_item("user")
+                .unwrap()
+                .get_item("columns")
+                .unwrap()
+                .get_item(0)
+                .unwrap()
+                .get_item("name")
+                .unwrap();
+
+            assert_eq!("user_id", column_name.extract::<String>().unwrap());
+            assert!(parse(py, "/missing/directory/file.sqlite3").is_err());
+
+            let missing = std::env::temp_dir().join("sqlite_parser_python_missing.sqlite3");
+            let _ = std::fs::remove_file(&missing);
+
+            assert!(parse(py, missing.to_str().unwrap()).is_err());
+            assert!(!missing.exists());
+        });
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
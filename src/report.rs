@@ -0,0 +1,224 @@
+//! Anomalies that are recovered from while parsing
+
+use std::fmt::{Display, Formatter, Result};
+
+use crate::Metadata;
+
+/// The metadata with the anomalies that were found while parsing it
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct ParseReport {
+    pub metadata: Metadata,
+    pub warnings: Vec<ParseWarning>,
+}
+
+/// An anomaly in the database that doesn't prevent parsing the rest of the schema
+#[derive(Debug, PartialEq, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParseWarning {
+    /// The declared type has parameters that aren't integers, like `DECIMAL(1.5)`, the column has
+    /// type `Type::Unknown`
+    UnknownType {
+        table: String,
+        column: String,
+        declared_type: String,
+    },
+    /// The index contains expressions, only the columns are part of the index
+    /// An index without columns is skipped
+    IndexExpression { table: String, index: String },
+    /// The index has the same columns as an earlier index, the index is skipped
+    DuplicateIndex {
+        table: String,
+        index: String,
+        duplicate_of: String,
+    },
+    /// The foreign key refers to a table that doesn't exist, the foreign key is skipped
+    MissingForeignKeyTable {
+        table: String,
+        referenced_table: String,
+    },
+    /// The foreign key refers to a column that doesn't exist, the foreign key is skipped
+    MissingForeignKeyColumn {
+        table: String,
+        referenced_table: String,
+        column: String,
+    },
+    /// The annotations refer to a table or column that doesn't exist, the annotation is skipped
+    UnknownAnnotation {
+        table: String,
+        column: Option<String>,
+    },
+}
+
+/// The anomalies that made parsing fail in `ParseMode::Strict`
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct StrictError {
+    pub warnings: Vec<ParseWarning>,
+}
+
+impl ParseWarning {
+    /// The table in which the anomaly was found
+    pub fn table(&self) -> &str {
+        match self {
+            ParseWarning::UnknownType { table, .. }
+            | ParseWarning::IndexExpression { table, .. }
+            | ParseWarning::DuplicateIndex { table, .. }
+            | ParseWarning::MissingForeignKeyTable { table, .. }
+            | ParseWarning::MissingForeignKeyColumn { table, .. }
+            | ParseWarning::UnknownAnnotation { table, .. } => table,
+        }
+    }
+}
+
+impl Display for ParseWarning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            ParseWarning::UnknownType {
+                table,
+                column,
+                declared_type,
+            } => write!(f, "{table}.{column}: unknown type '{declared_type}'"),
+            ParseWarning::IndexExpression { table, index } => {
+                write!(f, "{table}: index {index} contains expressions")
+            }
+            ParseWarning::DuplicateIndex {
+                table,
+                index,
+                duplicate_of,
+            } => write!(
+                f,
+                "{table}: index {index} has the same columns as {duplicate_of}"
+            ),
+            ParseWarning::MissingForeignKeyTable {
+                table,
+                referenced_table,
+            } => write!(
+                f,
+                "{table}: foreign key refers to missing table {referenced_table}"
+            ),
+            ParseWarning::MissingForeignKeyColumn {
+                table,
+                referenced_table,
+                column,
+            } => write!(
+                f,
+                "{table}: foreign key refers to missing column {referenced_table}.{column}"
+            ),
+            ParseWarning::UnknownAnnotation {
+                table,
+                column: None,
+            } => write!(f, "{table}: annotated table doesn't exist"),
+            ParseWarning::UnknownAnnotation {
+                table,
+                column: Some(column),
+            } => write!(f, "{table}.{column}: annotated column doesn't exist"),
+        }
+    }
+}
+
+impl Display for StrictError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "Found {} anomalies in strict mode:", self.warnings.len())?;
+
+        for warning in &self.warnings {
+            write!(f, "\n- {warning}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for StrictError {}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::{parse_sql_report, ParseMode, ParseOptions, Type};
+
+    #[test]
+    fn test_parse_report() {
+        let report = parse_sql_report(
+            "PRAGMA foreign_keys = OFF;
+            CREATE TABLE user (
+                user_id INTEGER NOT NULL PRIMARY KEY,
+                name VARCHAR(10),
+                email TEXT,
+                amount DECIMAL(1.5),
+                parent_id INTEGER REFERENCES parent(parent_id),
+                other_id INTEGER REFERENCES user(missing_id)
+            );
+            CREATE INDEX user_lower_name ON user(lower(name));
+            CREATE INDEX user_email ON user(email, lower(name));
+            CREATE INDEX user_email_2 ON user(email);",
+            ParseOptions::default(),
+        )
+        .unwrap();
+        let user = report.metadata.table("user").unwrap();
+
+        assert_eq!(Type::Text, user.column("name").unwrap().the_type);
+        assert_eq!(Type::Unknown, user.column("amount").unwrap().the_type);
+        assert!(user.foreign_keys.is_empty());
+        assert_eq!(
+            vec!["user_email"],
+            user.indexes.iter().map(|i| &i.name).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                "user.amount: unknown type 'DECIMAL(1.5)'",
+                "user: foreign key refers to missing column user.missing_id",
+                "user: foreign key refers to missing table parent",
+                "user: index user_lower_name contains expressions",
+                "user: index user_email contains expressions",
+                "user: index user_email_2 has the same columns as user_email",
+            ],
+            report
+                .warnings
+                .iter()
+                .map(|w| w.to_string())
+                .collect::<Vec<_>>()
+        );
+        assert!(report.warnings.iter().all(|w| w.table() == "user"));
+    }
+
+    #[test]
+    fn test_strict_mode() {
+        let sql = "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, rate DECIMAL(1.5));";
+        let strict = ParseOptions {
+            mode: ParseMode::Strict,
+            ..Default::default()
+        };
+        let error = parse_sql_report(sql, strict.clone()).unwrap_err();
+
+        assert_eq!(1, error.warnings.len());
+        assert_eq!(
+            "Found 1 anomalies in strict mode:\n- user.rate: unknown type 'DECIMAL(1.5)'",
+            error.to_string()
+        );
+
+        // Declared types that aren't one of the types get the type of their affinity
+        let report = parse_sql_report(
+            "CREATE TABLE user (
+                user_id BIGINT NOT NULL PRIMARY KEY,
+                name VARCHAR(255),
+                avatar,
+                score DOUBLE PRECISION,
+                balance MONEY
+            );",
+            strict,
+        )
+        .unwrap();
+        let user = report.metadata.table("user").unwrap();
+
+        assert_eq!(
+            vec![
+                Type::Integer,
+                Type::Text,
+                Type::Blob,
+                Type::Real,
+                Type::Numeric {
+                    precision: None,
+                    scale: None
+                }
+            ],
+            user.columns.iter().map(|c| c.the_type).collect::<Vec<_>>()
+        );
+    }
+}
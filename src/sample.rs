@@ -0,0 +1,170 @@
+//! Extracts example rows from the tables
+
+#[cfg(feature = "rusqlite")]
+use rusqlite::types::{ToSql, ToSqlOutput, ValueRef};
+#[cfg(feature = "rusqlite")]
+use rusqlite::Connection;
+
+#[cfg(feature = "rusqlite")]
+use crate::{quote_identifier, Table, Type};
+
+/// A single value read from the database
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value {
+    Null,
+    Integer(i64),
+    Real(f64),
+    Text(String),
+    Blob(Vec<u8>),
+}
+
+impl Value {
+    /// Converts a stored value, using the declared type of the column when the stored value can be
+    /// represented losslessly in it (e.g. an integer stored in a REAL column)
+    #[cfg(feature = "rusqlite")]
+    pub(crate) fn from_stored(value: ValueRef, the_type: Type) -> Self {
+        match (value, the_type) {
+            (ValueRef::Null, _) => Value::Null,
+            (ValueRef::Integer(i), Type::Real) => Value::Real(i as f64),
+            (ValueRef::Integer(i), _) => Value::Integer(i),
+            (ValueRef::Real(r), _) => Value::Real(r),
+            (ValueRef::Text(t), Type::Blob) => Value::Blob(t.to_vec()),
+            (ValueRef::Text(t), _) => Value::Text(String::from_utf8_lossy(t).to_string()),
+            (ValueRef::Blob(b), _) => Value::Blob(b.to_vec()),
+        }
+    }
+}
+
+#[cfg(feature = "rusqlite")]
+impl ToSql for Value {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::Borrowed(match self {
+            Value::Null => ValueRef::Null,
+            Value::Integer(i) => ValueRef::Integer(*i),
+            Value::Real(r) => ValueRef::Real(*r),
+            Value::Text(t) => ValueRef::Text(t.as_bytes()),
+            Value::Blob(b) => ValueRef::Blob(b),
+        }))
+    }
+}
+
+// Reals are compared by their bits, so a sample always equals itself (even NaN)
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Integer(a), Value::Integer(b)) => a == b,
+            (Value::Real(a), Value::Real(b)) => a.to_bits() == b.to_bits(),
+            (Value::Text(a), Value::Text(b)) => a == b,
+            (Value::Blob(a), Value::Blob(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+/// A row of a table, the values are in the same order as `Table::columns`
+#[derive(Debug, PartialEq, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SampleRow {
+    pub values: Vec<Value>,
+}
+
+/// Reads up to `n` rows of the table
+#[cfg(feature = "rusqlite")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(connection, table), fields(table = %table.table_name)))]
+pub fn sample_rows(connection: &Connection, table: &Table, n: usize) -> Vec<SampleRow> {
+    let columns = table
+        .columns
+        .iter()
+        .map(|c| quote_identifier(&c.name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut stmt = connection
+        .prepare(&format!(
+            "SELECT {columns} FROM {} LIMIT {n};",
+            quote_identifier(&table.table_name)
+        ))
+        .unwrap();
+    let mut rows = stmt.query([]).unwrap();
+    let mut samples = vec![];
+
+    while let Some(row) = rows.next().unwrap() {
+        let values = table
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| Value::from_stored(row.get_ref(i).unwrap(), column.the_type))
+            .collect();
+
+        samples.push(SampleRow { values });
+    }
+
+    samples
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::sample::{sample_rows, Value};
+    use crate::{parse_connection, Metadata, ParseOptions, Parser};
+
+    #[test]
+    fn test_sample_rows() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE user (
+                user_id INTEGER NOT NULL PRIMARY KEY,
+                name TEXT,
+                score REAL NOT NULL
+            );
+            INSERT INTO user VALUES (1, 'a', 1), (2, NULL, 2.5), (3, 'c', 3);",
+            )
+            .unwrap();
+
+        struct Parse(Option<Metadata>);
+
+        impl Parser for Parse {
+            fn parse_options(&self) -> ParseOptions {
+                ParseOptions {
+                    samples: Some(2),
+                    ..Default::default()
+                }
+            }
+
+            fn process_tables(&mut self, tables: Metadata) {
+                self.0 = Some(tables)
+            }
+        }
+
+        let mut parse = Parse(None);
+
+        parse_connection(&connection, &mut parse);
+
+        let metadata = parse.0.unwrap();
+        let samples = &metadata.samples["user"];
+
+        assert_eq!(2, samples.len());
+        assert_eq!(
+            vec![Value::Integer(2), Value::Null, Value::Real(2.5)],
+            samples[1].values
+        );
+        assert_eq!(
+            vec![
+                Value::Integer(1),
+                Value::Text("a".to_string()),
+                Value::Real(1.)
+            ],
+            samples[0].values
+        );
+        assert_eq!(
+            3,
+            sample_rows(&connection, metadata.table("user").unwrap(), 10).len()
+        );
+    }
+}
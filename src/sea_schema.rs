@@ -0,0 +1,214 @@
+//! Converts the metadata from and to the SQLite schema types of `sea-schema`, so the output can be
+//! used by `sea-query` and `sea-orm` tooling
+//!
+//! ```
+//! use sea_schema::sea_query::SqliteQueryBuilder;
+//! use sea_schema::sqlite::def::Schema;
+//! use sqlite_parser::parse_sql;
+//!
+//! let metadata = parse_sql("CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT);");
+//! let schema = Schema::from(&metadata);
+//!
+//! assert_eq!("user", schema.tables[0].name);
+//! println!("{}", schema.tables[0].write().to_string(SqliteQueryBuilder));
+//! ```
+//!
+//! The foreign keys are not converted to `sea-schema`, since the fields of `ForeignKeysInfo` are
+//! private. The other way around, the tables are written with `TableDef::write` and parsed, so the
+//! foreign keys are kept. The declared types are mapped with `sea-schema`'s `parse_type`.
+
+use ::sea_schema::sea_query::SqliteQueryBuilder;
+use ::sea_schema::sqlite::def::{parse_type, ColumnInfo, DefaultType, IndexInfo, Schema, TableDef};
+
+use crate::ddl::explicit_indexes;
+use crate::{parse_sql, Column, IndexOrigin, Metadata, Table};
+
+impl From<&Metadata> for Schema {
+    /// The tables are sorted by name, the indexes that are not created by a constraint are in
+    /// `Schema::indexes` like `sea-schema` discovers them
+    fn from(metadata: &Metadata) -> Self {
+        let mut tables = metadata
+            .sorted_tables()
+            .into_iter()
+            .map(TableDef::from)
+            .collect::<Vec<_>>();
+        let indexes = tables
+            .iter_mut()
+            .flat_map(|t| std::mem::take(&mut t.indexes))
+            .collect();
+
+        Schema { tables, indexes }
+    }
+}
+
+impl From<Metadata> for Schema {
+    fn from(metadata: Metadata) -> Self {
+        Schema::from(&metadata)
+    }
+}
+
+impl From<&Table> for TableDef {
+    fn from(table: &Table) -> Self {
+        let index_info = |name: &str, columns: &[Column], unique, origin: &str| IndexInfo {
+            r#type: "index".to_string(),
+            index_name: name.to_string(),
+            table_name: table.table_name.clone(),
+            unique,
+            origin: origin.to_string(),
+            partial: 0,
+            columns: columns.iter().map(|c| c.name.clone()).collect(),
+        };
+
+        TableDef {
+            name: table.table_name.clone(),
+            foreign_keys: vec![],
+            indexes: explicit_indexes(table)
+                .map(|i| index_info(&i.name, &i.columns, i.unique, "c"))
+                .collect(),
+            constraints: table
+                .automatic_indexes
+                .iter()
+                .filter(|i| i.origin == IndexOrigin::Unique)
+                .map(|i| index_info(&i.name, &i.columns, true, "u"))
+                .collect(),
+            columns: table
+                .columns
+                .iter()
+                .map(|c| ColumnInfo {
+                    cid: c.id,
+                    name: c.name.clone(),
+                    r#type: parse_type(&c.declared_type).unwrap(),
+                    not_null: !c.nullable,
+                    default_value: default_type(c.default_value.as_deref()),
+                    primary_key: c.part_of_pk,
+                })
+                .collect(),
+            auto_increment: table.has_autoincrement(),
+        }
+    }
+}
+
+impl From<&Schema> for Metadata {
+    /// Parses the CREATE TABLE and CREATE INDEX statements of `TableDef::write` and
+    /// `IndexInfo::write`
+    fn from(schema: &Schema) -> Self {
+        let statements = schema
+            .tables
+            .iter()
+            .map(|t| t.write().to_string(SqliteQueryBuilder))
+            .chain(
+                schema
+                    .tables
+                    .iter()
+                    .flat_map(|t| &t.indexes)
+                    .chain(&schema.indexes)
+                    .map(|i| i.write().to_string(SqliteQueryBuilder)),
+            )
+            .collect::<Vec<_>>();
+
+        parse_sql(&format!("{};", statements.join(";\n")))
+    }
+}
+
+impl From<Schema> for Metadata {
+    fn from(schema: Schema) -> Self {
+        Metadata::from(&schema)
+    }
+}
+
+/// The default value like `sea-schema` reads it from `pragma_table_info`
+fn default_type(default_value: Option<&str>) -> DefaultType {
+    let Some(default_value) = default_value else {
+        return DefaultType::Unspecified;
+    };
+
+    if default_value.eq_ignore_ascii_case("NULL") {
+        return DefaultType::Null;
+    }
+
+    let value = default_value.replace('\'', "");
+
+    if let Ok(integer) = value.parse() {
+        DefaultType::Integer(integer)
+    } else if let Ok(float) = value.parse() {
+        DefaultType::Float(float)
+    } else if value.eq_ignore_ascii_case("CURRENT_TIMESTAMP") {
+        DefaultType::CurrentTimestamp
+    } else {
+        DefaultType::String(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::sea_schema::sea_query::ColumnType;
+    use ::sea_schema::sqlite::def::{DefaultType, Schema};
+
+    use crate::tests::metadata_from_sql;
+    use crate::Metadata;
+
+    #[test]
+    fn test_sea_schema() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (
+                user_id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+                email VARCHAR(100) NOT NULL UNIQUE,
+                score REAL DEFAULT 1.5,
+                created TEXT DEFAULT CURRENT_TIMESTAMP
+            );
+            CREATE TABLE post (
+                post_id INTEGER NOT NULL PRIMARY KEY,
+                user_id INTEGER NOT NULL REFERENCES user
+            );
+            CREATE INDEX post_user_id ON post(user_id);",
+        );
+        let schema = Schema::from(&metadata);
+        let user = &schema.tables[1];
+
+        assert_eq!(
+            vec!["post", "user"],
+            schema.tables.iter().map(|t| &t.name).collect::<Vec<_>>()
+        );
+        assert!(user.auto_increment);
+        assert_eq!(ColumnType::Integer, user.columns[0].r#type);
+        assert!(user.columns[0].primary_key);
+        assert_eq!(DefaultType::Float(1.5), user.columns[2].default_value);
+        assert_eq!(DefaultType::CurrentTimestamp, user.columns[3].default_value);
+        assert_eq!(vec!["email"], user.constraints[0].columns);
+        assert_eq!("post_user_id", schema.indexes[0].index_name);
+
+        let converted = Metadata::from(&schema);
+
+        for table in metadata.tables.values() {
+            let converted = converted.table(&table.table_name).unwrap();
+
+            assert_eq!(
+                table.columns.iter().map(|c| &c.name).collect::<Vec<_>>(),
+                converted
+                    .columns
+                    .iter()
+                    .map(|c| &c.name)
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(
+                table
+                    .primary_key()
+                    .iter()
+                    .map(|c| &c.name)
+                    .collect::<Vec<_>>(),
+                converted
+                    .primary_key()
+                    .iter()
+                    .map(|c| &c.name)
+                    .collect::<Vec<_>>()
+            );
+            assert_eq!(table.has_autoincrement(), converted.has_autoincrement());
+        }
+
+        assert!(converted
+            .table("post")
+            .unwrap()
+            .index("post_user_id")
+            .is_some());
+    }
+}
@@ -0,0 +1,175 @@
+//! Reads the planner statistics gathered by `ANALYZE` from `sqlite_stat1` and `sqlite_stat4`
+
+#[cfg(feature = "rusqlite")]
+use std::collections::HashMap;
+
+#[cfg(feature = "rusqlite")]
+use rusqlite::Connection;
+
+#[cfg(feature = "rusqlite")]
+use crate::table_exists;
+
+/// The statistics of an index
+#[derive(Debug, PartialEq, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IndexStatistics {
+    /// The (estimated) amount of rows in the index
+    pub row_count: u64,
+    /// The average amount of rows that match the first 1, 2, ... columns of the index
+    pub rows_per_key: Vec<u64>,
+    /// The samples of `sqlite_stat4`, empty if not available
+    pub samples: Vec<Stat4Sample>,
+}
+
+impl IndexStatistics {
+    /// The fraction of rows an equality lookup on the first `columns` columns is expected to
+    /// return, lower is more selective
+    pub fn selectivity(&self, columns: usize) -> Option<f64> {
+        if self.row_count == 0 || columns == 0 {
+            return None;
+        }
+
+        let rows = *self.rows_per_key.get(columns - 1)?;
+
+        Some(rows as f64 / self.row_count as f64)
+    }
+}
+
+/// A single sample of `sqlite_stat4`, every list contains a number per index column prefix
+#[derive(Debug, PartialEq, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stat4Sample {
+    /// The approximate amount of rows equal to the sample
+    pub equal: Vec<u64>,
+    /// The approximate amount of rows less than the sample
+    pub less_than: Vec<u64>,
+    /// The approximate amount of distinct values less than the sample
+    pub distinct_less_than: Vec<u64>,
+}
+
+/// Queries the statistics of every analyzed index, or of the indexes of a single table, keyed by
+/// index name
+/// The map is empty when `ANALYZE` never ran
+#[cfg(feature = "rusqlite")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
+pub(crate) fn query_index_statistics(
+    connection: &Connection,
+    table_name: Option<&str>,
+) -> HashMap<String, IndexStatistics> {
+    let mut statistics = HashMap::new();
+
+    if !table_exists(connection, "sqlite_stat1") {
+        return statistics;
+    }
+
+    let mut stmt = connection
+        .prepare(
+            "SELECT idx, stat FROM sqlite_stat1 WHERE idx IS NOT NULL AND (?1 IS NULL OR tbl = ?1);",
+        )
+        .unwrap();
+    let mut rows = stmt.query([table_name]).unwrap();
+
+    while let Some(row) = rows.next().unwrap() {
+        let index: String = row.get(0).unwrap();
+        let stat: String = row.get(1).unwrap();
+        let mut numbers = parse_numbers(&stat).into_iter();
+
+        let row_count = match numbers.next() {
+            Some(row_count) => row_count,
+            None => continue,
+        };
+
+        statistics.insert(
+            index,
+            IndexStatistics {
+                row_count,
+                rows_per_key: numbers.collect(),
+                samples: vec![],
+            },
+        );
+    }
+
+    if !table_exists(connection, "sqlite_stat4") {
+        return statistics;
+    }
+
+    let mut stmt = connection
+        .prepare("SELECT idx, neq, nlt, ndlt FROM sqlite_stat4 WHERE ?1 IS NULL OR tbl = ?1;")
+        .unwrap();
+    let mut rows = stmt.query([table_name]).unwrap();
+
+    while let Some(row) = rows.next().unwrap() {
+        let index: String = row.get(0).unwrap();
+
+        if let Some(index) = statistics.get_mut(&index) {
+            index.samples.push(Stat4Sample {
+                equal: parse_numbers(&row.get::<_, String>(1).unwrap()),
+                less_than: parse_numbers(&row.get::<_, String>(2).unwrap()),
+                distinct_less_than: parse_numbers(&row.get::<_, String>(3).unwrap()),
+            });
+        }
+    }
+
+    statistics
+}
+
+/// Parses the leading space separated numbers, the stat column can end with keywords like
+/// `unordered` which are ignored
+#[cfg(feature = "rusqlite")]
+fn parse_numbers(stat: &str) -> Vec<u64> {
+    stat.split(' ').map_while(|n| n.parse().ok()).collect()
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::{parse_connection, Metadata, Parser};
+
+    #[test]
+    fn test_index_statistics() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT NOT NULL);
+            CREATE INDEX user_name ON user(name);
+            INSERT INTO user (name) VALUES ('a'), ('a'), ('b'), ('c');",
+            )
+            .unwrap();
+
+        struct Parse(Option<Metadata>);
+
+        impl Parser for Parse {
+            fn process_tables(&mut self, tables: Metadata) {
+                self.0 = Some(tables)
+            }
+        }
+
+        let mut parse = Parse(None);
+
+        parse_connection(&connection, &mut parse);
+
+        assert_eq!(
+            None,
+            parse.0.unwrap().table("user").unwrap().indexes[0].statistics
+        );
+
+        connection.execute_batch("ANALYZE;").unwrap();
+
+        let mut parse = Parse(None);
+
+        parse_connection(&connection, &mut parse);
+
+        let metadata = parse.0.unwrap();
+        let statistics = metadata.table("user").unwrap().indexes[0]
+            .statistics
+            .clone()
+            .unwrap();
+
+        assert_eq!(4, statistics.row_count);
+        assert_eq!(vec![2], statistics.rows_per_key);
+        assert_eq!(Some(0.5), statistics.selectivity(1));
+        assert!(!statistics.samples.is_empty());
+    }
+}
@@ -0,0 +1,104 @@
+//! Reads the disk usage of tables and indexes from the `dbstat` virtual table
+
+#[cfg(feature = "rusqlite")]
+use std::collections::HashMap;
+
+#[cfg(feature = "rusqlite")]
+use rusqlite::Connection;
+
+/// The disk usage of a table or index
+#[derive(Debug, PartialEq, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StorageStats {
+    /// The amount of pages used, including overflow pages
+    pub pages: u64,
+    /// The total size of the pages in bytes
+    pub bytes: u64,
+    /// The bytes used to store the actual records
+    pub payload: u64,
+    /// The bytes of the pages that are unused
+    pub unused: u64,
+}
+
+/// Queries the storage statistics of every table and index, or of a single table and its indexes,
+/// keyed by name
+/// Returns `None` if SQLite was compiled without the `dbstat` virtual table
+#[cfg(feature = "rusqlite")]
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
+pub(crate) fn query_storage_stats(
+    connection: &Connection,
+    table_name: Option<&str>,
+) -> Option<HashMap<String, StorageStats>> {
+    let mut stmt = connection
+        .prepare(
+            "SELECT name, COUNT(*), SUM(pgsize), SUM(payload), SUM(unused)
+FROM dbstat
+WHERE ?1 IS NULL OR name IN (SELECT name FROM sqlite_master WHERE tbl_name = ?1)
+GROUP BY name;",
+        )
+        .ok()?;
+    let mut rows = stmt.query([table_name]).unwrap();
+    let mut stats = HashMap::new();
+
+    while let Some(row) = rows.next().unwrap() {
+        stats.insert(
+            row.get(0).unwrap(),
+            StorageStats {
+                pages: row.get(1).unwrap(),
+                bytes: row.get(2).unwrap(),
+                payload: row.get(3).unwrap(),
+                unused: row.get(4).unwrap(),
+            },
+        );
+    }
+
+    Some(stats)
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::{parse_connection, Metadata, ParseOptions, Parser};
+
+    #[test]
+    fn test_storage_stats() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT NOT NULL);
+            CREATE INDEX user_name ON user(name);
+            INSERT INTO user (name) VALUES ('a'), ('b');",
+            )
+            .unwrap();
+
+        struct Parse(Option<Metadata>);
+
+        impl Parser for Parse {
+            fn parse_options(&self) -> ParseOptions {
+                ParseOptions {
+                    storage_stats: true,
+                    ..Default::default()
+                }
+            }
+
+            fn process_tables(&mut self, tables: Metadata) {
+                self.0 = Some(tables)
+            }
+        }
+
+        let mut parse = Parse(None);
+
+        parse_connection(&connection, &mut parse);
+
+        let metadata = parse.0.unwrap();
+        let user = metadata.table("user").unwrap();
+        let storage = user.storage.as_ref().unwrap();
+
+        assert_eq!(1, storage.pages);
+        assert!(storage.payload > 0);
+        assert!(storage.bytes >= storage.payload + storage.unused);
+        assert!(user.indexes[0].storage.is_some());
+    }
+}
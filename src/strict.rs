@@ -0,0 +1,367 @@
+//! Checks which tables can be converted to STRICT tables
+//! A STRICT table only accepts the declared types INT, INTEGER, REAL, TEXT, BLOB and ANY and
+//! rejects values that can't be converted to the type of the column, so both the schema and the
+//! stored values can block the conversion.
+
+use rusqlite::Connection;
+
+use crate::audit::StoredType;
+use crate::ddl::{parse_create_table, strict_create_table};
+use crate::migration::{rebuild_table, Migration};
+use crate::{quote_identifier, Affinity, Column, Metadata, Table, Type};
+
+/// The declared types that STRICT tables allow
+const STRICT_TYPES: [&str; 6] = ["INT", "INTEGER", "REAL", "TEXT", "BLOB", "ANY"];
+
+/// What blocks a table from becoming a STRICT table
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct StrictReadiness {
+    pub table: String,
+    /// Sorted by column id, the violations of a column are sorted like `StrictViolation`
+    pub violations: Vec<StrictViolation>,
+}
+
+impl StrictReadiness {
+    /// Checks if the table can be converted without changing the types of the columns
+    pub fn is_ready(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub enum StrictViolation {
+    /// Virtual tables can't be STRICT
+    VirtualTable,
+    /// The declared type is not allowed, `strict_type` is the allowed type with the same affinity
+    DeclaredType {
+        column: String,
+        declared_type: String,
+        strict_type: &'static str,
+    },
+    /// Sampled values that are stored with a type that `strict_type` of the column rejects
+    StoredType {
+        column: String,
+        stored: StoredType,
+        /// The amount of sampled values that are stored with the `stored` type
+        count: u64,
+    },
+}
+
+impl StrictViolation {
+    /// Checks if the violation keeps the table from being converted, only declared types can be
+    /// replaced by the conversion
+    pub fn is_blocking(&self) -> bool {
+        !matches!(self, StrictViolation::DeclaredType { .. })
+    }
+}
+
+/// The conversion of tables to STRICT tables, see `strict_conversion`
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct StrictConversion {
+    /// Rebuilds the tables that can be converted, run it with `Migration::apply`
+    pub migration: Migration,
+    /// The tables that are left as they are, with the violations that explain why
+    pub skipped: Vec<StrictReadiness>,
+}
+
+/// The STRICT type of a column, the allowed type with the same affinity as the declared type
+/// NUMERIC and DECIMAL become REAL. Other types with NUMERIC affinity, like DATETIME, and columns
+/// without a type become ANY, which keeps every value as it is.
+pub fn strict_type(column: &Column) -> &'static str {
+    match column.affinity() {
+        Affinity::Integer => "INTEGER",
+        Affinity::Text => "TEXT",
+        Affinity::Blob if !column.declared_type.trim().is_empty() => "BLOB",
+        Affinity::Real => "REAL",
+        Affinity::Numeric if matches!(column.the_type, Type::Numeric { .. }) => "REAL",
+        Affinity::Blob | Affinity::Numeric => "ANY",
+    }
+}
+
+/// Checks if a STRICT column of the type accepts a value that is stored with the type
+/// Values in a column with the same affinity are already converted where possible, e.g. TEXT
+/// columns store numbers as text.
+fn accepts(strict_type: &str, stored: StoredType) -> bool {
+    match strict_type {
+        "INT" | "INTEGER" => stored == StoredType::Integer,
+        "REAL" => matches!(stored, StoredType::Real | StoredType::Integer),
+        "TEXT" => stored != StoredType::Blob,
+        "BLOB" => stored == StoredType::Blob,
+        _ => true,
+    }
+}
+
+/// Checks the tables that aren't STRICT yet, sorted by table name
+/// Only the first `sample_size` rows of a table are read to find stored values that block the
+/// conversion. Note: check `DatabaseInfo::supports_strict_tables` as well.
+pub fn strict_readiness(
+    connection: &Connection,
+    metadata: &Metadata,
+    sample_size: usize,
+) -> Vec<StrictReadiness> {
+    let mut report = vec![];
+
+    for table in metadata.sorted_tables() {
+        if metadata.is_fts_shadow_table(&table.table_name) {
+            continue;
+        }
+
+        let violations = if table
+            .create_sql
+            .to_uppercase()
+            .starts_with("CREATE VIRTUAL")
+        {
+            vec![StrictViolation::VirtualTable]
+        } else if parse_create_table(&table.create_sql).is_some_and(|d| d.strict) {
+            continue;
+        } else {
+            table_violations(connection, table, sample_size)
+        };
+
+        report.push(StrictReadiness {
+            table: table.table_name.clone(),
+            violations,
+        });
+    }
+
+    report
+}
+
+/// Converts the tables of the readiness report to STRICT tables, tables with a violation that
+/// `is_blocking` are skipped
+/// The table is rebuilt with `strict_type` for the declared types that STRICT doesn't allow, the
+/// values of those columns are copied with `CAST`. A value that can't be converted, which the
+/// sample of the report missed, fails the migration.
+pub fn strict_conversion(metadata: &Metadata, report: &[StrictReadiness]) -> StrictConversion {
+    let mut conversion = StrictConversion {
+        migration: Migration::default(),
+        skipped: vec![],
+    };
+
+    for readiness in report {
+        let Some(table) = metadata.table(&readiness.table) else {
+            continue;
+        };
+
+        if readiness.violations.iter().any(|v| v.is_blocking()) {
+            conversion.skipped.push(readiness.clone());
+
+            continue;
+        }
+
+        let types = readiness
+            .violations
+            .iter()
+            .filter_map(|v| match v {
+                StrictViolation::DeclaredType {
+                    column,
+                    strict_type,
+                    ..
+                } => Some((column.as_str(), *strict_type)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let mut strict = table.clone();
+
+        strict.create_sql = strict_create_table(&table.create_sql, &types);
+
+        for column in &mut strict.columns {
+            if let Some((_, strict_type)) = types.iter().find(|(c, _)| *c == column.name) {
+                column.declared_type = strict_type.to_string();
+            }
+        }
+
+        conversion
+            .migration
+            .statements
+            .extend(rebuild_table(table, &strict, &[], true));
+        conversion
+            .migration
+            .rebuilt_tables
+            .push(table.table_name.clone());
+    }
+
+    conversion
+}
+
+fn table_violations(
+    connection: &Connection,
+    table: &Table,
+    sample_size: usize,
+) -> Vec<StrictViolation> {
+    let mut violations = vec![];
+
+    for column in &table.columns {
+        let declared_type = column.declared_type.trim().to_uppercase();
+        let strict_type = match STRICT_TYPES.into_iter().find(|t| *t == declared_type) {
+            Some(strict_type) => strict_type,
+            None => {
+                violations.push(StrictViolation::DeclaredType {
+                    column: column.name.clone(),
+                    declared_type: column.declared_type.clone(),
+                    strict_type: strict_type(column),
+                });
+
+                strict_type(column)
+            }
+        };
+        let c = quote_identifier(&column.name);
+        let mut stmt = connection
+            .prepare(&format!(
+                "SELECT typeof({c}), COUNT(*) FROM (SELECT {c} FROM {} LIMIT {sample_size}) GROUP BY 1 ORDER BY 1;",
+                quote_identifier(&table.table_name)
+            ))
+            .unwrap();
+        let mut rows = stmt.query([]).unwrap();
+
+        while let Some(row) = rows.next().unwrap() {
+            // NULL
+            let Some(stored) = StoredType::from_str(&row.get::<_, String>(0).unwrap()) else {
+                continue;
+            };
+
+            if !accepts(strict_type, stored) {
+                violations.push(StrictViolation::StoredType {
+                    column: column.name.clone(),
+                    stored,
+                    count: row.get(1).unwrap(),
+                });
+            }
+        }
+    }
+
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::audit::StoredType;
+    use crate::metadata_from_connection;
+    use crate::strict::{strict_conversion, strict_readiness, StrictViolation};
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_strict_readiness() {
+        let sql = "CREATE TABLE user (
+            user_id INTEGER NOT NULL PRIMARY KEY,
+            name VARCHAR(100),
+            age INT,
+            data
+        );
+        CREATE TABLE post (post_id INTEGER PRIMARY KEY, title TEXT) STRICT;
+        CREATE TABLE log (message TEXT);
+        INSERT INTO user VALUES (1, 'a', 20, 1), (2, 'b', 'unknown', 'x');";
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection.execute_batch(sql).unwrap();
+
+        let report = strict_readiness(&connection, &metadata_from_sql(sql), 100);
+
+        assert_eq!(
+            vec!["log", "user"],
+            report.iter().map(|r| &r.table).collect::<Vec<_>>()
+        );
+        assert!(report[0].is_ready());
+        assert_eq!(3, report[1].violations.len());
+        assert_eq!(
+            StrictViolation::DeclaredType {
+                column: "name".to_string(),
+                declared_type: "VARCHAR(100)".to_string(),
+                strict_type: "TEXT",
+            },
+            report[1].violations[0]
+        );
+        assert_eq!(
+            StrictViolation::StoredType {
+                column: "age".to_string(),
+                stored: StoredType::Text,
+                count: 1,
+            },
+            report[1].violations[1]
+        );
+        assert!(matches!(
+            &report[1].violations[2],
+            StrictViolation::DeclaredType { column, strict_type: "ANY", .. } if column == "data"
+        ));
+    }
+
+    #[test]
+    fn test_strict_conversion() {
+        let connection = Connection::open_in_memory().unwrap();
+
+        connection
+            .execute_batch(
+                "CREATE TABLE user (
+                    user_id INTEGER NOT NULL PRIMARY KEY,
+                    name VARCHAR(100) NOT NULL,
+                    price DECIMAL(10, 2),
+                    created DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    data
+                );
+                CREATE INDEX user_name ON user(name);
+                CREATE TABLE tag (tag TEXT PRIMARY KEY, weight INT) WITHOUT ROWID;
+                CREATE TABLE log (message TEXT, level INTEGER);
+                INSERT INTO user VALUES (1, 'a', '1.50', '2024-01-01', x'00');
+                INSERT INTO tag VALUES ('a', 1);
+                INSERT INTO log VALUES ('a', 'high');",
+            )
+            .unwrap();
+
+        let metadata = metadata_from_connection(&connection, Default::default());
+        let report = strict_readiness(&connection, &metadata, 100);
+        let conversion = strict_conversion(&metadata, &report);
+
+        assert_eq!(vec!["tag", "user"], conversion.migration.rebuilt_tables);
+        assert_eq!(
+            vec!["log"],
+            conversion
+                .skipped
+                .iter()
+                .map(|r| &r.table)
+                .collect::<Vec<_>>()
+        );
+        assert!(conversion.migration.statements.contains(
+            &"INSERT INTO \"new_user\" (\"user_id\", \"name\", \"price\", \"created\", \"data\") \
+            SELECT \"user_id\", CAST(\"name\" AS TEXT), CAST(\"price\" AS REAL), \"created\", \"data\" FROM \"user\";"
+                .to_string()
+        ));
+
+        conversion.migration.apply(&connection).unwrap();
+
+        let converted = metadata_from_connection(&connection, Default::default());
+
+        assert_eq!(
+            "CREATE TABLE \"user\" (
+                    user_id INTEGER NOT NULL PRIMARY KEY,
+                    name TEXT NOT NULL,
+                    price REAL,
+                    created ANY DEFAULT CURRENT_TIMESTAMP,
+                    data ANY
+                ) STRICT",
+            converted.table("user").unwrap().create_sql
+        );
+        assert!(converted
+            .table("tag")
+            .unwrap()
+            .create_sql
+            .ends_with("WITHOUT ROWID, STRICT"));
+        assert_eq!(
+            "user_name",
+            converted.table("user").unwrap().indexes[0].name
+        );
+        assert!(strict_readiness(&connection, &converted, 100)
+            .iter()
+            .all(|r| r.table == "log"));
+
+        let price: f64 = connection
+            .query_row("SELECT price FROM user;", [], |row| row.get(0))
+            .unwrap();
+
+        assert_eq!(1.5, price);
+        assert!(connection
+            .execute_batch("INSERT INTO user (user_id, name) VALUES (2, x'00');")
+            .is_err());
+    }
+}
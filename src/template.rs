@@ -0,0 +1,184 @@
+//! Renders user supplied Tera templates with the metadata, for output formats that the built-in
+//! generators don't cover
+//!
+//! ```
+//! use sqlite_parser::parse_sql;
+//! use sqlite_parser::template::TemplateGenerator;
+//!
+//! let metadata = parse_sql("CREATE TABLE user_roles (role_id INTEGER NOT NULL PRIMARY KEY);");
+//! let mut generator = TemplateGenerator::default();
+//!
+//! generator
+//!     .add_template(
+//!         "structs.rs",
+//!         "{% for table in tables %}pub struct {{ table.table_name | type_name }} {
+//! {% for column in table.columns %}    pub {{ column.name | field_name }}: i64,
+//! {% endfor %}}
+//! {% endfor %}",
+//!     )
+//!     .unwrap();
+//!
+//! assert_eq!(
+//!     "pub struct UserRole {\n    pub role_id: i64,\n}\n",
+//!     generator.render("structs.rs", &metadata).unwrap()
+//! );
+//! ```
+//!
+//! The context has the `tables` sorted by name, the `views`, the `triggers` and the `database`,
+//! serialized like the `serde` feature does. The `inflection` module is available as filters:
+//! `snake_case`, `camel_case`, `pascal_case`, `screaming_snake_case`, `pluralize`, `singularize`,
+//! `type_name`, `field_name` and `escape_reserved`. The last three take a `language` argument, one
+//! of `rust` (the default), `typescript`, `python`, `kotlin`, `swift` and `go`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tera::{Context, Tera, Value};
+
+use crate::inflection::{
+    camel_case, pascal_case, screaming_snake_case, snake_case, Inflector, Language,
+};
+use crate::Metadata;
+
+/// A filter that takes the `language` argument
+type Inflect = fn(&Inflector, &str, Language) -> String;
+
+/// A set of templates with the inflection filters registered
+pub struct TemplateGenerator {
+    pub tera: Tera,
+}
+
+impl Default for TemplateGenerator {
+    fn default() -> Self {
+        Self::new(Inflector::default())
+    }
+}
+
+impl TemplateGenerator {
+    /// The filters use the inflector, e.g. for its irregular plurals
+    pub fn new(inflector: Inflector) -> Self {
+        let mut tera = Tera::default();
+        let inflector = Arc::new(inflector);
+
+        for (name, convert) in [
+            ("snake_case", snake_case as fn(&str) -> String),
+            ("camel_case", camel_case),
+            ("pascal_case", pascal_case),
+            ("screaming_snake_case", screaming_snake_case),
+        ] {
+            tera.register_filter(name, move |value: &Value, _: &HashMap<String, Value>| {
+                Ok(Value::String(convert(string(value)?)))
+            });
+        }
+
+        let inflectors: [(&str, Inflect); 5] = [
+            ("pluralize", |i, s, _| i.pluralize(s)),
+            ("singularize", |i, s, _| i.singularize(s)),
+            ("type_name", |i, s, l| i.type_name(s, l)),
+            ("field_name", |i, s, l| i.field_name(s, l)),
+            ("escape_reserved", |_, s, l| l.escape(s)),
+        ];
+
+        for (name, inflect) in inflectors {
+            let inflector = inflector.clone();
+
+            tera.register_filter(name, move |value: &Value, args: &HashMap<String, Value>| {
+                Ok(Value::String(inflect(
+                    &inflector,
+                    string(value)?,
+                    language(args)?,
+                )))
+            });
+        }
+
+        Self { tera }
+    }
+
+    /// Adds a template, HTML and XML escaping is applied to names ending with `.html`, `.htm` and
+    /// `.xml`
+    pub fn add_template(&mut self, name: &str, template: &str) -> tera::Result<()> {
+        self.tera.add_raw_template(name, template)
+    }
+
+    pub fn render(&self, name: &str, metadata: &Metadata) -> tera::Result<String> {
+        self.tera.render(name, &context(metadata))
+    }
+}
+
+/// The context of the templates
+pub fn context(metadata: &Metadata) -> Context {
+    let mut context = Context::new();
+
+    context.insert("tables", &metadata.sorted_tables());
+    context.insert("views", &metadata.views);
+    context.insert("triggers", &metadata.triggers);
+    context.insert("database", &metadata.database);
+
+    context
+}
+
+fn string(value: &Value) -> tera::Result<&str> {
+    value
+        .as_str()
+        .ok_or_else(|| tera::Error::msg(format!("expected a string, found {value}")))
+}
+
+fn language(args: &HashMap<String, Value>) -> tera::Result<Language> {
+    let Some(language) = args.get("language") else {
+        return Ok(Language::Rust);
+    };
+
+    match string(language)?.to_lowercase().as_str() {
+        "rust" => Ok(Language::Rust),
+        "typescript" => Ok(Language::TypeScript),
+        "python" => Ok(Language::Python),
+        "kotlin" => Ok(Language::Kotlin),
+        "swift" => Ok(Language::Swift),
+        "go" => Ok(Language::Go),
+        other => Err(tera::Error::msg(format!("unknown language {other}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::inflection::Inflector;
+    use crate::template::TemplateGenerator;
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_template_generator() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE cactus (cactus_id INTEGER NOT NULL PRIMARY KEY, class TEXT);
+            CREATE TABLE box (box_id INTEGER NOT NULL PRIMARY KEY);",
+        );
+        let mut inflector = Inflector::default();
+
+        inflector
+            .plurals
+            .insert("cactus".to_string(), "cacti".to_string());
+
+        let mut generator = TemplateGenerator::new(inflector);
+
+        generator
+            .add_template(
+                "model.kt",
+                "{% for table in tables -%}
+                {{ table.table_name | pluralize | pascal_case }}: \
+                {% for column in table.columns %}{{ column.name | field_name(language=\"kotlin\") }} {% endfor %}
+                {% endfor %}",
+            )
+            .unwrap();
+        generator
+            .add_template(
+                "invalid",
+                "{{ tables | escape_reserved(language=\"cobol\") }}",
+            )
+            .unwrap();
+
+        assert_eq!(
+            "Boxes: boxId \n                Cacti: cactusId `class` \n                ",
+            generator.render("model.kt", &metadata).unwrap()
+        );
+        assert!(generator.render("invalid", &metadata).is_err());
+    }
+}
@@ -0,0 +1,324 @@
+//! A tokenizer for the SQL that SQLite stores in `sqlite_master`
+//! It doesn't validate the SQL, it only splits it so keywords inside strings, quoted identifiers
+//! and comments can be told apart from the actual keywords.
+
+#[derive(Debug, PartialEq, Copy, Clone, Eq)]
+pub(crate) enum TokenKind {
+    /// A keyword or an unquoted identifier
+    Word,
+    /// An identifier quoted with "", [] or ``
+    QuotedIdentifier,
+    /// A string literal quoted with ''
+    String,
+    Number,
+    /// A blob literal like x'00'
+    Blob,
+    /// A parameter like ?, ?1, :name, @name or $name
+    Variable,
+    /// A single character like ( or an operator like <=
+    Punctuation,
+    LineComment,
+    BlockComment,
+    Whitespace,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Eq)]
+pub(crate) struct Token<'a> {
+    pub kind: TokenKind,
+    /// The text as it is in the SQL, including quotes
+    pub text: &'a str,
+    /// The byte offset of the token in the SQL
+    pub offset: usize,
+}
+
+impl<'a> Token<'a> {
+    /// Checks if this token is the keyword, case insensitive
+    pub fn is_keyword(&self, keyword: &str) -> bool {
+        self.kind == TokenKind::Word && self.text.eq_ignore_ascii_case(keyword)
+    }
+
+    /// Checks if this token is one of the keywords of SQLite, case insensitive
+    /// SQLite accepts many keywords as identifiers, so this is not necessarily a keyword.
+    pub fn is_any_keyword(&self) -> bool {
+        self.kind == TokenKind::Word
+            && KEYWORDS
+                .split_whitespace()
+                .any(|k| self.text.eq_ignore_ascii_case(k))
+    }
+
+    /// Whitespace and comments don't have any meaning
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self.kind,
+            TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment
+        )
+    }
+}
+
+/// The keywords of https://www.sqlite.org/lang_keywords.html, separated by whitespace
+const KEYWORDS: &str =
+    "ABORT ACTION ADD AFTER ALL ALTER ALWAYS ANALYZE AND AS ASC ATTACH AUTOINCREMENT BEFORE \
+    BEGIN BETWEEN BY CASCADE CASE CAST CHECK COLLATE COLUMN COMMIT CONFLICT CONSTRAINT CREATE \
+    CROSS CURRENT CURRENT_DATE CURRENT_TIME CURRENT_TIMESTAMP DATABASE DEFAULT DEFERRABLE \
+    DEFERRED DELETE DESC DETACH DISTINCT DO DROP EACH ELSE END ESCAPE EXCEPT EXCLUDE \
+    EXCLUSIVE EXISTS EXPLAIN FAIL FILTER FIRST FOLLOWING FOR FOREIGN FROM FULL GENERATED GLOB \
+    GROUP GROUPS HAVING IF IGNORE IMMEDIATE IN INDEX INDEXED INITIALLY INNER INSERT INSTEAD \
+    INTERSECT INTO IS ISNULL JOIN KEY LAST LEFT LIKE LIMIT MATCH MATERIALIZED NATURAL NO NOT \
+    NOTHING NOTNULL NULL NULLS OF OFFSET ON OR ORDER OTHERS OUTER OVER PARTITION PLAN PRAGMA \
+    PRECEDING PRIMARY QUERY RAISE RANGE RECURSIVE REFERENCES REGEXP REINDEX RELEASE RENAME \
+    REPLACE RESTRICT RETURNING RIGHT ROLLBACK ROW ROWS SAVEPOINT SELECT SET TABLE TEMP \
+    TEMPORARY THEN TIES TO TRANSACTION TRIGGER UNBOUNDED UNION UNIQUE UPDATE USING VACUUM \
+    VALUES VIEW VIRTUAL WHEN WHERE WINDOW WITH WITHOUT";
+
+/// Splits the SQL into tokens, every byte of the SQL is part of exactly one token
+pub(crate) fn tokenize(sql: &str) -> Vec<Token<'_>> {
+    let bytes = sql.as_bytes();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let start = i;
+        let c = bytes[i];
+        let kind = match c {
+            b' ' | b'\t' | b'\n' | b'\r' | b'\x0c' => {
+                while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+                    i += 1;
+                }
+
+                TokenKind::Whitespace
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+
+                TokenKind::LineComment
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+
+                while i < bytes.len() && !(bytes[i] == b'*' && bytes.get(i + 1) == Some(&b'/')) {
+                    i += 1;
+                }
+
+                // An unterminated comment runs until the end
+                i = (i + 2).min(bytes.len());
+
+                TokenKind::BlockComment
+            }
+            b'\'' => {
+                i = end_of_quoted(bytes, i, b'\'');
+
+                TokenKind::String
+            }
+            b'"' | b'`' => {
+                i = end_of_quoted(bytes, i, c);
+
+                TokenKind::QuotedIdentifier
+            }
+            b'[' => {
+                while i < bytes.len() && bytes[i] != b']' {
+                    i += 1;
+                }
+
+                i = (i + 1).min(bytes.len());
+
+                TokenKind::QuotedIdentifier
+            }
+            b'x' | b'X' if bytes.get(i + 1) == Some(&b'\'') => {
+                i = end_of_quoted(bytes, i + 1, b'\'');
+
+                TokenKind::Blob
+            }
+            b'0'..=b'9' => {
+                i = end_of_number(bytes, i);
+
+                TokenKind::Number
+            }
+            b'.' if bytes.get(i + 1).map(|b| b.is_ascii_digit()) == Some(true) => {
+                i = end_of_number(bytes, i);
+
+                TokenKind::Number
+            }
+            b'?' | b':' | b'@' | b'$' => {
+                i += 1;
+
+                while i < bytes.len() && is_word_byte(bytes[i]) {
+                    i += 1;
+                }
+
+                TokenKind::Variable
+            }
+            _ if is_word_byte(c) => {
+                while i < bytes.len() && is_word_byte(bytes[i]) {
+                    i += 1;
+                }
+
+                TokenKind::Word
+            }
+            _ => {
+                let two = sql.get(i..i + 2);
+
+                i += if matches!(
+                    two,
+                    Some("<=" | ">=" | "<>" | "!=" | "==" | "||" | "<<" | ">>" | "->")
+                ) {
+                    if sql.get(i..i + 3) == Some("->>") {
+                        3
+                    } else {
+                        2
+                    }
+                } else {
+                    // Multibyte characters that are not part of a word are a single token
+                    sql[i..].chars().next().map(|c| c.len_utf8()).unwrap_or(1)
+                };
+
+                TokenKind::Punctuation
+            }
+        };
+
+        tokens.push(Token {
+            kind,
+            text: &sql[start..i],
+            offset: start,
+        });
+    }
+
+    tokens
+}
+
+/// Tokenizes and removes whitespace and comments
+pub(crate) fn tokenize_without_trivia(sql: &str) -> Vec<Token<'_>> {
+    tokenize(sql)
+        .into_iter()
+        .filter(|t| !t.is_trivia())
+        .collect()
+}
+
+/// Removes the quotes of a quoted identifier or string, doubled quotes are undoubled
+/// Other text is returned as is
+pub(crate) fn unquote(text: &str) -> String {
+    let quote = match text.chars().next() {
+        Some('[') => {
+            return text[1..]
+                .strip_suffix(']')
+                .unwrap_or(&text[1..])
+                .to_string()
+        }
+        Some(c @ ('"' | '\'' | '`')) => c,
+        _ => return text.to_string(),
+    };
+    let inner = text
+        .strip_prefix(quote)
+        .and_then(|t| t.strip_suffix(quote))
+        .unwrap_or(&text[1..]);
+
+    inner.replace(&format!("{quote}{quote}"), &quote.to_string())
+}
+
+fn is_word_byte(b: u8) -> bool {
+    // Non ascii characters are allowed in identifiers
+    b.is_ascii_alphanumeric() || b == b'_' || b >= 0x80
+}
+
+/// Returns the index after the closing quote, a doubled quote is an escaped quote
+fn end_of_quoted(bytes: &[u8], start: usize, quote: u8) -> usize {
+    let mut i = start + 1;
+
+    while i < bytes.len() {
+        if bytes[i] == quote {
+            if bytes.get(i + 1) == Some(&quote) {
+                i += 2;
+
+                continue;
+            }
+
+            return i + 1;
+        }
+
+        i += 1;
+    }
+
+    bytes.len()
+}
+
+fn end_of_number(bytes: &[u8], start: usize) -> usize {
+    let mut i = start;
+
+    if bytes[i] == b'0' && matches!(bytes.get(i + 1), Some(b'x' | b'X')) {
+        i += 2;
+
+        while i < bytes.len() && bytes[i].is_ascii_hexdigit() {
+            i += 1;
+        }
+
+        return i;
+    }
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'0'..=b'9' | b'.' | b'_' => i += 1,
+            b'e' | b'E' => {
+                i += 1;
+
+                if matches!(bytes.get(i), Some(b'+' | b'-')) {
+                    i += 1;
+                }
+            }
+            _ => break,
+        }
+    }
+
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tokenizer::{tokenize, tokenize_without_trivia, unquote, TokenKind};
+
+    #[test]
+    fn test_tokenize() {
+        let sql = "CREATE TABLE \"my \"\"table\"\" \" ( -- a comment\n[a b] TEXT DEFAULT 'it''s', /* x */ `c` INT CHECK(c >= 1.5e3))";
+
+        assert_eq!(
+            sql,
+            tokenize(sql)
+                .into_iter()
+                .map(|t| t.text)
+                .collect::<String>()
+        );
+
+        let tokens = tokenize_without_trivia(sql);
+
+        assert_eq!(
+            vec![
+                (TokenKind::Word, "CREATE"),
+                (TokenKind::Word, "TABLE"),
+                (TokenKind::QuotedIdentifier, "\"my \"\"table\"\" \""),
+                (TokenKind::Punctuation, "("),
+                (TokenKind::QuotedIdentifier, "[a b]"),
+                (TokenKind::Word, "TEXT"),
+                (TokenKind::Word, "DEFAULT"),
+                (TokenKind::String, "'it''s'"),
+                (TokenKind::Punctuation, ","),
+                (TokenKind::QuotedIdentifier, "`c`"),
+                (TokenKind::Word, "INT"),
+                (TokenKind::Word, "CHECK"),
+                (TokenKind::Punctuation, "("),
+                (TokenKind::Word, "c"),
+                (TokenKind::Punctuation, ">="),
+                (TokenKind::Number, "1.5e3"),
+                (TokenKind::Punctuation, ")"),
+                (TokenKind::Punctuation, ")"),
+            ],
+            tokens.iter().map(|t| (t.kind, t.text)).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["my \"table\" ", "a b", "it's", "c", "INT"],
+            [tokens[2], tokens[4], tokens[7], tokens[9], tokens[10]]
+                .iter()
+                .map(|t| unquote(t.text))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,195 @@
+//! Renders the schema as an indented tree, for printing to a terminal
+//!
+//! ```text
+//! user
+//! ├── columns
+//! │   ├── user_id INTEGER NOT NULL PRIMARY KEY
+//! │   └── name TEXT
+//! └── indexes
+//!     └── UNIQUE INDEX user_name (name)
+//! ```
+
+use crate::{Column, Metadata, Table};
+
+/// How the tree is drawn
+#[derive(Debug, PartialEq, Copy, Clone, Eq)]
+pub struct TreeOptions {
+    /// Highlights table names, primary key columns and types with ANSI escape codes
+    pub colors: bool,
+    /// Draws the branches with box-drawing characters instead of ASCII
+    pub unicode: bool,
+}
+
+impl Default for TreeOptions {
+    fn default() -> Self {
+        Self {
+            colors: false,
+            unicode: true,
+        }
+    }
+}
+
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const YELLOW: &str = "\x1b[33m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+struct Branches {
+    middle: &'static str,
+    last: &'static str,
+    through: &'static str,
+    empty: &'static str,
+}
+
+impl TreeOptions {
+    fn branches(&self) -> Branches {
+        if self.unicode {
+            Branches {
+                middle: "├── ",
+                last: "└── ",
+                through: "│   ",
+                empty: "    ",
+            }
+        } else {
+            Branches {
+                middle: "|-- ",
+                last: "`-- ",
+                through: "|   ",
+                empty: "    ",
+            }
+        }
+    }
+
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.colors {
+            format!("{color}{text}{RESET}")
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+impl Metadata {
+    /// Renders the tables sorted by name, with their columns, foreign keys and indexes as children
+    /// Groups without children are left out
+    pub fn render_tree(&self, options: TreeOptions) -> String {
+        self.sorted_tables()
+            .into_iter()
+            .map(|t| render_table(t, &options))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+fn render_table(table: &Table, options: &TreeOptions) -> String {
+    let branches = options.branches();
+    let groups = [
+        (
+            "columns",
+            table
+                .columns
+                .iter()
+                .map(|c| render_column(c, options))
+                .collect::<Vec<_>>(),
+        ),
+        (
+            "foreign keys",
+            table.foreign_keys.iter().map(|f| f.to_string()).collect(),
+        ),
+        (
+            "indexes",
+            table.indexes.iter().map(|i| i.to_string()).collect(),
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, children)| !children.is_empty())
+    .collect::<Vec<_>>();
+    let mut lines = vec![options.paint(BOLD, &table.table_name)];
+
+    for (i, (name, children)) in groups.iter().enumerate() {
+        let last_group = i == groups.len() - 1;
+        let (branch, indent) = if last_group {
+            (branches.last, branches.empty)
+        } else {
+            (branches.middle, branches.through)
+        };
+
+        lines.push(format!("{branch}{}", options.paint(DIM, name)));
+
+        for (j, child) in children.iter().enumerate() {
+            let branch = if j == children.len() - 1 {
+                branches.last
+            } else {
+                branches.middle
+            };
+
+            lines.push(format!("{indent}{branch}{child}"));
+        }
+    }
+
+    lines.join("\n") + "\n"
+}
+
+fn render_column(column: &Column, options: &TreeOptions) -> String {
+    let name = if column.part_of_pk {
+        options.paint(YELLOW, &column.name)
+    } else {
+        column.name.clone()
+    };
+    let rest = column.to_string();
+    // The Display form starts with the name and the type
+    let rest = &rest[column.name.len() + 1..];
+    let type_len = column.the_type.to_string().len();
+
+    format!(
+        "{name} {}{}",
+        options.paint(CYAN, &rest[..type_len]),
+        &rest[type_len..]
+    )
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::tests::metadata_from_sql;
+    use crate::tree::TreeOptions;
+
+    #[test]
+    fn test_render_tree() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT);
+        CREATE TABLE post (
+            post_id INTEGER NOT NULL PRIMARY KEY,
+            user_id INTEGER NOT NULL,
+            FOREIGN KEY(user_id) REFERENCES user(user_id)
+        );
+        CREATE UNIQUE INDEX user_name ON user(name);",
+        );
+
+        assert_eq!(
+            "post
+├── columns
+│   ├── post_id INTEGER NOT NULL PRIMARY KEY
+│   └── user_id INTEGER NOT NULL
+└── foreign keys
+    └── FOREIGN KEY (user_id) REFERENCES user (user_id)
+
+user
+├── columns
+│   ├── user_id INTEGER NOT NULL PRIMARY KEY
+│   └── name TEXT
+└── indexes
+    └── UNIQUE INDEX user_name (name)
+",
+            metadata.render_tree(TreeOptions::default())
+        );
+
+        let colored = metadata.render_tree(TreeOptions {
+            colors: true,
+            unicode: false,
+        });
+
+        assert!(colored.starts_with("\x1b[1mpost\x1b[0m\n|-- \x1b[2mcolumns\x1b[0m\n"));
+        assert!(colored.contains("|   `-- user_id \x1b[36mINTEGER\x1b[0m NOT NULL\n"));
+    }
+}
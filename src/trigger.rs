@@ -0,0 +1,350 @@
+//! The triggers of the schema, read from `sqlite_master`
+
+use std::collections::VecDeque;
+
+#[cfg(feature = "rusqlite")]
+use rusqlite::Connection;
+
+use crate::dependency::{AccessKind, Accesses, TableAccess};
+use crate::tokenizer::{tokenize_without_trivia, unquote};
+use crate::Metadata;
+
+#[derive(Debug, PartialEq, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Trigger {
+    pub name: String,
+    /// The table or view the trigger is on
+    pub table_name: String,
+    pub timing: TriggerTiming,
+    pub event: TriggerEvent,
+    /// The CREATE TRIGGER statement as stored in `sqlite_master`
+    pub sql: String,
+    /// The tables and columns that the WHEN clause and the body read or write, sorted by table
+    /// name and kind
+    /// The columns of `new` and `old` are reads of the table of the trigger.
+    pub accesses: Vec<TableAccess>,
+}
+
+/// When the trigger runs, relative to the statement
+#[derive(Debug, PartialEq, Copy, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TriggerTiming {
+    /// Also the timing when the statement doesn't specify one
+    Before,
+    After,
+    /// Only for triggers on views
+    InsteadOf,
+}
+
+#[derive(Debug, PartialEq, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TriggerEvent {
+    Insert,
+    /// The columns of `UPDATE OF`, empty when the trigger runs on an update of any column
+    Update {
+        columns: Vec<String>,
+    },
+    Delete,
+}
+
+impl Trigger {
+    /// Reads the timing and event from the CREATE TRIGGER statement
+    pub(crate) fn new(name: String, table_name: String, sql: String) -> Self {
+        let tokens = tokenize_without_trivia(&sql);
+        let mut timing = TriggerTiming::Before;
+        let mut event = TriggerEvent::Insert;
+
+        for (i, token) in tokens.iter().enumerate() {
+            if token.is_keyword("AFTER") {
+                timing = TriggerTiming::After;
+            } else if token.is_keyword("INSTEAD") {
+                timing = TriggerTiming::InsteadOf;
+            } else if token.is_keyword("DELETE") {
+                event = TriggerEvent::Delete;
+            } else if token.is_keyword("UPDATE") {
+                let mut columns = vec![];
+                let mut j = i + 2;
+
+                if tokens.get(i + 1).map(|t| t.is_keyword("OF")) == Some(true) {
+                    while let Some(column) = tokens.get(j) {
+                        columns.push(unquote(column.text));
+
+                        if tokens.get(j + 1).map(|t| t.text) != Some(",") {
+                            break;
+                        }
+
+                        j += 2;
+                    }
+                }
+
+                event = TriggerEvent::Update { columns };
+            } else if token.is_keyword("INSERT") {
+                event = TriggerEvent::Insert;
+            } else if token.is_keyword("ON") {
+                // The table name and the body follow
+                break;
+            }
+        }
+
+        Trigger {
+            name,
+            table_name,
+            timing,
+            event,
+            sql,
+            accesses: vec![],
+        }
+    }
+
+    /// The tables and columns that the trigger reads
+    pub fn reads(&self) -> impl Iterator<Item = &TableAccess> {
+        self.accesses.iter().filter(|a| a.kind == AccessKind::Read)
+    }
+
+    /// The tables and columns that the trigger inserts, updates or deletes
+    pub fn writes(&self) -> impl Iterator<Item = &TableAccess> {
+        self.accesses.iter().filter(|a| a.kind != AccessKind::Read)
+    }
+
+    /// Checks if the trigger runs on the event, an UPDATE runs the triggers of any of the updated
+    /// columns
+    /// The columns of an UPDATE are compared case insensitive, no columns means any column.
+    pub fn fires_on(&self, event: &TriggerEvent) -> bool {
+        match (&self.event, event) {
+            (TriggerEvent::Update { columns: of }, TriggerEvent::Update { columns }) => {
+                of.is_empty()
+                    || columns.is_empty()
+                    || of
+                        .iter()
+                        .any(|o| columns.iter().any(|c| c.eq_ignore_ascii_case(o)))
+            }
+            (own, event) => own == event,
+        }
+    }
+
+    /// Checks if the trigger runs on an INSERT
+    pub fn on_insert(&self) -> bool {
+        self.event == TriggerEvent::Insert
+    }
+
+    /// Checks if the trigger runs on an UPDATE, of any column
+    pub fn on_update(&self) -> bool {
+        matches!(self.event, TriggerEvent::Update { .. })
+    }
+
+    /// Checks if the trigger runs on a DELETE
+    pub fn on_delete(&self) -> bool {
+        self.event == TriggerEvent::Delete
+    }
+
+    /// Finds the accesses of the WHEN clause and the statements of the body
+    fn analyze(&self, metadata: &Metadata) -> Vec<TableAccess> {
+        let tokens = tokenize_without_trivia(&self.sql);
+        let mut accesses = Accesses::new(metadata);
+        let Some(on) = tokens.iter().position(|t| t.is_keyword("ON")) else {
+            return vec![];
+        };
+        let Some(begin) = tokens.iter().position(|t| t.is_keyword("BEGIN")) else {
+            return vec![];
+        };
+        let end = tokens
+            .iter()
+            .rposition(|t| t.is_keyword("END"))
+            .filter(|e| *e > begin)
+            .unwrap_or(tokens.len());
+
+        if let Some(when) = tokens[on..begin].iter().position(|t| t.is_keyword("WHEN")) {
+            accesses.statement(&tokens[on + when + 1..begin], Some(&self.table_name));
+        }
+
+        for statement in tokens[begin + 1..end].split(|t| t.text == ";") {
+            accesses.statement(statement, Some(&self.table_name));
+        }
+
+        accesses.into_vec()
+    }
+}
+
+impl Metadata {
+    /// The triggers that run when the event happens on the table, in the order they are fired
+    /// The triggers that the writes of a fired trigger fire are included, every trigger is
+    /// listed once.
+    pub fn fired_triggers(&self, table_name: &str, event: &TriggerEvent) -> Vec<&Trigger> {
+        let mut fired: Vec<&Trigger> = vec![];
+        let mut pending = VecDeque::from([(table_name.to_string(), event.clone())]);
+
+        while let Some((table_name, event)) = pending.pop_front() {
+            for trigger in self.triggers_on(&table_name) {
+                if !trigger.fires_on(&event) || fired.iter().any(|f| f.name == trigger.name) {
+                    continue;
+                }
+
+                fired.push(trigger);
+                pending.extend(
+                    trigger
+                        .writes()
+                        .filter_map(|w| Some((w.table_name.clone(), w.event()?))),
+                );
+            }
+        }
+
+        fired
+    }
+
+    /// Sets `Trigger::accesses`, after the tables are parsed
+    pub(crate) fn analyze_triggers(&mut self) {
+        let mut triggers = std::mem::take(&mut self.triggers);
+
+        for trigger in &mut triggers {
+            trigger.accesses = trigger.analyze(self);
+        }
+
+        self.triggers = triggers;
+    }
+}
+
+/// The triggers of the database or of a single table, sorted by name
+#[cfg(feature = "rusqlite")]
+pub(crate) fn query_triggers(connection: &Connection, table_name: Option<&str>) -> Vec<Trigger> {
+    let mut stmt = connection
+        .prepare(
+            "SELECT name, tbl_name, sql FROM sqlite_master
+WHERE type = 'trigger' AND (?1 IS NULL OR tbl_name = ?1)
+ORDER BY name;",
+        )
+        .unwrap();
+    let mut rows = stmt.query([table_name]).unwrap();
+    let mut triggers = vec![];
+
+    while let Some(row) = rows.next().unwrap() {
+        triggers.push(Trigger::new(
+            row.get(0).unwrap(),
+            row.get(1).unwrap(),
+            row.get(2).unwrap(),
+        ));
+    }
+
+    triggers
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::dependency::{AccessKind, TableAccess};
+    use crate::tests::metadata_from_sql;
+    use crate::trigger::{TriggerEvent, TriggerTiming};
+
+    #[test]
+    fn test_triggers() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT, email TEXT);
+            CREATE TABLE log (message TEXT);
+            CREATE VIEW user_view AS SELECT * FROM user;
+            CREATE TRIGGER user_insert AFTER INSERT ON user BEGIN
+                INSERT INTO log VALUES ('update');
+            END;
+            CREATE TRIGGER \"user update\" UPDATE OF name, \"email\" ON user BEGIN
+                DELETE FROM log;
+            END;
+            CREATE TRIGGER user_view_delete INSTEAD OF DELETE ON user_view BEGIN
+                DELETE FROM user WHERE user_id = old.user_id;
+            END;",
+        );
+        let triggers = &metadata.triggers;
+
+        assert_eq!(
+            vec!["user update", "user_insert", "user_view_delete"],
+            triggers.iter().map(|t| &t.name).collect::<Vec<_>>()
+        );
+        assert_eq!(TriggerTiming::Before, triggers[0].timing);
+        assert_eq!(
+            TriggerEvent::Update {
+                columns: vec!["name".to_string(), "email".to_string()]
+            },
+            triggers[0].event
+        );
+        assert_eq!(TriggerTiming::After, triggers[1].timing);
+        assert!(triggers[1].on_insert());
+        assert_eq!("user_view", triggers[2].table_name);
+        assert_eq!(TriggerTiming::InsteadOf, triggers[2].timing);
+        assert!(triggers[2].on_delete());
+        assert_eq!(2, metadata.triggers_on("USER").len());
+    }
+
+    #[test]
+    fn test_trigger_accesses() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE contact (contact_id INTEGER NOT NULL PRIMARY KEY, email TEXT, updated TEXT);
+            CREATE TABLE audit (contact_id INTEGER, old_email TEXT, new_email TEXT);
+            CREATE TABLE stats (stat_id INTEGER NOT NULL PRIMARY KEY, contacts INTEGER);
+            CREATE TRIGGER contact_email AFTER UPDATE OF email ON contact
+            WHEN old.email IS NOT new.email BEGIN
+                INSERT INTO audit (contact_id, old_email, new_email)
+                VALUES (new.contact_id, old.email, new.email);
+                UPDATE contact SET updated = CURRENT_TIMESTAMP WHERE contact_id = new.contact_id;
+            END;
+            CREATE TRIGGER audit_insert AFTER INSERT ON audit BEGIN
+                UPDATE stats
+                SET contacts = (SELECT COUNT(*) FROM contact c WHERE c.email IS NOT NULL)
+                WHERE stats.stat_id = 1;
+            END;
+            CREATE TRIGGER contact_delete BEFORE DELETE ON contact BEGIN
+                DELETE FROM audit WHERE contact_id = old.contact_id;
+            END;",
+        );
+        let access = |table_name: &str, kind, columns: &[&str]| TableAccess {
+            table_name: table_name.to_string(),
+            kind,
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+        };
+        let trigger = |name: &str| metadata.triggers.iter().find(|t| t.name == name).unwrap();
+
+        assert_eq!(
+            vec![
+                access(
+                    "audit",
+                    AccessKind::Insert,
+                    &["contact_id", "new_email", "old_email"]
+                ),
+                access("contact", AccessKind::Read, &["contact_id", "email"]),
+                access("contact", AccessKind::Update, &["updated"]),
+            ],
+            trigger("contact_email").accesses
+        );
+        assert_eq!(
+            vec![
+                access("contact", AccessKind::Read, &["email"]),
+                access("stats", AccessKind::Read, &["stat_id"]),
+                access("stats", AccessKind::Update, &["contacts"]),
+            ],
+            trigger("audit_insert").accesses
+        );
+        assert_eq!(
+            vec![
+                access("audit", AccessKind::Read, &["contact_id"]),
+                access("audit", AccessKind::Delete, &[]),
+                access("contact", AccessKind::Read, &["contact_id"]),
+            ],
+            trigger("contact_delete").accesses
+        );
+
+        let fired = |event| {
+            metadata
+                .fired_triggers("contact", &event)
+                .into_iter()
+                .map(|t| t.name.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            vec!["contact_email", "audit_insert"],
+            fired(TriggerEvent::Update {
+                columns: vec!["EMAIL".to_string()]
+            })
+        );
+        assert!(fired(TriggerEvent::Update {
+            columns: vec!["updated".to_string()]
+        })
+        .is_empty());
+        assert_eq!(vec!["contact_delete"], fired(TriggerEvent::Delete));
+    }
+}
@@ -0,0 +1,589 @@
+//! Checks that the tables and columns a SQL statement refers to exist, without executing it
+//! Example:
+//!
+//! ```
+//! use sqlite_parser::validate::validate_sql;
+//! use sqlite_parser::Metadata;
+//!
+//! fn check(metadata: &Metadata) {
+//!     for unknown in validate_sql(metadata, "SELECT emial FROM user;") {
+//!         // Unknown column 'emial', did you mean 'email'?
+//!         println!("{unknown}");
+//!     }
+//! }
+//! ```
+//!
+//! The SQL is only tokenized, not parsed. Tables are read after FROM, JOIN, INTO and UPDATE,
+//! columns are the other names that are not a keyword, function, alias or CTE. Unqualified columns
+//! are only checked when every table of the statement is in the metadata, since the columns of a
+//! subquery or CTE are unknown. Views are checked with `View::columns`, unless they are empty.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+
+use crate::tokenizer::{tokenize_without_trivia, unquote, Token, TokenKind};
+use crate::view::SchemaObject;
+use crate::{Metadata, Table};
+
+/// A table or column that doesn't exist
+#[derive(Debug, PartialEq, Clone, Eq)]
+pub struct UnknownReference {
+    pub kind: ReferenceKind,
+    /// The name as written, without quotes
+    pub name: String,
+    /// The table or alias of a qualified column
+    pub qualifier: Option<String>,
+    /// The byte offset of the name in the SQL
+    pub offset: usize,
+    /// The closest existing name, if one is close enough to be a typo
+    pub suggestion: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Copy, Clone, Eq, Hash, PartialOrd, Ord)]
+pub enum ReferenceKind {
+    /// A table or an alias of a table
+    Table,
+    Column,
+}
+
+/// The columns that every rowid table has
+const ROWID_NAMES: [&str; 3] = ["rowid", "oid", "_rowid_"];
+
+/// The unknown tables and columns of the statement, in the order of the SQL
+pub fn validate_sql(metadata: &Metadata, sql: &str) -> Vec<UnknownReference> {
+    let tokens = tokenize_without_trivia(sql);
+    let mut statement = Statement::new(metadata, &tokens);
+
+    statement.read_sources();
+    statement.check_columns();
+    statement.unknown.sort_by_key(|u| u.offset);
+    statement.unknown
+}
+
+/// The state of checking a statement
+struct Statement<'a, 't> {
+    metadata: &'a Metadata,
+    tokens: &'t [Token<'t>],
+    /// The tables and views by lowercase name and alias, `None` for CTEs, subqueries, table-valued
+    /// functions and views with unknown columns
+    sources: HashMap<String, Option<SchemaObject<'a>>>,
+    /// The table of INSERT, which is also named `excluded` in an upsert
+    insert_table: Option<&'a Table>,
+    /// Lowercase names that are declared in the statement, e.g. the column aliases
+    declared: HashSet<String>,
+    /// The indexes of the tokens that are not a column
+    skipped: HashSet<usize>,
+    unknown: Vec<UnknownReference>,
+}
+
+impl<'a, 't> Statement<'a, 't> {
+    fn new(metadata: &'a Metadata, tokens: &'t [Token<'t>]) -> Self {
+        Self {
+            metadata,
+            tokens,
+            sources: HashMap::new(),
+            insert_table: None,
+            declared: HashSet::new(),
+            skipped: HashSet::new(),
+            unknown: vec![],
+        }
+    }
+
+    fn token(&self, i: usize) -> Option<&'t Token<'t>> {
+        self.tokens.get(i)
+    }
+
+    fn is_text(&self, i: usize, text: &str) -> bool {
+        self.token(i).is_some_and(|t| t.text == text)
+    }
+
+    fn is_name(&self, i: usize) -> bool {
+        self.token(i).is_some_and(is_name)
+    }
+
+    /// Reads the CTEs, tables and aliases
+    fn read_sources(&mut self) {
+        // A stack with an entry per parenthesis, which is true inside a FROM clause
+        let mut in_from = vec![false];
+
+        for i in 0..self.tokens.len() {
+            let token = &self.tokens[i];
+            let previous = i.checked_sub(1).map(|p| &self.tokens[p]);
+
+            match token.text {
+                "(" => in_from.push(false),
+                ")" => {
+                    // An unbalanced ) doesn't close the statement itself
+                    if in_from.len() > 1 {
+                        in_from.pop();
+                    }
+
+                    // The alias of a subquery
+                    if *in_from.last().unwrap() {
+                        self.declare_alias(i + 1, None);
+                    }
+                }
+                _ if token.is_keyword("FROM") || token.is_keyword("JOIN") => {
+                    *in_from.last_mut().unwrap() = true;
+                }
+                _ if ENDS_FROM.iter().any(|k| token.is_keyword(k)) => {
+                    *in_from.last_mut().unwrap() = false;
+                }
+                _ => {}
+            }
+
+            if !is_name(token) {
+                continue;
+            }
+
+            if self.is_cte(i) {
+                self.sources
+                    .insert(unquote(token.text).to_lowercase(), None);
+                self.skipped.insert(i);
+
+                continue;
+            }
+
+            // Column aliases and the types of CAST
+            if previous.is_some_and(|p| p.is_keyword("AS")) && !self.is_text(i + 1, "(") {
+                self.declared.insert(unquote(token.text).to_lowercase());
+                self.skipped.insert(i);
+
+                continue;
+            }
+
+            let is_table = previous.is_some_and(|p| {
+                ["FROM", "JOIN", "INTO", "UPDATE"]
+                    .iter()
+                    .any(|k| p.is_keyword(k))
+                    || (p.text == "," && *in_from.last().unwrap())
+            });
+
+            if is_table && !self.skipped.contains(&i) {
+                self.read_table(i, previous.unwrap().is_keyword("INTO"));
+            }
+        }
+    }
+
+    /// Checks if the name is a CTE, `WITH name AS (` or `WITH name(columns) AS (`
+    fn is_cte(&mut self, i: usize) -> bool {
+        let after_with = i.checked_sub(1).is_some_and(|p| {
+            let p = &self.tokens[p];
+
+            p.is_keyword("WITH") || p.is_keyword("RECURSIVE") || p.text == ","
+        });
+
+        if !after_with {
+            return false;
+        }
+
+        if self.token(i + 1).is_some_and(|t| t.is_keyword("AS")) {
+            return true;
+        }
+
+        if !self.is_text(i + 1, "(") {
+            return false;
+        }
+
+        let close = matching_paren(self.tokens, i + 1);
+
+        if !self.token(close + 1).is_some_and(|t| t.is_keyword("AS")) {
+            return false;
+        }
+
+        for column in i + 2..close {
+            if is_name(&self.tokens[column]) {
+                self.declared
+                    .insert(unquote(self.tokens[column].text).to_lowercase());
+                self.skipped.insert(column);
+            }
+        }
+
+        true
+    }
+
+    /// Reads a table reference, `schema.table [AS] alias` or a table-valued function
+    fn read_table(&mut self, mut i: usize, is_insert: bool) {
+        self.skipped.insert(i);
+
+        if self.is_text(i + 1, ".") && self.is_name(i + 2) {
+            i += 2;
+            self.skipped.insert(i);
+        }
+
+        let token = &self.tokens[i];
+        let name = unquote(token.text);
+
+        if self.is_text(i + 1, "(") && !is_insert {
+            // A table-valued function like json_each
+            self.declare_alias(matching_paren(self.tokens, i + 1) + 1, None);
+
+            return;
+        }
+
+        let source = match self.sources.get(&name.to_lowercase()) {
+            // A CTE
+            Some(None) => None,
+            _ => match (self.metadata.table(&name), self.metadata.view(&name)) {
+                (Some(table), _) => Some(SchemaObject::Table(table)),
+                // Without columns the columns of the view are unknown, so they are not checked
+                (None, Some(view)) if view.columns.is_empty() => None,
+                (None, Some(view)) => Some(SchemaObject::View(view)),
+                (None, None) => {
+                    let suggestion = closest(
+                        &name,
+                        self.metadata
+                            .tables
+                            .keys()
+                            .map(String::as_str)
+                            .chain(self.metadata.views.iter().map(|v| v.name.as_str()))
+                            .chain(self.sources.keys().map(String::as_str)),
+                    );
+
+                    self.unknown.push(UnknownReference {
+                        kind: ReferenceKind::Table,
+                        name: name.clone(),
+                        qualifier: None,
+                        offset: token.offset,
+                        suggestion,
+                    });
+
+                    None
+                }
+            },
+        };
+
+        self.sources.insert(name.to_lowercase(), source);
+
+        if is_insert {
+            self.insert_table = match source {
+                Some(SchemaObject::Table(table)) => Some(table),
+                _ => None,
+            };
+        }
+
+        self.declare_alias(i + 1, source);
+    }
+
+    /// Declares the alias at the index, `AS alias` or `alias`, if there is one
+    fn declare_alias(&mut self, mut i: usize, source: Option<SchemaObject<'a>>) {
+        if self.token(i).is_some_and(|t| t.is_keyword("AS")) {
+            i += 1;
+        }
+
+        if self.is_name(i) && !self.is_text(i + 1, "(") {
+            self.sources
+                .insert(unquote(self.tokens[i].text).to_lowercase(), source);
+            self.skipped.insert(i);
+        }
+    }
+
+    fn check_columns(&mut self) {
+        // Unqualified columns can be a column of a subquery or CTE
+        let check_unqualified =
+            !self.sources.is_empty() && self.sources.values().all(Option::is_some);
+
+        for i in 0..self.tokens.len() {
+            let token = &self.tokens[i];
+
+            if !is_name(token) || self.skipped.contains(&i) || self.is_text(i + 1, "(") {
+                continue;
+            }
+
+            let previous = i.checked_sub(1).map(|p| &self.tokens[p]);
+
+            if previous.is_some_and(|p| {
+                p.text == "."
+                    || ["COLLATE", "WINDOW", "OVER"]
+                        .iter()
+                        .any(|k| p.is_keyword(k))
+            }) {
+                continue;
+            }
+
+            let name = unquote(token.text);
+
+            if self.is_text(i + 1, ".") {
+                if self.is_name(i + 2) && !self.is_text(i + 3, ".") {
+                    self.check_qualified(&name, token, &self.tokens[i + 2]);
+                }
+
+                continue;
+            }
+
+            let known = self.declared.contains(&name.to_lowercase())
+                || ROWID_NAMES.iter().any(|r| r.eq_ignore_ascii_case(&name))
+                || self.sources().any(|s| self.has_column(s, &name));
+
+            if check_unqualified && !known {
+                let suggestion = closest(&name, self.sources().flat_map(column_names));
+
+                self.unknown.push(UnknownReference {
+                    kind: ReferenceKind::Column,
+                    name,
+                    qualifier: None,
+                    offset: token.offset,
+                    suggestion,
+                });
+            }
+        }
+    }
+
+    fn check_qualified(&mut self, qualifier: &str, qualifier_token: &Token, column: &Token) {
+        let source = match self.sources.get(&qualifier.to_lowercase()) {
+            Some(source) => *source,
+            None if qualifier.eq_ignore_ascii_case("excluded") => {
+                self.insert_table.map(SchemaObject::Table)
+            }
+            // The rows of a trigger and schema names
+            None if ["new", "old", "main", "temp"]
+                .iter()
+                .any(|q| q.eq_ignore_ascii_case(qualifier)) =>
+            {
+                None
+            }
+            None => {
+                let suggestion = closest(qualifier, self.sources.keys().map(String::as_str));
+
+                self.unknown.push(UnknownReference {
+                    kind: ReferenceKind::Table,
+                    name: qualifier.to_string(),
+                    qualifier: None,
+                    offset: qualifier_token.offset,
+                    suggestion,
+                });
+
+                None
+            }
+        };
+        let Some(source) = source else {
+            return;
+        };
+        let name = unquote(column.text);
+
+        if self.has_column(source, &name)
+            || ROWID_NAMES.iter().any(|r| r.eq_ignore_ascii_case(&name))
+        {
+            return;
+        }
+
+        self.unknown.push(UnknownReference {
+            kind: ReferenceKind::Column,
+            suggestion: closest(&name, column_names(source).into_iter()),
+            name,
+            qualifier: Some(qualifier.to_string()),
+            offset: column.offset,
+        });
+    }
+
+    /// The distinct tables and views of the metadata in the statement
+    fn sources(&self) -> impl Iterator<Item = SchemaObject<'a>> + '_ {
+        let mut seen = HashSet::new();
+
+        self.sources
+            .values()
+            .flatten()
+            .copied()
+            .filter(move |s| seen.insert(s.name().to_string()))
+    }
+
+    fn has_column(&self, source: SchemaObject, name: &str) -> bool {
+        match source {
+            SchemaObject::Table(table) => table.column(name).is_some(),
+            SchemaObject::View(view) => view
+                .columns
+                .iter()
+                .any(|c| self.metadata.lookup.matches(c, name)),
+        }
+    }
+}
+
+fn column_names(source: SchemaObject<'_>) -> Vec<&str> {
+    match source {
+        SchemaObject::Table(table) => table.columns.iter().map(|c| c.name.as_str()).collect(),
+        SchemaObject::View(view) => view.columns.iter().map(String::as_str).collect(),
+    }
+}
+
+/// Keywords that end a FROM clause
+const ENDS_FROM: [&str; 12] = [
+    "WHERE",
+    "ON",
+    "USING",
+    "GROUP",
+    "ORDER",
+    "LIMIT",
+    "HAVING",
+    "WINDOW",
+    "UNION",
+    "EXCEPT",
+    "INTERSECT",
+    "SET",
+];
+
+fn is_name(token: &Token) -> bool {
+    token.kind == TokenKind::QuotedIdentifier
+        || (token.kind == TokenKind::Word
+            && !token.is_any_keyword()
+            && !token.is_keyword("TRUE")
+            && !token.is_keyword("FALSE"))
+}
+
+/// The index of the closing parenthesis, or the last index if it is missing
+fn matching_paren(tokens: &[Token], open: usize) -> usize {
+    let mut depth = 0;
+
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        match token.text {
+            "(" => depth += 1,
+            ")" => depth -= 1,
+            _ => continue,
+        }
+
+        if depth == 0 {
+            return i;
+        }
+    }
+
+    tokens.len() - 1
+}
+
+/// The candidate with the smallest edit distance, if it is at most a third of the name
+fn closest<'c>(name: &str, candidates: impl Iterator<Item = &'c str>) -> Option<String> {
+    let name = name.to_lowercase();
+    let max = name.chars().count() / 3;
+
+    candidates
+        .map(|c| (edit_distance(&name, &c.to_lowercase()), c))
+        .filter(|(distance, _)| *distance <= max)
+        .min()
+        .map(|(_, c)| c.to_string())
+}
+
+/// The edit distance where swapping two adjacent characters is a single edit, like in typos
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    let mut distances = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    distances[0] = (0..=b.len()).collect();
+
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
+impl Display for UnknownReference {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            ReferenceKind::Table => "table",
+            ReferenceKind::Column => "column",
+        };
+
+        match &self.qualifier {
+            Some(qualifier) => write!(f, "Unknown {kind} '{qualifier}.{}'", self.name)?,
+            None => write!(f, "Unknown {kind} '{}'", self.name)?,
+        }
+
+        match &self.suggestion {
+            Some(suggestion) => write!(f, ", did you mean '{suggestion}'?"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::tests::metadata_from_sql;
+    use crate::validate::{validate_sql, ReferenceKind};
+
+    #[test]
+    fn test_validate_sql() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, email TEXT, name TEXT);
+            CREATE TABLE post (post_id INTEGER NOT NULL PRIMARY KEY, user_id INTEGER, title TEXT);
+            CREATE VIEW author AS SELECT user_id, name AS full_name FROM user;",
+        );
+        let valid = [
+            "SELECT u.email, count(*) AS amount FROM user u JOIN post AS p ON p.user_id = u.user_id
+            WHERE name LIKE 'a%' COLLATE NOCASE GROUP BY u.email ORDER BY amount DESC;",
+            "INSERT INTO user (user_id, email) VALUES (?, :email)
+            ON CONFLICT (user_id) DO UPDATE SET email = excluded.email;",
+            "WITH recent (id) AS (SELECT post_id FROM post ORDER BY post_id DESC LIMIT 10)
+            SELECT id, x.value FROM recent, json_each('[1]') AS x;",
+            "UPDATE post SET title = CAST(rowid AS TEXT) WHERE user_id IN (SELECT user_id FROM user);",
+            "SELECT * FROM main.\"user\" WHERE \"email\" IS NOT NULL AND TRUE;",
+            "SELECT a.full_name, title FROM author a JOIN post ON post.user_id = a.user_id;",
+        ];
+
+        for sql in valid {
+            assert_eq!(Vec::<String>::new(), unknown(&metadata, sql), "{sql}");
+        }
+
+        // Unbalanced parentheses don't panic
+        validate_sql(&metadata, "SELECT 1) FROM user;");
+        validate_sql(&metadata, "SELECT a FROM user), x;");
+
+        assert_eq!(
+            vec!["Unknown column 'emial', did you mean 'email'?"],
+            unknown(&metadata, "SELECT emial FROM user;")
+        );
+        // The columns of an unknown table are unknown as well
+        assert_eq!(
+            vec!["Unknown table 'usr', did you mean 'user'?"],
+            unknown(&metadata, "SELECT emial FROM usr;")
+        );
+        assert_eq!(
+            vec![
+                "Unknown column 'p.titel', did you mean 'title'?",
+                "Unknown table 'q'",
+                "Unknown column 'nonsense'",
+            ],
+            unknown(
+                &metadata,
+                "SELECT p.titel, q.title FROM post p WHERE nonsense = 1;"
+            )
+        );
+
+        assert_eq!(
+            vec!["Unknown column 'a.fullname', did you mean 'full_name'?"],
+            unknown(&metadata, "SELECT a.fullname FROM author a;")
+        );
+
+        // The columns of a view without columns are unknown, so they are not checked
+        let mut without_columns = metadata.clone();
+
+        without_columns.views[0].columns.clear();
+
+        assert!(unknown(&without_columns, "SELECT name FROM author;").is_empty());
+
+        let unknown = validate_sql(&metadata, "DELETE FROM post WHERE titel = ?;");
+
+        assert_eq!(ReferenceKind::Column, unknown[0].kind);
+        assert_eq!(23, unknown[0].offset);
+    }
+
+    fn unknown(metadata: &crate::Metadata, sql: &str) -> Vec<String> {
+        validate_sql(metadata, sql)
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+}
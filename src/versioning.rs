@@ -0,0 +1,189 @@
+//! Schema versioning with `PRAGMA user_version`, e.g. to check at startup that the database has
+//! the schema the application expects
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter, Result};
+
+use rusqlite::Connection;
+
+use crate::diff::SchemaDiff;
+use crate::{metadata_from_connection, Metadata};
+
+pub fn user_version(connection: &Connection) -> rusqlite::Result<i32> {
+    connection.query_row("PRAGMA user_version;", [], |row| row.get(0))
+}
+
+pub fn set_user_version(connection: &Connection, version: i32) -> rusqlite::Result<()> {
+    connection.execute_batch(&format!("PRAGMA user_version = {version};"))
+}
+
+/// Increments the user version, returns the new version
+pub fn bump_user_version(connection: &Connection) -> rusqlite::Result<i32> {
+    let version = user_version(connection)? + 1;
+
+    set_user_version(connection, version)?;
+
+    Ok(version)
+}
+
+/// The expected schema of every user version
+#[derive(Debug, PartialEq, Clone, Eq, Default)]
+pub struct SchemaVersions {
+    pub snapshots: BTreeMap<i32, Metadata>,
+}
+
+/// Why the schema of a database doesn't match the expected schema
+#[derive(Debug)]
+pub enum VersionError {
+    Sqlite(rusqlite::Error),
+    /// There is no snapshot for the user version of the database
+    UnknownVersion(i32),
+    /// The changes from the snapshot to the schema of the database
+    Mismatch {
+        version: i32,
+        diff: SchemaDiff,
+    },
+}
+
+impl SchemaVersions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the expected schema of the version, an existing snapshot of the version is replaced
+    pub fn with_version(mut self, version: i32, metadata: Metadata) -> Self {
+        self.snapshots.insert(version, metadata);
+        self
+    }
+
+    pub fn snapshot(&self, version: i32) -> Option<&Metadata> {
+        self.snapshots.get(&version)
+    }
+
+    /// The highest version with its snapshot
+    pub fn latest(&self) -> Option<(i32, &Metadata)> {
+        self.snapshots.iter().next_back().map(|(v, m)| (*v, m))
+    }
+
+    /// Checks that the schema of the database matches the snapshot of its user version, returns
+    /// the user version
+    /// Only the structure is compared, see `Metadata::diff`.
+    pub fn verify(&self, connection: &Connection) -> std::result::Result<i32, VersionError> {
+        let version = user_version(connection).map_err(VersionError::Sqlite)?;
+        let snapshot = self
+            .snapshot(version)
+            .ok_or(VersionError::UnknownVersion(version))?;
+        let diff = snapshot.diff(&metadata_from_connection(connection, Default::default()));
+
+        if diff.is_empty() {
+            Ok(version)
+        } else {
+            Err(VersionError::Mismatch { version, diff })
+        }
+    }
+
+    /// Verifies the database and migrates it to the latest snapshot with `Metadata::migration_to`,
+    /// returns the new user version
+    pub fn migrate_to_latest(
+        &self,
+        connection: &Connection,
+    ) -> std::result::Result<i32, VersionError> {
+        let version = self.verify(connection)?;
+        let (latest, latest_snapshot) = self.latest().unwrap();
+
+        if version != latest {
+            let mut migration = self.snapshots[&version].migration_to(latest_snapshot);
+
+            // The user version is committed in the same transaction as the schema
+            migration
+                .statements
+                .push(format!("PRAGMA user_version = {latest};"));
+            migration.apply(connection).map_err(VersionError::Sqlite)?;
+        }
+
+        Ok(latest)
+    }
+}
+
+impl Display for VersionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            VersionError::Sqlite(e) => write!(f, "{e}"),
+            VersionError::UnknownVersion(version) => {
+                write!(f, "No schema snapshot for user version {version}")
+            }
+            VersionError::Mismatch { version, diff } => {
+                write!(
+                    f,
+                    "The schema doesn't match the snapshot of user version {version}:\n{diff}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for VersionError {}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use crate::tests::metadata_from_sql;
+    use crate::versioning::{bump_user_version, user_version, SchemaVersions, VersionError};
+
+    #[test]
+    fn test_schema_versions() {
+        let v1 = "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY);";
+        let v2 = "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT);";
+        let versions = SchemaVersions::new()
+            .with_version(1, metadata_from_sql(v1))
+            .with_version(2, metadata_from_sql(v2));
+        let connection = Connection::open_in_memory().unwrap();
+
+        assert!(matches!(
+            versions.verify(&connection),
+            Err(VersionError::UnknownVersion(0))
+        ));
+
+        connection.execute_batch(v1).unwrap();
+
+        assert_eq!(1, bump_user_version(&connection).unwrap());
+        assert_eq!(1, versions.verify(&connection).unwrap());
+
+        connection
+            .execute_batch("ALTER TABLE user ADD COLUMN email TEXT;")
+            .unwrap();
+
+        let Err(VersionError::Mismatch { version: 1, diff }) = versions.verify(&connection) else {
+            panic!("Expected a mismatch");
+        };
+
+        assert_eq!(1, diff.changed_tables[0].added_columns.len());
+
+        connection
+            .execute_batch("ALTER TABLE user DROP COLUMN email;")
+            .unwrap();
+
+        assert_eq!(2, versions.migrate_to_latest(&connection).unwrap());
+        assert_eq!(2, user_version(&connection).unwrap());
+        assert_eq!(2, versions.verify(&connection).unwrap());
+
+        // A failing migration rolls back the schema and the user version
+        let versions = versions.with_version(
+            3,
+            metadata_from_sql(
+                "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT NOT NULL);",
+            ),
+        );
+
+        connection
+            .execute_batch("INSERT INTO user VALUES (1, NULL);")
+            .unwrap();
+
+        assert!(matches!(
+            versions.migrate_to_latest(&connection),
+            Err(VersionError::Sqlite(_))
+        ));
+        assert_eq!(2, versions.verify(&connection).unwrap());
+    }
+}
@@ -0,0 +1,251 @@
+//! The views of the schema and the tables and views they select from
+
+use std::collections::{HashMap, HashSet};
+
+use petgraph::graph::{DiGraph, NodeIndex};
+#[cfg(feature = "rusqlite")]
+use rusqlite::Connection;
+
+use crate::dependency::{AccessKind, Accesses, TableAccess};
+use crate::tokenizer::tokenize_without_trivia;
+use crate::{Metadata, Table};
+
+#[derive(Debug, PartialEq, Clone, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct View {
+    pub name: String,
+    /// The names of the result columns, empty if the backend can't resolve them
+    pub columns: Vec<String>,
+    /// The CREATE VIEW statement as stored in `sqlite_master`
+    pub sql: String,
+    /// The tables, views and columns that the SELECT reads, sorted by name
+    pub accesses: Vec<TableAccess>,
+}
+
+/// A node of `ViewDependencyGraph`
+#[derive(Debug, PartialEq, Copy, Clone, Eq)]
+pub enum SchemaObject<'a> {
+    Table(&'a Table),
+    View(&'a View),
+}
+
+impl SchemaObject<'_> {
+    pub fn name(&self) -> &str {
+        match self {
+            SchemaObject::Table(table) => &table.table_name,
+            SchemaObject::View(view) => &view.name,
+        }
+    }
+}
+
+/// A graph with a node per table and view, with an edge from every view to the tables and views
+/// it selects from
+pub type ViewDependencyGraph<'a> = DiGraph<SchemaObject<'a>, ()>;
+
+impl View {
+    /// The names of the tables and views that the view selects from
+    pub fn references(&self) -> impl Iterator<Item = &str> {
+        self.accesses.iter().map(|a| a.table_name.as_str())
+    }
+
+    /// Finds the accesses of the SELECT
+    fn analyze(&self, metadata: &Metadata) -> Vec<TableAccess> {
+        let tokens = tokenize_without_trivia(&self.sql);
+        let mut accesses = Accesses::new(metadata);
+
+        if let Some(select) = tokens.iter().position(|t| t.is_keyword("AS")) {
+            accesses.statement(&tokens[select + 1..], None);
+        }
+
+        accesses
+            .into_vec()
+            .into_iter()
+            .filter(|a| a.kind == AccessKind::Read)
+            .collect()
+    }
+}
+
+impl Metadata {
+    /// Finds the view, the name is compared with `lookup`
+    pub fn view(&self, name: &str) -> Option<&View> {
+        self.views
+            .iter()
+            .find(|v| self.lookup.matches(&v.name, name))
+    }
+
+    /// The views that select from the table or view
+    pub fn views_referencing(&self, name: &str) -> Vec<&View> {
+        self.views
+            .iter()
+            .filter(|v| v.references().any(|r| self.lookup.matches(r, name)))
+            .collect()
+    }
+
+    /// Builds the graph of the views, nodes are added for the tables sorted by name followed by
+    /// the views sorted by name
+    /// References to tables and views that don't exist are skipped.
+    pub fn view_dependency_graph(&self) -> ViewDependencyGraph<'_> {
+        let mut graph = ViewDependencyGraph::new();
+        let mut nodes: HashMap<String, NodeIndex> = HashMap::new();
+
+        for table in self.sorted_tables() {
+            let node = graph.add_node(SchemaObject::Table(table));
+
+            nodes.insert(table.table_name.to_lowercase(), node);
+        }
+
+        for view in &self.views {
+            let node = graph.add_node(SchemaObject::View(view));
+
+            nodes.insert(view.name.to_lowercase(), node);
+        }
+
+        for view in &self.views {
+            for reference in view.references() {
+                if let Some(referenced) = nodes.get(&reference.to_lowercase()) {
+                    graph.add_edge(nodes[&view.name.to_lowercase()], *referenced, ());
+                }
+            }
+        }
+
+        graph
+    }
+
+    /// The views sorted so that a view comes after the views it selects from, which is the order
+    /// to create them in. Drop them in the reverse order.
+    /// Views without a relation between them are sorted by name.
+    pub fn views_in_dependency_order(&self) -> Vec<&View> {
+        let mut ordered = vec![];
+        let mut visited = HashSet::new();
+
+        for view in &self.views {
+            self.visit_view(view, &mut visited, &mut ordered);
+        }
+
+        ordered
+    }
+
+    /// Adds the views the view selects from and then the view itself, depth first
+    fn visit_view<'a>(
+        &'a self,
+        view: &'a View,
+        visited: &mut HashSet<&'a str>,
+        ordered: &mut Vec<&'a View>,
+    ) {
+        if !visited.insert(&view.name) {
+            return;
+        }
+
+        for reference in view.references() {
+            if let Some(referenced) = self.view(reference) {
+                self.visit_view(referenced, visited, ordered);
+            }
+        }
+
+        ordered.push(view);
+    }
+
+    /// Sets the accesses of the views and triggers, after the tables are parsed
+    pub(crate) fn analyze_dependencies(&mut self) {
+        let accesses = self
+            .views
+            .iter()
+            .map(|v| v.analyze(self))
+            .collect::<Vec<_>>();
+
+        for (view, accesses) in self.views.iter_mut().zip(accesses) {
+            view.accesses = accesses;
+        }
+
+        self.analyze_triggers();
+    }
+}
+
+/// The views of the database with their columns, sorted by name
+#[cfg(feature = "rusqlite")]
+pub(crate) fn query_views(connection: &Connection) -> Vec<View> {
+    let mut stmt = connection
+        .prepare("SELECT name, sql FROM sqlite_master WHERE type = 'view' ORDER BY name;")
+        .unwrap();
+    let mut rows = stmt.query([]).unwrap();
+    let mut views = vec![];
+
+    while let Some(row) = rows.next().unwrap() {
+        let name: String = row.get(0).unwrap();
+        // A view that refers to a table that doesn't exist has no columns
+        let columns = connection
+            .prepare("SELECT name FROM pragma_table_info(?);")
+            .and_then(|mut stmt| {
+                stmt.query_map([&name], |row| row.get(0))?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .unwrap_or_default();
+
+        views.push(View {
+            name,
+            columns,
+            sql: row.get(1).unwrap(),
+            accesses: vec![],
+        });
+    }
+
+    views
+}
+
+#[cfg(all(test, feature = "rusqlite"))]
+mod tests {
+    use crate::dependency::{AccessKind, TableAccess};
+    use crate::tests::metadata_from_sql;
+
+    #[test]
+    fn test_views() {
+        let metadata = metadata_from_sql(
+            "CREATE TABLE user (user_id INTEGER NOT NULL PRIMARY KEY, name TEXT);
+            CREATE TABLE post (post_id INTEGER NOT NULL PRIMARY KEY, user_id INTEGER, title TEXT);
+            CREATE VIEW a_post_count AS
+                SELECT u.name, (SELECT COUNT(*) FROM post p WHERE p.user_id = u.user_id) AS posts
+                FROM user_names u;
+            CREATE VIEW user_names AS SELECT user_id, name FROM user;
+            CREATE VIEW titles(title) AS SELECT title FROM post WHERE title IS NOT NULL;",
+        );
+        let view = metadata.view("A_POST_COUNT").unwrap();
+
+        assert_eq!(vec!["name", "posts"], view.columns);
+        assert_eq!(
+            vec![
+                TableAccess {
+                    table_name: "post".to_string(),
+                    kind: AccessKind::Read,
+                    columns: vec!["user_id".to_string()],
+                },
+                TableAccess {
+                    table_name: "user_names".to_string(),
+                    kind: AccessKind::Read,
+                    columns: vec!["name".to_string(), "user_id".to_string()],
+                },
+            ],
+            view.accesses
+        );
+        assert_eq!(
+            vec!["user_names", "a_post_count", "titles"],
+            metadata
+                .views_in_dependency_order()
+                .iter()
+                .map(|v| &v.name)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec!["a_post_count"],
+            metadata
+                .views_referencing("user_names")
+                .iter()
+                .map(|v| &v.name)
+                .collect::<Vec<_>>()
+        );
+
+        let graph = metadata.view_dependency_graph();
+
+        assert_eq!(5, graph.node_count());
+        assert_eq!(4, graph.edge_count());
+    }
+}
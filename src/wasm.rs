@@ -0,0 +1,16 @@
+//! The JavaScript bindings, the database file is read with the pure-Rust `file_format` backend
+//! since SQLite itself can't be linked on `wasm32-unknown-unknown`
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::file_format::parse_file_bytes;
+
+/// Parses the bytes of a database file, e.g. from a `File` picked by the user, to the metadata
+/// as a plain JavaScript object (maps are objects, like `JSON.parse` would return)
+#[wasm_bindgen(js_name = parseFile)]
+pub fn parse_file(bytes: &[u8]) -> Result<JsValue, JsError> {
+    let metadata = parse_file_bytes(bytes)?;
+
+    Ok(metadata.serialize(&serde_wasm_bindgen::Serializer::json_compatible())?)
+}